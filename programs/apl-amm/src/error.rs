@@ -37,6 +37,21 @@ pub enum AmmError {
     /// Invalid instruction data
     #[error("Invalid instruction")]
     InvalidInstruction,
+    /// Account is not the fee recipient of the pool
+    #[error("Fee recipient mismatch")]
+    FeeRecipientMismatch,
+    /// Deposit would mint no LP tokens
+    #[error("Zero LP amount")]
+    ZeroLpAmount,
+    /// Amplification coefficient or ramp out of bounds
+    #[error("Invalid amplification coefficient")]
+    InvalidAmp,
+    /// StableSwap calculation did not converge
+    #[error("Calculation did not converge")]
+    ConvergenceFailure,
+    /// Oracle observations out of order or of another pool
+    #[error("Invalid observation")]
+    InvalidObservation,
 }
 
 impl From<AmmError> for ProgramError {
@@ -69,6 +84,11 @@ impl PrintProgramError for AmmError {
             AmmError::InvalidPoolState => msg!("Error: Invalid pool state"),
             AmmError::MathOverflow => msg!("Error: Math operation overflow"),
             AmmError::InvalidInstruction => msg!("Error: Invalid instruction"),
+            AmmError::FeeRecipientMismatch => msg!("Error: Fee recipient mismatch"),
+            AmmError::ZeroLpAmount => msg!("Error: Deposit would mint no LP tokens"),
+            AmmError::InvalidAmp => msg!("Error: Invalid amplification coefficient or ramp"),
+            AmmError::ConvergenceFailure => msg!("Error: StableSwap calculation did not converge"),
+            AmmError::InvalidObservation => msg!("Error: Invalid oracle observation"),
         }
     }
 }
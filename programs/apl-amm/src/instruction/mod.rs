@@ -1,8 +1,14 @@
 //! Instruction types
 
 use {
-    crate::error::AmmError,
-    arch_program::program_error::ProgramError,
+    crate::{
+        error::AmmError, get_oracle_address_and_bump_seed,
+        get_pool_authority_address_and_bump_seed, state::CurveType,
+    },
+    arch_program::{
+        account::AccountMeta, instruction::Instruction, program_error::ProgramError, pubkey::Pubkey,
+    },
+    num_traits::FromPrimitive,
     std::{convert::TryInto, mem::size_of},
 };
 
@@ -18,17 +24,35 @@ pub enum AmmInstruction {
     ///   1. `[]` Token A mint
     ///   2. `[]` Token B mint
     ///   3. `[writable]` LP token mint (must be created beforehand)
-    ///   4. `[writable]` Token A vault
-    ///   5. `[writable]` Token B vault
-    ///   6. `[signer]` Pool authority
+    ///   4. `[writable]` Token A vault, owned by the pool authority
+    ///   5. `[writable]` Token B vault, owned by the pool authority
+    ///   6. `[]` Pool authority, derived from the pool address. It must be
+    ///      the mint authority of the LP token mint
+    ///   7. `[]` Protocol fee recipient
     InitializePool {
         /// Fee numerator, fee = numerator/denominator
         fee_numerator: u16,
         /// Fee denominator
         fee_denominator: u16,
+        /// Protocol fee numerator, the protocol share of the fees being
+        /// numerator/denominator
+        protocol_fee_numerator: u16,
+        /// Protocol fee denominator
+        protocol_fee_denominator: u16,
+        /// Curve pricing the swaps
+        curve_type: CurveType,
+        /// Amplification coefficient of a StableSwap pool, zero for other
+        /// curves
+        amp: u64,
     },
 
-    /// Adds liquidity to the pool.
+    /// Adds liquidity to the pool at its current reserve ratio.
+    ///
+    /// Only the optimal pair of amounts, up to the desired amounts, is
+    /// transferred from the user. The first deposit sets the ratio and
+    /// transfers the desired amounts. The deposited token A and token B
+    /// amounts and the minted LP amount are returned as little-endian `u64`s
+    /// through the return data.
     ///
     /// Accounts expected by this instruction:
     ///
@@ -40,11 +64,20 @@ pub enum AmmInstruction {
     ///   5. `[writable]` User's token B account
     ///   6. `[writable]` User's LP token account
     ///   7. `[signer]` User authority
+    ///   8. `[]` Pool authority
+    ///   9. `[]` Token program
+    ///   10. `[writable]` LP token account held by the pool authority, where
+    ///       `MINIMUM_LIQUIDITY` is locked. Only required by the first
+    ///       deposit, while the LP supply is zero
     AddLiquidity {
-        /// Amount of token A to add
-        token_a_amount: u64,
-        /// Amount of token B to add
-        token_b_amount: u64,
+        /// Maximum amount of token A to add
+        amount_a_desired: u64,
+        /// Maximum amount of token B to add
+        amount_b_desired: u64,
+        /// Minimum amount of token A to add
+        amount_a_min: u64,
+        /// Minimum amount of token B to add
+        amount_b_min: u64,
         /// Minimum LP tokens to mint, prevents excessive slippage
         min_lp_amount: u64,
     },
@@ -61,6 +94,8 @@ pub enum AmmInstruction {
     ///   5. `[writable]` User's token B account
     ///   6. `[writable]` User's LP token account
     ///   7. `[signer]` User authority
+    ///   8. `[]` Pool authority
+    ///   9. `[]` Token program
     RemoveLiquidity {
         /// Amount of LP tokens to burn
         lp_amount: u64,
@@ -75,17 +110,132 @@ pub enum AmmInstruction {
     /// Accounts expected by this instruction:
     ///
     ///   0. `[writable]` The pool account
-    ///   1. `[writable]` Token input vault
-    ///   2. `[writable]` Token output vault
-    ///   3. `[writable]` User's token input account
-    ///   4. `[writable]` User's token output account
+    ///   1. `[writable]` Input vault: token A vault if swapping A to B, token
+    ///      B vault otherwise
+    ///   2. `[writable]` Output vault: the other vault of the pool
+    ///   3. `[writable]` User's account of the input token
+    ///   4. `[writable]` User's account of the output token
     ///   5. `[signer]` User authority
+    ///   6. `[]` Pool authority
+    ///   7. `[]` Token program
     Swap {
         /// Amount of input tokens to swap
         amount_in: u64,
         /// Minimum output tokens to receive
         min_amount_out: u64,
+        /// Is `true` to swap token A for token B, `false` for the reverse
+        a_to_b: bool,
+    },
+
+    /// Swaps tokens in the pool for an exact amount out.
+    ///
+    /// Accounts expected by this instruction are the same as for `Swap`.
+    SwapExactOut {
+        /// Amount of output tokens to receive
+        amount_out: u64,
+        /// Maximum input tokens to pay, prevents excessive slippage
+        max_amount_in: u64,
+        /// Is `true` to swap token A for token B, `false` for the reverse
+        a_to_b: bool,
+    },
+
+    /// Withdraws the protocol fees owed to the fee recipient.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The pool account
+    ///   1. `[writable]` Token A vault
+    ///   2. `[writable]` Token B vault
+    ///   3. `[writable]` Destination token A account
+    ///   4. `[writable]` Destination token B account
+    ///   5. `[signer]` Fee recipient
+    ///   6. `[]` Pool authority
+    ///   7. `[]` Token program
+    WithdrawProtocolFees,
+
+    /// Adds liquidity to the pool with a single token, as if swapping half of
+    /// it for the other token before depositing both.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The pool account
+    ///   1. `[writable]` Vault of the deposited token
+    ///   2. `[]` Vault of the other token
+    ///   3. `[writable]` LP token mint
+    ///   4. `[writable]` User's account of the deposited token
+    ///   5. `[writable]` User's LP token account
+    ///   6. `[signer]` User authority
+    ///   7. `[]` Pool authority
+    ///   8. `[]` Token program
+    DepositSingleTokenExactIn {
+        /// Amount of tokens to deposit
+        amount_in: u64,
+        /// Minimum LP tokens to mint, prevents excessive slippage
+        min_lp_amount: u64,
+        /// Is `true` to deposit token A, `false` for token B
+        is_token_a: bool,
+    },
+
+    /// Removes liquidity from the pool for an exact amount of a single token,
+    /// as if swapping the other token withdrawn for it.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The pool account
+    ///   1. `[writable]` Vault of the withdrawn token
+    ///   2. `[]` Vault of the other token
+    ///   3. `[writable]` LP token mint
+    ///   4. `[writable]` User's account of the withdrawn token
+    ///   5. `[writable]` User's LP token account
+    ///   6. `[signer]` User authority
+    ///   7. `[]` Pool authority
+    ///   8. `[]` Token program
+    WithdrawSingleTokenExactOut {
+        /// Amount of tokens to receive
+        amount_out: u64,
+        /// Maximum LP tokens to burn, prevents excessive slippage
+        max_lp_amount: u64,
+        /// Is `true` to withdraw token A, `false` for token B
+        is_token_a: bool,
+    },
+
+    /// Ramps the amplification coefficient of a StableSwap pool linearly
+    /// from its current value to the target, until the stop timestamp.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The pool account
+    ///   1. `[signer]` Fee recipient
+    RampAmp {
+        /// Amplification coefficient at the end of the ramp
+        target_amp: u64,
+        /// Unix timestamp of the end of the ramp
+        ramp_stop_timestamp: i64,
+    },
+
+    /// Creates the oracle of a pool, a ring buffer of its cumulative prices
+    /// for on-chain lookbacks, recording the first observation.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[]` The pool account
+    ///   1. `[writable]` Oracle, derived from the pool address
+    ///   2. `[]` System program
+    InitializeOracle {
+        /// Transaction id of the UTXO backing the oracle account
+        txid: [u8; 32],
+        /// Output index of the UTXO backing the oracle account
+        vout: u32,
     },
+
+    /// Records the current cumulative prices of a pool in its oracle. Anyone
+    /// may record, at most one observation being kept per second.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[]` The pool account
+    ///   1. `[writable]` Oracle of the pool
+    RecordObservation,
 }
 
 impl AmmInstruction {
@@ -96,19 +246,33 @@ impl AmmInstruction {
         Ok(match tag {
             0 => {
                 let (fee_numerator, rest) = Self::unpack_u16(rest)?;
-                let (fee_denominator, _) = Self::unpack_u16(rest)?;
+                let (fee_denominator, rest) = Self::unpack_u16(rest)?;
+                let (protocol_fee_numerator, rest) = Self::unpack_u16(rest)?;
+                let (protocol_fee_denominator, rest) = Self::unpack_u16(rest)?;
+                let (&curve_type, rest) = rest.split_first().ok_or(AmmError::InvalidInstruction)?;
+                let curve_type =
+                    CurveType::from_u8(curve_type).ok_or(AmmError::InvalidInstruction)?;
+                let (amp, _) = Self::unpack_u64(rest)?;
                 Self::InitializePool {
                     fee_numerator,
                     fee_denominator,
+                    protocol_fee_numerator,
+                    protocol_fee_denominator,
+                    curve_type,
+                    amp,
                 }
             }
             1 => {
-                let (token_a_amount, rest) = Self::unpack_u64(rest)?;
-                let (token_b_amount, rest) = Self::unpack_u64(rest)?;
+                let (amount_a_desired, rest) = Self::unpack_u64(rest)?;
+                let (amount_b_desired, rest) = Self::unpack_u64(rest)?;
+                let (amount_a_min, rest) = Self::unpack_u64(rest)?;
+                let (amount_b_min, rest) = Self::unpack_u64(rest)?;
                 let (min_lp_amount, _) = Self::unpack_u64(rest)?;
                 Self::AddLiquidity {
-                    token_a_amount,
-                    token_b_amount,
+                    amount_a_desired,
+                    amount_b_desired,
+                    amount_a_min,
+                    amount_b_min,
                     min_lp_amount,
                 }
             }
@@ -124,12 +288,59 @@ impl AmmInstruction {
             }
             3 => {
                 let (amount_in, rest) = Self::unpack_u64(rest)?;
-                let (min_amount_out, _) = Self::unpack_u64(rest)?;
+                let (min_amount_out, rest) = Self::unpack_u64(rest)?;
+                let (a_to_b, _) = Self::unpack_bool(rest)?;
                 Self::Swap {
                     amount_in,
                     min_amount_out,
+                    a_to_b,
+                }
+            }
+            4 => {
+                let (amount_out, rest) = Self::unpack_u64(rest)?;
+                let (max_amount_in, rest) = Self::unpack_u64(rest)?;
+                let (a_to_b, _) = Self::unpack_bool(rest)?;
+                Self::SwapExactOut {
+                    amount_out,
+                    max_amount_in,
+                    a_to_b,
+                }
+            }
+            5 => Self::WithdrawProtocolFees,
+            6 => {
+                let (amount_in, rest) = Self::unpack_u64(rest)?;
+                let (min_lp_amount, rest) = Self::unpack_u64(rest)?;
+                let (is_token_a, _) = Self::unpack_bool(rest)?;
+                Self::DepositSingleTokenExactIn {
+                    amount_in,
+                    min_lp_amount,
+                    is_token_a,
+                }
+            }
+            7 => {
+                let (amount_out, rest) = Self::unpack_u64(rest)?;
+                let (max_lp_amount, rest) = Self::unpack_u64(rest)?;
+                let (is_token_a, _) = Self::unpack_bool(rest)?;
+                Self::WithdrawSingleTokenExactOut {
+                    amount_out,
+                    max_lp_amount,
+                    is_token_a,
+                }
+            }
+            8 => {
+                let (target_amp, rest) = Self::unpack_u64(rest)?;
+                let (ramp_stop_timestamp, _) = Self::unpack_i64(rest)?;
+                Self::RampAmp {
+                    target_amp,
+                    ramp_stop_timestamp,
                 }
             }
+            9 => {
+                let (txid, rest) = Self::unpack_txid(rest)?;
+                let (vout, _) = Self::unpack_u32(rest)?;
+                Self::InitializeOracle { txid, vout }
+            }
+            10 => Self::RecordObservation,
             _ => return Err(AmmError::InvalidInstruction.into()),
         })
     }
@@ -141,19 +352,31 @@ impl AmmInstruction {
             &Self::InitializePool {
                 fee_numerator,
                 fee_denominator,
+                protocol_fee_numerator,
+                protocol_fee_denominator,
+                curve_type,
+                amp,
             } => {
                 buf.push(0);
                 buf.extend_from_slice(&fee_numerator.to_le_bytes());
                 buf.extend_from_slice(&fee_denominator.to_le_bytes());
+                buf.extend_from_slice(&protocol_fee_numerator.to_le_bytes());
+                buf.extend_from_slice(&protocol_fee_denominator.to_le_bytes());
+                buf.push(curve_type as u8);
+                buf.extend_from_slice(&amp.to_le_bytes());
             }
             &Self::AddLiquidity {
-                token_a_amount,
-                token_b_amount,
+                amount_a_desired,
+                amount_b_desired,
+                amount_a_min,
+                amount_b_min,
                 min_lp_amount,
             } => {
                 buf.push(1);
-                buf.extend_from_slice(&token_a_amount.to_le_bytes());
-                buf.extend_from_slice(&token_b_amount.to_le_bytes());
+                buf.extend_from_slice(&amount_a_desired.to_le_bytes());
+                buf.extend_from_slice(&amount_b_desired.to_le_bytes());
+                buf.extend_from_slice(&amount_a_min.to_le_bytes());
+                buf.extend_from_slice(&amount_b_min.to_le_bytes());
                 buf.extend_from_slice(&min_lp_amount.to_le_bytes());
             }
             &Self::RemoveLiquidity {
@@ -169,11 +392,58 @@ impl AmmInstruction {
             &Self::Swap {
                 amount_in,
                 min_amount_out,
+                a_to_b,
             } => {
                 buf.push(3);
                 buf.extend_from_slice(&amount_in.to_le_bytes());
                 buf.extend_from_slice(&min_amount_out.to_le_bytes());
+                buf.push(a_to_b as u8);
+            }
+            &Self::SwapExactOut {
+                amount_out,
+                max_amount_in,
+                a_to_b,
+            } => {
+                buf.push(4);
+                buf.extend_from_slice(&amount_out.to_le_bytes());
+                buf.extend_from_slice(&max_amount_in.to_le_bytes());
+                buf.push(a_to_b as u8);
+            }
+            Self::WithdrawProtocolFees => buf.push(5),
+            &Self::DepositSingleTokenExactIn {
+                amount_in,
+                min_lp_amount,
+                is_token_a,
+            } => {
+                buf.push(6);
+                buf.extend_from_slice(&amount_in.to_le_bytes());
+                buf.extend_from_slice(&min_lp_amount.to_le_bytes());
+                buf.push(is_token_a as u8);
+            }
+            &Self::WithdrawSingleTokenExactOut {
+                amount_out,
+                max_lp_amount,
+                is_token_a,
+            } => {
+                buf.push(7);
+                buf.extend_from_slice(&amount_out.to_le_bytes());
+                buf.extend_from_slice(&max_lp_amount.to_le_bytes());
+                buf.push(is_token_a as u8);
+            }
+            &Self::RampAmp {
+                target_amp,
+                ramp_stop_timestamp,
+            } => {
+                buf.push(8);
+                buf.extend_from_slice(&target_amp.to_le_bytes());
+                buf.extend_from_slice(&ramp_stop_timestamp.to_le_bytes());
             }
+            Self::InitializeOracle { txid, vout } => {
+                buf.push(9);
+                buf.extend_from_slice(txid);
+                buf.extend_from_slice(&vout.to_le_bytes());
+            }
+            Self::RecordObservation => buf.push(10),
         };
         buf
     }
@@ -184,11 +454,492 @@ impl AmmInstruction {
         Ok((value, rest))
     }
 
+    fn unpack_u32(input: &[u8]) -> Result<(u32, &[u8]), ProgramError> {
+        let (bytes, rest) = input.split_at(4);
+        let value = u32::from_le_bytes(bytes.try_into().unwrap());
+        Ok((value, rest))
+    }
+
     fn unpack_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
         let (bytes, rest) = input.split_at(8);
         let value = u64::from_le_bytes(bytes.try_into().unwrap());
         Ok((value, rest))
     }
+
+    fn unpack_i64(input: &[u8]) -> Result<(i64, &[u8]), ProgramError> {
+        let (bytes, rest) = input.split_at(8);
+        let value = i64::from_le_bytes(bytes.try_into().unwrap());
+        Ok((value, rest))
+    }
+
+    fn unpack_txid(input: &[u8]) -> Result<([u8; 32], &[u8]), ProgramError> {
+        let (bytes, rest) = input.split_at(32);
+        Ok((bytes.try_into().unwrap(), rest))
+    }
+
+    fn unpack_bool(input: &[u8]) -> Result<(bool, &[u8]), ProgramError> {
+        match input.split_first() {
+            Some((0, rest)) => Ok((false, rest)),
+            Some((1, rest)) => Ok((true, rest)),
+            _ => Err(AmmError::InvalidInstruction.into()),
+        }
+    }
+}
+
+/// Creates an `InitializePool` instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn initialize_pool(
+    amm_program_id: &Pubkey,
+    pool_pubkey: &Pubkey,
+    token_a_mint_pubkey: &Pubkey,
+    token_b_mint_pubkey: &Pubkey,
+    lp_mint_pubkey: &Pubkey,
+    token_a_vault_pubkey: &Pubkey,
+    token_b_vault_pubkey: &Pubkey,
+    fee_recipient_pubkey: &Pubkey,
+    fee_numerator: u16,
+    fee_denominator: u16,
+    protocol_fee_numerator: u16,
+    protocol_fee_denominator: u16,
+    curve_type: CurveType,
+    amp: u64,
+) -> Instruction {
+    let (pool_authority, _) = get_pool_authority_address_and_bump_seed(pool_pubkey, amm_program_id);
+
+    Instruction {
+        program_id: *amm_program_id,
+        accounts: vec![
+            AccountMeta::new(*pool_pubkey, false),
+            AccountMeta::new_readonly(*token_a_mint_pubkey, false),
+            AccountMeta::new_readonly(*token_b_mint_pubkey, false),
+            AccountMeta::new(*lp_mint_pubkey, false),
+            AccountMeta::new(*token_a_vault_pubkey, false),
+            AccountMeta::new(*token_b_vault_pubkey, false),
+            AccountMeta::new_readonly(pool_authority, false),
+            AccountMeta::new_readonly(*fee_recipient_pubkey, false),
+        ],
+        data: AmmInstruction::InitializePool {
+            fee_numerator,
+            fee_denominator,
+            protocol_fee_numerator,
+            protocol_fee_denominator,
+            curve_type,
+            amp,
+        }
+        .pack(),
+    }
+}
+
+/// Creates an `AddLiquidity` instruction. The locked LP token account is only
+/// needed for the first deposit into a pool.
+#[allow(clippy::too_many_arguments)]
+pub fn add_liquidity(
+    amm_program_id: &Pubkey,
+    pool_pubkey: &Pubkey,
+    token_a_vault_pubkey: &Pubkey,
+    token_b_vault_pubkey: &Pubkey,
+    lp_mint_pubkey: &Pubkey,
+    user_token_a_pubkey: &Pubkey,
+    user_token_b_pubkey: &Pubkey,
+    user_lp_pubkey: &Pubkey,
+    user_authority_pubkey: &Pubkey,
+    locked_lp_pubkey: Option<&Pubkey>,
+    amount_a_desired: u64,
+    amount_b_desired: u64,
+    amount_a_min: u64,
+    amount_b_min: u64,
+    min_lp_amount: u64,
+) -> Instruction {
+    let mut accounts = liquidity_accounts(
+        amm_program_id,
+        pool_pubkey,
+        token_a_vault_pubkey,
+        token_b_vault_pubkey,
+        lp_mint_pubkey,
+        user_token_a_pubkey,
+        user_token_b_pubkey,
+        user_lp_pubkey,
+        user_authority_pubkey,
+    );
+    if let Some(locked_lp_pubkey) = locked_lp_pubkey {
+        accounts.push(AccountMeta::new(*locked_lp_pubkey, false));
+    }
+
+    Instruction {
+        program_id: *amm_program_id,
+        accounts,
+        data: AmmInstruction::AddLiquidity {
+            amount_a_desired,
+            amount_b_desired,
+            amount_a_min,
+            amount_b_min,
+            min_lp_amount,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a `RemoveLiquidity` instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn remove_liquidity(
+    amm_program_id: &Pubkey,
+    pool_pubkey: &Pubkey,
+    token_a_vault_pubkey: &Pubkey,
+    token_b_vault_pubkey: &Pubkey,
+    lp_mint_pubkey: &Pubkey,
+    user_token_a_pubkey: &Pubkey,
+    user_token_b_pubkey: &Pubkey,
+    user_lp_pubkey: &Pubkey,
+    user_authority_pubkey: &Pubkey,
+    lp_amount: u64,
+    min_token_a_amount: u64,
+    min_token_b_amount: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *amm_program_id,
+        accounts: liquidity_accounts(
+            amm_program_id,
+            pool_pubkey,
+            token_a_vault_pubkey,
+            token_b_vault_pubkey,
+            lp_mint_pubkey,
+            user_token_a_pubkey,
+            user_token_b_pubkey,
+            user_lp_pubkey,
+            user_authority_pubkey,
+        ),
+        data: AmmInstruction::RemoveLiquidity {
+            lp_amount,
+            min_token_a_amount,
+            min_token_b_amount,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a `Swap` instruction, ordering the vaults for the direction.
+#[allow(clippy::too_many_arguments)]
+pub fn swap(
+    amm_program_id: &Pubkey,
+    pool_pubkey: &Pubkey,
+    token_a_vault_pubkey: &Pubkey,
+    token_b_vault_pubkey: &Pubkey,
+    user_input_pubkey: &Pubkey,
+    user_output_pubkey: &Pubkey,
+    user_authority_pubkey: &Pubkey,
+    amount_in: u64,
+    min_amount_out: u64,
+    a_to_b: bool,
+) -> Instruction {
+    Instruction {
+        program_id: *amm_program_id,
+        accounts: swap_accounts(
+            amm_program_id,
+            pool_pubkey,
+            token_a_vault_pubkey,
+            token_b_vault_pubkey,
+            user_input_pubkey,
+            user_output_pubkey,
+            user_authority_pubkey,
+            a_to_b,
+        ),
+        data: AmmInstruction::Swap {
+            amount_in,
+            min_amount_out,
+            a_to_b,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a `SwapExactOut` instruction, ordering the vaults for the
+/// direction.
+#[allow(clippy::too_many_arguments)]
+pub fn swap_exact_out(
+    amm_program_id: &Pubkey,
+    pool_pubkey: &Pubkey,
+    token_a_vault_pubkey: &Pubkey,
+    token_b_vault_pubkey: &Pubkey,
+    user_input_pubkey: &Pubkey,
+    user_output_pubkey: &Pubkey,
+    user_authority_pubkey: &Pubkey,
+    amount_out: u64,
+    max_amount_in: u64,
+    a_to_b: bool,
+) -> Instruction {
+    Instruction {
+        program_id: *amm_program_id,
+        accounts: swap_accounts(
+            amm_program_id,
+            pool_pubkey,
+            token_a_vault_pubkey,
+            token_b_vault_pubkey,
+            user_input_pubkey,
+            user_output_pubkey,
+            user_authority_pubkey,
+            a_to_b,
+        ),
+        data: AmmInstruction::SwapExactOut {
+            amount_out,
+            max_amount_in,
+            a_to_b,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a `WithdrawProtocolFees` instruction.
+pub fn withdraw_protocol_fees(
+    amm_program_id: &Pubkey,
+    pool_pubkey: &Pubkey,
+    token_a_vault_pubkey: &Pubkey,
+    token_b_vault_pubkey: &Pubkey,
+    destination_a_pubkey: &Pubkey,
+    destination_b_pubkey: &Pubkey,
+    fee_recipient_pubkey: &Pubkey,
+) -> Instruction {
+    let (pool_authority, _) = get_pool_authority_address_and_bump_seed(pool_pubkey, amm_program_id);
+
+    Instruction {
+        program_id: *amm_program_id,
+        accounts: vec![
+            AccountMeta::new(*pool_pubkey, false),
+            AccountMeta::new(*token_a_vault_pubkey, false),
+            AccountMeta::new(*token_b_vault_pubkey, false),
+            AccountMeta::new(*destination_a_pubkey, false),
+            AccountMeta::new(*destination_b_pubkey, false),
+            AccountMeta::new_readonly(*fee_recipient_pubkey, true),
+            AccountMeta::new_readonly(pool_authority, false),
+            AccountMeta::new_readonly(apl_token::id(), false),
+        ],
+        data: AmmInstruction::WithdrawProtocolFees.pack(),
+    }
+}
+
+/// Creates a `DepositSingleTokenExactIn` instruction, ordering the vaults
+/// for the deposited token.
+#[allow(clippy::too_many_arguments)]
+pub fn deposit_single_token_exact_in(
+    amm_program_id: &Pubkey,
+    pool_pubkey: &Pubkey,
+    token_a_vault_pubkey: &Pubkey,
+    token_b_vault_pubkey: &Pubkey,
+    lp_mint_pubkey: &Pubkey,
+    user_token_pubkey: &Pubkey,
+    user_lp_pubkey: &Pubkey,
+    user_authority_pubkey: &Pubkey,
+    amount_in: u64,
+    min_lp_amount: u64,
+    is_token_a: bool,
+) -> Instruction {
+    Instruction {
+        program_id: *amm_program_id,
+        accounts: single_token_accounts(
+            amm_program_id,
+            pool_pubkey,
+            token_a_vault_pubkey,
+            token_b_vault_pubkey,
+            lp_mint_pubkey,
+            user_token_pubkey,
+            user_lp_pubkey,
+            user_authority_pubkey,
+            is_token_a,
+        ),
+        data: AmmInstruction::DepositSingleTokenExactIn {
+            amount_in,
+            min_lp_amount,
+            is_token_a,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a `WithdrawSingleTokenExactOut` instruction, ordering the vaults
+/// for the withdrawn token.
+#[allow(clippy::too_many_arguments)]
+pub fn withdraw_single_token_exact_out(
+    amm_program_id: &Pubkey,
+    pool_pubkey: &Pubkey,
+    token_a_vault_pubkey: &Pubkey,
+    token_b_vault_pubkey: &Pubkey,
+    lp_mint_pubkey: &Pubkey,
+    user_token_pubkey: &Pubkey,
+    user_lp_pubkey: &Pubkey,
+    user_authority_pubkey: &Pubkey,
+    amount_out: u64,
+    max_lp_amount: u64,
+    is_token_a: bool,
+) -> Instruction {
+    Instruction {
+        program_id: *amm_program_id,
+        accounts: single_token_accounts(
+            amm_program_id,
+            pool_pubkey,
+            token_a_vault_pubkey,
+            token_b_vault_pubkey,
+            lp_mint_pubkey,
+            user_token_pubkey,
+            user_lp_pubkey,
+            user_authority_pubkey,
+            is_token_a,
+        ),
+        data: AmmInstruction::WithdrawSingleTokenExactOut {
+            amount_out,
+            max_lp_amount,
+            is_token_a,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a `RampAmp` instruction.
+pub fn ramp_amp(
+    amm_program_id: &Pubkey,
+    pool_pubkey: &Pubkey,
+    fee_recipient_pubkey: &Pubkey,
+    target_amp: u64,
+    ramp_stop_timestamp: i64,
+) -> Instruction {
+    Instruction {
+        program_id: *amm_program_id,
+        accounts: vec![
+            AccountMeta::new(*pool_pubkey, false),
+            AccountMeta::new_readonly(*fee_recipient_pubkey, true),
+        ],
+        data: AmmInstruction::RampAmp {
+            target_amp,
+            ramp_stop_timestamp,
+        }
+        .pack(),
+    }
+}
+
+/// Creates an `InitializeOracle` instruction.
+pub fn initialize_oracle(
+    amm_program_id: &Pubkey,
+    pool_pubkey: &Pubkey,
+    txid: [u8; 32],
+    vout: u32,
+) -> Instruction {
+    let (oracle, _) = get_oracle_address_and_bump_seed(pool_pubkey, amm_program_id);
+
+    Instruction {
+        program_id: *amm_program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*pool_pubkey, false),
+            AccountMeta::new(oracle, false),
+            AccountMeta::new_readonly(Pubkey::system_program(), false),
+        ],
+        data: AmmInstruction::InitializeOracle { txid, vout }.pack(),
+    }
+}
+
+/// Creates a `RecordObservation` instruction.
+pub fn record_observation(amm_program_id: &Pubkey, pool_pubkey: &Pubkey) -> Instruction {
+    let (oracle, _) = get_oracle_address_and_bump_seed(pool_pubkey, amm_program_id);
+
+    Instruction {
+        program_id: *amm_program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*pool_pubkey, false),
+            AccountMeta::new(oracle, false),
+        ],
+        data: AmmInstruction::RecordObservation.pack(),
+    }
+}
+
+/// Accounts of the `Swap` and `SwapExactOut` instructions
+#[allow(clippy::too_many_arguments)]
+fn swap_accounts(
+    amm_program_id: &Pubkey,
+    pool_pubkey: &Pubkey,
+    token_a_vault_pubkey: &Pubkey,
+    token_b_vault_pubkey: &Pubkey,
+    user_input_pubkey: &Pubkey,
+    user_output_pubkey: &Pubkey,
+    user_authority_pubkey: &Pubkey,
+    a_to_b: bool,
+) -> Vec<AccountMeta> {
+    let (pool_authority, _) = get_pool_authority_address_and_bump_seed(pool_pubkey, amm_program_id);
+    let (input_vault_pubkey, output_vault_pubkey) = if a_to_b {
+        (token_a_vault_pubkey, token_b_vault_pubkey)
+    } else {
+        (token_b_vault_pubkey, token_a_vault_pubkey)
+    };
+
+    vec![
+        AccountMeta::new(*pool_pubkey, false),
+        AccountMeta::new(*input_vault_pubkey, false),
+        AccountMeta::new(*output_vault_pubkey, false),
+        AccountMeta::new(*user_input_pubkey, false),
+        AccountMeta::new(*user_output_pubkey, false),
+        AccountMeta::new_readonly(*user_authority_pubkey, true),
+        AccountMeta::new_readonly(pool_authority, false),
+        AccountMeta::new_readonly(apl_token::id(), false),
+    ]
+}
+
+/// Accounts of the `DepositSingleTokenExactIn` and
+/// `WithdrawSingleTokenExactOut` instructions
+#[allow(clippy::too_many_arguments)]
+fn single_token_accounts(
+    amm_program_id: &Pubkey,
+    pool_pubkey: &Pubkey,
+    token_a_vault_pubkey: &Pubkey,
+    token_b_vault_pubkey: &Pubkey,
+    lp_mint_pubkey: &Pubkey,
+    user_token_pubkey: &Pubkey,
+    user_lp_pubkey: &Pubkey,
+    user_authority_pubkey: &Pubkey,
+    is_token_a: bool,
+) -> Vec<AccountMeta> {
+    let (pool_authority, _) = get_pool_authority_address_and_bump_seed(pool_pubkey, amm_program_id);
+    let (vault_pubkey, other_vault_pubkey) = if is_token_a {
+        (token_a_vault_pubkey, token_b_vault_pubkey)
+    } else {
+        (token_b_vault_pubkey, token_a_vault_pubkey)
+    };
+
+    vec![
+        AccountMeta::new(*pool_pubkey, false),
+        AccountMeta::new(*vault_pubkey, false),
+        AccountMeta::new_readonly(*other_vault_pubkey, false),
+        AccountMeta::new(*lp_mint_pubkey, false),
+        AccountMeta::new(*user_token_pubkey, false),
+        AccountMeta::new(*user_lp_pubkey, false),
+        AccountMeta::new_readonly(*user_authority_pubkey, true),
+        AccountMeta::new_readonly(pool_authority, false),
+        AccountMeta::new_readonly(apl_token::id(), false),
+    ]
+}
+
+/// Accounts of the `AddLiquidity` and `RemoveLiquidity` instructions
+#[allow(clippy::too_many_arguments)]
+fn liquidity_accounts(
+    amm_program_id: &Pubkey,
+    pool_pubkey: &Pubkey,
+    token_a_vault_pubkey: &Pubkey,
+    token_b_vault_pubkey: &Pubkey,
+    lp_mint_pubkey: &Pubkey,
+    user_token_a_pubkey: &Pubkey,
+    user_token_b_pubkey: &Pubkey,
+    user_lp_pubkey: &Pubkey,
+    user_authority_pubkey: &Pubkey,
+) -> Vec<AccountMeta> {
+    let (pool_authority, _) = get_pool_authority_address_and_bump_seed(pool_pubkey, amm_program_id);
+
+    vec![
+        AccountMeta::new(*pool_pubkey, false),
+        AccountMeta::new(*token_a_vault_pubkey, false),
+        AccountMeta::new(*token_b_vault_pubkey, false),
+        AccountMeta::new(*lp_mint_pubkey, false),
+        AccountMeta::new(*user_token_a_pubkey, false),
+        AccountMeta::new(*user_token_b_pubkey, false),
+        AccountMeta::new(*user_lp_pubkey, false),
+        AccountMeta::new_readonly(*user_authority_pubkey, true),
+        AccountMeta::new_readonly(pool_authority, false),
+        AccountMeta::new_readonly(apl_token::id(), false),
+    ]
 }
 
 #[cfg(test)]
@@ -206,11 +957,26 @@ mod tests {
         check(&AmmInstruction::InitializePool {
             fee_numerator: 25,
             fee_denominator: 10000,
+            protocol_fee_numerator: 1,
+            protocol_fee_denominator: 6,
+            curve_type: CurveType::ConstantProduct,
+            amp: 0,
+        });
+
+        check(&AmmInstruction::InitializePool {
+            fee_numerator: 1,
+            fee_denominator: 10000,
+            protocol_fee_numerator: 0,
+            protocol_fee_denominator: 1,
+            curve_type: CurveType::StableSwap,
+            amp: 100,
         });
 
         check(&AmmInstruction::AddLiquidity {
-            token_a_amount: 1000,
-            token_b_amount: 2000,
+            amount_a_desired: 1000,
+            amount_b_desired: 2000,
+            amount_a_min: 990,
+            amount_b_min: 1980,
             min_lp_amount: 500,
         });
 
@@ -223,6 +989,113 @@ mod tests {
         check(&AmmInstruction::Swap {
             amount_in: 1000,
             min_amount_out: 900,
+            a_to_b: true,
+        });
+
+        check(&AmmInstruction::Swap {
+            amount_in: 1000,
+            min_amount_out: 900,
+            a_to_b: false,
+        });
+
+        check(&AmmInstruction::SwapExactOut {
+            amount_out: 900,
+            max_amount_in: 1000,
+            a_to_b: true,
+        });
+
+        check(&AmmInstruction::WithdrawProtocolFees);
+
+        check(&AmmInstruction::DepositSingleTokenExactIn {
+            amount_in: 1000,
+            min_lp_amount: 450,
+            is_token_a: true,
+        });
+
+        check(&AmmInstruction::WithdrawSingleTokenExactOut {
+            amount_out: 1000,
+            max_lp_amount: 550,
+            is_token_a: false,
         });
+
+        check(&AmmInstruction::RampAmp {
+            target_amp: 1000,
+            ramp_stop_timestamp: -1,
+        });
+
+        check(&AmmInstruction::InitializeOracle {
+            txid: [7; 32],
+            vout: 3,
+        });
+
+        check(&AmmInstruction::RecordObservation);
+
+        let mut packed = AmmInstruction::Swap {
+            amount_in: 1000,
+            min_amount_out: 900,
+            a_to_b: false,
+        }
+        .pack();
+        *packed.last_mut().unwrap() = 2;
+        assert_eq!(
+            AmmInstruction::unpack(&packed),
+            Err(AmmError::InvalidInstruction.into())
+        );
+
+        let mut packed = AmmInstruction::InitializePool {
+            fee_numerator: 25,
+            fee_denominator: 10000,
+            protocol_fee_numerator: 1,
+            protocol_fee_denominator: 6,
+            curve_type: CurveType::StableSwap,
+            amp: 100,
+        }
+        .pack();
+        packed[9] = 2;
+        assert_eq!(
+            AmmInstruction::unpack(&packed),
+            Err(AmmError::InvalidInstruction.into())
+        );
+    }
+
+    #[test]
+    fn test_instruction_builders() {
+        let program_id = Pubkey::new_unique();
+        let pool = Pubkey::new_unique();
+        let token_a_vault = Pubkey::new_unique();
+        let token_b_vault = Pubkey::new_unique();
+        let (pool_authority, _) = get_pool_authority_address_and_bump_seed(&pool, &program_id);
+
+        for a_to_b in [true, false] {
+            let instruction = swap(
+                &program_id,
+                &pool,
+                &token_a_vault,
+                &token_b_vault,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                1000,
+                900,
+                a_to_b,
+            );
+            let (input_vault, output_vault) = if a_to_b {
+                (token_a_vault, token_b_vault)
+            } else {
+                (token_b_vault, token_a_vault)
+            };
+            assert_eq!(instruction.accounts[1].pubkey, input_vault);
+            assert_eq!(instruction.accounts[2].pubkey, output_vault);
+            assert_eq!(instruction.accounts[6].pubkey, pool_authority);
+            assert_eq!(instruction.accounts[7].pubkey, apl_token::id());
+            assert_eq!(
+                AmmInstruction::unpack(&instruction.data),
+                Ok(AmmInstruction::Swap {
+                    amount_in: 1000,
+                    min_amount_out: 900,
+                    a_to_b,
+                })
+            );
+        }
     }
 }
@@ -3,9 +3,34 @@ pub mod instruction;
 pub mod processor;
 pub mod state;
 
-use arch_program::{account::AccountInfo, entrypoint, entrypoint::ProgramResult, pubkey::Pubkey};
+use arch_program::pubkey::Pubkey;
+#[cfg(not(feature = "no-entrypoint"))]
+use arch_program::{account::AccountInfo, entrypoint, entrypoint::ProgramResult};
 
+/// Seed of the authority owning the vaults and minting the LP tokens of a
+/// pool, derived with the pool address
+pub const POOL_AUTHORITY_SEED: &[u8] = b"pool-authority";
+
+/// Derives the authority of a pool, and its bump seed
+pub fn get_pool_authority_address_and_bump_seed(
+    pool: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[POOL_AUTHORITY_SEED, &pool.serialize()], program_id)
+}
+
+/// Seed of the oracle recording the observations of a pool, derived with the
+/// pool address
+pub const ORACLE_SEED: &[u8] = b"oracle";
+
+/// Derives the oracle of a pool, and its bump seed
+pub fn get_oracle_address_and_bump_seed(pool: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ORACLE_SEED, &pool.serialize()], program_id)
+}
+
+#[cfg(not(feature = "no-entrypoint"))]
 entrypoint!(process_instruction);
+#[cfg(not(feature = "no-entrypoint"))]
 fn process_instruction(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
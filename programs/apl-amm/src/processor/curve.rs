@@ -0,0 +1,224 @@
+//! Curves pricing the swaps and single-sided liquidity of a pool
+
+use {
+    super::math::*,
+    crate::{error::AmmError, state::CurveType},
+    arch_program::program_error::ProgramError,
+};
+
+/// Curve of a pool, with its parameters at the time of the instruction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Curve {
+    /// Constant product invariant `x * y = k`
+    ConstantProduct,
+    /// StableSwap invariant
+    StableSwap {
+        /// Current amplification coefficient
+        amp: u64,
+    },
+}
+
+impl Curve {
+    /// Resolves the curve of a pool, ramping the amplification coefficient
+    /// to `timestamp`
+    pub fn new(
+        curve_type: CurveType,
+        initial_amp: u64,
+        target_amp: u64,
+        ramp_start_timestamp: i64,
+        ramp_stop_timestamp: i64,
+        timestamp: i64,
+    ) -> Self {
+        match curve_type {
+            CurveType::ConstantProduct => Curve::ConstantProduct,
+            CurveType::StableSwap => Curve::StableSwap {
+                amp: calculate_amp(
+                    initial_amp,
+                    target_amp,
+                    ramp_start_timestamp,
+                    ramp_stop_timestamp,
+                    timestamp,
+                ),
+            },
+        }
+    }
+
+    /// Calculates the output amount of a swap
+    pub fn swap_output(
+        &self,
+        amount_in: u64,
+        reserve_in: u64,
+        reserve_out: u64,
+        fee_numerator: u16,
+        fee_denominator: u16,
+    ) -> Result<u64, ProgramError> {
+        match *self {
+            Curve::ConstantProduct => calculate_swap_output(
+                amount_in,
+                reserve_in,
+                reserve_out,
+                fee_numerator,
+                fee_denominator,
+            ),
+            Curve::StableSwap { amp } => calculate_stable_swap_output(
+                amount_in,
+                reserve_in,
+                reserve_out,
+                amp,
+                fee_numerator,
+                fee_denominator,
+            ),
+        }
+    }
+
+    /// Calculates the input amount a swap needs to get at least `amount_out`
+    pub fn swap_input(
+        &self,
+        amount_out: u64,
+        reserve_in: u64,
+        reserve_out: u64,
+        fee_numerator: u16,
+        fee_denominator: u16,
+    ) -> Result<u64, ProgramError> {
+        match *self {
+            Curve::ConstantProduct => calculate_swap_input(
+                amount_out,
+                reserve_in,
+                reserve_out,
+                fee_numerator,
+                fee_denominator,
+            ),
+            Curve::StableSwap { amp } => calculate_stable_swap_input(
+                amount_out,
+                reserve_in,
+                reserve_out,
+                amp,
+                fee_numerator,
+                fee_denominator,
+            ),
+        }
+    }
+
+    /// Calculates the LP tokens of the first deposit, minimum liquidity
+    /// included
+    pub fn initial_lp_amount(
+        &self,
+        token_a_amount: u64,
+        token_b_amount: u64,
+    ) -> Result<u64, ProgramError> {
+        match *self {
+            Curve::ConstantProduct => calculate_initial_lp_amount(token_a_amount, token_b_amount),
+            Curve::StableSwap { amp } => {
+                let invariant = calculate_stable_invariant(amp, token_a_amount, token_b_amount)
+                    .map_err(|_| AmmError::InsufficientLiquidity)?;
+                u64::try_from(invariant).map_err(|_| AmmError::MathOverflow.into())
+            }
+        }
+    }
+
+    /// Calculates the LP tokens minted for a single-sided deposit, and the
+    /// fee charged on it
+    pub fn single_deposit_lp_amount(
+        &self,
+        amount_in: u64,
+        reserve_in: u64,
+        reserve_other: u64,
+        lp_supply: u64,
+        fee_numerator: u16,
+        fee_denominator: u16,
+    ) -> Result<(u64, u64), ProgramError> {
+        match *self {
+            Curve::ConstantProduct => calculate_single_deposit_lp_amount(
+                amount_in,
+                reserve_in,
+                lp_supply,
+                fee_numerator,
+                fee_denominator,
+            ),
+            Curve::StableSwap { amp } => calculate_stable_single_deposit_lp_amount(
+                amount_in,
+                reserve_in,
+                reserve_other,
+                lp_supply,
+                amp,
+                fee_numerator,
+                fee_denominator,
+            ),
+        }
+    }
+
+    /// Calculates the LP tokens burned for a single-sided withdrawal, and the
+    /// fee charged on it
+    pub fn single_withdraw_lp_amount(
+        &self,
+        amount_out: u64,
+        reserve_out: u64,
+        reserve_other: u64,
+        lp_supply: u64,
+        fee_numerator: u16,
+        fee_denominator: u16,
+    ) -> Result<(u64, u64), ProgramError> {
+        match *self {
+            Curve::ConstantProduct => calculate_single_withdraw_lp_amount(
+                amount_out,
+                reserve_out,
+                lp_supply,
+                fee_numerator,
+                fee_denominator,
+            ),
+            Curve::StableSwap { amp } => calculate_stable_single_withdraw_lp_amount(
+                amount_out,
+                reserve_out,
+                reserve_other,
+                lp_supply,
+                amp,
+                fee_numerator,
+                fee_denominator,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_curve_new() {
+        assert_eq!(
+            Curve::new(CurveType::ConstantProduct, 0, 0, 0, 0, 0),
+            Curve::ConstantProduct
+        );
+        assert_eq!(
+            Curve::new(CurveType::StableSwap, 100, 200, 1_000, 2_000, 1_500),
+            Curve::StableSwap { amp: 150 }
+        );
+    }
+
+    #[test]
+    fn test_stable_swap_flatter_than_constant_product() {
+        let constant_product = Curve::ConstantProduct;
+        let stable_swap = Curve::StableSwap { amp: 100 };
+
+        // Near the balanced point, the StableSwap curve pays out close to
+        // one for one
+        let constant_product_out = constant_product
+            .swap_output(10_000, 1_000_000, 1_000_000, 0, 1)
+            .unwrap();
+        let stable_swap_out = stable_swap
+            .swap_output(10_000, 1_000_000, 1_000_000, 0, 1)
+            .unwrap();
+        assert_eq!(constant_product_out, 9_900);
+        assert!(stable_swap_out > 9_990 && stable_swap_out < 10_000);
+
+        // Balanced deposits mint the invariant
+        assert_eq!(
+            stable_swap.initial_lp_amount(1_000_000, 1_000_000).unwrap(),
+            2_000_000
+        );
+        assert_eq!(
+            stable_swap.initial_lp_amount(0, 1_000_000),
+            Err(AmmError::InsufficientLiquidity.into())
+        );
+    }
+}
@@ -3,6 +3,29 @@
 use crate::error::AmmError;
 use arch_program::program_error::ProgramError;
 
+/// LP tokens locked forever on the first deposit, so that the LP supply can
+/// never be brought down to a few units and inflated by donations
+pub const MINIMUM_LIQUIDITY: u64 = 1000;
+
+/// Lowest amplification coefficient of a StableSwap pool
+pub const MIN_AMP: u64 = 1;
+
+/// Highest amplification coefficient of a StableSwap pool
+pub const MAX_AMP: u64 = 1_000_000;
+
+/// Shortest amplification ramp, and shortest time between the starts of two
+/// ramps, in seconds
+pub const MIN_RAMP_DURATION: i64 = 86_400;
+
+/// Fractional bits of the fixed-point prices accumulated by the pools
+pub const PRICE_FRACTIONAL_BITS: u32 = 64;
+
+/// Largest factor by which a single ramp may change the amplification
+pub const MAX_AMP_CHANGE: u64 = 10;
+
+/// Newton iterations after which a StableSwap calculation gives up
+const MAX_ITERATIONS: usize = 255;
+
 /// Calculates the output amount for a swap using constant product formula
 pub fn calculate_swap_output(
     amount_in: u64,
@@ -45,6 +68,441 @@ pub fn calculate_swap_output(
     Ok(amount_out as u64)
 }
 
+/// Calculates the fee charged on a swap input, as deducted by
+/// `calculate_swap_output`, and the protocol share of that fee
+pub fn calculate_fees(
+    amount_in: u64,
+    fee_numerator: u16,
+    fee_denominator: u16,
+    protocol_fee_numerator: u16,
+    protocol_fee_denominator: u16,
+) -> Result<(u64, u64), ProgramError> {
+    let fee = (amount_in as u128)
+        .checked_mul(fee_numerator as u128)
+        .ok_or(AmmError::MathOverflow)?
+        .checked_div(fee_denominator as u128)
+        .ok_or(AmmError::MathOverflow)?;
+
+    // At most amount_in as long as the fee fraction is at most one
+    let fee = fee as u64;
+    let protocol_fee =
+        calculate_protocol_fee(fee, protocol_fee_numerator, protocol_fee_denominator)?;
+
+    Ok((fee, protocol_fee))
+}
+
+/// Calculates the protocol share of a fee
+pub fn calculate_protocol_fee(
+    fee: u64,
+    protocol_fee_numerator: u16,
+    protocol_fee_denominator: u16,
+) -> Result<u64, ProgramError> {
+    let protocol_fee = (fee as u128)
+        .checked_mul(protocol_fee_numerator as u128)
+        .ok_or(AmmError::MathOverflow)?
+        .checked_div(protocol_fee_denominator as u128)
+        .ok_or(AmmError::MathOverflow)?;
+
+    // At most the fee as long as the protocol fraction is at most one
+    Ok(protocol_fee as u64)
+}
+
+/// Calculates the input amount a swap needs to get at least `amount_out`,
+/// inverting `calculate_swap_output` and rounding up in favour of the pool
+pub fn calculate_swap_input(
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    fee_numerator: u16,
+    fee_denominator: u16,
+) -> Result<u64, ProgramError> {
+    if amount_out >= reserve_out {
+        return Err(AmmError::InsufficientLiquidity.into());
+    }
+    if fee_numerator >= fee_denominator {
+        return Err(AmmError::InvalidFeeConfig.into());
+    }
+
+    // Smallest fee-adjusted input with y * dx / (x + dx) >= dy, that is
+    // dx = ceil(x * dy / (y - dy))
+    let amount_in_after_fee = (reserve_in as u128)
+        .checked_mul(amount_out as u128)
+        .ok_or(AmmError::MathOverflow)?
+        .div_ceil(reserve_out as u128 - amount_out as u128);
+
+    // Gross the input up by the fee, rounding up
+    let amount_in = amount_in_after_fee
+        .checked_mul(fee_denominator as u128)
+        .ok_or(AmmError::MathOverflow)?
+        .div_ceil((fee_denominator - fee_numerator) as u128);
+
+    u64::try_from(amount_in).map_err(|_| AmmError::MathOverflow.into())
+}
+
+/// Calculates the largest pair of deposit amounts, up to the desired amounts,
+/// at the ratio of the reserves
+pub fn calculate_deposit_amounts(
+    amount_a_desired: u64,
+    amount_b_desired: u64,
+    amount_a_min: u64,
+    amount_b_min: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+) -> Result<(u64, u64), ProgramError> {
+    if reserve_a == 0 || reserve_b == 0 {
+        return Err(AmmError::InsufficientLiquidity.into());
+    }
+
+    // Deposit all of token A if the matching token B amount is available
+    let amount_b_optimal = quote(amount_a_desired, reserve_a, reserve_b);
+    if amount_b_optimal <= amount_b_desired as u128 {
+        if amount_b_optimal < amount_b_min as u128 {
+            return Err(AmmError::SlippageExceeded.into());
+        }
+        return Ok((amount_a_desired, amount_b_optimal as u64));
+    }
+
+    // Otherwise deposit all of token B, which then needs less than the
+    // desired token A amount
+    let amount_a_optimal = quote(amount_b_desired, reserve_b, reserve_a);
+    if amount_a_optimal < amount_a_min as u128 {
+        return Err(AmmError::SlippageExceeded.into());
+    }
+    Ok((amount_a_optimal as u64, amount_b_desired))
+}
+
+/// Calculates the LP tokens minted for depositing `amount_in` of a single
+/// token, and the fee charged on it.
+///
+/// The deposit is equivalent to swapping half of it for the other token, then
+/// depositing both at the reserve ratio, so the fee is charged on the swapped
+/// half only. The pool grows by `sqrt((reserve + amount_in - fee) / reserve)`,
+/// and so does the LP supply, rounding down.
+pub fn calculate_single_deposit_lp_amount(
+    amount_in: u64,
+    reserve_in: u64,
+    lp_supply: u64,
+    fee_numerator: u16,
+    fee_denominator: u16,
+) -> Result<(u64, u64), ProgramError> {
+    if reserve_in == 0 || lp_supply == 0 {
+        return Err(AmmError::InsufficientLiquidity.into());
+    }
+    if fee_numerator >= fee_denominator {
+        return Err(AmmError::InvalidFeeConfig.into());
+    }
+
+    let fee = calculate_single_deposit_fee(amount_in, fee_numerator, fee_denominator);
+    let new_reserve = reserve_in
+        .checked_add(amount_in - fee)
+        .ok_or(AmmError::MathOverflow)?;
+    let new_lp_supply = scale_by_sqrt_ratio(lp_supply, new_reserve, reserve_in);
+
+    let lp_amount = new_lp_supply - lp_supply as u128;
+    let lp_amount = u64::try_from(lp_amount).map_err(|_| AmmError::MathOverflow)?;
+
+    Ok((lp_amount, fee))
+}
+
+/// Calculates the LP tokens burned for withdrawing exactly `amount_out` of a
+/// single token, and the fee charged on it.
+///
+/// The withdrawal is equivalent to withdrawing both tokens at the reserve
+/// ratio, then swapping the other token for this one, so the fee is charged
+/// on the swapped half only, on top of `amount_out`. The pool shrinks by
+/// `sqrt((reserve - amount_out - fee) / reserve)`, and so does the LP supply,
+/// rounding the burned amount up.
+pub fn calculate_single_withdraw_lp_amount(
+    amount_out: u64,
+    reserve_out: u64,
+    lp_supply: u64,
+    fee_numerator: u16,
+    fee_denominator: u16,
+) -> Result<(u64, u64), ProgramError> {
+    if reserve_out == 0 || lp_supply == 0 {
+        return Err(AmmError::InsufficientLiquidity.into());
+    }
+    if fee_numerator >= fee_denominator {
+        return Err(AmmError::InvalidFeeConfig.into());
+    }
+
+    let fee = calculate_single_withdraw_fee(amount_out, fee_numerator, fee_denominator);
+    let amount_removed = amount_out.checked_add(fee).ok_or(AmmError::MathOverflow)?;
+    if amount_removed >= reserve_out {
+        return Err(AmmError::InsufficientLiquidity.into());
+    }
+    let new_lp_supply = scale_by_sqrt_ratio(lp_supply, reserve_out - amount_removed, reserve_out);
+
+    // Never more than the supply as the ratio is below one
+    Ok((lp_supply - new_lp_supply as u64, fee))
+}
+
+/// Calculates the fee of a single-sided deposit, charged on the swapped half
+/// and rounding up
+fn calculate_single_deposit_fee(amount_in: u64, fee_numerator: u16, fee_denominator: u16) -> u64 {
+    let swapped = amount_in - amount_in / 2;
+    (swapped as u128 * fee_numerator as u128).div_ceil(fee_denominator as u128) as u64
+}
+
+/// Calculates the fee of a single-sided withdrawal, grossing up the swapped
+/// half and rounding up as for swaps out. The fee must be below one.
+fn calculate_single_withdraw_fee(amount_out: u64, fee_numerator: u16, fee_denominator: u16) -> u64 {
+    let swapped = amount_out - amount_out / 2;
+    (swapped as u128 * fee_numerator as u128).div_ceil((fee_denominator - fee_numerator) as u128)
+        as u64
+}
+
+/// Calculates the amplification coefficient at `timestamp`, interpolated
+/// linearly while ramping from `initial_amp` to `target_amp`
+pub fn calculate_amp(
+    initial_amp: u64,
+    target_amp: u64,
+    ramp_start_timestamp: i64,
+    ramp_stop_timestamp: i64,
+    timestamp: i64,
+) -> u64 {
+    if timestamp >= ramp_stop_timestamp {
+        return target_amp;
+    }
+    if timestamp <= ramp_start_timestamp {
+        return initial_amp;
+    }
+
+    let elapsed = (timestamp as i128 - ramp_start_timestamp as i128) as u128;
+    let duration = (ramp_stop_timestamp as i128 - ramp_start_timestamp as i128) as u128;
+    // Less than the difference of the amplifications as elapsed < duration
+    let change = |difference: u64| (difference as u128 * elapsed / duration) as u64;
+    if target_amp > initial_amp {
+        initial_amp + change(target_amp - initial_amp)
+    } else {
+        initial_amp - change(initial_amp - target_amp)
+    }
+}
+
+/// Calculates the StableSwap invariant `D` of the reserves, solving
+/// `4A(x + y) + D = 4AD + D^3 / 4xy` with Newton's method
+pub fn calculate_stable_invariant(
+    amp: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+) -> Result<u128, ProgramError> {
+    if reserve_a == 0 || reserve_b == 0 {
+        return Err(AmmError::InsufficientLiquidity.into());
+    }
+
+    let leverage = amp as u128 * 4;
+    let sum = reserve_a as u128 + reserve_b as u128;
+    let (mut invariant, mut before_previous) = (sum, 0);
+    for _ in 0..MAX_ITERATIONS {
+        // D^3 / 4xy
+        let product = mul_div3(invariant, reserve_b as u128 * 2, reserve_a as u128 * 2)?;
+
+        // D = (4A(x + y) + 2 D^3 / 4xy) D / ((4A - 1) D + 3 D^3 / 4xy)
+        let numerator = leverage
+            .checked_mul(sum)
+            .and_then(|value| value.checked_add(product.checked_mul(2)?))
+            .ok_or(AmmError::MathOverflow)?;
+        let denominator = (leverage - 1)
+            .checked_mul(invariant)
+            .and_then(|value| value.checked_add(product.checked_mul(3)?))
+            .ok_or(AmmError::MathOverflow)?;
+        let previous = invariant;
+        invariant = mul_div(numerator, invariant, denominator).ok_or(AmmError::MathOverflow)?;
+
+        // The rounding may also have the iteration alternate between two
+        // neighbouring values
+        if invariant.abs_diff(previous) <= 1 {
+            return Ok(invariant);
+        }
+        if invariant == before_previous {
+            return Ok(invariant.min(previous));
+        }
+        before_previous = previous;
+    }
+
+    Err(AmmError::ConvergenceFailure.into())
+}
+
+/// Calculates the reserve of one token keeping the StableSwap invariant given
+/// the reserve `x` of the other, solving `y^2 + (x + D / 4A - D) y = D^3 / 16Ax`
+/// with Newton's method
+fn calculate_stable_reserve(
+    amp: u64,
+    reserve_other: u128,
+    invariant: u128,
+) -> Result<u128, ProgramError> {
+    if reserve_other == 0 {
+        return Err(AmmError::InsufficientLiquidity.into());
+    }
+
+    let leverage = amp as u128 * 4;
+    let c = mul_div3(invariant, reserve_other * 2, leverage * 2)?;
+    let b = reserve_other + invariant / leverage;
+    let (mut reserve, mut before_previous) = (invariant, 0);
+    for _ in 0..MAX_ITERATIONS {
+        // y = (y^2 + c) / (2y + b - D), the numerator not fitting in a u128
+        let denominator = (reserve * 2 + b)
+            .checked_sub(invariant)
+            .filter(|denominator| *denominator > 0)
+            .ok_or(AmmError::ConvergenceFailure)?;
+        let (quotient, remainder) =
+            mul_div_rem(reserve, reserve, denominator).ok_or(AmmError::MathOverflow)?;
+        let previous = reserve;
+        reserve = quotient + c / denominator + (remainder + c % denominator) / denominator;
+
+        if reserve.abs_diff(previous) <= 1 {
+            return Ok(reserve);
+        }
+        if reserve == before_previous {
+            return Ok(reserve.max(previous));
+        }
+        before_previous = previous;
+    }
+
+    Err(AmmError::ConvergenceFailure.into())
+}
+
+/// Calculates the output amount for a swap on the StableSwap curve, with
+/// the fee deducted from the input as by `calculate_swap_output`
+pub fn calculate_stable_swap_output(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    amp: u64,
+    fee_numerator: u16,
+    fee_denominator: u16,
+) -> Result<u64, ProgramError> {
+    let fee_amount = (amount_in as u128)
+        .checked_mul(fee_numerator as u128)
+        .ok_or(AmmError::MathOverflow)?
+        .checked_div(fee_denominator as u128)
+        .ok_or(AmmError::MathOverflow)?;
+    let amount_in_after_fee = (amount_in as u128)
+        .checked_sub(fee_amount)
+        .ok_or(AmmError::MathOverflow)?;
+
+    let invariant = calculate_stable_invariant(amp, reserve_in, reserve_out)?;
+    let new_reserve_out =
+        calculate_stable_reserve(amp, reserve_in as u128 + amount_in_after_fee, invariant)?;
+
+    // One less in favour of the pool, for the rounding of the reserve
+    Ok((reserve_out as u128)
+        .saturating_sub(new_reserve_out)
+        .saturating_sub(1) as u64)
+}
+
+/// Calculates the input amount a swap on the StableSwap curve needs to get at
+/// least `amount_out`, inverting `calculate_stable_swap_output` and rounding
+/// up in favour of the pool
+pub fn calculate_stable_swap_input(
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    amp: u64,
+    fee_numerator: u16,
+    fee_denominator: u16,
+) -> Result<u64, ProgramError> {
+    if amount_out >= reserve_out {
+        return Err(AmmError::InsufficientLiquidity.into());
+    }
+    if fee_numerator >= fee_denominator {
+        return Err(AmmError::InvalidFeeConfig.into());
+    }
+    if amount_out == 0 {
+        return Ok(0);
+    }
+
+    // Input reserve keeping the invariant with one more token out, matching
+    // the rounding of the output
+    let invariant = calculate_stable_invariant(amp, reserve_in, reserve_out)?;
+    let new_reserve_in =
+        calculate_stable_reserve(amp, (reserve_out - amount_out - 1) as u128, invariant)?;
+    let amount_in_after_fee = new_reserve_in.saturating_sub(reserve_in as u128) + 1;
+
+    // Gross the input up by the fee, rounding up
+    let amount_in = amount_in_after_fee
+        .checked_mul(fee_denominator as u128)
+        .ok_or(AmmError::MathOverflow)?
+        .div_ceil((fee_denominator - fee_numerator) as u128);
+
+    u64::try_from(amount_in).map_err(|_| AmmError::MathOverflow.into())
+}
+
+/// Calculates the LP tokens minted for depositing `amount_in` of a single
+/// token into a StableSwap pool, and the fee charged on it, as by
+/// `calculate_single_deposit_lp_amount`. The LP supply grows with the
+/// invariant, rounding down.
+pub fn calculate_stable_single_deposit_lp_amount(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_other: u64,
+    lp_supply: u64,
+    amp: u64,
+    fee_numerator: u16,
+    fee_denominator: u16,
+) -> Result<(u64, u64), ProgramError> {
+    if lp_supply == 0 {
+        return Err(AmmError::InsufficientLiquidity.into());
+    }
+    if fee_numerator >= fee_denominator {
+        return Err(AmmError::InvalidFeeConfig.into());
+    }
+
+    let fee = calculate_single_deposit_fee(amount_in, fee_numerator, fee_denominator);
+    let new_reserve = reserve_in
+        .checked_add(amount_in - fee)
+        .ok_or(AmmError::MathOverflow)?;
+    let invariant = calculate_stable_invariant(amp, reserve_in, reserve_other)?;
+    let new_invariant = calculate_stable_invariant(amp, new_reserve, reserve_other)?;
+
+    // One less in favour of the pool, for the rounding of the invariants
+    let growth = new_invariant.saturating_sub(invariant + 1);
+    let lp_amount = mul_div(lp_supply as u128, growth, invariant).ok_or(AmmError::MathOverflow)?;
+    let lp_amount = u64::try_from(lp_amount).map_err(|_| AmmError::MathOverflow)?;
+
+    Ok((lp_amount, fee))
+}
+
+/// Calculates the LP tokens burned for withdrawing exactly `amount_out` of a
+/// single token from a StableSwap pool, and the fee charged on it, as by
+/// `calculate_single_withdraw_lp_amount`. The LP supply shrinks with the
+/// invariant, rounding the burned amount up.
+pub fn calculate_stable_single_withdraw_lp_amount(
+    amount_out: u64,
+    reserve_out: u64,
+    reserve_other: u64,
+    lp_supply: u64,
+    amp: u64,
+    fee_numerator: u16,
+    fee_denominator: u16,
+) -> Result<(u64, u64), ProgramError> {
+    if lp_supply == 0 {
+        return Err(AmmError::InsufficientLiquidity.into());
+    }
+    if fee_numerator >= fee_denominator {
+        return Err(AmmError::InvalidFeeConfig.into());
+    }
+
+    let fee = calculate_single_withdraw_fee(amount_out, fee_numerator, fee_denominator);
+    let amount_removed = amount_out.checked_add(fee).ok_or(AmmError::MathOverflow)?;
+    if amount_removed >= reserve_out {
+        return Err(AmmError::InsufficientLiquidity.into());
+    }
+    let invariant = calculate_stable_invariant(amp, reserve_out, reserve_other)?;
+    let new_invariant =
+        calculate_stable_invariant(amp, reserve_out - amount_removed, reserve_other)?;
+
+    // One more in favour of the pool, for the rounding of the invariants
+    let shrinkage = (invariant + 1).saturating_sub(new_invariant);
+    let lp_amount =
+        mul_div_ceil(lp_supply as u128, shrinkage, invariant).ok_or(AmmError::MathOverflow)?;
+    if lp_amount > lp_supply as u128 {
+        return Err(AmmError::InsufficientLiquidity.into());
+    }
+
+    Ok((lp_amount as u64, fee))
+}
+
 /// Calculates the minimum LP token amount for initial deposit
 pub fn calculate_initial_lp_amount(
     token_a_amount: u64,
@@ -95,6 +553,137 @@ pub fn calculate_lp_amount(
     Ok(lp_amount as u64)
 }
 
+/// Accumulates the prices of token A in token B and of token B in token A at
+/// the given reserves over the elapsed seconds. The prices are Q64.64
+/// fixed-point numbers, and the sums wrap around on overflow since only
+/// their differences are meaningful. Nothing accrues while a reserve is
+/// empty.
+pub fn calculate_cumulative_prices(
+    price_a_cumulative: u128,
+    price_b_cumulative: u128,
+    reserve_a: u64,
+    reserve_b: u64,
+    elapsed: i64,
+) -> (u128, u128) {
+    if elapsed <= 0 || reserve_a == 0 || reserve_b == 0 {
+        return (price_a_cumulative, price_b_cumulative);
+    }
+    // Cannot overflow, a u64 shifted by 64 bits fits in a u128
+    let price_a = ((reserve_b as u128) << PRICE_FRACTIONAL_BITS) / reserve_a as u128;
+    let price_b = ((reserve_a as u128) << PRICE_FRACTIONAL_BITS) / reserve_b as u128;
+    (
+        price_a_cumulative.wrapping_add(price_a.wrapping_mul(elapsed as u128)),
+        price_b_cumulative.wrapping_add(price_b.wrapping_mul(elapsed as u128)),
+    )
+}
+
+/// Calculates the time-weighted average price between two observations of a
+/// cumulative price, as a Q64.64 fixed-point number
+pub fn calculate_twap(
+    price_cumulative_start: u128,
+    price_cumulative_end: u128,
+    timestamp_start: i64,
+    timestamp_end: i64,
+) -> Result<u128, ProgramError> {
+    let elapsed = timestamp_end
+        .checked_sub(timestamp_start)
+        .filter(|elapsed| *elapsed > 0)
+        .ok_or(AmmError::InvalidObservation)?;
+    Ok(price_cumulative_end.wrapping_sub(price_cumulative_start) / elapsed as u128)
+}
+
+/// Converts an amount of one token to the other at the ratio of the reserves,
+/// rounding down. `reserve_from` must not be zero.
+fn quote(amount: u64, reserve_from: u64, reserve_to: u64) -> u128 {
+    // Cannot overflow, the product of two u64 fits in a u128
+    amount as u128 * reserve_to as u128 / reserve_from as u128
+}
+
+/// Scales `amount` by `sqrt(numerator / denominator)`, rounding down.
+///
+/// Computed as `amount * sqrt(numerator * denominator) / denominator` so that
+/// the products fit in a `u128`, which loses less than `amount / denominator`
+/// to the integer square root. `denominator` must not be zero.
+fn scale_by_sqrt_ratio(amount: u64, numerator: u64, denominator: u64) -> u128 {
+    let sqrt_product = integer_sqrt(numerator as u128 * denominator as u128);
+    amount as u128 * sqrt_product / denominator as u128
+}
+
+/// Calculates `value^3 / (first_divisor * second_divisor)`, rounding down
+/// the cube divided by `first_divisor`, then the rest, so that the result is
+/// at most one less than exact
+fn mul_div3(value: u128, first_divisor: u128, second_divisor: u128) -> Result<u128, ProgramError> {
+    // value^2 = quotient * first_divisor + remainder
+    let (quotient, remainder) =
+        mul_div_rem(value, value, first_divisor).ok_or(AmmError::MathOverflow)?;
+    let whole = mul_div(quotient, value, second_divisor).ok_or(AmmError::MathOverflow)?;
+    let rest =
+        mul_div(remainder, value, second_divisor).ok_or(AmmError::MathOverflow)? / first_divisor;
+
+    whole
+        .checked_add(rest)
+        .ok_or_else(|| AmmError::MathOverflow.into())
+}
+
+/// Calculates `a * b / denominator`, rounding down, through a 256-bit
+/// product so that only the result has to fit in a `u128`
+fn mul_div(a: u128, b: u128, denominator: u128) -> Option<u128> {
+    mul_div_rem(a, b, denominator).map(|(quotient, _)| quotient)
+}
+
+/// Calculates `a * b / denominator`, rounding up, as `mul_div`
+fn mul_div_ceil(a: u128, b: u128, denominator: u128) -> Option<u128> {
+    let (quotient, remainder) = mul_div_rem(a, b, denominator)?;
+    if remainder > 0 {
+        quotient.checked_add(1)
+    } else {
+        Some(quotient)
+    }
+}
+
+/// Calculates the quotient and remainder of `a * b / denominator` through a
+/// 256-bit product, or `None` if the denominator is zero or the quotient
+/// does not fit in a `u128`
+fn mul_div_rem(a: u128, b: u128, denominator: u128) -> Option<(u128, u128)> {
+    if denominator == 0 {
+        return None;
+    }
+
+    // Schoolbook multiplication of the 64-bit halves
+    const LOW_BITS: u128 = u64::MAX as u128;
+    let (a_high, a_low) = (a >> 64, a & LOW_BITS);
+    let (b_high, b_low) = (b >> 64, b & LOW_BITS);
+    let low_low = a_low * b_low;
+    let low_high = a_low * b_high;
+    let high_low = a_high * b_low;
+    let middle = (low_low >> 64) + (low_high & LOW_BITS) + (high_low & LOW_BITS);
+    let low = (low_low & LOW_BITS) | (middle << 64);
+    let high = a_high * b_high + (low_high >> 64) + (high_low >> 64) + (middle >> 64);
+
+    if high == 0 {
+        return Some((low / denominator, low % denominator));
+    }
+    if high >= denominator {
+        return None;
+    }
+
+    // Long division of the low half, the remainder staying below the
+    // denominator but possibly overflowing when shifted
+    let mut remainder = high;
+    let mut quotient = 0;
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= denominator {
+            remainder = remainder.wrapping_sub(denominator);
+            quotient |= 1;
+        }
+    }
+
+    Some((quotient, remainder))
+}
+
 /// Integer square root using the Babylonian method
 fn integer_sqrt(value: u128) -> u128 {
     if value < 2 {
@@ -137,6 +726,521 @@ mod tests {
         assert!(amount_out > 900_000); // ~10% less due to fees and slippage
     }
 
+    #[test]
+    fn test_calculate_swap_input() {
+        // Inverts the basic swap above
+        let amount_in = calculate_swap_input(90, 1000, 1000, 25, 10000).unwrap();
+        assert_eq!(amount_in, 100);
+        assert_eq!(
+            calculate_swap_output(amount_in, 1000, 1000, 25, 10000).unwrap(),
+            90
+        );
+
+        assert_eq!(calculate_swap_input(0, 1000, 1000, 25, 10000).unwrap(), 0);
+        assert_eq!(
+            calculate_swap_input(1000, 1000, 1000, 25, 10000),
+            Err(AmmError::InsufficientLiquidity.into())
+        );
+        assert_eq!(
+            calculate_swap_input(u64::MAX - 1, u64::MAX, u64::MAX, 25, 10000),
+            Err(AmmError::MathOverflow.into())
+        );
+    }
+
+    #[test]
+    fn test_calculate_swap_input_never_undercharges() {
+        // Deterministic xorshift, so that failures can be replayed
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = |bound: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % bound
+        };
+
+        for _ in 0..10_000 {
+            let reserve_in_bits = 1 + next(48);
+            let reserve_in = 1 + next(1 << reserve_in_bits);
+            let reserve_out_bits = 1 + next(48);
+            let reserve_out = 2 + next(1 << reserve_out_bits);
+            let amount_out = 1 + next(reserve_out - 1);
+            let fee_denominator = 1 + next(10_000) as u16;
+            let fee_numerator = next(fee_denominator as u64) as u16;
+
+            let amount_in = match calculate_swap_input(
+                amount_out,
+                reserve_in,
+                reserve_out,
+                fee_numerator,
+                fee_denominator,
+            ) {
+                Ok(amount_in) => amount_in,
+                Err(error) => {
+                    assert_eq!(error, AmmError::MathOverflow.into());
+                    continue;
+                }
+            };
+            let paid_out = calculate_swap_output(
+                amount_in,
+                reserve_in,
+                reserve_out,
+                fee_numerator,
+                fee_denominator,
+            )
+            .unwrap();
+            assert!(
+                paid_out >= amount_out,
+                "{amount_in} in for {paid_out} < {amount_out} out of {reserve_in}/{reserve_out} at {fee_numerator}/{fee_denominator}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_calculate_fees() {
+        assert_eq!(calculate_fees(100_000, 25, 10000, 1, 6).unwrap(), (250, 41));
+        assert_eq!(calculate_fees(100_000, 25, 10000, 0, 1).unwrap(), (250, 0));
+        assert_eq!(calculate_fees(39, 25, 10000, 1, 6).unwrap(), (0, 0));
+        assert_eq!(
+            calculate_fees(u64::MAX, 9999, 10000, 1, 1).unwrap(),
+            (
+                u64::MAX - u64::MAX / 10000 - 1,
+                u64::MAX - u64::MAX / 10000 - 1
+            )
+        );
+
+        // Matches the fee deducted from the swap input
+        let (fee, _) = calculate_fees(100, 25, 10000, 1, 6).unwrap();
+        assert_eq!(
+            calculate_swap_output(100, 1000, 1000, 25, 10000).unwrap(),
+            calculate_swap_output(100 - fee, 1000, 1000, 0, 10000).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_calculate_deposit_amounts() {
+        // Token A limits the deposit
+        assert_eq!(
+            calculate_deposit_amounts(100, 500, 0, 0, 1000, 2000).unwrap(),
+            (100, 200)
+        );
+
+        // Token B limits the deposit
+        assert_eq!(
+            calculate_deposit_amounts(500, 100, 0, 0, 1000, 2000).unwrap(),
+            (50, 100)
+        );
+
+        // Exact ratio deposits everything
+        assert_eq!(
+            calculate_deposit_amounts(100, 200, 100, 200, 1000, 2000).unwrap(),
+            (100, 200)
+        );
+
+        // Rounds down in favour of the pool
+        assert_eq!(
+            calculate_deposit_amounts(10, 10, 0, 0, 3, 2).unwrap(),
+            (10, 6)
+        );
+
+        // Minimum amounts protect against ratio moves
+        assert_eq!(
+            calculate_deposit_amounts(100, 500, 0, 201, 1000, 2000),
+            Err(AmmError::SlippageExceeded.into())
+        );
+        assert_eq!(
+            calculate_deposit_amounts(500, 100, 51, 0, 1000, 2000),
+            Err(AmmError::SlippageExceeded.into())
+        );
+
+        assert_eq!(
+            calculate_deposit_amounts(100, 100, 0, 0, 0, 1000),
+            Err(AmmError::InsufficientLiquidity.into())
+        );
+        assert_eq!(
+            calculate_deposit_amounts(u64::MAX, u64::MAX, 0, 0, 1, u64::MAX),
+            Ok((1, u64::MAX))
+        );
+    }
+
+    /// Exact LP amount of a single-sided deposit: the largest amount with
+    /// `(supply + lp)^2 * reserve <= supply^2 * (reserve + amount)`, found by
+    /// bisection. Only for amounts small enough not to overflow.
+    fn reference_single_deposit_lp_amount(amount: u64, reserve: u64, supply: u64) -> u64 {
+        let fits = |lp: u64| {
+            let new_supply = (supply + lp) as u128;
+            new_supply * new_supply * reserve as u128
+                <= supply as u128 * supply as u128 * (reserve + amount) as u128
+        };
+        let (mut low, mut high) = (0, supply);
+        while fits(high) {
+            (low, high) = (high, high * 2);
+        }
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            if fits(mid) {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+
+    /// Exact LP amount of a single-sided withdrawal: the smallest amount with
+    /// `(supply - lp)^2 * reserve <= supply^2 * (reserve - amount)`, found by
+    /// bisection. Only for amounts small enough not to overflow.
+    fn reference_single_withdraw_lp_amount(amount: u64, reserve: u64, supply: u64) -> u64 {
+        let fits = |lp: u64| {
+            let new_supply = (supply - lp) as u128;
+            new_supply * new_supply * reserve as u128
+                <= supply as u128 * supply as u128 * (reserve - amount) as u128
+        };
+        let (mut low, mut high) = (0, supply);
+        if fits(low) {
+            return low;
+        }
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            if fits(mid) {
+                high = mid;
+            } else {
+                low = mid;
+            }
+        }
+        high
+    }
+
+    #[test]
+    fn test_calculate_single_deposit_lp_amount() {
+        // Quadrupling the reserve doubles the supply, the fee being charged
+        // on the swapped half only
+        assert_eq!(
+            calculate_single_deposit_lp_amount(3000, 1000, 500, 0, 10000).unwrap(),
+            (500, 0)
+        );
+        assert_eq!(
+            calculate_single_deposit_lp_amount(3000, 1000, 500, 25, 10000).unwrap(),
+            (499, 4)
+        );
+        assert_eq!(
+            calculate_single_deposit_lp_amount(0, 1000, 500, 25, 10000).unwrap(),
+            (0, 0)
+        );
+        assert_eq!(
+            calculate_single_deposit_lp_amount(1000, 0, 0, 25, 10000),
+            Err(AmmError::InsufficientLiquidity.into())
+        );
+        assert_eq!(
+            calculate_single_deposit_lp_amount(u64::MAX, u64::MAX / 2, 1, 25, 10000),
+            Err(AmmError::MathOverflow.into())
+        );
+
+        // Never more than the exact amount, and less by at most the LP value
+        // of a token unit
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = |bound: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % bound
+        };
+
+        for _ in 0..10_000 {
+            let reserve_bits = 1 + next(40);
+            let reserve = 1 + next(1 << reserve_bits);
+            let supply_bits = 1 + next(40);
+            let supply = 1 + next(1 << supply_bits);
+            let amount_bits = 1 + next(40);
+            let amount = next(1 << amount_bits);
+
+            let (lp_amount, fee) =
+                calculate_single_deposit_lp_amount(amount, reserve, supply, 30, 10000).unwrap();
+            assert_eq!(fee, ((amount - amount / 2) * 30).div_ceil(10000));
+
+            let exact = reference_single_deposit_lp_amount(amount - fee, reserve, supply);
+            assert!(
+                lp_amount <= exact && exact - lp_amount <= supply / reserve + 1,
+                "{lp_amount} instead of {exact} for {amount} into {reserve} with supply {supply}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_calculate_single_withdraw_lp_amount() {
+        // Quartering the reserve halves the supply
+        assert_eq!(
+            calculate_single_withdraw_lp_amount(750, 1000, 500, 0, 10000).unwrap(),
+            (250, 0)
+        );
+        assert_eq!(
+            calculate_single_withdraw_lp_amount(750, 1000, 500, 25, 10000).unwrap(),
+            (251, 1)
+        );
+        assert_eq!(
+            calculate_single_withdraw_lp_amount(0, 1000, 500, 25, 10000).unwrap(),
+            (0, 0)
+        );
+        assert_eq!(
+            calculate_single_withdraw_lp_amount(1000, 1000, 500, 25, 10000),
+            Err(AmmError::InsufficientLiquidity.into())
+        );
+        assert_eq!(
+            calculate_single_withdraw_lp_amount(998, 1000, 500, 25, 10000),
+            Err(AmmError::InsufficientLiquidity.into())
+        );
+
+        // Never less than the exact amount, and more by at most the LP value
+        // of a token unit
+        let mut state = 0xd1b5_4a32_d192_ed03u64;
+        let mut next = |bound: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % bound
+        };
+
+        for _ in 0..10_000 {
+            let reserve_bits = 1 + next(40);
+            let reserve = 2 + next(1 << reserve_bits);
+            let supply_bits = 1 + next(40);
+            let supply = 1 + next(1 << supply_bits);
+            let amount = next(reserve / 2);
+
+            let (lp_amount, fee) =
+                calculate_single_withdraw_lp_amount(amount, reserve, supply, 30, 10000).unwrap();
+            assert_eq!(fee, ((amount - amount / 2) * 30).div_ceil(9970));
+
+            let exact = reference_single_withdraw_lp_amount(amount + fee, reserve, supply);
+            assert!(
+                lp_amount >= exact && lp_amount - exact <= supply / reserve + 1,
+                "{lp_amount} instead of {exact} for {amount} out of {reserve} with supply {supply}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_calculate_amp() {
+        // Ramping up and down, linearly
+        assert_eq!(calculate_amp(100, 200, 1_000, 2_000, 0), 100);
+        assert_eq!(calculate_amp(100, 200, 1_000, 2_000, 1_000), 100);
+        assert_eq!(calculate_amp(100, 200, 1_000, 2_000, 1_250), 125);
+        assert_eq!(calculate_amp(100, 200, 1_000, 2_000, 1_999), 199);
+        assert_eq!(calculate_amp(100, 200, 1_000, 2_000, 2_000), 200);
+        assert_eq!(calculate_amp(200, 100, 1_000, 2_000, 1_250), 175);
+        assert_eq!(calculate_amp(200, 100, 1_000, 2_000, i64::MAX), 100);
+
+        // No ramp
+        assert_eq!(calculate_amp(100, 100, 0, 0, 0), 100);
+        assert_eq!(
+            calculate_amp(MIN_AMP, MAX_AMP, i64::MIN, i64::MAX, 0),
+            MIN_AMP + (MAX_AMP - MIN_AMP) / 2
+        );
+    }
+
+    #[test]
+    fn test_mul_div() {
+        assert_eq!(mul_div(6, 7, 4), Some(10));
+        assert_eq!(mul_div_ceil(6, 7, 4), Some(11));
+        assert_eq!(mul_div_ceil(6, 8, 4), Some(12));
+        assert_eq!(mul_div(6, 7, 0), None);
+        assert_eq!(mul_div(u128::MAX, u128::MAX, u128::MAX), Some(u128::MAX));
+        assert_eq!(
+            mul_div(u128::MAX, u128::MAX - 1, u128::MAX),
+            Some(u128::MAX - 1)
+        );
+        assert_eq!(mul_div(1 << 100, 1 << 100, 1 << 73), Some(1 << 127));
+        assert_eq!(mul_div(1 << 100, 1 << 100, 1 << 72), None);
+        assert_eq!(mul_div_ceil(u128::MAX, 3, 3), Some(u128::MAX));
+        assert_eq!(mul_div_rem(u128::MAX, u128::MAX, u128::MAX - 1), None);
+        assert_eq!(
+            mul_div_rem(u64::MAX as u128 + 2, u64::MAX as u128, u64::MAX as u128 + 1),
+            Some((u64::MAX as u128, u64::MAX as u128))
+        );
+
+        // Matches u128 arithmetic whenever the product fits
+        let mut state = 0x1234_5678_9abc_def1u64;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..10_000 {
+            let a = next() as u128;
+            let b = (next() >> (next() % 64)) as u128;
+            let denominator = (next() >> (next() % 64)) as u128 + 1;
+            assert_eq!(
+                mul_div_rem(a, b, denominator),
+                Some((a * b / denominator, a * b % denominator))
+            );
+
+            // And scales up by multiples of the denominator
+            let shift = next() % 64;
+            assert_eq!(
+                mul_div(a << 64, b << shift, denominator << shift),
+                (a * b / denominator)
+                    .checked_mul(1 << 64)
+                    .map(|scaled| scaled + ((a * b % denominator) << 64) / denominator)
+            );
+        }
+    }
+
+    #[test]
+    fn test_calculate_stable_invariant() {
+        // The invariant of balanced reserves is their sum
+        assert_eq!(
+            calculate_stable_invariant(100, 1_000_000, 1_000_000).unwrap(),
+            2_000_000
+        );
+        assert_eq!(
+            calculate_stable_invariant(MAX_AMP, u64::MAX, u64::MAX).unwrap(),
+            2 * u64::MAX as u128
+        );
+
+        // Between the constant product and constant sum invariants otherwise,
+        // closer to the sum the higher the amplification
+        let low = calculate_stable_invariant(1, 1_000_000, 4_000_000).unwrap();
+        let high = calculate_stable_invariant(1_000, 1_000_000, 4_000_000).unwrap();
+        assert!(4_000_000 < low && low < high && high < 5_000_000);
+
+        assert_eq!(
+            calculate_stable_invariant(100, 0, 1_000_000),
+            Err(AmmError::InsufficientLiquidity.into())
+        );
+    }
+
+    #[test]
+    fn test_calculate_stable_swap_never_undercharges() {
+        let mut state = 0x5851_f42d_4c95_7f2du64;
+        let mut next = |bound: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % bound
+        };
+
+        for _ in 0..2_000 {
+            let amp = 1 + next(10_000);
+            let reserve_in_bits = 10 + next(40);
+            let reserve_in = 1_000 + next(1 << reserve_in_bits);
+            let reserve_out_bits = 10 + next(40);
+            let reserve_out = 1_000 + next(1 << reserve_out_bits);
+            let fee_denominator = 1 + next(10_000) as u16;
+            let fee_numerator = next(fee_denominator as u64) as u16;
+            let invariant = calculate_stable_invariant(amp, reserve_in, reserve_out)
+                .unwrap_or_else(|e| panic!("{e:?} {reserve_in}/{reserve_out} at {amp}"));
+
+            // Swaps in never decrease the invariant
+            let amount_in = next(reserve_in.max(reserve_out));
+            let amount_out =
+                calculate_stable_swap_output(amount_in, reserve_in, reserve_out, amp, 0, 1)
+                    .unwrap();
+            let new_invariant =
+                calculate_stable_invariant(amp, reserve_in + amount_in, reserve_out - amount_out)
+                    .unwrap_or_else(|e| panic!("{e:?} {amount_in} in for {amount_out} out of {reserve_in}/{reserve_out} at {amp}"));
+            assert!(
+                new_invariant >= invariant,
+                "{amount_in} in for {amount_out} out of {reserve_in}/{reserve_out} at {amp}"
+            );
+
+            // Swaps out pay at least the requested amount
+            let amount_out = 1 + next(reserve_out - 2);
+            let amount_in = match calculate_stable_swap_input(
+                amount_out,
+                reserve_in,
+                reserve_out,
+                amp,
+                fee_numerator,
+                fee_denominator,
+            ) {
+                Ok(amount_in) => amount_in,
+                Err(error) => {
+                    assert!(
+                        error == AmmError::MathOverflow.into()
+                            || error == AmmError::InsufficientLiquidity.into()
+                    );
+                    continue;
+                }
+            };
+            let paid_out = calculate_stable_swap_output(
+                amount_in,
+                reserve_in,
+                reserve_out,
+                amp,
+                fee_numerator,
+                fee_denominator,
+            )
+            .unwrap();
+            assert!(
+                paid_out >= amount_out,
+                "{amount_in} in for {paid_out} < {amount_out} out of {reserve_in}/{reserve_out} at {amp}, {fee_numerator}/{fee_denominator}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_calculate_stable_single_sided_lp_amounts() {
+        // Close to the swapped value in a balanced pool with low fees
+        let (lp_amount, fee) = calculate_stable_single_deposit_lp_amount(
+            10_000, 1_000_000, 1_000_000, 2_000_000, 100, 1, 10000,
+        )
+        .unwrap();
+        assert_eq!(fee, 1);
+        assert!(lp_amount > 9_990 && lp_amount < 10_000);
+
+        assert_eq!(
+            calculate_stable_single_withdraw_lp_amount(
+                1_000_000, 1_000_000, 1_000_000, 2_000_000, 100, 1, 10000,
+            ),
+            Err(AmmError::InsufficientLiquidity.into())
+        );
+
+        // Withdrawing a deposit burns at least the LP tokens it minted
+        let mut state = 0x2f1e_4d3c_5b6a_7980u64;
+        let mut next = |bound: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % bound
+        };
+
+        for _ in 0..2_000 {
+            let amp = 1 + next(10_000);
+            let reserve_in_bits = 10 + next(40);
+            let reserve_in = 1_000 + next(1 << reserve_in_bits);
+            let reserve_other_bits = 10 + next(40);
+            let reserve_other = 1_000 + next(1 << reserve_other_bits);
+            let lp_supply_bits = 10 + next(40);
+            let lp_supply = 1_000 + next(1 << lp_supply_bits);
+            let amount = next(reserve_in);
+
+            let (minted, _) = calculate_stable_single_deposit_lp_amount(
+                amount,
+                reserve_in,
+                reserve_other,
+                lp_supply,
+                amp,
+                0,
+                1,
+            )
+            .unwrap();
+            let (burned, _) = calculate_stable_single_withdraw_lp_amount(
+                amount,
+                reserve_in + amount,
+                reserve_other,
+                lp_supply + minted,
+                amp,
+                0,
+                1,
+            )
+            .unwrap();
+            assert!(
+                burned >= minted,
+                "{burned} < {minted} for {amount} into {reserve_in}/{reserve_other} at {amp}"
+            );
+        }
+    }
+
     #[test]
     fn test_calculate_initial_lp_amount() {
         // Test equal amounts
@@ -158,4 +1262,46 @@ mod tests {
         let lp_amount = calculate_lp_amount(200, 100, 1000, 1000, 1000).unwrap();
         assert_eq!(lp_amount, 100);
     }
+
+    #[test]
+    fn test_calculate_twap() {
+        let one = 1u128 << PRICE_FRACTIONAL_BITS;
+
+        // 10 seconds at 2 B per A, then 30 seconds at 4 B per A
+        let (price_a_cumulative, price_b_cumulative) =
+            calculate_cumulative_prices(0, 0, 1_000, 2_000, 10);
+        assert_eq!(
+            (price_a_cumulative, price_b_cumulative),
+            (20 * one, 5 * one)
+        );
+        let (price_a_cumulative, price_b_cumulative) =
+            calculate_cumulative_prices(price_a_cumulative, price_b_cumulative, 1_000, 4_000, 30);
+        assert_eq!(
+            calculate_twap(0, price_a_cumulative, 100, 140).unwrap(),
+            7 * one / 2
+        );
+        assert_eq!(
+            calculate_twap(0, price_b_cumulative, 100, 140).unwrap(),
+            (5 * one + 30 * one / 4) / 40
+        );
+
+        // Nothing accrues without time or liquidity
+        assert_eq!(calculate_cumulative_prices(1, 2, 1_000, 2_000, 0), (1, 2));
+        assert_eq!(calculate_cumulative_prices(1, 2, 0, 2_000, 10), (1, 2));
+
+        // The averages survive the sums wrapping around
+        let start = u128::MAX - one;
+        let (end, _) = calculate_cumulative_prices(start, 0, 1_000, 3_000, 5);
+        assert!(end < start);
+        assert_eq!(calculate_twap(start, end, 0, 5).unwrap(), 3 * one);
+
+        assert_eq!(
+            calculate_twap(0, one, 5, 5),
+            Err(AmmError::InvalidObservation.into())
+        );
+        assert_eq!(
+            calculate_twap(0, one, i64::MIN, i64::MAX),
+            Err(AmmError::InvalidObservation.into())
+        );
+    }
 }
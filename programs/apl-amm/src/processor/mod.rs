@@ -1,21 +1,33 @@
 //! Program processor
 
 use {
-    crate::{error::AmmError, instruction::AmmInstruction, state::Pool},
+    crate::{
+        error::AmmError,
+        get_oracle_address_and_bump_seed, get_pool_authority_address_and_bump_seed,
+        instruction::AmmInstruction,
+        state::{CurveType, Observation, Oracle, Pool},
+        ORACLE_SEED, POOL_AUTHORITY_SEED,
+    },
+    apl_token::{
+        extension::BaseState,
+        state::{Account, Mint},
+    },
     arch_program::{
         account::{next_account_info, AccountInfo},
         entrypoint::ProgramResult,
         msg,
-        program::{invoke, invoke_signed},
+        program::{get_clock, invoke_signed, set_return_data},
         program_error::ProgramError,
+        program_option::COption,
         program_pack::Pack,
         pubkey::Pubkey,
+        system_instruction,
     },
-    apl_token,
 };
 
-mod math;
-use math::*;
+pub mod curve;
+pub mod math;
+use {curve::Curve, math::*};
 
 /// Program state handler.
 pub struct Processor {}
@@ -23,7 +35,7 @@ pub struct Processor {}
 impl Processor {
     /// Processes an instruction
     pub fn process(
-        _program_id: &Pubkey,
+        program_id: &Pubkey,
         accounts: &[AccountInfo],
         instruction_data: &[u8],
     ) -> ProgramResult {
@@ -33,17 +45,40 @@ impl Processor {
             AmmInstruction::InitializePool {
                 fee_numerator,
                 fee_denominator,
+                protocol_fee_numerator,
+                protocol_fee_denominator,
+                curve_type,
+                amp,
             } => {
                 msg!("Instruction: InitializePool");
-                Self::process_initialize_pool(accounts, fee_numerator, fee_denominator)
+                Self::process_initialize_pool(
+                    program_id,
+                    accounts,
+                    fee_numerator,
+                    fee_denominator,
+                    protocol_fee_numerator,
+                    protocol_fee_denominator,
+                    curve_type,
+                    amp,
+                )
             }
             AmmInstruction::AddLiquidity {
-                token_a_amount,
-                token_b_amount,
+                amount_a_desired,
+                amount_b_desired,
+                amount_a_min,
+                amount_b_min,
                 min_lp_amount,
             } => {
                 msg!("Instruction: AddLiquidity");
-                Self::process_add_liquidity(accounts, token_a_amount, token_b_amount, min_lp_amount)
+                Self::process_add_liquidity(
+                    program_id,
+                    accounts,
+                    amount_a_desired,
+                    amount_b_desired,
+                    amount_a_min,
+                    amount_b_min,
+                    min_lp_amount,
+                )
             }
             AmmInstruction::RemoveLiquidity {
                 lp_amount,
@@ -52,6 +87,7 @@ impl Processor {
             } => {
                 msg!("Instruction: RemoveLiquidity");
                 Self::process_remove_liquidity(
+                    program_id,
                     accounts,
                     lp_amount,
                     min_token_a_amount,
@@ -61,18 +97,86 @@ impl Processor {
             AmmInstruction::Swap {
                 amount_in,
                 min_amount_out,
+                a_to_b,
             } => {
                 msg!("Instruction: Swap");
-                Self::process_swap(accounts, amount_in, min_amount_out)
+                Self::process_swap(program_id, accounts, amount_in, min_amount_out, a_to_b)
+            }
+            AmmInstruction::SwapExactOut {
+                amount_out,
+                max_amount_in,
+                a_to_b,
+            } => {
+                msg!("Instruction: SwapExactOut");
+                Self::process_swap_exact_out(
+                    program_id,
+                    accounts,
+                    amount_out,
+                    max_amount_in,
+                    a_to_b,
+                )
+            }
+            AmmInstruction::WithdrawProtocolFees => {
+                msg!("Instruction: WithdrawProtocolFees");
+                Self::process_withdraw_protocol_fees(program_id, accounts)
+            }
+            AmmInstruction::DepositSingleTokenExactIn {
+                amount_in,
+                min_lp_amount,
+                is_token_a,
+            } => {
+                msg!("Instruction: DepositSingleTokenExactIn");
+                Self::process_deposit_single_token_exact_in(
+                    program_id,
+                    accounts,
+                    amount_in,
+                    min_lp_amount,
+                    is_token_a,
+                )
+            }
+            AmmInstruction::WithdrawSingleTokenExactOut {
+                amount_out,
+                max_lp_amount,
+                is_token_a,
+            } => {
+                msg!("Instruction: WithdrawSingleTokenExactOut");
+                Self::process_withdraw_single_token_exact_out(
+                    program_id,
+                    accounts,
+                    amount_out,
+                    max_lp_amount,
+                    is_token_a,
+                )
+            }
+            AmmInstruction::RampAmp {
+                target_amp,
+                ramp_stop_timestamp,
+            } => {
+                msg!("Instruction: RampAmp");
+                Self::process_ramp_amp(program_id, accounts, target_amp, ramp_stop_timestamp)
+            }
+            AmmInstruction::InitializeOracle { txid, vout } => {
+                msg!("Instruction: InitializeOracle");
+                Self::process_initialize_oracle(program_id, accounts, txid, vout)
+            }
+            AmmInstruction::RecordObservation => {
+                msg!("Instruction: RecordObservation");
+                Self::process_record_observation(program_id, accounts)
             }
         }
     }
 
     /// Processes an [InitializePool](enum.Instruction.html) instruction.
+    #[allow(clippy::too_many_arguments)]
     pub fn process_initialize_pool(
+        program_id: &Pubkey,
         accounts: &[AccountInfo],
         fee_numerator: u16,
         fee_denominator: u16,
+        protocol_fee_numerator: u16,
+        protocol_fee_denominator: u16,
+        curve_type: CurveType,
+        amp: u64,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let pool_info = next_account_info(account_info_iter)?;
@@ -81,9 +185,11 @@ impl Processor {
         let lp_mint_info = next_account_info(account_info_iter)?;
         let token_a_vault_info = next_account_info(account_info_iter)?;
         let token_b_vault_info = next_account_info(account_info_iter)?;
-        let _authority_info = next_account_info(account_info_iter)?;
+        let pool_authority_info = next_account_info(account_info_iter)?;
+        let fee_recipient_info = next_account_info(account_info_iter)?;
 
         // Validate pool state
+        Self::check_account_owner(pool_info, program_id)?;
         let mut pool = Pool::unpack_unchecked(&pool_info.data.borrow())?;
         if pool.is_initialized {
             return Err(AmmError::AlreadyInitialized.into());
@@ -93,6 +199,56 @@ impl Processor {
         if fee_numerator == 0 || fee_denominator == 0 || fee_numerator >= fee_denominator {
             return Err(AmmError::InvalidFeeConfig.into());
         }
+        if protocol_fee_denominator == 0 || protocol_fee_numerator > protocol_fee_denominator {
+            return Err(AmmError::InvalidFeeConfig.into());
+        }
+
+        // Validate curve configuration
+        let amp_range = match curve_type {
+            CurveType::ConstantProduct => 0..=0,
+            CurveType::StableSwap => MIN_AMP..=MAX_AMP,
+        };
+        if !amp_range.contains(&amp) {
+            return Err(AmmError::InvalidAmp.into());
+        }
+
+        // Validate that the pool authority holds the vaults and mints LP tokens
+        let (pool_authority, authority_bump) =
+            get_pool_authority_address_and_bump_seed(pool_info.key, program_id);
+        if *pool_authority_info.key != pool_authority {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        for mint_info in [token_a_mint_info, token_b_mint_info] {
+            Self::check_account_owner(mint_info, &apl_token::id())?;
+            Mint::unpack_base(&mint_info.data.borrow())?;
+        }
+        if token_a_mint_info.key == token_b_mint_info.key {
+            return Err(AmmError::InvalidPoolTokens.into());
+        }
+
+        // Validate that the vaults start empty, out of reach of anyone but the
+        // pool authority, and that no LP tokens were issued beforehand
+        let vault_a = Self::unpack_vault(
+            token_a_vault_info,
+            token_a_vault_info.key,
+            token_a_mint_info.key,
+            &pool_authority,
+        )?;
+        let vault_b = Self::unpack_vault(
+            token_b_vault_info,
+            token_b_vault_info.key,
+            token_b_mint_info.key,
+            &pool_authority,
+        )?;
+        for vault in [vault_a, vault_b] {
+            if vault.amount != 0 || vault.delegate.is_some() || vault.close_authority.is_some() {
+                return Err(AmmError::InvalidPoolTokens.into());
+            }
+        }
+        let lp_mint = Self::unpack_lp_mint(lp_mint_info, lp_mint_info.key, &pool_authority)?;
+        if lp_mint.supply != 0 || lp_mint.freeze_authority.is_some() {
+            return Err(AmmError::InvalidPoolTokens.into());
+        }
 
         // Initialize pool
         pool.token_a = *token_a_mint_info.key;
@@ -103,6 +259,19 @@ impl Processor {
         pool.fee_numerator = fee_numerator;
         pool.fee_denominator = fee_denominator;
         pool.is_initialized = true;
+        pool.authority_bump = authority_bump;
+        pool.protocol_fee_numerator = protocol_fee_numerator;
+        pool.protocol_fee_denominator = protocol_fee_denominator;
+        pool.fee_recipient = *fee_recipient_info.key;
+        pool.curve_type = curve_type;
+        pool.initial_amp = amp;
+        pool.target_amp = amp;
+        let timestamp = get_clock().unix_timestamp;
+        if curve_type == CurveType::StableSwap {
+            pool.ramp_start_timestamp = timestamp;
+            pool.ramp_stop_timestamp = timestamp;
+        }
+        pool.last_update_timestamp = timestamp;
 
         Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
 
@@ -111,9 +280,12 @@ impl Processor {
 
     /// Processes an [AddLiquidity](enum.Instruction.html) instruction.
     pub fn process_add_liquidity(
+        program_id: &Pubkey,
         accounts: &[AccountInfo],
-        token_a_amount: u64,
-        token_b_amount: u64,
+        amount_a_desired: u64,
+        amount_b_desired: u64,
+        amount_a_min: u64,
+        amount_b_min: u64,
         min_lp_amount: u64,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
@@ -125,31 +297,66 @@ impl Processor {
         let user_token_b_info = next_account_info(account_info_iter)?;
         let user_lp_info = next_account_info(account_info_iter)?;
         let user_authority_info = next_account_info(account_info_iter)?;
+        let pool_authority_info = next_account_info(account_info_iter)?;
         let token_program_info = next_account_info(account_info_iter)?;
+        let locked_lp_info = account_info_iter.next();
 
         // Validate pool state
-        let pool = Pool::unpack(&pool_info.data.borrow())?;
+        Self::check_account_owner(pool_info, program_id)?;
+        let mut pool = Pool::unpack(&pool_info.data.borrow())?;
         if !pool.is_initialized {
             return Err(AmmError::InvalidPoolState.into());
         }
+        let pool_key = pool_info.key.serialize();
+        let pool_authority_seeds: &[&[u8]] =
+            &[POOL_AUTHORITY_SEED, &pool_key, &[pool.authority_bump]];
+        Self::check_pool_authority(program_id, pool_authority_seeds, pool_authority_info)?;
+        apl_token::check_program_account(token_program_info.key)?;
 
-        // Get current vault balances
-        let token_a_reserve = token_a_vault_info.try_borrow_data()?[64..72]
-            .try_into()
-            .map(u64::from_le_bytes)
-            .map_err(|_| AmmError::InvalidPoolState)?;
-        let token_b_reserve = token_b_vault_info.try_borrow_data()?[64..72]
-            .try_into()
-            .map(u64::from_le_bytes)
-            .map_err(|_| AmmError::InvalidPoolState)?;
-        let lp_supply = lp_mint_info.try_borrow_data()?[36..44]
-            .try_into()
-            .map(u64::from_le_bytes)
-            .map_err(|_| AmmError::InvalidPoolState)?;
+        // Get current reserves, the vault balances less the protocol fees owed
+        let token_a_reserve = Self::unpack_vault(
+            token_a_vault_info,
+            &pool.token_a_vault,
+            &pool.token_a,
+            pool_authority_info.key,
+        )?
+        .amount
+        .checked_sub(pool.protocol_fees_a)
+        .ok_or(AmmError::InvalidPoolState)?;
+        let token_b_reserve = Self::unpack_vault(
+            token_b_vault_info,
+            &pool.token_b_vault,
+            &pool.token_b,
+            pool_authority_info.key,
+        )?
+        .amount
+        .checked_sub(pool.protocol_fees_b)
+        .ok_or(AmmError::InvalidPoolState)?;
+        let lp_supply =
+            Self::unpack_lp_mint(lp_mint_info, &pool.lp_mint, pool_authority_info.key)?.supply;
 
-        // Calculate LP tokens to mint
+        // Only take the amounts matching the reserve ratio, the first deposit
+        // setting it
+        let (token_a_amount, token_b_amount) = if lp_supply == 0 {
+            (amount_a_desired, amount_b_desired)
+        } else {
+            calculate_deposit_amounts(
+                amount_a_desired,
+                amount_b_desired,
+                amount_a_min,
+                amount_b_min,
+                token_a_reserve,
+                token_b_reserve,
+            )?
+        };
+
+        // Calculate LP tokens to mint, locking the minimum liquidity out of
+        // the first deposit
         let lp_amount = if lp_supply == 0 {
-            calculate_initial_lp_amount(token_a_amount, token_b_amount)?
+            Self::curve(&pool)
+                .initial_lp_amount(token_a_amount, token_b_amount)?
+                .checked_sub(MINIMUM_LIQUIDITY)
+                .ok_or(AmmError::InsufficientLiquidity)?
         } else {
             calculate_lp_amount(
                 token_a_amount,
@@ -160,10 +367,25 @@ impl Processor {
             )?
         };
 
+        if lp_amount == 0 {
+            return Err(AmmError::ZeroLpAmount.into());
+        }
         if lp_amount < min_lp_amount {
             return Err(AmmError::SlippageExceeded.into());
         }
 
+        // Update the price accumulators with the reserves after the deposit
+        Self::update_cumulative_prices(
+            &mut pool,
+            token_a_reserve
+                .checked_add(token_a_amount)
+                .ok_or(AmmError::MathOverflow)?,
+            token_b_reserve
+                .checked_add(token_b_amount)
+                .ok_or(AmmError::MathOverflow)?,
+        );
+        Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
+
         // Transfer token A from user to vault
         let transfer_a_ix = apl_token::instruction::transfer(
             &apl_token::id(),
@@ -173,7 +395,7 @@ impl Processor {
             &[],
             token_a_amount,
         )?;
-        invoke(
+        invoke_signed(
             &transfer_a_ix,
             &[
                 user_token_a_info.clone(),
@@ -181,6 +403,7 @@ impl Processor {
                 user_authority_info.clone(),
                 token_program_info.clone(),
             ],
+            &[],
         )?;
 
         // Transfer token B from user to vault
@@ -192,7 +415,7 @@ impl Processor {
             &[],
             token_b_amount,
         )?;
-        invoke(
+        invoke_signed(
             &transfer_b_ix,
             &[
                 user_token_b_info.clone(),
@@ -200,32 +423,70 @@ impl Processor {
                 user_authority_info.clone(),
                 token_program_info.clone(),
             ],
+            &[],
         )?;
 
+        // Lock the minimum liquidity in an account held by the pool authority,
+        // which never transfers LP tokens
+        if lp_supply == 0 {
+            let locked_lp_info = locked_lp_info.ok_or(ProgramError::NotEnoughAccountKeys)?;
+            Self::check_account_owner(locked_lp_info, &apl_token::id())?;
+            let locked_lp = Account::unpack_base(&locked_lp_info.data.borrow())?;
+            if locked_lp.mint != pool.lp_mint || locked_lp.owner != *pool_authority_info.key {
+                return Err(AmmError::InvalidPoolTokens.into());
+            }
+            let lock_ix = apl_token::instruction::mint_to(
+                &apl_token::id(),
+                lp_mint_info.key,
+                locked_lp_info.key,
+                pool_authority_info.key,
+                &[],
+                MINIMUM_LIQUIDITY,
+            )?;
+            invoke_signed(
+                &lock_ix,
+                &[
+                    lp_mint_info.clone(),
+                    locked_lp_info.clone(),
+                    pool_authority_info.clone(),
+                    token_program_info.clone(),
+                ],
+                &[pool_authority_seeds],
+            )?;
+        }
+
         // Mint LP tokens to user
         let mint_to_ix = apl_token::instruction::mint_to(
             &apl_token::id(),
             lp_mint_info.key,
             user_lp_info.key,
-            pool_info.key,
+            pool_authority_info.key,
             &[],
             lp_amount,
         )?;
-        invoke(
+        invoke_signed(
             &mint_to_ix,
             &[
                 lp_mint_info.clone(),
                 user_lp_info.clone(),
-                pool_info.clone(),
+                pool_authority_info.clone(),
                 token_program_info.clone(),
             ],
+            &[pool_authority_seeds],
         )?;
 
+        let mut return_data = [0u8; 24];
+        return_data[..8].copy_from_slice(&token_a_amount.to_le_bytes());
+        return_data[8..16].copy_from_slice(&token_b_amount.to_le_bytes());
+        return_data[16..].copy_from_slice(&lp_amount.to_le_bytes());
+        set_return_data(&return_data);
+
         Ok(())
     }
 
     /// Processes a [RemoveLiquidity](enum.Instruction.html) instruction.
     pub fn process_remove_liquidity(
+        program_id: &Pubkey,
         accounts: &[AccountInfo],
         lp_amount: u64,
         min_token_a_amount: u64,
@@ -240,27 +501,42 @@ impl Processor {
         let user_token_b_info = next_account_info(account_info_iter)?;
         let user_lp_info = next_account_info(account_info_iter)?;
         let user_authority_info = next_account_info(account_info_iter)?;
+        let pool_authority_info = next_account_info(account_info_iter)?;
         let token_program_info = next_account_info(account_info_iter)?;
 
         // Validate pool state
-        let pool = Pool::unpack(&pool_info.data.borrow())?;
+        Self::check_account_owner(pool_info, program_id)?;
+        let mut pool = Pool::unpack(&pool_info.data.borrow())?;
         if !pool.is_initialized {
             return Err(AmmError::InvalidPoolState.into());
         }
+        let pool_key = pool_info.key.serialize();
+        let pool_authority_seeds: &[&[u8]] =
+            &[POOL_AUTHORITY_SEED, &pool_key, &[pool.authority_bump]];
+        Self::check_pool_authority(program_id, pool_authority_seeds, pool_authority_info)?;
+        apl_token::check_program_account(token_program_info.key)?;
 
-        // Get current vault balances
-        let token_a_reserve = token_a_vault_info.try_borrow_data()?[64..72]
-            .try_into()
-            .map(u64::from_le_bytes)
-            .map_err(|_| AmmError::InvalidPoolState)?;
-        let token_b_reserve = token_b_vault_info.try_borrow_data()?[64..72]
-            .try_into()
-            .map(u64::from_le_bytes)
-            .map_err(|_| AmmError::InvalidPoolState)?;
-        let lp_supply = lp_mint_info.try_borrow_data()?[36..44]
-            .try_into()
-            .map(u64::from_le_bytes)
-            .map_err(|_| AmmError::InvalidPoolState)?;
+        // Get current reserves, the vault balances less the protocol fees owed
+        let token_a_reserve = Self::unpack_vault(
+            token_a_vault_info,
+            &pool.token_a_vault,
+            &pool.token_a,
+            pool_authority_info.key,
+        )?
+        .amount
+        .checked_sub(pool.protocol_fees_a)
+        .ok_or(AmmError::InvalidPoolState)?;
+        let token_b_reserve = Self::unpack_vault(
+            token_b_vault_info,
+            &pool.token_b_vault,
+            &pool.token_b,
+            pool_authority_info.key,
+        )?
+        .amount
+        .checked_sub(pool.protocol_fees_b)
+        .ok_or(AmmError::InvalidPoolState)?;
+        let lp_supply =
+            Self::unpack_lp_mint(lp_mint_info, &pool.lp_mint, pool_authority_info.key)?.supply;
 
         // Calculate token amounts
         let token_a_amount = (token_a_reserve as u128)
@@ -279,6 +555,18 @@ impl Processor {
             return Err(AmmError::SlippageExceeded.into());
         }
 
+        // Update the price accumulators with the reserves after the withdrawal
+        Self::update_cumulative_prices(
+            &mut pool,
+            token_a_reserve
+                .checked_sub(token_a_amount)
+                .ok_or(AmmError::InsufficientLiquidity)?,
+            token_b_reserve
+                .checked_sub(token_b_amount)
+                .ok_or(AmmError::InsufficientLiquidity)?,
+        );
+        Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
+
         // Burn LP tokens from user
         let burn_ix = apl_token::instruction::burn(
             &apl_token::id(),
@@ -288,7 +576,7 @@ impl Processor {
             &[],
             lp_amount,
         )?;
-        invoke(
+        invoke_signed(
             &burn_ix,
             &[
                 user_lp_info.clone(),
@@ -296,6 +584,7 @@ impl Processor {
                 user_authority_info.clone(),
                 token_program_info.clone(),
             ],
+            &[],
         )?;
 
         // Transfer token A from vault to user
@@ -303,18 +592,19 @@ impl Processor {
             &apl_token::id(),
             token_a_vault_info.key,
             user_token_a_info.key,
-            pool_info.key,
+            pool_authority_info.key,
             &[],
             token_a_amount,
         )?;
-        invoke(
+        invoke_signed(
             &transfer_a_ix,
             &[
                 token_a_vault_info.clone(),
                 user_token_a_info.clone(),
-                pool_info.clone(),
+                pool_authority_info.clone(),
                 token_program_info.clone(),
             ],
+            &[pool_authority_seeds],
         )?;
 
         // Transfer token B from vault to user
@@ -322,18 +612,19 @@ impl Processor {
             &apl_token::id(),
             token_b_vault_info.key,
             user_token_b_info.key,
-            pool_info.key,
+            pool_authority_info.key,
             &[],
             token_b_amount,
         )?;
-        invoke(
+        invoke_signed(
             &transfer_b_ix,
             &[
                 token_b_vault_info.clone(),
                 user_token_b_info.clone(),
-                pool_info.clone(),
+                pool_authority_info.clone(),
                 token_program_info.clone(),
             ],
+            &[pool_authority_seeds],
         )?;
 
         Ok(())
@@ -341,10 +632,72 @@ impl Processor {
 
     /// Processes a [Swap](enum.Instruction.html) instruction.
     pub fn process_swap(
+        program_id: &Pubkey,
         accounts: &[AccountInfo],
         amount_in: u64,
         min_amount_out: u64,
+        a_to_b: bool,
+    ) -> ProgramResult {
+        Self::swap(
+            program_id,
+            accounts,
+            a_to_b,
+            |pool, curve, reserve_in, reserve_out| {
+                let amount_out = curve.swap_output(
+                    amount_in,
+                    reserve_in,
+                    reserve_out,
+                    pool.fee_numerator,
+                    pool.fee_denominator,
+                )?;
+                if amount_out < min_amount_out {
+                    return Err(AmmError::SlippageExceeded.into());
+                }
+                Ok((amount_in, amount_out))
+            },
+        )
+    }
+
+    /// Processes a [SwapExactOut](enum.Instruction.html) instruction.
+    pub fn process_swap_exact_out(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount_out: u64,
+        max_amount_in: u64,
+        a_to_b: bool,
     ) -> ProgramResult {
+        Self::swap(
+            program_id,
+            accounts,
+            a_to_b,
+            |pool, curve, reserve_in, reserve_out| {
+                let amount_in = curve.swap_input(
+                    amount_out,
+                    reserve_in,
+                    reserve_out,
+                    pool.fee_numerator,
+                    pool.fee_denominator,
+                )?;
+                if amount_in > max_amount_in {
+                    return Err(AmmError::SlippageExceeded.into());
+                }
+                Ok((amount_in, amount_out))
+            },
+        )
+    }
+
+    /// Swaps between the vaults of the pool in the given direction, the
+    /// amounts in and out being calculated from the pool, its curve and its
+    /// reserves
+    fn swap<F>(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        a_to_b: bool,
+        calculate_amounts: F,
+    ) -> ProgramResult
+    where
+        F: FnOnce(&Pool, &Curve, u64, u64) -> Result<(u64, u64), ProgramError>,
+    {
         let account_info_iter = &mut accounts.iter();
         let pool_info = next_account_info(account_info_iter)?;
         let input_vault_info = next_account_info(account_info_iter)?;
@@ -352,36 +705,81 @@ impl Processor {
         let user_input_info = next_account_info(account_info_iter)?;
         let user_output_info = next_account_info(account_info_iter)?;
         let user_authority_info = next_account_info(account_info_iter)?;
+        let pool_authority_info = next_account_info(account_info_iter)?;
         let token_program_info = next_account_info(account_info_iter)?;
 
         // Validate pool state
-        let pool = Pool::unpack(&pool_info.data.borrow())?;
+        Self::check_account_owner(pool_info, program_id)?;
+        let mut pool = Pool::unpack(&pool_info.data.borrow())?;
         if !pool.is_initialized {
             return Err(AmmError::InvalidPoolState.into());
         }
+        let pool_key = pool_info.key.serialize();
+        let pool_authority_seeds: &[&[u8]] =
+            &[POOL_AUTHORITY_SEED, &pool_key, &[pool.authority_bump]];
+        Self::check_pool_authority(program_id, pool_authority_seeds, pool_authority_info)?;
+        apl_token::check_program_account(token_program_info.key)?;
+
+        // Get current reserves, the vaults being ordered by direction
+        let (input_vault, input_mint, input_protocol_fees) = if a_to_b {
+            (pool.token_a_vault, pool.token_a, pool.protocol_fees_a)
+        } else {
+            (pool.token_b_vault, pool.token_b, pool.protocol_fees_b)
+        };
+        let (output_vault, output_mint, output_protocol_fees) = if a_to_b {
+            (pool.token_b_vault, pool.token_b, pool.protocol_fees_b)
+        } else {
+            (pool.token_a_vault, pool.token_a, pool.protocol_fees_a)
+        };
+        let reserve_in = Self::unpack_vault(
+            input_vault_info,
+            &input_vault,
+            &input_mint,
+            pool_authority_info.key,
+        )?
+        .amount
+        .checked_sub(input_protocol_fees)
+        .ok_or(AmmError::InvalidPoolState)?;
+        let reserve_out = Self::unpack_vault(
+            output_vault_info,
+            &output_vault,
+            &output_mint,
+            pool_authority_info.key,
+        )?
+        .amount
+        .checked_sub(output_protocol_fees)
+        .ok_or(AmmError::InvalidPoolState)?;
+        Self::check_user_account(user_input_info, &input_mint)?;
+        Self::check_user_account(user_output_info, &output_mint)?;
 
-        // Get current vault balances
-        let reserve_in = input_vault_info.try_borrow_data()?[64..72]
-            .try_into()
-            .map(u64::from_le_bytes)
-            .map_err(|_| AmmError::InvalidPoolState)?;
-        let reserve_out = output_vault_info.try_borrow_data()?[64..72]
-            .try_into()
-            .map(u64::from_le_bytes)
-            .map_err(|_| AmmError::InvalidPoolState)?;
+        // Calculate swap amounts
+        let curve = Self::curve(&pool);
+        let (amount_in, amount_out) = calculate_amounts(&pool, &curve, reserve_in, reserve_out)?;
 
-        // Calculate output amount
-        let amount_out = calculate_swap_output(
+        // Account for the fee, charged in the input token
+        let (fee, protocol_fee) = calculate_fees(
             amount_in,
-            reserve_in,
-            reserve_out,
             pool.fee_numerator,
             pool.fee_denominator,
+            pool.protocol_fee_numerator,
+            pool.protocol_fee_denominator,
         )?;
+        Self::accrue_fees(&mut pool, a_to_b, fee, protocol_fee)?;
 
-        if amount_out < min_amount_out {
-            return Err(AmmError::SlippageExceeded.into());
+        // Update the price accumulators with the reserves after the swap, the
+        // protocol fee leaving the input reserve
+        let reserve_in = reserve_in
+            .checked_add(amount_in - protocol_fee)
+            .ok_or(AmmError::MathOverflow)?;
+        let reserve_out = reserve_out
+            .checked_sub(amount_out)
+            .ok_or(AmmError::InsufficientLiquidity)?;
+        if a_to_b {
+            Self::update_cumulative_prices(&mut pool, reserve_in, reserve_out);
+        } else {
+            Self::update_cumulative_prices(&mut pool, reserve_out, reserve_in);
         }
+        Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
 
         // Transfer input tokens from user to vault
         let transfer_in_ix = apl_token::instruction::transfer(
@@ -392,7 +790,7 @@ impl Processor {
             &[],
             amount_in,
         )?;
-        invoke(
+        invoke_signed(
             &transfer_in_ix,
             &[
                 user_input_info.clone(),
@@ -400,6 +798,7 @@ impl Processor {
                 user_authority_info.clone(),
                 token_program_info.clone(),
             ],
+            &[],
         )?;
 
         // Transfer output tokens from vault to user
@@ -407,23 +806,619 @@ impl Processor {
             &apl_token::id(),
             output_vault_info.key,
             user_output_info.key,
-            pool_info.key,
+            pool_authority_info.key,
             &[],
             amount_out,
         )?;
-        invoke(
+        invoke_signed(
             &transfer_out_ix,
             &[
                 output_vault_info.clone(),
                 user_output_info.clone(),
-                pool_info.clone(),
+                pool_authority_info.clone(),
                 token_program_info.clone(),
             ],
+            &[pool_authority_seeds],
         )?;
 
         Ok(())
     }
 
+    /// Processes a [WithdrawProtocolFees](enum.Instruction.html) instruction.
+    pub fn process_withdraw_protocol_fees(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let pool_info = next_account_info(account_info_iter)?;
+        let token_a_vault_info = next_account_info(account_info_iter)?;
+        let token_b_vault_info = next_account_info(account_info_iter)?;
+        let destination_a_info = next_account_info(account_info_iter)?;
+        let destination_b_info = next_account_info(account_info_iter)?;
+        let fee_recipient_info = next_account_info(account_info_iter)?;
+        let pool_authority_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        // Validate pool state
+        Self::check_account_owner(pool_info, program_id)?;
+        let mut pool = Pool::unpack(&pool_info.data.borrow())?;
+        let pool_key = pool_info.key.serialize();
+        let pool_authority_seeds: &[&[u8]] =
+            &[POOL_AUTHORITY_SEED, &pool_key, &[pool.authority_bump]];
+        Self::check_pool_authority(program_id, pool_authority_seeds, pool_authority_info)?;
+        apl_token::check_program_account(token_program_info.key)?;
+        Self::unpack_vault(
+            token_a_vault_info,
+            &pool.token_a_vault,
+            &pool.token_a,
+            pool_authority_info.key,
+        )?;
+        Self::unpack_vault(
+            token_b_vault_info,
+            &pool.token_b_vault,
+            &pool.token_b,
+            pool_authority_info.key,
+        )?;
+
+        // Validate fee recipient
+        if *fee_recipient_info.key != pool.fee_recipient {
+            return Err(AmmError::FeeRecipientMismatch.into());
+        }
+        if !fee_recipient_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let (token_a_amount, token_b_amount) = (pool.protocol_fees_a, pool.protocol_fees_b);
+        pool.protocol_fees_a = 0;
+        pool.protocol_fees_b = 0;
+        Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
+
+        // Transfer the fees from the vaults to the destinations
+        for (vault_info, destination_info, amount) in [
+            (token_a_vault_info, destination_a_info, token_a_amount),
+            (token_b_vault_info, destination_b_info, token_b_amount),
+        ] {
+            if amount == 0 {
+                continue;
+            }
+            let transfer_ix = apl_token::instruction::transfer(
+                &apl_token::id(),
+                vault_info.key,
+                destination_info.key,
+                pool_authority_info.key,
+                &[],
+                amount,
+            )?;
+            invoke_signed(
+                &transfer_ix,
+                &[
+                    vault_info.clone(),
+                    destination_info.clone(),
+                    pool_authority_info.clone(),
+                    token_program_info.clone(),
+                ],
+                &[pool_authority_seeds],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Processes a [DepositSingleTokenExactIn](enum.Instruction.html)
+    /// instruction.
+    pub fn process_deposit_single_token_exact_in(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount_in: u64,
+        min_lp_amount: u64,
+        is_token_a: bool,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let pool_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let other_vault_info = next_account_info(account_info_iter)?;
+        let lp_mint_info = next_account_info(account_info_iter)?;
+        let user_token_info = next_account_info(account_info_iter)?;
+        let user_lp_info = next_account_info(account_info_iter)?;
+        let user_authority_info = next_account_info(account_info_iter)?;
+        let pool_authority_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        // Validate pool state
+        Self::check_account_owner(pool_info, program_id)?;
+        let mut pool = Pool::unpack(&pool_info.data.borrow())?;
+        if !pool.is_initialized {
+            return Err(AmmError::InvalidPoolState.into());
+        }
+        let pool_key = pool_info.key.serialize();
+        let pool_authority_seeds: &[&[u8]] =
+            &[POOL_AUTHORITY_SEED, &pool_key, &[pool.authority_bump]];
+        Self::check_pool_authority(program_id, pool_authority_seeds, pool_authority_info)?;
+        apl_token::check_program_account(token_program_info.key)?;
+
+        // Get the current reserves, the vaults being ordered by the deposited
+        // token
+        let (vault, mint, protocol_fees) = if is_token_a {
+            (pool.token_a_vault, pool.token_a, pool.protocol_fees_a)
+        } else {
+            (pool.token_b_vault, pool.token_b, pool.protocol_fees_b)
+        };
+        let (other_vault, other_mint, other_protocol_fees) = if is_token_a {
+            (pool.token_b_vault, pool.token_b, pool.protocol_fees_b)
+        } else {
+            (pool.token_a_vault, pool.token_a, pool.protocol_fees_a)
+        };
+        let reserve = Self::unpack_vault(vault_info, &vault, &mint, pool_authority_info.key)?
+            .amount
+            .checked_sub(protocol_fees)
+            .ok_or(AmmError::InvalidPoolState)?;
+        let other_reserve = Self::unpack_vault(
+            other_vault_info,
+            &other_vault,
+            &other_mint,
+            pool_authority_info.key,
+        )?
+        .amount
+        .checked_sub(other_protocol_fees)
+        .ok_or(AmmError::InvalidPoolState)?;
+        let lp_supply =
+            Self::unpack_lp_mint(lp_mint_info, &pool.lp_mint, pool_authority_info.key)?.supply;
+        Self::check_user_account(user_token_info, &mint)?;
+
+        // Calculate LP tokens to mint
+        let (lp_amount, fee) = Self::curve(&pool).single_deposit_lp_amount(
+            amount_in,
+            reserve,
+            other_reserve,
+            lp_supply,
+            pool.fee_numerator,
+            pool.fee_denominator,
+        )?;
+        if lp_amount == 0 {
+            return Err(AmmError::ZeroLpAmount.into());
+        }
+        if lp_amount < min_lp_amount {
+            return Err(AmmError::SlippageExceeded.into());
+        }
+
+        // Account for the fee on the swapped half
+        let protocol_fee = calculate_protocol_fee(
+            fee,
+            pool.protocol_fee_numerator,
+            pool.protocol_fee_denominator,
+        )?;
+        Self::accrue_fees(&mut pool, is_token_a, fee, protocol_fee)?;
+        let reserve = reserve
+            .checked_add(amount_in - protocol_fee)
+            .ok_or(AmmError::MathOverflow)?;
+        if is_token_a {
+            Self::update_cumulative_prices(&mut pool, reserve, other_reserve);
+        } else {
+            Self::update_cumulative_prices(&mut pool, other_reserve, reserve);
+        }
+        Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
+
+        // Transfer tokens from user to vault
+        let transfer_ix = apl_token::instruction::transfer(
+            &apl_token::id(),
+            user_token_info.key,
+            vault_info.key,
+            user_authority_info.key,
+            &[],
+            amount_in,
+        )?;
+        invoke_signed(
+            &transfer_ix,
+            &[
+                user_token_info.clone(),
+                vault_info.clone(),
+                user_authority_info.clone(),
+                token_program_info.clone(),
+            ],
+            &[],
+        )?;
+
+        // Mint LP tokens to user
+        let mint_to_ix = apl_token::instruction::mint_to(
+            &apl_token::id(),
+            lp_mint_info.key,
+            user_lp_info.key,
+            pool_authority_info.key,
+            &[],
+            lp_amount,
+        )?;
+        invoke_signed(
+            &mint_to_ix,
+            &[
+                lp_mint_info.clone(),
+                user_lp_info.clone(),
+                pool_authority_info.clone(),
+                token_program_info.clone(),
+            ],
+            &[pool_authority_seeds],
+        )?;
+
+        Ok(())
+    }
+
+    /// Processes a [WithdrawSingleTokenExactOut](enum.Instruction.html)
+    /// instruction.
+    pub fn process_withdraw_single_token_exact_out(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount_out: u64,
+        max_lp_amount: u64,
+        is_token_a: bool,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let pool_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let other_vault_info = next_account_info(account_info_iter)?;
+        let lp_mint_info = next_account_info(account_info_iter)?;
+        let user_token_info = next_account_info(account_info_iter)?;
+        let user_lp_info = next_account_info(account_info_iter)?;
+        let user_authority_info = next_account_info(account_info_iter)?;
+        let pool_authority_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        // Validate pool state
+        Self::check_account_owner(pool_info, program_id)?;
+        let mut pool = Pool::unpack(&pool_info.data.borrow())?;
+        if !pool.is_initialized {
+            return Err(AmmError::InvalidPoolState.into());
+        }
+        let pool_key = pool_info.key.serialize();
+        let pool_authority_seeds: &[&[u8]] =
+            &[POOL_AUTHORITY_SEED, &pool_key, &[pool.authority_bump]];
+        Self::check_pool_authority(program_id, pool_authority_seeds, pool_authority_info)?;
+        apl_token::check_program_account(token_program_info.key)?;
+
+        // Get the current reserves, the vaults being ordered by the withdrawn
+        // token
+        let (vault, mint, protocol_fees) = if is_token_a {
+            (pool.token_a_vault, pool.token_a, pool.protocol_fees_a)
+        } else {
+            (pool.token_b_vault, pool.token_b, pool.protocol_fees_b)
+        };
+        let (other_vault, other_mint, other_protocol_fees) = if is_token_a {
+            (pool.token_b_vault, pool.token_b, pool.protocol_fees_b)
+        } else {
+            (pool.token_a_vault, pool.token_a, pool.protocol_fees_a)
+        };
+        let reserve = Self::unpack_vault(vault_info, &vault, &mint, pool_authority_info.key)?
+            .amount
+            .checked_sub(protocol_fees)
+            .ok_or(AmmError::InvalidPoolState)?;
+        let other_reserve = Self::unpack_vault(
+            other_vault_info,
+            &other_vault,
+            &other_mint,
+            pool_authority_info.key,
+        )?
+        .amount
+        .checked_sub(other_protocol_fees)
+        .ok_or(AmmError::InvalidPoolState)?;
+        let lp_supply =
+            Self::unpack_lp_mint(lp_mint_info, &pool.lp_mint, pool_authority_info.key)?.supply;
+        Self::check_user_account(user_token_info, &mint)?;
+
+        // Calculate LP tokens to burn
+        let (lp_amount, fee) = Self::curve(&pool).single_withdraw_lp_amount(
+            amount_out,
+            reserve,
+            other_reserve,
+            lp_supply,
+            pool.fee_numerator,
+            pool.fee_denominator,
+        )?;
+        if lp_amount > max_lp_amount {
+            return Err(AmmError::SlippageExceeded.into());
+        }
+
+        // Account for the fee on the swapped half, which stays in the vault
+        let protocol_fee = calculate_protocol_fee(
+            fee,
+            pool.protocol_fee_numerator,
+            pool.protocol_fee_denominator,
+        )?;
+        Self::accrue_fees(&mut pool, is_token_a, fee, protocol_fee)?;
+        let reserve = reserve
+            .checked_sub(amount_out)
+            .and_then(|reserve| reserve.checked_sub(protocol_fee))
+            .ok_or(AmmError::InsufficientLiquidity)?;
+        if is_token_a {
+            Self::update_cumulative_prices(&mut pool, reserve, other_reserve);
+        } else {
+            Self::update_cumulative_prices(&mut pool, other_reserve, reserve);
+        }
+        Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
+
+        // Burn LP tokens from user
+        let burn_ix = apl_token::instruction::burn(
+            &apl_token::id(),
+            user_lp_info.key,
+            lp_mint_info.key,
+            user_authority_info.key,
+            &[],
+            lp_amount,
+        )?;
+        invoke_signed(
+            &burn_ix,
+            &[
+                user_lp_info.clone(),
+                lp_mint_info.clone(),
+                user_authority_info.clone(),
+                token_program_info.clone(),
+            ],
+            &[],
+        )?;
+
+        // Transfer tokens from vault to user
+        let transfer_ix = apl_token::instruction::transfer(
+            &apl_token::id(),
+            vault_info.key,
+            user_token_info.key,
+            pool_authority_info.key,
+            &[],
+            amount_out,
+        )?;
+        invoke_signed(
+            &transfer_ix,
+            &[
+                vault_info.clone(),
+                user_token_info.clone(),
+                pool_authority_info.clone(),
+                token_program_info.clone(),
+            ],
+            &[pool_authority_seeds],
+        )?;
+
+        Ok(())
+    }
+
+    /// Processes a [RampAmp](enum.Instruction.html) instruction.
+    pub fn process_ramp_amp(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        target_amp: u64,
+        ramp_stop_timestamp: i64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let pool_info = next_account_info(account_info_iter)?;
+        let fee_recipient_info = next_account_info(account_info_iter)?;
+
+        // Validate pool state
+        Self::check_account_owner(pool_info, program_id)?;
+        let mut pool = Pool::unpack(&pool_info.data.borrow())?;
+        if pool.curve_type != CurveType::StableSwap {
+            return Err(AmmError::InvalidPoolState.into());
+        }
+
+        // Validate fee recipient
+        if *fee_recipient_info.key != pool.fee_recipient {
+            return Err(AmmError::FeeRecipientMismatch.into());
+        }
+        if !fee_recipient_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Ramps must last, and be spaced, at least the minimum duration, and
+        // change the amplification by a bounded factor
+        let timestamp = get_clock().unix_timestamp;
+        let current_amp = calculate_amp(
+            pool.initial_amp,
+            pool.target_amp,
+            pool.ramp_start_timestamp,
+            pool.ramp_stop_timestamp,
+            timestamp,
+        );
+        if timestamp < pool.ramp_start_timestamp.saturating_add(MIN_RAMP_DURATION)
+            || ramp_stop_timestamp < timestamp.saturating_add(MIN_RAMP_DURATION)
+        {
+            return Err(AmmError::InvalidAmp.into());
+        }
+        if !(MIN_AMP..=MAX_AMP).contains(&target_amp)
+            || target_amp > current_amp * MAX_AMP_CHANGE
+            || current_amp > target_amp * MAX_AMP_CHANGE
+        {
+            return Err(AmmError::InvalidAmp.into());
+        }
+
+        pool.initial_amp = current_amp;
+        pool.target_amp = target_amp;
+        pool.ramp_start_timestamp = timestamp;
+        pool.ramp_stop_timestamp = ramp_stop_timestamp;
+        Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Processes an [InitializeOracle](enum.Instruction.html) instruction.
+    pub fn process_initialize_oracle(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        txid: [u8; 32],
+        vout: u32,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let pool_info = next_account_info(account_info_iter)?;
+        let oracle_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        // Validate pool state
+        Self::check_account_owner(pool_info, program_id)?;
+        let pool = Pool::unpack(&pool_info.data.borrow())?;
+
+        let (oracle, bump) = get_oracle_address_and_bump_seed(pool_info.key, program_id);
+        if oracle != *oracle_info.key {
+            msg!("Error: Oracle address does not match seed derivation");
+            return Err(ProgramError::InvalidSeeds);
+        }
+        let pool_key = pool_info.key.serialize();
+        let oracle_signer_seeds: &[&[u8]] = &[ORACLE_SEED, &pool_key, &[bump]];
+
+        invoke_signed(
+            &system_instruction::create_account(txid, vout, *oracle_info.key),
+            &[oracle_info.clone(), system_program_info.clone()],
+            &[oracle_signer_seeds],
+        )?;
+        invoke_signed(
+            &system_instruction::write_bytes(
+                0,
+                Oracle::LEN as u32,
+                vec![0; Oracle::LEN],
+                *oracle_info.key,
+            ),
+            &[oracle_info.clone(), system_program_info.clone()],
+            &[oracle_signer_seeds],
+        )?;
+        invoke_signed(
+            &system_instruction::assign(*oracle_info.key, *program_id),
+            &[oracle_info.clone(), system_program_info.clone()],
+            &[oracle_signer_seeds],
+        )?;
+
+        let mut oracle = Oracle {
+            is_initialized: true,
+            bump,
+            pool: *pool_info.key,
+            ..Oracle::default()
+        };
+        oracle.record(Observation::new(&pool, get_clock().unix_timestamp))?;
+        Oracle::pack(oracle, &mut oracle_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Processes a [RecordObservation](enum.Instruction.html) instruction.
+    pub fn process_record_observation(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let pool_info = next_account_info(account_info_iter)?;
+        let oracle_info = next_account_info(account_info_iter)?;
+
+        // Validate pool and oracle state
+        Self::check_account_owner(pool_info, program_id)?;
+        let pool = Pool::unpack(&pool_info.data.borrow())?;
+        Self::check_account_owner(oracle_info, program_id)?;
+        let mut oracle = Oracle::unpack(&oracle_info.data.borrow())?;
+        if oracle.pool != *pool_info.key {
+            return Err(AmmError::InvalidObservation.into());
+        }
+
+        oracle.record(Observation::new(&pool, get_clock().unix_timestamp))?;
+        Oracle::pack(oracle, &mut oracle_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Accrues the cumulative prices of the pool at its last reserves up to
+    /// now, then records the reserves left by the swap or liquidity change
+    fn update_cumulative_prices(pool: &mut Pool, reserve_a: u64, reserve_b: u64) {
+        let observation = Observation::new(pool, get_clock().unix_timestamp);
+        pool.price_a_cumulative = observation.price_a_cumulative;
+        pool.price_b_cumulative = observation.price_b_cumulative;
+        pool.last_update_timestamp = observation.timestamp;
+        pool.reserve_a_last = reserve_a;
+        pool.reserve_b_last = reserve_b;
+    }
+
+    /// Resolves the curve of the pool, reading the clock only to ramp the
+    /// amplification of StableSwap pools
+    fn curve(pool: &Pool) -> Curve {
+        let timestamp = match pool.curve_type {
+            CurveType::ConstantProduct => 0,
+            CurveType::StableSwap => get_clock().unix_timestamp,
+        };
+        Curve::new(
+            pool.curve_type,
+            pool.initial_amp,
+            pool.target_amp,
+            pool.ramp_start_timestamp,
+            pool.ramp_stop_timestamp,
+            timestamp,
+        )
+    }
+
+    /// Accrues a fee charged in token A or token B, of which the protocol
+    /// share stays in the vault out of the reserves until withdrawn
+    fn accrue_fees(
+        pool: &mut Pool,
+        is_token_a: bool,
+        fee: u64,
+        protocol_fee: u64,
+    ) -> ProgramResult {
+        let (protocol_fees, cumulative_fees) = if is_token_a {
+            (&mut pool.protocol_fees_a, &mut pool.cumulative_fees_a)
+        } else {
+            (&mut pool.protocol_fees_b, &mut pool.cumulative_fees_b)
+        };
+        *protocol_fees = protocol_fees
+            .checked_add(protocol_fee)
+            .ok_or(AmmError::MathOverflow)?;
+        *cumulative_fees = cumulative_fees
+            .checked_add(fee as u128)
+            .ok_or(AmmError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Checks that the account is the pool authority derived with the given
+    /// seeds
+    fn check_pool_authority(
+        program_id: &Pubkey,
+        pool_authority_seeds: &[&[u8]],
+        pool_authority_info: &AccountInfo,
+    ) -> ProgramResult {
+        let pool_authority = Pubkey::create_program_address(pool_authority_seeds, program_id)?;
+        if pool_authority != *pool_authority_info.key {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(())
+    }
+
+    /// Unpacks a vault, checking that it is the expected token account of the
+    /// mint, held by the pool authority
+    fn unpack_vault(
+        vault_info: &AccountInfo,
+        vault: &Pubkey,
+        mint: &Pubkey,
+        pool_authority: &Pubkey,
+    ) -> Result<Account, ProgramError> {
+        Self::check_account_owner(vault_info, &apl_token::id())?;
+        let account = Account::unpack_base(&vault_info.data.borrow())?;
+        if vault_info.key != vault || account.mint != *mint || account.owner != *pool_authority {
+            return Err(AmmError::InvalidPoolTokens.into());
+        }
+        Ok(account)
+    }
+
+    /// Unpacks the LP token mint, checking that it is the expected mint, with
+    /// the pool authority as mint authority
+    fn unpack_lp_mint(
+        lp_mint_info: &AccountInfo,
+        lp_mint: &Pubkey,
+        pool_authority: &Pubkey,
+    ) -> Result<Mint, ProgramError> {
+        Self::check_account_owner(lp_mint_info, &apl_token::id())?;
+        let mint = Mint::unpack_base(&lp_mint_info.data.borrow())?;
+        if lp_mint_info.key != lp_mint || mint.mint_authority != COption::Some(*pool_authority) {
+            return Err(AmmError::InvalidPoolTokens.into());
+        }
+        Ok(mint)
+    }
+
+    /// Checks that the user token account holds the given mint
+    fn check_user_account(account_info: &AccountInfo, mint: &Pubkey) -> ProgramResult {
+        Self::check_account_owner(account_info, &apl_token::id())?;
+        let account = Account::unpack_base(&account_info.data.borrow())?;
+        if account.mint != *mint {
+            return Err(AmmError::InvalidPoolTokens.into());
+        }
+        Ok(())
+    }
+
     /// Checks that the account is owned by the expected program
     pub fn check_account_owner(account_info: &AccountInfo, program_id: &Pubkey) -> ProgramResult {
         if account_info.owner != program_id {
@@ -436,11 +1431,1045 @@ impl Processor {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use {super::*, arch_program::utxo::UtxoMeta};
+
+    struct TestAccount {
+        key: Pubkey,
+        owner: Pubkey,
+        data: Vec<u8>,
+        utxo: UtxoMeta,
+        is_signer: bool,
+    }
+
+    impl TestAccount {
+        fn new(owner: &Pubkey, data: Vec<u8>) -> Self {
+            Self {
+                key: Pubkey::new_unique(),
+                owner: *owner,
+                data,
+                utxo: UtxoMeta::from([0; 32], 0),
+                is_signer: false,
+            }
+        }
+
+        fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Self {
+            let mut data = vec![0; Account::LEN];
+            let account = Account {
+                mint: *mint,
+                owner: *owner,
+                amount,
+                state: apl_token::state::AccountState::Initialized,
+                ..Account::default()
+            };
+            Account::pack(account, &mut data).unwrap();
+            Self::new(&apl_token::id(), data)
+        }
+
+        fn mint(mint_authority: &Pubkey, supply: u64) -> Self {
+            let mut data = vec![0; Mint::LEN];
+            let mint = Mint {
+                mint_authority: COption::Some(*mint_authority),
+                supply,
+                is_initialized: true,
+                ..Mint::default()
+            };
+            Mint::pack(mint, &mut data).unwrap();
+            Self::new(&apl_token::id(), data)
+        }
+
+        fn set_amount(&mut self, amount: u64) {
+            let mut account = Account::unpack(&self.data).unwrap();
+            account.amount = amount;
+            Account::pack(account, &mut self.data).unwrap();
+        }
+
+        fn set_supply(&mut self, supply: u64) {
+            let mut mint = Mint::unpack(&self.data).unwrap();
+            mint.supply = supply;
+            Mint::pack(mint, &mut self.data).unwrap();
+        }
+
+        fn with_key(self, key: &Pubkey) -> Self {
+            Self { key: *key, ..self }
+        }
+
+        fn signer(self) -> Self {
+            Self {
+                is_signer: true,
+                ..self
+            }
+        }
+
+        fn info(&mut self) -> AccountInfo<'_> {
+            AccountInfo::new(
+                &self.key,
+                &mut self.data,
+                &self.owner,
+                &self.utxo,
+                self.is_signer,
+                true,
+                false,
+            )
+        }
+    }
+
+    /// Initialized pool with its vaults, LP mint and the accounts of a user
+    struct TestPool {
+        program_id: Pubkey,
+        mint_b: Pubkey,
+        pool: TestAccount,
+        pool_authority: TestAccount,
+        lp_mint: TestAccount,
+        vault_a: TestAccount,
+        vault_b: TestAccount,
+        user_a: TestAccount,
+        user_b: TestAccount,
+        user_lp: TestAccount,
+        locked_lp: TestAccount,
+        user: TestAccount,
+        fee_recipient: TestAccount,
+        recipient_a: TestAccount,
+        recipient_b: TestAccount,
+        token_program: TestAccount,
+    }
+
+    impl TestPool {
+        fn new(reserve_a: u64, reserve_b: u64, lp_supply: u64) -> Self {
+            let program_id = Pubkey::new_unique();
+            let mut pool = TestAccount::new(&program_id, vec![0; Pool::LEN]);
+            let (pool_authority, authority_bump) =
+                get_pool_authority_address_and_bump_seed(&pool.key, &program_id);
+            let mint_a = Pubkey::new_unique();
+            let mint_b = Pubkey::new_unique();
+            let lp_mint = TestAccount::mint(&pool_authority, lp_supply);
+            let vault_a = TestAccount::token_account(&mint_a, &pool_authority, reserve_a);
+            let vault_b = TestAccount::token_account(&mint_b, &pool_authority, reserve_b);
+            let user = TestAccount::new(&Pubkey::default(), vec![]);
+            let fee_recipient = TestAccount::new(&Pubkey::default(), vec![]).signer();
+
+            let state = Pool {
+                token_a: mint_a,
+                token_b: mint_b,
+                lp_mint: lp_mint.key,
+                token_a_vault: vault_a.key,
+                token_b_vault: vault_b.key,
+                fee_numerator: 3,
+                fee_denominator: 1000,
+                is_initialized: true,
+                authority_bump,
+                protocol_fee_numerator: 1,
+                protocol_fee_denominator: 6,
+                fee_recipient: fee_recipient.key,
+                ..Pool::default()
+            };
+            Pool::pack(state, &mut pool.data).unwrap();
+
+            Self {
+                program_id,
+                mint_b,
+                pool,
+                pool_authority: TestAccount::new(&Pubkey::default(), vec![])
+                    .with_key(&pool_authority),
+                user_a: TestAccount::token_account(&mint_a, &user.key, u64::MAX),
+                user_b: TestAccount::token_account(&mint_b, &user.key, u64::MAX),
+                user_lp: TestAccount::token_account(&lp_mint.key, &user.key, 0),
+                locked_lp: TestAccount::token_account(&lp_mint.key, &pool_authority, 0),
+                recipient_a: TestAccount::token_account(&mint_a, &fee_recipient.key, 0),
+                recipient_b: TestAccount::token_account(&mint_b, &fee_recipient.key, 0),
+                lp_mint,
+                vault_a,
+                vault_b,
+                user,
+                fee_recipient,
+                token_program: TestAccount::new(&Pubkey::default(), vec![])
+                    .with_key(&apl_token::id()),
+            }
+        }
+
+        /// Accounts of a first `AddLiquidity`, of which later deposits and
+        /// `RemoveLiquidity` take all but the last
+        fn liquidity_accounts(&mut self) -> Vec<AccountInfo<'_>> {
+            vec![
+                self.pool.info(),
+                self.vault_a.info(),
+                self.vault_b.info(),
+                self.lp_mint.info(),
+                self.user_a.info(),
+                self.user_b.info(),
+                self.user_lp.info(),
+                self.user.info(),
+                self.pool_authority.info(),
+                self.token_program.info(),
+                self.locked_lp.info(),
+            ]
+        }
+
+        /// Accounts of a `Swap` in the given direction
+        fn swap_accounts(&mut self, a_to_b: bool) -> Vec<AccountInfo<'_>> {
+            let (input_vault, output_vault, user_input, user_output) = if a_to_b {
+                (
+                    &mut self.vault_a,
+                    &mut self.vault_b,
+                    &mut self.user_a,
+                    &mut self.user_b,
+                )
+            } else {
+                (
+                    &mut self.vault_b,
+                    &mut self.vault_a,
+                    &mut self.user_b,
+                    &mut self.user_a,
+                )
+            };
+            vec![
+                self.pool.info(),
+                input_vault.info(),
+                output_vault.info(),
+                user_input.info(),
+                user_output.info(),
+                self.user.info(),
+                self.pool_authority.info(),
+                self.token_program.info(),
+            ]
+        }
+
+        /// Accounts of a `DepositSingleTokenExactIn` or
+        /// `WithdrawSingleTokenExactOut` of the given token
+        fn single_token_accounts(&mut self, is_token_a: bool) -> Vec<AccountInfo<'_>> {
+            let (vault, other_vault, user_token) = if is_token_a {
+                (&mut self.vault_a, &mut self.vault_b, &mut self.user_a)
+            } else {
+                (&mut self.vault_b, &mut self.vault_a, &mut self.user_b)
+            };
+            vec![
+                self.pool.info(),
+                vault.info(),
+                other_vault.info(),
+                self.lp_mint.info(),
+                user_token.info(),
+                self.user_lp.info(),
+                self.user.info(),
+                self.pool_authority.info(),
+                self.token_program.info(),
+            ]
+        }
+
+        /// Accounts of `WithdrawProtocolFees`
+        fn withdraw_accounts(&mut self) -> Vec<AccountInfo<'_>> {
+            vec![
+                self.pool.info(),
+                self.vault_a.info(),
+                self.vault_b.info(),
+                self.recipient_a.info(),
+                self.recipient_b.info(),
+                self.fee_recipient.info(),
+                self.pool_authority.info(),
+                self.token_program.info(),
+            ]
+        }
+
+        /// Sets the vault balances and LP supply, standing in for the token
+        /// transfers that the tests do not execute
+        fn set_balances(&mut self, token_a_amount: u64, token_b_amount: u64, lp_supply: u64) {
+            self.vault_a.set_amount(token_a_amount);
+            self.vault_b.set_amount(token_b_amount);
+            self.lp_mint.set_supply(lp_supply);
+        }
+
+        /// Switches the pool to the StableSwap curve, its last ramp having
+        /// started long enough ago for a new one
+        fn set_stable_swap(&mut self, amp: u64) {
+            let state = Pool {
+                curve_type: CurveType::StableSwap,
+                initial_amp: amp,
+                target_amp: amp,
+                ramp_start_timestamp: -MIN_RAMP_DURATION,
+                ramp_stop_timestamp: -MIN_RAMP_DURATION,
+                ..self.state()
+            };
+            Pool::pack(state, &mut self.pool.data).unwrap();
+        }
+
+        fn state(&self) -> Pool {
+            Pool::unpack(&self.pool.data).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_initialize_pool() {
+        let program_id = Pubkey::new_unique();
+        let mut pool = TestAccount::new(&program_id, vec![0; Pool::LEN]);
+        let (pool_authority, authority_bump) =
+            get_pool_authority_address_and_bump_seed(&pool.key, &program_id);
+        let mut mint_a = TestAccount::mint(&Pubkey::new_unique(), 0);
+        let mut mint_b = TestAccount::mint(&Pubkey::new_unique(), 0);
+        let mut lp_mint = TestAccount::mint(&pool_authority, 0);
+        let mut vault_a = TestAccount::token_account(&mint_a.key, &pool_authority, 0);
+        let mut vault_b = TestAccount::token_account(&mint_b.key, &pool_authority, 0);
+        let mut foreign_vault = TestAccount::token_account(&mint_b.key, &Pubkey::new_unique(), 0);
+        let mut foreign_lp_mint = TestAccount::mint(&Pubkey::new_unique(), 0);
+        let mut issued_lp_mint = TestAccount::mint(&pool_authority, 1);
+        let mut freezable_lp_mint = TestAccount::mint(&pool_authority, 0);
+        let mut mint = Mint::unpack(&freezable_lp_mint.data).unwrap();
+        mint.freeze_authority = COption::Some(Pubkey::new_unique());
+        Mint::pack(mint, &mut freezable_lp_mint.data).unwrap();
+        let mut funded_vault = TestAccount::token_account(&mint_b.key, &pool_authority, 1);
+        let mut delegated_vault = TestAccount::token_account(&mint_b.key, &pool_authority, 0);
+        let mut vault = Account::unpack(&delegated_vault.data).unwrap();
+        vault.delegate = COption::Some(Pubkey::new_unique());
+        vault.delegated_amount = 1;
+        Account::pack(vault, &mut delegated_vault.data).unwrap();
+        let mut closable_vault = TestAccount::token_account(&mint_b.key, &pool_authority, 0);
+        let mut vault = Account::unpack(&closable_vault.data).unwrap();
+        vault.close_authority = COption::Some(Pubkey::new_unique());
+        Account::pack(vault, &mut closable_vault.data).unwrap();
+        let mut authority = TestAccount::new(&Pubkey::default(), vec![]).with_key(&pool_authority);
+        let mut wrong_authority = TestAccount::new(&Pubkey::default(), vec![]);
+        let mut fee_recipient = TestAccount::new(&Pubkey::default(), vec![]);
+
+        let pool_info = pool.info();
+        let mint_a_info = mint_a.info();
+        let mint_b_info = mint_b.info();
+        let lp_mint_info = lp_mint.info();
+        let vault_a_info = vault_a.info();
+        let vault_b_info = vault_b.info();
+        let foreign_vault_info = foreign_vault.info();
+        let foreign_lp_mint_info = foreign_lp_mint.info();
+        let authority_info = authority.info();
+        let wrong_authority_info = wrong_authority.info();
+        let mut accounts = vec![
+            pool_info.clone(),
+            mint_a_info,
+            mint_b_info,
+            lp_mint_info.clone(),
+            vault_a_info,
+            vault_b_info.clone(),
+            wrong_authority_info,
+            fee_recipient.info(),
+        ];
+        assert_eq!(
+            Processor::process_initialize_pool(
+                &program_id,
+                &accounts,
+                25,
+                10000,
+                1,
+                6,
+                CurveType::ConstantProduct,
+                0
+            ),
+            Err(ProgramError::InvalidSeeds)
+        );
+        accounts[6] = authority_info;
+        accounts[5] = foreign_vault_info;
+        assert_eq!(
+            Processor::process_initialize_pool(
+                &program_id,
+                &accounts,
+                25,
+                10000,
+                1,
+                6,
+                CurveType::ConstantProduct,
+                0
+            ),
+            Err(AmmError::InvalidPoolTokens.into())
+        );
+        accounts[5] = vault_b_info;
+        accounts[3] = foreign_lp_mint_info;
+        assert_eq!(
+            Processor::process_initialize_pool(
+                &program_id,
+                &accounts,
+                25,
+                10000,
+                1,
+                6,
+                CurveType::ConstantProduct,
+                0
+            ),
+            Err(AmmError::InvalidPoolTokens.into())
+        );
+        accounts[3] = lp_mint_info;
+
+        // The LP mint must be unissued and unfreezable, and the vaults empty
+        // with no delegate or close authority
+        for (index, account_info) in [
+            (3, issued_lp_mint.info()),
+            (3, freezable_lp_mint.info()),
+            (5, funded_vault.info()),
+            (5, delegated_vault.info()),
+            (5, closable_vault.info()),
+        ] {
+            let valid_info = std::mem::replace(&mut accounts[index], account_info);
+            assert_eq!(
+                Processor::process_initialize_pool(
+                    &program_id,
+                    &accounts,
+                    25,
+                    10000,
+                    1,
+                    6,
+                    CurveType::ConstantProduct,
+                    0
+                ),
+                Err(AmmError::InvalidPoolTokens.into())
+            );
+            accounts[index] = valid_info;
+        }
+
+        assert_eq!(
+            Processor::process_initialize_pool(
+                &program_id,
+                &accounts,
+                25,
+                10000,
+                7,
+                6,
+                CurveType::ConstantProduct,
+                0
+            ),
+            Err(AmmError::InvalidFeeConfig.into())
+        );
+
+        // Only StableSwap pools take an amplification coefficient, within
+        // bounds
+        for (curve_type, amp) in [
+            (CurveType::ConstantProduct, 1),
+            (CurveType::StableSwap, MIN_AMP - 1),
+            (CurveType::StableSwap, MAX_AMP + 1),
+        ] {
+            assert_eq!(
+                Processor::process_initialize_pool(
+                    &program_id,
+                    &accounts,
+                    25,
+                    10000,
+                    1,
+                    6,
+                    curve_type,
+                    amp
+                ),
+                Err(AmmError::InvalidAmp.into())
+            );
+        }
+
+        Processor::process_initialize_pool(
+            &program_id,
+            &accounts,
+            25,
+            10000,
+            1,
+            6,
+            CurveType::ConstantProduct,
+            0,
+        )
+        .unwrap();
+        let state = Pool::unpack(&pool_info.data.borrow()).unwrap();
+        assert_eq!(state.authority_bump, authority_bump);
+        assert_eq!(state.lp_mint, lp_mint.key);
+        assert_eq!(state.token_b_vault, vault_b.key);
+        assert_eq!(state.fee_recipient, fee_recipient.key);
+        assert_eq!(
+            (state.protocol_fee_numerator, state.protocol_fee_denominator),
+            (1, 6)
+        );
+        assert_eq!(state.curve_type, CurveType::ConstantProduct);
+    }
+
+    #[test]
+    fn test_account_validation() {
+        let mut test_pool = TestPool::new(1_000_000, 1_000_000, 1_000_000);
+        let program_id = test_pool.program_id;
+        let pool_authority = test_pool.pool_authority.key;
+        let mut fake_vault =
+            TestAccount::token_account(&test_pool.mint_b, &pool_authority, u64::MAX);
+        let mut foreign_vault =
+            TestAccount::token_account(&test_pool.mint_b, &Pubkey::new_unique(), u64::MAX)
+                .with_key(&test_pool.vault_b.key);
+        let mut fake_lp_mint = TestAccount::mint(&pool_authority, 1);
+        let mut fake_token_program = TestAccount::new(&Pubkey::default(), vec![]);
+        let mut foreign_pool = TestAccount::new(&Pubkey::new_unique(), test_pool.pool.data.clone())
+            .with_key(&test_pool.pool.key);
+        let mut accounts = test_pool.liquidity_accounts();
+
+        Processor::process_add_liquidity(&program_id, &accounts, 1_000, 2_000, 0, 0, 1_000)
+            .unwrap();
+        Processor::process_remove_liquidity(&program_id, &accounts, 1_000, 1_000, 1_000).unwrap();
+
+        let pool_info = std::mem::replace(&mut accounts[0], foreign_pool.info());
+        assert_eq!(
+            Processor::process_add_liquidity(&program_id, &accounts, 1_000, 1_000, 0, 0, 0),
+            Err(ProgramError::IncorrectProgramId)
+        );
+        accounts[0] = pool_info;
+
+        let vault_b_info = std::mem::replace(&mut accounts[2], fake_vault.info());
+        assert_eq!(
+            Processor::process_remove_liquidity(&program_id, &accounts, 1_000, 0, 0),
+            Err(AmmError::InvalidPoolTokens.into())
+        );
+        accounts[2] = foreign_vault.info();
+        assert_eq!(
+            Processor::process_remove_liquidity(&program_id, &accounts, 1_000, 0, 0),
+            Err(AmmError::InvalidPoolTokens.into())
+        );
+        accounts[2] = vault_b_info;
+
+        let lp_mint_info = std::mem::replace(&mut accounts[3], fake_lp_mint.info());
+        assert_eq!(
+            Processor::process_add_liquidity(&program_id, &accounts, 1_000, 1_000, 0, 0, 0),
+            Err(AmmError::InvalidPoolTokens.into())
+        );
+        accounts[3] = lp_mint_info;
+
+        accounts[9] = fake_token_program.info();
+        assert_eq!(
+            Processor::process_add_liquidity(&program_id, &accounts, 1_000, 1_000, 0, 0, 0),
+            Err(ProgramError::IncorrectProgramId)
+        );
+        drop(accounts);
+
+        let mut accounts = test_pool.swap_accounts(true);
+        accounts[2] = fake_vault.info();
+        assert_eq!(
+            Processor::process_swap(&program_id, &accounts, 1_000, 1, true),
+            Err(AmmError::InvalidPoolTokens.into())
+        );
+        accounts[2] = accounts[1].clone();
+        assert_eq!(
+            Processor::process_swap(&program_id, &accounts, 1_000, 1, true),
+            Err(AmmError::InvalidPoolTokens.into())
+        );
+    }
+
+    #[test]
+    fn test_swap() {
+        let mut test_pool = TestPool::new(1_000_000, 2_000_000, 1_000_000);
+        let program_id = test_pool.program_id;
+
+        for a_to_b in [true, false] {
+            let mut accounts = test_pool.swap_accounts(a_to_b);
+            Processor::process_swap(&program_id, &accounts, 1_000, 1, a_to_b).unwrap();
+
+            // The vaults must match the direction
+            assert_eq!(
+                Processor::process_swap(&program_id, &accounts, 1_000, 1, !a_to_b),
+                Err(AmmError::InvalidPoolTokens.into())
+            );
+
+            // The user accounts must hold the input and output tokens
+            accounts.swap(3, 4);
+            assert_eq!(
+                Processor::process_swap(&program_id, &accounts, 1_000, 1, a_to_b),
+                Err(AmmError::InvalidPoolTokens.into())
+            );
+            accounts.swap(3, 4);
+
+            assert_eq!(
+                Processor::process_swap(&program_id, &accounts, 1_000, u64::MAX, a_to_b),
+                Err(AmmError::SlippageExceeded.into())
+            );
+
+            Processor::process_swap_exact_out(&program_id, &accounts, 1_000, u64::MAX, a_to_b)
+                .unwrap();
+            assert_eq!(
+                Processor::process_swap_exact_out(&program_id, &accounts, 1_000, 100, a_to_b),
+                Err(AmmError::SlippageExceeded.into())
+            );
+        }
+    }
+
+    #[test]
+    fn test_protocol_fees() {
+        let mut test_pool = TestPool::new(1_000_000, 1_000_000, 1_000_000);
+        let program_id = test_pool.program_id;
+
+        // 0.3% fee on 100_000 is 300, a sixth of it for the protocol
+        let accounts = test_pool.swap_accounts(true);
+        Processor::process_swap(&program_id, &accounts, 100_000, 1, true).unwrap();
+        drop(accounts);
+        let state = test_pool.state();
+        assert_eq!((state.protocol_fees_a, state.cumulative_fees_a), (50, 300));
+        assert_eq!((state.protocol_fees_b, state.cumulative_fees_b), (0, 0));
+
+        let accounts = test_pool.swap_accounts(false);
+        Processor::process_swap_exact_out(&program_id, &accounts, 10_000, u64::MAX, false).unwrap();
+        drop(accounts);
+        let state = test_pool.state();
+        let amount_in = calculate_swap_input(10_000, 1_000_000, 1_000_000 - 50, 3, 1000).unwrap();
+        assert_eq!(state.cumulative_fees_b, (amount_in * 3 / 1000) as u128);
+        assert_eq!(state.protocol_fees_b, amount_in * 3 / 1000 / 6);
+
+        // Only the fee recipient may withdraw
+        let mut accounts = test_pool.withdraw_accounts();
+        let fee_recipient_info = accounts[5].clone();
+        accounts[5] = accounts[7].clone();
+        assert_eq!(
+            Processor::process_withdraw_protocol_fees(&program_id, &accounts),
+            Err(AmmError::FeeRecipientMismatch.into())
+        );
+        accounts[5] = AccountInfo {
+            is_signer: false,
+            ..fee_recipient_info.clone()
+        };
+        assert_eq!(
+            Processor::process_withdraw_protocol_fees(&program_id, &accounts),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+        accounts[5] = fee_recipient_info;
+        Processor::process_withdraw_protocol_fees(&program_id, &accounts).unwrap();
+        drop(accounts);
+        let state = test_pool.state();
+        assert_eq!((state.protocol_fees_a, state.protocol_fees_b), (0, 0));
+        assert_eq!(state.cumulative_fees_a, 300);
+    }
+
+    #[test]
+    fn test_single_token_liquidity() {
+        let mut test_pool = TestPool::new(1_000_000, 2_000_000, 1_000_000);
+        let program_id = test_pool.program_id;
+
+        // 0.3% fee on the swapped half of 3_000_000 is 4_500, a sixth of it
+        // for the protocol
+        let (lp_amount, fee) =
+            calculate_single_deposit_lp_amount(3_000_000, 1_000_000, 1_000_000, 3, 1000).unwrap();
+        assert_eq!(fee, 4_500);
+        let mut accounts = test_pool.single_token_accounts(true);
+        assert_eq!(
+            Processor::process_deposit_single_token_exact_in(
+                &program_id,
+                &accounts,
+                3_000_000,
+                lp_amount + 1,
+                true
+            ),
+            Err(AmmError::SlippageExceeded.into())
+        );
+        let user_lp_info = accounts[5].clone();
+        let user_token_info = std::mem::replace(&mut accounts[4], user_lp_info);
+        assert_eq!(
+            Processor::process_deposit_single_token_exact_in(
+                &program_id,
+                &accounts,
+                3_000_000,
+                0,
+                true
+            ),
+            Err(AmmError::InvalidPoolTokens.into())
+        );
+        accounts[4] = user_token_info;
+        Processor::process_deposit_single_token_exact_in(
+            &program_id,
+            &accounts,
+            3_000_000,
+            lp_amount,
+            true,
+        )
+        .unwrap();
+        drop(accounts);
+        let state = test_pool.state();
+        assert_eq!(
+            (state.protocol_fees_a, state.cumulative_fees_a),
+            (750, 4_500)
+        );
+        test_pool.set_balances(4_000_000, 2_000_000, 1_000_000 + lp_amount);
+
+        let (lp_amount, fee) =
+            calculate_single_withdraw_lp_amount(500_000, 2_000_000, 1_000_000 + lp_amount, 3, 1000)
+                .unwrap();
+        assert_eq!(fee, 753);
+        let accounts = test_pool.single_token_accounts(false);
+        assert_eq!(
+            Processor::process_withdraw_single_token_exact_out(
+                &program_id,
+                &accounts,
+                500_000,
+                lp_amount - 1,
+                false
+            ),
+            Err(AmmError::SlippageExceeded.into())
+        );
+        assert_eq!(
+            Processor::process_withdraw_single_token_exact_out(
+                &program_id,
+                &accounts,
+                2_000_000,
+                u64::MAX,
+                false
+            ),
+            Err(AmmError::InsufficientLiquidity.into())
+        );
+        Processor::process_withdraw_single_token_exact_out(
+            &program_id,
+            &accounts,
+            500_000,
+            lp_amount,
+            false,
+        )
+        .unwrap();
+        drop(accounts);
+        let state = test_pool.state();
+        assert_eq!((state.protocol_fees_b, state.cumulative_fees_b), (125, 753));
+    }
+
+    #[test]
+    fn test_first_deposit_donation_attack() {
+        let mut test_pool = TestPool::new(0, 0, 0);
+        let program_id = test_pool.program_id;
+
+        // The first deposit must mint more than the locked liquidity
+        let mut accounts = test_pool.liquidity_accounts();
+        assert_eq!(
+            Processor::process_add_liquidity(&program_id, &accounts[..10], 1_001, 1_001, 0, 0, 0),
+            Err(ProgramError::NotEnoughAccountKeys)
+        );
+        assert_eq!(
+            Processor::process_add_liquidity(&program_id, &accounts, 1, 1, 0, 0, 0),
+            Err(AmmError::InsufficientLiquidity.into())
+        );
+        assert_eq!(
+            Processor::process_add_liquidity(&program_id, &accounts, 1_000, 1_000, 0, 0, 0),
+            Err(AmmError::ZeroLpAmount.into())
+        );
+
+        // The minimum liquidity can only be locked with the pool authority
+        let user_lp_info = accounts[6].clone();
+        let locked_lp_info = std::mem::replace(&mut accounts[10], user_lp_info);
+        assert_eq!(
+            Processor::process_add_liquidity(&program_id, &accounts, 1_001, 1_001, 0, 0, 0),
+            Err(AmmError::InvalidPoolTokens.into())
+        );
+        accounts[10] = locked_lp_info;
+
+        // The attacker mints a single LP token for themselves, then donates
+        // to the vaults to inflate the value of each LP token
+        Processor::process_add_liquidity(&program_id, &accounts, 1_001, 1_001, 0, 0, 1).unwrap();
+        drop(accounts);
+        let donation = 1_000_000;
+        test_pool.set_balances(1_001 + donation, 1_001 + donation, MINIMUM_LIQUIDITY + 1);
+
+        // A deposit rounding down to no LP tokens is rejected instead of
+        // being captured by the attacker
+        let accounts = test_pool.liquidity_accounts();
+        assert_eq!(
+            Processor::process_add_liquidity(&program_id, &accounts, 1_000, 1_000, 0, 0, 0),
+            Err(AmmError::ZeroLpAmount.into())
+        );
+
+        // The victim's deposit of 10_000 of each token mints 9 LP tokens
+        assert_eq!(
+            Processor::process_add_liquidity(&program_id, &accounts, 10_000, 10_000, 0, 0, 10),
+            Err(AmmError::SlippageExceeded.into())
+        );
+        Processor::process_add_liquidity(&program_id, &accounts, 10_000, 10_000, 0, 0, 9).unwrap();
+        drop(accounts);
+        let victim_lp = 9;
+        test_pool.set_balances(
+            1_001 + donation + 10_000,
+            1_001 + donation + 10_000,
+            MINIMUM_LIQUIDITY + 1 + victim_lp,
+        );
+
+        // Rounding costs the victim less than a tenth of the deposit, most
+        // of the donation going to the locked liquidity
+        let accounts = test_pool.liquidity_accounts();
+        assert_eq!(
+            Processor::process_remove_liquidity(&program_id, &accounts[..10], victim_lp, 9_009, 0),
+            Err(AmmError::SlippageExceeded.into())
+        );
+        Processor::process_remove_liquidity(&program_id, &accounts[..10], victim_lp, 9_008, 9_008)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_add_liquidity() {
+        let mut test_pool = TestPool::new(1_000_000, 2_000_000, 1_000_000);
+        let program_id = test_pool.program_id;
+        let accounts = test_pool.liquidity_accounts();
+
+        // Only 2_000 of the desired token B is taken for 1_000 token A, so
+        // the excess is not donated to the pool
+        Processor::process_add_liquidity(&program_id, &accounts, 1_000, 5_000, 0, 2_000, 1_000)
+            .unwrap();
+        assert_eq!(
+            Processor::process_add_liquidity(&program_id, &accounts, 1_000, 5_000, 0, 2_001, 0),
+            Err(AmmError::SlippageExceeded.into())
+        );
+
+        // Token B limits the deposit to 500 token A, and deposits after the
+        // first do not need the locked LP account
+        Processor::process_add_liquidity(&program_id, &accounts[..10], 5_000, 1_000, 500, 0, 500)
+            .unwrap();
+        assert_eq!(
+            Processor::process_add_liquidity(&program_id, &accounts, 5_000, 1_000, 501, 0, 0),
+            Err(AmmError::SlippageExceeded.into())
+        );
+        assert_eq!(
+            Processor::process_add_liquidity(&program_id, &accounts, 5_000, 1_000, 0, 0, 501),
+            Err(AmmError::SlippageExceeded.into())
+        );
+    }
+
+    #[test]
+    fn test_stable_swap() {
+        let mut test_pool = TestPool::new(1_000_000, 1_000_000, 2_000_000);
+        test_pool.set_stable_swap(100);
+        let program_id = test_pool.program_id;
+
+        // Swaps near the peg pay out close to one for one, above the 9_970
+        // the constant product curve would
+        let amount_out =
+            calculate_stable_swap_output(10_000, 1_000_000, 1_000_000, 100, 3, 1000).unwrap();
+        assert!(amount_out > 9_960 && amount_out < 9_970);
+        let accounts = test_pool.swap_accounts(true);
+        Processor::process_swap(&program_id, &accounts, 10_000, amount_out, true).unwrap();
+        assert_eq!(
+            Processor::process_swap(&program_id, &accounts, 10_000, amount_out + 1, true),
+            Err(AmmError::SlippageExceeded.into())
+        );
+        Processor::process_swap_exact_out(&program_id, &accounts, 10_000, 10_100, true).unwrap();
+        drop(accounts);
+
+        // Single-sided liquidity is priced on the StableSwap invariant
+        let (lp_amount, _) = calculate_stable_single_deposit_lp_amount(
+            10_000, 1_000_000, 1_000_000, 2_000_000, 100, 3, 1000,
+        )
+        .unwrap();
+        let accounts = test_pool.single_token_accounts(false);
+        Processor::process_deposit_single_token_exact_in(
+            &program_id,
+            &accounts,
+            10_000,
+            lp_amount,
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            Processor::process_deposit_single_token_exact_in(
+                &program_id,
+                &accounts,
+                10_000,
+                lp_amount + 1,
+                false
+            ),
+            Err(AmmError::SlippageExceeded.into())
+        );
+        let protocol_fees = Pool::unpack(&accounts[0].data.borrow())
+            .unwrap()
+            .protocol_fees_b;
+        let (lp_amount, _) = calculate_stable_single_withdraw_lp_amount(
+            10_000,
+            1_000_000 - protocol_fees,
+            1_000_000,
+            2_000_000,
+            100,
+            3,
+            1000,
+        )
+        .unwrap();
+        Processor::process_withdraw_single_token_exact_out(
+            &program_id,
+            &accounts,
+            10_000,
+            lp_amount,
+            false,
+        )
+        .unwrap();
+
+        // The other vault must be the pool's
+        let mut accounts = accounts;
+        accounts[2] = accounts[1].clone();
+        assert_eq!(
+            Processor::process_withdraw_single_token_exact_out(
+                &program_id,
+                &accounts,
+                10_000,
+                u64::MAX,
+                false
+            ),
+            Err(AmmError::InvalidPoolTokens.into())
+        );
+    }
+
+    #[test]
+    fn test_ramp_amp() {
+        let mut test_pool = TestPool::new(1_000_000, 1_000_000, 2_000_000);
+        let program_id = test_pool.program_id;
+
+        // Constant product pools have no amplification to ramp
+        let mut accounts = vec![test_pool.pool.info(), test_pool.fee_recipient.info()];
+        assert_eq!(
+            Processor::process_ramp_amp(&program_id, &accounts, 200, MIN_RAMP_DURATION),
+            Err(AmmError::InvalidPoolState.into())
+        );
+        drop(accounts);
+
+        test_pool.set_stable_swap(100);
+        accounts = vec![test_pool.pool.info(), test_pool.fee_recipient.info()];
+
+        // Only the fee recipient may ramp
+        let fee_recipient_info = accounts[1].clone();
+        accounts[1] = AccountInfo {
+            is_signer: false,
+            ..fee_recipient_info.clone()
+        };
+        assert_eq!(
+            Processor::process_ramp_amp(&program_id, &accounts, 200, MIN_RAMP_DURATION),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+        accounts[1] = accounts[0].clone();
+        assert_eq!(
+            Processor::process_ramp_amp(&program_id, &accounts, 200, MIN_RAMP_DURATION),
+            Err(AmmError::FeeRecipientMismatch.into())
+        );
+        accounts[1] = fee_recipient_info;
+
+        // The ramp must last long enough and change the amplification by at
+        // most a factor of ten
+        for (target_amp, ramp_stop_timestamp) in [
+            (200, MIN_RAMP_DURATION - 1),
+            (1_001, MIN_RAMP_DURATION),
+            (9, MIN_RAMP_DURATION),
+            (MAX_AMP + 1, MIN_RAMP_DURATION),
+        ] {
+            assert_eq!(
+                Processor::process_ramp_amp(
+                    &program_id,
+                    &accounts,
+                    target_amp,
+                    ramp_stop_timestamp
+                ),
+                Err(AmmError::InvalidAmp.into())
+            );
+        }
+
+        Processor::process_ramp_amp(&program_id, &accounts, 1_000, MIN_RAMP_DURATION).unwrap();
+        drop(accounts);
+        let state = test_pool.state();
+        assert_eq!((state.initial_amp, state.target_amp), (100, 1_000));
+        assert_eq!(
+            (state.ramp_start_timestamp, state.ramp_stop_timestamp),
+            (0, MIN_RAMP_DURATION)
+        );
+
+        // Ramps must be spaced by the minimum duration
+        let accounts = vec![test_pool.pool.info(), test_pool.fee_recipient.info()];
+        assert_eq!(
+            Processor::process_ramp_amp(&program_id, &accounts, 100, MIN_RAMP_DURATION),
+            Err(AmmError::InvalidAmp.into())
+        );
+    }
+
+    #[test]
+    fn test_cumulative_prices() {
+        let mut test_pool = TestPool::new(1_000_000, 2_000_000, 1_000_000);
+        let program_id = test_pool.program_id;
+        let state = Pool {
+            reserve_a_last: 1_000_000,
+            reserve_b_last: 2_000_000,
+            last_update_timestamp: -100,
+            ..test_pool.state()
+        };
+        Pool::pack(state, &mut test_pool.pool.data).unwrap();
+
+        // The reserves before the swap accrue over the 100 seconds since the
+        // last update, the reserves after it being recorded
+        let amount_out = calculate_swap_output(1_000, 1_000_000, 2_000_000, 3, 1000).unwrap();
+        let accounts = test_pool.swap_accounts(true);
+        Processor::process_swap(&program_id, &accounts, 1_000, 1, true).unwrap();
+        drop(accounts);
+        let state = test_pool.state();
+        assert_eq!(
+            (state.price_a_cumulative, state.price_b_cumulative),
+            (200 << PRICE_FRACTIONAL_BITS, 50 << PRICE_FRACTIONAL_BITS)
+        );
+        assert_eq!(
+            (state.reserve_a_last, state.reserve_b_last),
+            (1_001_000, 2_000_000 - amount_out)
+        );
+        assert_eq!(state.last_update_timestamp, 0);
+
+        // Liquidity changes record the reserves too
+        let accounts = test_pool.liquidity_accounts();
+        Processor::process_remove_liquidity(&program_id, &accounts[..10], 500_000, 0, 0).unwrap();
+        drop(accounts);
+        let state = test_pool.state();
+        assert_eq!(
+            (state.reserve_a_last, state.reserve_b_last),
+            (500_000, 1_000_000)
+        );
+        assert_eq!(state.price_a_cumulative, 200 << PRICE_FRACTIONAL_BITS);
+    }
+
+    #[test]
+    fn test_oracle() {
+        let mut test_pool = TestPool::new(1_000_000, 2_000_000, 1_000_000);
+        let program_id = test_pool.program_id;
+        let state = Pool {
+            reserve_a_last: 1_000_000,
+            reserve_b_last: 2_000_000,
+            last_update_timestamp: -100,
+            ..test_pool.state()
+        };
+        Pool::pack(state, &mut test_pool.pool.data).unwrap();
+        let pool_key = test_pool.pool.key;
+        let (oracle_key, bump) = get_oracle_address_and_bump_seed(&pool_key, &program_id);
+        let mut oracle = TestAccount::new(&program_id, vec![0; Oracle::LEN]).with_key(&oracle_key);
+        let mut wrong_oracle = TestAccount::new(&program_id, vec![0; Oracle::LEN]);
+        let mut system_program = TestAccount::new(&Pubkey::default(), vec![]);
+        let pool_info = test_pool.pool.info();
+        let oracle_info = oracle.info();
+        let mut accounts = vec![
+            pool_info.clone(),
+            wrong_oracle.info(),
+            system_program.info(),
+        ];
+
+        assert_eq!(
+            Processor::process_initialize_oracle(&program_id, &accounts, [0; 32], 0),
+            Err(ProgramError::InvalidSeeds)
+        );
+        accounts[1] = oracle_info.clone();
+        Processor::process_initialize_oracle(&program_id, &accounts, [0; 32], 0).unwrap();
+        let mut state = Oracle::unpack(&oracle_info.data.borrow()).unwrap();
+        assert_eq!((state.pool, state.bump, state.len), (pool_key, bump, 1));
+        assert_eq!(
+            state.latest(),
+            Some(&Observation {
+                timestamp: 0,
+                price_a_cumulative: 200 << PRICE_FRACTIONAL_BITS,
+                price_b_cumulative: 50 << PRICE_FRACTIONAL_BITS,
+            })
+        );
+
+        // Record over an observation moved back in time, standing in for the
+        // clock moving forward
+        state.observations[0] =
+            Observation::new(&Pool::unpack(&pool_info.data.borrow()).unwrap(), -40);
+        Oracle::pack(state, &mut oracle_info.data.borrow_mut()).unwrap();
+        Processor::process_record_observation(&program_id, &accounts[..2]).unwrap();
+        let state = Oracle::unpack(&oracle_info.data.borrow()).unwrap();
+        assert_eq!(state.len, 2);
+        let start = state.observation_at_or_before(-1).unwrap();
+        assert_eq!(
+            state.latest().unwrap().twap(start).unwrap(),
+            (2 << PRICE_FRACTIONAL_BITS, 1 << (PRICE_FRACTIONAL_BITS - 1))
+        );
+
+        // Only the first observation of a second is kept
+        Processor::process_record_observation(&program_id, &accounts[..2]).unwrap();
+        assert_eq!(Oracle::unpack(&oracle_info.data.borrow()).unwrap().len, 2);
+
+        // The oracle must be the pool's
+        let mut other_pool = TestAccount::new(&program_id, pool_info.data.borrow().to_vec());
+        accounts[0] = other_pool.info();
+        assert_eq!(
+            Processor::process_record_observation(&program_id, &accounts[..2]),
+            Err(AmmError::InvalidObservation.into())
+        );
+    }
 
     // TODO: Add tests for each instruction
-    // - test_initialize_pool
-    // - test_add_liquidity
     // - test_remove_liquidity
-    // - test_swap
 }
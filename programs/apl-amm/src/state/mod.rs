@@ -1,2 +1,3 @@
+pub mod oracle;
 pub mod pool;
-pub use pool::*;
+pub use {oracle::*, pool::*};
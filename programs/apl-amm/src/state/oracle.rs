@@ -0,0 +1,292 @@
+//! Oracle state definitions
+
+use {
+    super::Pool,
+    crate::{
+        error::AmmError,
+        processor::math::{calculate_cumulative_prices, calculate_twap},
+    },
+    arch_program::{
+        program_error::ProgramError,
+        program_pack::{IsInitialized, Pack, Sealed},
+        pubkey::Pubkey,
+    },
+    arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs},
+};
+
+/// Observations kept by an oracle, the oldest being overwritten once full
+pub const ORACLE_CAPACITY: usize = 32;
+
+/// Cumulative prices of a pool at a point in time.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Observation {
+    /// Unix timestamp of the observation
+    pub timestamp: i64,
+    /// Cumulative price of token A in token B
+    pub price_a_cumulative: u128,
+    /// Cumulative price of token B in token A
+    pub price_b_cumulative: u128,
+}
+
+impl Observation {
+    /// Length of a packed observation
+    pub const LEN: usize = 8 + 16 * 2;
+
+    /// Observes the cumulative prices of a pool at a timestamp, accruing its
+    /// last reserves since its last update
+    pub fn new(pool: &Pool, timestamp: i64) -> Self {
+        let (price_a_cumulative, price_b_cumulative) = calculate_cumulative_prices(
+            pool.price_a_cumulative,
+            pool.price_b_cumulative,
+            pool.reserve_a_last,
+            pool.reserve_b_last,
+            timestamp.saturating_sub(pool.last_update_timestamp),
+        );
+        Self {
+            timestamp,
+            price_a_cumulative,
+            price_b_cumulative,
+        }
+    }
+
+    /// Calculates the time-weighted average prices of token A and token B
+    /// from an earlier observation to this one, as Q64.64 fixed-point numbers
+    pub fn twap(&self, earlier: &Observation) -> Result<(u128, u128), ProgramError> {
+        Ok((
+            calculate_twap(
+                earlier.price_a_cumulative,
+                self.price_a_cumulative,
+                earlier.timestamp,
+                self.timestamp,
+            )?,
+            calculate_twap(
+                earlier.price_b_cumulative,
+                self.price_b_cumulative,
+                earlier.timestamp,
+                self.timestamp,
+            )?,
+        ))
+    }
+
+    fn unpack_from_slice(src: &[u8; Observation::LEN]) -> Self {
+        let (timestamp, price_a_cumulative, price_b_cumulative) = array_refs![src, 8, 16, 16];
+        Self {
+            timestamp: i64::from_le_bytes(*timestamp),
+            price_a_cumulative: u128::from_le_bytes(*price_a_cumulative),
+            price_b_cumulative: u128::from_le_bytes(*price_b_cumulative),
+        }
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8; Observation::LEN]) {
+        let (timestamp_dst, price_a_cumulative_dst, price_b_cumulative_dst) =
+            mut_array_refs![dst, 8, 16, 16];
+        *timestamp_dst = self.timestamp.to_le_bytes();
+        *price_a_cumulative_dst = self.price_a_cumulative.to_le_bytes();
+        *price_b_cumulative_dst = self.price_b_cumulative.to_le_bytes();
+    }
+}
+
+/// Ring buffer of the observations of a pool, for lookbacks on chain.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Oracle {
+    /// Is `true` if this structure has been initialized
+    pub is_initialized: bool,
+    /// Bump seed of the oracle, derived from the pool address
+    pub bump: u8,
+    /// Pool observed
+    pub pool: Pubkey,
+    /// Position of the latest observation
+    pub index: u16,
+    /// Number of observations recorded, up to the capacity
+    pub len: u16,
+    /// Observations, the oldest following the latest once full
+    pub observations: [Observation; ORACLE_CAPACITY],
+}
+
+impl Oracle {
+    /// Records an observation, overwriting the oldest one once full. Only the
+    /// first observation of each second is kept.
+    pub fn record(&mut self, observation: Observation) -> Result<(), ProgramError> {
+        match self.latest() {
+            Some(latest) if observation.timestamp < latest.timestamp => {
+                return Err(AmmError::InvalidObservation.into());
+            }
+            Some(latest) if observation.timestamp == latest.timestamp => return Ok(()),
+            Some(_) => self.index = (self.index + 1) % ORACLE_CAPACITY as u16,
+            None => self.index = 0,
+        }
+        self.observations[self.index as usize] = observation;
+        self.len = (self.len + 1).min(ORACLE_CAPACITY as u16);
+        Ok(())
+    }
+
+    /// Latest observation
+    pub fn latest(&self) -> Option<&Observation> {
+        (self.len > 0).then(|| &self.observations[self.index as usize])
+    }
+
+    /// Latest observation at or before a timestamp, from which to average
+    /// the prices over a lookback window
+    pub fn observation_at_or_before(&self, timestamp: i64) -> Option<&Observation> {
+        (0..self.len as usize)
+            .map(|age| {
+                &self.observations[(self.index as usize + ORACLE_CAPACITY - age) % ORACLE_CAPACITY]
+            })
+            .find(|observation| observation.timestamp <= timestamp)
+    }
+}
+
+impl Sealed for Oracle {}
+impl IsInitialized for Oracle {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for Oracle {
+    const LEN: usize = 1 + 1 + 32 + 2 + 2 + Observation::LEN * ORACLE_CAPACITY;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 1318];
+        let (is_initialized, bump, pool, index, len, observations) =
+            array_refs![src, 1, 1, 32, 2, 2, Observation::LEN * ORACLE_CAPACITY];
+        let index = u16::from_le_bytes(*index);
+        let len = u16::from_le_bytes(*len);
+        if index as usize >= ORACLE_CAPACITY || len as usize > ORACLE_CAPACITY {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut oracle = Oracle {
+            is_initialized: match is_initialized {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            bump: bump[0],
+            pool: Pubkey::from_slice(pool),
+            index,
+            len,
+            ..Oracle::default()
+        };
+        for (observation, src) in oracle
+            .observations
+            .iter_mut()
+            .zip(observations.chunks_exact(Observation::LEN))
+        {
+            *observation = Observation::unpack_from_slice(array_ref![src, 0, Observation::LEN]);
+        }
+        Ok(oracle)
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 1318];
+        let (is_initialized_dst, bump_dst, pool_dst, index_dst, len_dst, observations_dst) =
+            mut_array_refs![dst, 1, 1, 32, 2, 2, Observation::LEN * ORACLE_CAPACITY];
+
+        is_initialized_dst[0] = self.is_initialized as u8;
+        bump_dst[0] = self.bump;
+        pool_dst.copy_from_slice(self.pool.as_ref());
+        *index_dst = self.index.to_le_bytes();
+        *len_dst = self.len.to_le_bytes();
+        for (observation, dst) in self
+            .observations
+            .iter()
+            .zip(observations_dst.chunks_exact_mut(Observation::LEN))
+        {
+            observation.pack_into_slice(array_mut_ref![dst, 0, Observation::LEN]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn observation(timestamp: i64) -> Observation {
+        Observation {
+            timestamp,
+            price_a_cumulative: timestamp as u128 * 2,
+            price_b_cumulative: timestamp as u128 * 3,
+        }
+    }
+
+    #[test]
+    fn test_pack_unpack() {
+        let mut oracle = Oracle {
+            is_initialized: true,
+            bump: 254,
+            pool: Pubkey::new_unique(),
+            ..Oracle::default()
+        };
+        for timestamp in 1..=ORACLE_CAPACITY as i64 + 3 {
+            oracle.record(observation(timestamp)).unwrap();
+        }
+
+        let mut packed = vec![0u8; Oracle::LEN];
+        Oracle::pack(oracle, &mut packed).unwrap();
+        let unpacked = Oracle::unpack(&packed).unwrap();
+        assert_eq!(oracle, unpacked);
+
+        packed[34] = ORACLE_CAPACITY as u8;
+        assert_eq!(
+            Oracle::unpack_unchecked(&packed),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn test_record() {
+        let mut oracle = Oracle::default();
+        assert_eq!(oracle.latest(), None);
+        assert_eq!(oracle.observation_at_or_before(i64::MAX), None);
+
+        oracle.record(observation(10)).unwrap();
+        oracle.record(observation(20)).unwrap();
+        assert_eq!(
+            oracle.record(observation(19)),
+            Err(AmmError::InvalidObservation.into())
+        );
+
+        // Only the first observation of a second is kept
+        oracle
+            .record(Observation {
+                price_a_cumulative: 0,
+                ..observation(20)
+            })
+            .unwrap();
+        assert_eq!(oracle.latest(), Some(&observation(20)));
+        assert_eq!(oracle.len, 2);
+
+        // Once full, the oldest observations are overwritten
+        for timestamp in 3..=ORACLE_CAPACITY as i64 + 1 {
+            oracle.record(observation(timestamp * 10)).unwrap();
+        }
+        assert_eq!(oracle.len as usize, ORACLE_CAPACITY);
+        assert_eq!(oracle.index, 0);
+        assert_eq!(oracle.observation_at_or_before(15), None);
+        assert_eq!(oracle.observation_at_or_before(25), Some(&observation(20)));
+        assert_eq!(
+            oracle.observation_at_or_before(i64::MAX),
+            Some(&observation(ORACLE_CAPACITY as i64 * 10 + 10))
+        );
+    }
+
+    #[test]
+    fn test_twap() {
+        let pool = Pool {
+            reserve_a_last: 1_000,
+            reserve_b_last: 2_000,
+            price_a_cumulative: 7,
+            price_b_cumulative: 8,
+            last_update_timestamp: 100,
+            ..Pool::default()
+        };
+
+        // The last reserves accrue until the observation
+        let start = Observation::new(&pool, 100);
+        let end = Observation::new(&pool, 160);
+        assert_eq!((start.price_a_cumulative, start.price_b_cumulative), (7, 8));
+        assert_eq!(end.twap(&start).unwrap(), (2 << 64, 1 << 63));
+        assert_eq!(start.twap(&end), Err(AmmError::InvalidObservation.into()));
+    }
+}
@@ -7,8 +7,22 @@ use {
         pubkey::Pubkey,
     },
     arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs},
+    num_derive::FromPrimitive,
+    num_traits::FromPrimitive,
 };
 
+/// Curve pricing the swaps of a pool.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, FromPrimitive)]
+pub enum CurveType {
+    /// Constant product invariant `x * y = k`
+    #[default]
+    ConstantProduct,
+    /// StableSwap invariant, flatter around the balanced point the higher
+    /// the amplification coefficient, for pegged assets
+    StableSwap,
+}
+
 /// Pool data.
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
@@ -29,6 +43,45 @@ pub struct Pool {
     pub fee_denominator: u16,
     /// Is `true` if this structure has been initialized
     pub is_initialized: bool,
+    /// Bump seed of the pool authority
+    pub authority_bump: u8,
+    /// Protocol fee numerator, the protocol share of the swap fees being
+    /// numerator/denominator
+    pub protocol_fee_numerator: u16,
+    /// Protocol fee denominator
+    pub protocol_fee_denominator: u16,
+    /// Account allowed to withdraw the protocol fees
+    pub fee_recipient: Pubkey,
+    /// Protocol fees of token A owed to the fee recipient, held in the vault
+    /// but not part of the reserves
+    pub protocol_fees_a: u64,
+    /// Protocol fees of token B owed to the fee recipient
+    pub protocol_fees_b: u64,
+    /// Total swap fees ever charged in token A, protocol share included
+    pub cumulative_fees_a: u128,
+    /// Total swap fees ever charged in token B, protocol share included
+    pub cumulative_fees_b: u128,
+    /// Curve pricing the swaps
+    pub curve_type: CurveType,
+    /// StableSwap amplification coefficient at the start of the ramp
+    pub initial_amp: u64,
+    /// StableSwap amplification coefficient at the end of the ramp
+    pub target_amp: u64,
+    /// Unix timestamp of the start of the amplification ramp
+    pub ramp_start_timestamp: i64,
+    /// Unix timestamp of the end of the amplification ramp
+    pub ramp_stop_timestamp: i64,
+    /// Reserve of token A after the last swap or liquidity change
+    pub reserve_a_last: u64,
+    /// Reserve of token B after the last swap or liquidity change
+    pub reserve_b_last: u64,
+    /// Sum over time of the price of token A in token B, as a Q64.64
+    /// fixed-point number per second, wrapping around on overflow
+    pub price_a_cumulative: u128,
+    /// Sum over time of the price of token B in token A
+    pub price_b_cumulative: u128,
+    /// Unix timestamp of the last update of the cumulative prices
+    pub last_update_timestamp: i64,
 }
 
 impl Sealed for Pool {}
@@ -39,10 +92,11 @@ impl IsInitialized for Pool {
 }
 
 impl Pack for Pool {
-    const LEN: usize = 32 * 5 + 2 * 2 + 1;
+    const LEN: usize =
+        32 * 5 + 2 * 2 + 1 + 1 + 2 * 2 + 32 + 8 * 2 + 16 * 2 + 1 + 8 * 4 + 8 * 2 + 16 * 2 + 8;
 
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
-        let src = array_ref![src, 0, 165];
+        let src = array_ref![src, 0, 339];
         let (
             token_a,
             token_b,
@@ -52,7 +106,28 @@ impl Pack for Pool {
             fee_numerator,
             fee_denominator,
             is_initialized,
-        ) = array_refs![src, 32, 32, 32, 32, 32, 2, 2, 1];
+            authority_bump,
+            protocol_fee_numerator,
+            protocol_fee_denominator,
+            fee_recipient,
+            protocol_fees_a,
+            protocol_fees_b,
+            cumulative_fees_a,
+            cumulative_fees_b,
+            curve_type,
+            initial_amp,
+            target_amp,
+            ramp_start_timestamp,
+            ramp_stop_timestamp,
+            reserve_a_last,
+            reserve_b_last,
+            price_a_cumulative,
+            price_b_cumulative,
+            last_update_timestamp,
+        ) = array_refs![
+            src, 32, 32, 32, 32, 32, 2, 2, 1, 1, 2, 2, 32, 8, 8, 16, 16, 1, 8, 8, 8, 8, 8, 8, 16,
+            16, 8
+        ];
 
         Ok(Pool {
             token_a: Pubkey::from_slice(token_a),
@@ -67,11 +142,30 @@ impl Pack for Pool {
                 [1] => true,
                 _ => return Err(ProgramError::InvalidAccountData),
             },
+            authority_bump: authority_bump[0],
+            protocol_fee_numerator: u16::from_le_bytes(*protocol_fee_numerator),
+            protocol_fee_denominator: u16::from_le_bytes(*protocol_fee_denominator),
+            fee_recipient: Pubkey::from_slice(fee_recipient),
+            protocol_fees_a: u64::from_le_bytes(*protocol_fees_a),
+            protocol_fees_b: u64::from_le_bytes(*protocol_fees_b),
+            cumulative_fees_a: u128::from_le_bytes(*cumulative_fees_a),
+            cumulative_fees_b: u128::from_le_bytes(*cumulative_fees_b),
+            curve_type: CurveType::from_u8(curve_type[0])
+                .ok_or(ProgramError::InvalidAccountData)?,
+            initial_amp: u64::from_le_bytes(*initial_amp),
+            target_amp: u64::from_le_bytes(*target_amp),
+            ramp_start_timestamp: i64::from_le_bytes(*ramp_start_timestamp),
+            ramp_stop_timestamp: i64::from_le_bytes(*ramp_stop_timestamp),
+            reserve_a_last: u64::from_le_bytes(*reserve_a_last),
+            reserve_b_last: u64::from_le_bytes(*reserve_b_last),
+            price_a_cumulative: u128::from_le_bytes(*price_a_cumulative),
+            price_b_cumulative: u128::from_le_bytes(*price_b_cumulative),
+            last_update_timestamp: i64::from_le_bytes(*last_update_timestamp),
         })
     }
 
     fn pack_into_slice(&self, dst: &mut [u8]) {
-        let dst = array_mut_ref![dst, 0, 165];
+        let dst = array_mut_ref![dst, 0, 339];
         let (
             token_a_dst,
             token_b_dst,
@@ -81,7 +175,28 @@ impl Pack for Pool {
             fee_numerator_dst,
             fee_denominator_dst,
             is_initialized_dst,
-        ) = mut_array_refs![dst, 32, 32, 32, 32, 32, 2, 2, 1];
+            authority_bump_dst,
+            protocol_fee_numerator_dst,
+            protocol_fee_denominator_dst,
+            fee_recipient_dst,
+            protocol_fees_a_dst,
+            protocol_fees_b_dst,
+            cumulative_fees_a_dst,
+            cumulative_fees_b_dst,
+            curve_type_dst,
+            initial_amp_dst,
+            target_amp_dst,
+            ramp_start_timestamp_dst,
+            ramp_stop_timestamp_dst,
+            reserve_a_last_dst,
+            reserve_b_last_dst,
+            price_a_cumulative_dst,
+            price_b_cumulative_dst,
+            last_update_timestamp_dst,
+        ) = mut_array_refs![
+            dst, 32, 32, 32, 32, 32, 2, 2, 1, 1, 2, 2, 32, 8, 8, 16, 16, 1, 8, 8, 8, 8, 8, 8, 16,
+            16, 8
+        ];
 
         token_a_dst.copy_from_slice(self.token_a.as_ref());
         token_b_dst.copy_from_slice(self.token_b.as_ref());
@@ -91,6 +206,24 @@ impl Pack for Pool {
         *fee_numerator_dst = self.fee_numerator.to_le_bytes();
         *fee_denominator_dst = self.fee_denominator.to_le_bytes();
         is_initialized_dst[0] = self.is_initialized as u8;
+        authority_bump_dst[0] = self.authority_bump;
+        *protocol_fee_numerator_dst = self.protocol_fee_numerator.to_le_bytes();
+        *protocol_fee_denominator_dst = self.protocol_fee_denominator.to_le_bytes();
+        fee_recipient_dst.copy_from_slice(self.fee_recipient.as_ref());
+        *protocol_fees_a_dst = self.protocol_fees_a.to_le_bytes();
+        *protocol_fees_b_dst = self.protocol_fees_b.to_le_bytes();
+        *cumulative_fees_a_dst = self.cumulative_fees_a.to_le_bytes();
+        *cumulative_fees_b_dst = self.cumulative_fees_b.to_le_bytes();
+        curve_type_dst[0] = self.curve_type as u8;
+        *initial_amp_dst = self.initial_amp.to_le_bytes();
+        *target_amp_dst = self.target_amp.to_le_bytes();
+        *ramp_start_timestamp_dst = self.ramp_start_timestamp.to_le_bytes();
+        *ramp_stop_timestamp_dst = self.ramp_stop_timestamp.to_le_bytes();
+        *reserve_a_last_dst = self.reserve_a_last.to_le_bytes();
+        *reserve_b_last_dst = self.reserve_b_last.to_le_bytes();
+        *price_a_cumulative_dst = self.price_a_cumulative.to_le_bytes();
+        *price_b_cumulative_dst = self.price_b_cumulative.to_le_bytes();
+        *last_update_timestamp_dst = self.last_update_timestamp.to_le_bytes();
     }
 }
 
@@ -109,11 +242,35 @@ mod tests {
             fee_numerator: 25,
             fee_denominator: 10000,
             is_initialized: true,
+            authority_bump: 255,
+            protocol_fee_numerator: 1,
+            protocol_fee_denominator: 6,
+            fee_recipient: Pubkey::new_unique(),
+            protocol_fees_a: 7,
+            protocol_fees_b: 8,
+            cumulative_fees_a: u64::MAX as u128 + 9,
+            cumulative_fees_b: 10,
+            curve_type: CurveType::StableSwap,
+            initial_amp: 100,
+            target_amp: 200,
+            ramp_start_timestamp: 1_700_000_000,
+            ramp_stop_timestamp: 1_700_086_400,
+            reserve_a_last: 11,
+            reserve_b_last: 12,
+            price_a_cumulative: u128::MAX - 13,
+            price_b_cumulative: 14,
+            last_update_timestamp: 1_700_000_015,
         };
 
         let mut packed = vec![0u8; Pool::LEN];
         Pool::pack(pool, &mut packed).unwrap();
         let unpacked = Pool::unpack(&packed).unwrap();
         assert_eq!(pool, unpacked);
+
+        packed[250] = 2;
+        assert_eq!(
+            Pool::unpack_unchecked(&packed),
+            Err(ProgramError::InvalidAccountData)
+        );
     }
 }
@@ -127,7 +127,13 @@ pub const MAX_RETURN_DATA: usize = 1024;
 /// The maximum size of return data is [`MAX_RETURN_DATA`]. Return data is
 /// retrieved by the caller with [`get_return_data`].
 pub fn set_return_data(data: &[u8]) {
-    unsafe { crate::syscalls::sol_set_return_data(data.as_ptr(), data.len() as u64) };
+    #[cfg(target_os = "solana")]
+    unsafe {
+        crate::syscalls::sol_set_return_data(data.as_ptr(), data.len() as u64)
+    };
+
+    #[cfg(not(target_os = "solana"))]
+    crate::program_stubs::sol_set_return_data(data.as_ptr(), data.len() as u64);
 }
 
 /// Get the return data from an invoked program.
@@ -232,8 +238,20 @@ pub fn get_bitcoin_block_height() -> u64 {
     unsafe { crate::syscalls::arch_get_bitcoin_block_height() }
 }
 
+/// Gets the clock of the current slot.
+///
+/// Off-chain, the host stub leaves the clock at [`Clock::default`], so every
+/// call returns slot 0 and unix timestamp 0.
 pub fn get_clock() -> Clock {
     let mut clock = Clock::default();
-    unsafe { crate::syscalls::arch_get_clock(&mut clock) };
+
+    #[cfg(target_os = "solana")]
+    unsafe {
+        crate::syscalls::arch_get_clock(&mut clock)
+    };
+
+    #[cfg(not(target_os = "solana"))]
+    crate::program_stubs::arch_get_clock(&mut clock);
+
     clock
 }
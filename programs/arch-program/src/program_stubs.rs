@@ -2,7 +2,15 @@
 
 pub const UNIMPLEMENTED: u64 = 0;
 use crate::{
-    account::AccountInfo, entrypoint::ProgramResult, instruction::Instruction, pubkey::Pubkey,
+    account::AccountInfo,
+    clock::Clock,
+    entrypoint::ProgramResult,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    sol_secp256k1_recover::{
+        Secp256k1RecoverError, HASH_BYTES, SECP256K1_PUBLIC_KEY_LENGTH, SECP256K1_SIGNATURE_LENGTH,
+        SUCCESS,
+    },
     utxo::UtxoMeta,
 };
 
@@ -21,8 +29,19 @@ pub(crate) fn sol_memmove(_dst: *mut u8, _src: *const u8, _n: usize) {
 pub(crate) fn sol_memcpy(_dst: *mut u8, _src: *const u8, _n: usize) {
     sol_log("UNAVAILABLE");
 }
-pub(crate) fn sol_memcmp(_s1: *const u8, _s2: *const u8, _n: usize, _result: *mut i32) {
-    sol_log("UNAVAILABLE");
+/// Compares bytes like the syscall, as programs rely on it to compare keys.
+pub(crate) fn sol_memcmp(s1: *const u8, s2: *const u8, n: usize, result: *mut i32) {
+    let mut i = 0;
+    while i < n {
+        let a = unsafe { *s1.add(i) };
+        let b = unsafe { *s2.add(i) };
+        if a != b {
+            unsafe { *result = a as i32 - b as i32 };
+            return;
+        }
+        i += 1;
+    }
+    unsafe { *result = 0 };
 }
 pub(crate) fn sol_set_return_data(_data: *const u8, _length: u64) {
     sol_log("UNAVAILABLE");
@@ -54,6 +73,12 @@ pub(crate) fn arch_validate_utxo_ownership(_utxo: *const UtxoMeta, _owner: *cons
     UNIMPLEMENTED
 }
 pub(crate) fn arch_get_account_script_pubkey(_buf: &mut [u8; 34], _pubkey: &Pubkey) {}
+/// Leaves the clock untouched, so `get_clock` returns `Clock::default()`
+/// (slot 0, unix timestamp 0) off-chain.
+pub(crate) fn arch_get_clock(_clock: *mut Clock) -> u64 {
+    sol_log("UNAVAILABLE");
+    UNIMPLEMENTED
+}
 
 pub(crate) fn sol_invoke_signed(
     _instruction_addr: &Instruction,
@@ -64,12 +89,34 @@ pub(crate) fn sol_invoke_signed(
     Ok(())
 }
 
+/// Recovers the key like the syscall, so signature checks can run off-chain.
 pub(crate) fn sol_secp256k1_recover(
-    _hash_addr: *const u8,
-    _recovery_id_val: u64,
-    _signature_addr: *const u8,
-    _result_addr: *mut u8,
+    hash_addr: *const u8,
+    recovery_id_val: u64,
+    signature_addr: *const u8,
+    result_addr: *mut u8,
 ) -> u64 {
-    sol_log("UNAVAILABLE");
-    UNIMPLEMENTED
+    let hash = unsafe { std::slice::from_raw_parts(hash_addr, HASH_BYTES) };
+    let signature =
+        unsafe { std::slice::from_raw_parts(signature_addr, SECP256K1_SIGNATURE_LENGTH) };
+    let Ok(message) = libsecp256k1::Message::parse_slice(hash) else {
+        return Secp256k1RecoverError::InvalidHash.into();
+    };
+    let Some(recovery_id) = u8::try_from(recovery_id_val)
+        .ok()
+        .and_then(|recovery_id| libsecp256k1::RecoveryId::parse(recovery_id).ok())
+    else {
+        return Secp256k1RecoverError::InvalidRecoveryId.into();
+    };
+    let Ok(signature) = libsecp256k1::Signature::parse_standard_slice(signature) else {
+        return Secp256k1RecoverError::InvalidSignature.into();
+    };
+    let Ok(pubkey) = libsecp256k1::recover(&message, &signature, &recovery_id) else {
+        return Secp256k1RecoverError::InvalidSignature.into();
+    };
+    // The syscall returns the uncompressed key without its format prefix
+    let result =
+        unsafe { std::slice::from_raw_parts_mut(result_addr, SECP256K1_PUBLIC_KEY_LENGTH) };
+    result.copy_from_slice(&pubkey.serialize()[1..]);
+    SUCCESS
 }
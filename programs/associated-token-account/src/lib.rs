@@ -2,13 +2,13 @@
 
 mod tools;
 
+use apl_token::extension::{get_required_account_extensions, AccountType, ExtensionType};
 use arch_program::{
     account::{next_account_info, AccountInfo},
     entrypoint::ProgramResult,
     msg,
     program::{invoke, invoke_signed},
     program_error::ProgramError,
-    program_pack::Pack,
     pubkey::Pubkey,
     system_instruction,
 };
@@ -67,12 +67,17 @@ pub fn process_instruction(
         associated_token_account_signer_seeds,
     )?;
 
+    let account_len = ExtensionType::try_calculate_account_len(
+        AccountType::Account,
+        &get_required_account_extensions(&spl_token_mint_info.data.borrow())?,
+    )?;
+
     msg!("Allocate space for the associated token account");
     invoke_signed(
         &system_instruction::write_bytes(
             0,
-            apl_token::state::Account::LEN as u32,
-            vec![0; apl_token::state::Account::LEN],
+            account_len as u32,
+            vec![0; account_len],
             associated_token_account_info.key.clone(),
         ),
         &[
@@ -92,6 +97,8 @@ pub fn process_instruction(
         &[&associated_token_account_signer_seeds],
     )?;
 
+    // The token program starts the account in the mint's default state, so
+    // associated accounts of permissioned mints are created frozen
     msg!("Initialize the associated token account");
     invoke(
         &apl_token::instruction::initialize_account(
@@ -0,0 +1,94 @@
+//! Error types
+
+use {
+    arch_program::{
+        decode_error::DecodeError,
+        msg,
+        program_error::{PrintProgramError, ProgramError},
+    },
+    num_derive::FromPrimitive,
+    thiserror::Error,
+};
+
+/// Errors that may be returned by the Escrow program.
+#[derive(Clone, Debug, Eq, Error, FromPrimitive, PartialEq)]
+pub enum EscrowError {
+    // 0
+    /// Invalid instruction
+    #[error("Invalid instruction")]
+    InvalidInstruction,
+    /// The offer cannot be initialized because it is already being used.
+    #[error("Already in use")]
+    AlreadyInUse,
+    /// The offered or requested amount is zero.
+    #[error("Invalid offer")]
+    InvalidOffer,
+    /// The vault is not a token account of the offered mint owned by the
+    /// offer's vault authority.
+    #[error("Invalid vault")]
+    InvalidVault,
+    /// The maker does not match the offer.
+    #[error("Maker mismatch")]
+    MakerMismatch,
+
+    // 5
+    /// The offer is reserved for another taker.
+    #[error("Taker mismatch")]
+    TakerMismatch,
+    /// The account receiving the maker's tokens does not match the offer.
+    #[error("Receive account mismatch")]
+    ReceiveAccountMismatch,
+    /// The offer can no longer be taken.
+    #[error("Offer expired")]
+    OfferExpired,
+    /// The offer has been filled or cancelled.
+    #[error("Offer closed")]
+    OfferClosed,
+    /// The taker asked for more tokens than remain in the offer.
+    #[error("Amount exceeds offer")]
+    AmountExceedsOffer,
+
+    // 10
+    /// Operation overflowed
+    #[error("Operation overflowed")]
+    Overflow,
+    /// A mint account does not match the offer.
+    #[error("Mint mismatch")]
+    MintMismatch,
+}
+impl From<EscrowError> for ProgramError {
+    fn from(e: EscrowError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+impl<T> DecodeError<T> for EscrowError {
+    fn type_of() -> &'static str {
+        "EscrowError"
+    }
+}
+
+impl PrintProgramError for EscrowError {
+    fn print<E>(&self)
+    where
+        E: 'static
+            + std::error::Error
+            + DecodeError<E>
+            + PrintProgramError
+            + num_traits::FromPrimitive,
+    {
+        match self {
+            EscrowError::InvalidInstruction => msg!("Error: Invalid instruction"),
+            EscrowError::AlreadyInUse => msg!("Error: offer already in use"),
+            EscrowError::InvalidOffer => msg!("Error: invalid offer"),
+            EscrowError::InvalidVault => msg!("Error: invalid vault"),
+            EscrowError::MakerMismatch => msg!("Error: maker mismatch"),
+            EscrowError::TakerMismatch => msg!("Error: taker mismatch"),
+            EscrowError::ReceiveAccountMismatch => msg!("Error: receive account mismatch"),
+            EscrowError::OfferExpired => msg!("Error: offer expired"),
+            EscrowError::OfferClosed => msg!("Error: offer closed"),
+            EscrowError::AmountExceedsOffer => msg!("Error: amount exceeds offer"),
+            EscrowError::Overflow => msg!("Error: Operation overflowed"),
+            EscrowError::MintMismatch => msg!("Error: mint mismatch"),
+        }
+    }
+}
@@ -0,0 +1,385 @@
+//! Instruction types
+
+use {
+    crate::{check_program_account, error::EscrowError, get_vault_authority_address},
+    arch_program::{
+        account::AccountMeta, instruction::Instruction, program_error::ProgramError,
+        program_option::COption, pubkey::Pubkey,
+    },
+    std::{convert::TryInto, mem::size_of},
+};
+
+/// Instructions supported by the escrow program.
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum EscrowInstruction {
+    /// Creates an offer and moves the offered tokens from the maker into its
+    /// vault. The offer account must be allocated and assigned to the escrow
+    /// program, and the vault must be a token account owned by the offer's
+    /// vault authority.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The offer account to initialize.
+    ///   1. `[writable]` The vault token account.
+    ///   2. `[]` The offered token mint.
+    ///   3. `[writable]` The maker's token account holding the offered tokens.
+    ///   4. `[]` The maker's token account receiving the requested tokens.
+    ///   5. `[signer]` The maker, owner of the source account.
+    ///   6. `[]` The token program.
+    MakeOffer {
+        /// Amount of tokens offered.
+        amount_a: u64,
+        /// Amount of tokens requested for all of the offered tokens.
+        amount_b: u64,
+        /// Unix timestamp from which the offer can no longer be taken, or
+        /// zero if it never expires.
+        expiry_timestamp: i64,
+        /// The only account allowed to take the offer, if any.
+        taker: COption<Pubkey>,
+    },
+    /// Takes some or all of the tokens left in an offer, paying the maker
+    /// their share of the requested amount.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The offer account.
+    ///   1. `[writable]` The vault token account.
+    ///   2. `[]` The offered token mint.
+    ///   3. `[]` The requested token mint.
+    ///   4. `[writable]` The taker's token account paying the requested tokens.
+    ///   5. `[writable]` The taker's token account receiving the offered
+    ///      tokens.
+    ///   6. `[writable]` The maker's token account receiving the requested
+    ///      tokens.
+    ///   7. `[signer]` The taker, owner of the paying account.
+    ///   8. `[]` The vault authority.
+    ///   9. `[]` The token program.
+    TakeOffer {
+        /// Amount of offered tokens to take.
+        amount_a: u64,
+    },
+    /// Cancels an offer and returns the tokens left in its vault to the
+    /// maker.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The offer account.
+    ///   1. `[writable]` The vault token account.
+    ///   2. `[]` The offered token mint.
+    ///   3. `[writable]` The token account receiving the tokens left.
+    ///   4. `[signer]` The maker.
+    ///   5. `[]` The vault authority.
+    ///   6. `[]` The token program.
+    CancelOffer,
+}
+
+impl EscrowInstruction {
+    /// Unpacks a byte buffer into a
+    /// [`EscrowInstruction`](enum.EscrowInstruction.html).
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        use EscrowError::InvalidInstruction;
+
+        let (&tag, rest) = input.split_first().ok_or(InvalidInstruction)?;
+        Ok(match tag {
+            0 => {
+                let (amount_a, rest) = Self::unpack_u64(rest)?;
+                let (amount_b, rest) = Self::unpack_u64(rest)?;
+                let (expiry_timestamp, rest) = Self::unpack_i64(rest)?;
+                let (taker, _rest) = Self::unpack_pubkey_option(rest)?;
+                Self::MakeOffer {
+                    amount_a,
+                    amount_b,
+                    expiry_timestamp,
+                    taker,
+                }
+            }
+            1 => {
+                let (amount_a, _rest) = Self::unpack_u64(rest)?;
+                Self::TakeOffer { amount_a }
+            }
+            2 => Self::CancelOffer,
+            _ => return Err(InvalidInstruction.into()),
+        })
+    }
+
+    /// Packs a [`EscrowInstruction`](enum.EscrowInstruction.html) into a
+    /// byte buffer.
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(size_of::<Self>());
+        match self {
+            &Self::MakeOffer {
+                amount_a,
+                amount_b,
+                expiry_timestamp,
+                ref taker,
+            } => {
+                buf.push(0);
+                buf.extend_from_slice(&amount_a.to_le_bytes());
+                buf.extend_from_slice(&amount_b.to_le_bytes());
+                buf.extend_from_slice(&expiry_timestamp.to_le_bytes());
+                Self::pack_pubkey_option(taker, &mut buf);
+            }
+            &Self::TakeOffer { amount_a } => {
+                buf.push(1);
+                buf.extend_from_slice(&amount_a.to_le_bytes());
+            }
+            Self::CancelOffer => buf.push(2),
+        };
+        buf
+    }
+
+    fn unpack_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
+        let value = input
+            .get(..8)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(EscrowError::InvalidInstruction)?;
+        Ok((value, &input[8..]))
+    }
+
+    fn unpack_i64(input: &[u8]) -> Result<(i64, &[u8]), ProgramError> {
+        let value = input
+            .get(..8)
+            .and_then(|slice| slice.try_into().ok())
+            .map(i64::from_le_bytes)
+            .ok_or(EscrowError::InvalidInstruction)?;
+        Ok((value, &input[8..]))
+    }
+
+    fn unpack_pubkey_option(input: &[u8]) -> Result<(COption<Pubkey>, &[u8]), ProgramError> {
+        match input.split_first() {
+            Option::Some((&0, rest)) => Ok((COption::None, rest)),
+            Option::Some((&1, rest)) if rest.len() >= 32 => {
+                let (key, rest) = rest.split_at(32);
+                Ok((COption::Some(Pubkey::from_slice(key)), rest))
+            }
+            _ => Err(EscrowError::InvalidInstruction.into()),
+        }
+    }
+
+    fn pack_pubkey_option(value: &COption<Pubkey>, buf: &mut Vec<u8>) {
+        match *value {
+            COption::Some(ref key) => {
+                buf.push(1);
+                buf.extend_from_slice(&key.serialize());
+            }
+            COption::None => buf.push(0),
+        }
+    }
+}
+
+/// Creates a `MakeOffer` instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn make_offer(
+    escrow_program_id: &Pubkey,
+    offer_pubkey: &Pubkey,
+    vault_pubkey: &Pubkey,
+    mint_a_pubkey: &Pubkey,
+    source_pubkey: &Pubkey,
+    maker_receive_pubkey: &Pubkey,
+    maker_pubkey: &Pubkey,
+    amount_a: u64,
+    amount_b: u64,
+    expiry_timestamp: i64,
+    taker: Option<&Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(escrow_program_id)?;
+    let data = EscrowInstruction::MakeOffer {
+        amount_a,
+        amount_b,
+        expiry_timestamp,
+        taker: taker.cloned().into(),
+    }
+    .pack();
+
+    let accounts = vec![
+        AccountMeta::new(*offer_pubkey, false),
+        AccountMeta::new(*vault_pubkey, false),
+        AccountMeta::new_readonly(*mint_a_pubkey, false),
+        AccountMeta::new(*source_pubkey, false),
+        AccountMeta::new_readonly(*maker_receive_pubkey, false),
+        AccountMeta::new_readonly(*maker_pubkey, true),
+        AccountMeta::new_readonly(apl_token::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *escrow_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `TakeOffer` instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn take_offer(
+    escrow_program_id: &Pubkey,
+    offer_pubkey: &Pubkey,
+    vault_pubkey: &Pubkey,
+    mint_a_pubkey: &Pubkey,
+    mint_b_pubkey: &Pubkey,
+    taker_source_pubkey: &Pubkey,
+    taker_receive_pubkey: &Pubkey,
+    maker_receive_pubkey: &Pubkey,
+    taker_pubkey: &Pubkey,
+    amount_a: u64,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(escrow_program_id)?;
+
+    Ok(Instruction {
+        program_id: *escrow_program_id,
+        accounts: vec![
+            AccountMeta::new(*offer_pubkey, false),
+            AccountMeta::new(*vault_pubkey, false),
+            AccountMeta::new_readonly(*mint_a_pubkey, false),
+            AccountMeta::new_readonly(*mint_b_pubkey, false),
+            AccountMeta::new(*taker_source_pubkey, false),
+            AccountMeta::new(*taker_receive_pubkey, false),
+            AccountMeta::new(*maker_receive_pubkey, false),
+            AccountMeta::new_readonly(*taker_pubkey, true),
+            AccountMeta::new_readonly(get_vault_authority_address(offer_pubkey), false),
+            AccountMeta::new_readonly(apl_token::id(), false),
+        ],
+        data: EscrowInstruction::TakeOffer { amount_a }.pack(),
+    })
+}
+
+/// Creates a `CancelOffer` instruction.
+pub fn cancel_offer(
+    escrow_program_id: &Pubkey,
+    offer_pubkey: &Pubkey,
+    vault_pubkey: &Pubkey,
+    mint_a_pubkey: &Pubkey,
+    refund_pubkey: &Pubkey,
+    maker_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(escrow_program_id)?;
+
+    Ok(Instruction {
+        program_id: *escrow_program_id,
+        accounts: vec![
+            AccountMeta::new(*offer_pubkey, false),
+            AccountMeta::new(*vault_pubkey, false),
+            AccountMeta::new_readonly(*mint_a_pubkey, false),
+            AccountMeta::new(*refund_pubkey, false),
+            AccountMeta::new_readonly(*maker_pubkey, true),
+            AccountMeta::new_readonly(get_vault_authority_address(offer_pubkey), false),
+            AccountMeta::new_readonly(apl_token::id(), false),
+        ],
+        data: EscrowInstruction::CancelOffer.pack(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_instruction_packing() {
+        let taker = Pubkey::new_unique();
+        let check = EscrowInstruction::MakeOffer {
+            amount_a: 1,
+            amount_b: 2,
+            expiry_timestamp: 3,
+            taker: COption::Some(taker),
+        };
+        let packed = check.pack();
+        let mut expect = vec![0u8];
+        for value in [1u64, 2, 3] {
+            expect.extend_from_slice(&value.to_le_bytes());
+        }
+        expect.push(1);
+        expect.extend_from_slice(&taker.serialize());
+        assert_eq!(packed, expect);
+        let unpacked = EscrowInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = EscrowInstruction::MakeOffer {
+            amount_a: 1,
+            amount_b: 2,
+            expiry_timestamp: 0,
+            taker: COption::None,
+        };
+        let packed = check.pack();
+        let mut expect = vec![0u8];
+        for value in [1u64, 2, 0] {
+            expect.extend_from_slice(&value.to_le_bytes());
+        }
+        expect.push(0);
+        assert_eq!(packed, expect);
+        let unpacked = EscrowInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = EscrowInstruction::TakeOffer { amount_a: 4 };
+        let packed = check.pack();
+        let mut expect = vec![1u8];
+        expect.extend_from_slice(&4u64.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = EscrowInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = EscrowInstruction::CancelOffer;
+        let packed = check.pack();
+        let expect = vec![2u8];
+        assert_eq!(packed, expect);
+        let unpacked = EscrowInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn test_instruction_unpack_errors() {
+        let mut data = EscrowInstruction::MakeOffer {
+            amount_a: 1,
+            amount_b: 2,
+            expiry_timestamp: 3,
+            taker: COption::None,
+        }
+        .pack();
+        *data.last_mut().unwrap() = 1;
+        assert_eq!(
+            EscrowInstruction::unpack(&data),
+            Err(EscrowError::InvalidInstruction.into())
+        );
+        assert_eq!(
+            EscrowInstruction::unpack(&[1, 0, 0]),
+            Err(EscrowError::InvalidInstruction.into())
+        );
+        assert_eq!(
+            EscrowInstruction::unpack(&[3]),
+            Err(EscrowError::InvalidInstruction.into())
+        );
+    }
+
+    #[test]
+    fn test_instruction_builders() {
+        let offer = Pubkey::new_unique();
+        let instruction = take_offer(
+            &crate::id(),
+            &offer,
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            1,
+        )
+        .unwrap();
+        assert_eq!(
+            instruction.accounts[8].pubkey,
+            get_vault_authority_address(&offer)
+        );
+        assert_eq!(
+            cancel_offer(
+                &Pubkey::new_unique(),
+                &offer,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+            ),
+            Err(ProgramError::IncorrectProgramId)
+        );
+    }
+}
@@ -0,0 +1,48 @@
+#![deny(missing_docs)]
+#![cfg_attr(not(test), forbid(unsafe_code))]
+
+//! A program that escrows APL tokens offered by a maker until a taker pays
+//! the requested amount of another token for them
+
+pub mod error;
+pub mod instruction;
+pub mod processor;
+pub mod state;
+
+#[cfg(not(feature = "no-entrypoint"))]
+mod entrypoint;
+
+// Export current sdk types for downstream users building with a different sdk
+// version
+pub use arch_program;
+use arch_program::{entrypoint::ProgramResult, program_error::ProgramError, pubkey::Pubkey};
+
+/// Seed of the authority owning the vault of an offer, derived with the
+/// offer address
+pub const VAULT_AUTHORITY_SEED: &[u8] = b"vault-authority";
+
+/// The program id of the escrow program
+pub fn id() -> Pubkey {
+    Pubkey::from_slice(b"apl-escrow0000000000000000000000")
+}
+
+/// Checks that the supplied program ID is the correct one for APL-escrow
+pub fn check_program_account(escrow_program_id: &Pubkey) -> ProgramResult {
+    if escrow_program_id != &id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
+
+/// Derives the authority owning the vault of an offer, and its bump seed
+pub fn get_vault_authority_address_and_bump_seed(
+    offer: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[VAULT_AUTHORITY_SEED, &offer.serialize()], program_id)
+}
+
+/// Derives the authority owning the vault of an offer
+pub fn get_vault_authority_address(offer: &Pubkey) -> Pubkey {
+    get_vault_authority_address_and_bump_seed(offer, &id()).0
+}
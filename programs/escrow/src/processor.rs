@@ -0,0 +1,342 @@
+//! Program state processor
+
+use {
+    crate::{
+        error::EscrowError, get_vault_authority_address_and_bump_seed,
+        instruction::EscrowInstruction, state::Offer, VAULT_AUTHORITY_SEED,
+    },
+    apl_token::{
+        extension::BaseState,
+        state::{Account, Mint},
+    },
+    arch_program::{
+        account::{next_account_info, AccountInfo},
+        entrypoint::ProgramResult,
+        msg,
+        program::{get_clock, invoke_signed},
+        program_error::ProgramError,
+        program_option::COption,
+        program_pack::Pack,
+        pubkey::Pubkey,
+    },
+};
+
+/// Program state handler.
+pub struct Processor {}
+impl Processor {
+    /// Processes a [`MakeOffer`](enum.EscrowInstruction.html) instruction.
+    pub fn process_make_offer(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount_a: u64,
+        amount_b: u64,
+        expiry_timestamp: i64,
+        taker: COption<Pubkey>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let offer_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let mint_a_info = next_account_info(account_info_iter)?;
+        let source_info = next_account_info(account_info_iter)?;
+        let maker_receive_info = next_account_info(account_info_iter)?;
+        let maker_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        Self::check_account_owner(program_id, offer_info)?;
+        let offer = Offer::unpack_unchecked(&offer_info.data.borrow())?;
+        if offer.is_initialized {
+            return Err(EscrowError::AlreadyInUse.into());
+        }
+        if !maker_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if amount_a == 0 || amount_b == 0 {
+            return Err(EscrowError::InvalidOffer.into());
+        }
+
+        let (vault_authority, vault_authority_bump) =
+            get_vault_authority_address_and_bump_seed(offer_info.key, program_id);
+        let vault = Self::unpack_token_account(vault_info)?;
+        if vault.owner != vault_authority {
+            return Err(EscrowError::InvalidVault.into());
+        }
+        let decimals_a = Self::unpack_mint(mint_a_info, &vault.mint)?;
+        let maker_receive = Self::unpack_token_account(maker_receive_info)?;
+
+        let offer = Offer {
+            is_initialized: true,
+            vault_authority_bump,
+            is_cancelled: false,
+            maker: *maker_info.key,
+            taker: taker.unwrap_or_default(),
+            mint_a: vault.mint,
+            mint_b: maker_receive.mint,
+            vault: *vault_info.key,
+            maker_receive: *maker_receive_info.key,
+            expiry_timestamp,
+            amount_a,
+            amount_b,
+            filled_amount_a: 0,
+        };
+        Offer::pack(offer, &mut offer_info.data.borrow_mut())?;
+
+        Self::transfer(
+            token_program_info,
+            source_info,
+            mint_a_info,
+            vault_info,
+            maker_info,
+            amount_a,
+            decimals_a,
+            &[],
+        )
+    }
+
+    /// Processes a [`TakeOffer`](enum.EscrowInstruction.html) instruction.
+    pub fn process_take_offer(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount_a: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let offer_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let mint_a_info = next_account_info(account_info_iter)?;
+        let mint_b_info = next_account_info(account_info_iter)?;
+        let taker_source_info = next_account_info(account_info_iter)?;
+        let taker_receive_info = next_account_info(account_info_iter)?;
+        let maker_receive_info = next_account_info(account_info_iter)?;
+        let taker_info = next_account_info(account_info_iter)?;
+        let vault_authority_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        let mut offer = Self::unpack_offer(program_id, offer_info, vault_info)?;
+        if !offer.is_taker(taker_info.key) {
+            return Err(EscrowError::TakerMismatch.into());
+        }
+        if !taker_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if offer.maker_receive != *maker_receive_info.key {
+            return Err(EscrowError::ReceiveAccountMismatch.into());
+        }
+        if offer.is_expired(get_clock().unix_timestamp) {
+            return Err(EscrowError::OfferExpired.into());
+        }
+        if amount_a == 0 {
+            return Err(EscrowError::InvalidOffer.into());
+        }
+        let decimals_b = Self::unpack_mint(mint_b_info, &offer.mint_b)?;
+
+        let amount_b = offer
+            .fill(amount_a)
+            .ok_or(EscrowError::AmountExceedsOffer)?;
+        Offer::pack(offer, &mut offer_info.data.borrow_mut())?;
+
+        if amount_b > 0 {
+            Self::transfer(
+                token_program_info,
+                taker_source_info,
+                mint_b_info,
+                maker_receive_info,
+                taker_info,
+                amount_b,
+                decimals_b,
+                &[],
+            )?;
+        }
+        Self::transfer_from_vault(
+            program_id,
+            &offer,
+            offer_info,
+            token_program_info,
+            vault_info,
+            mint_a_info,
+            taker_receive_info,
+            vault_authority_info,
+            amount_a,
+        )
+    }
+
+    /// Processes a [`CancelOffer`](enum.EscrowInstruction.html) instruction.
+    pub fn process_cancel_offer(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let offer_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let mint_a_info = next_account_info(account_info_iter)?;
+        let refund_info = next_account_info(account_info_iter)?;
+        let maker_info = next_account_info(account_info_iter)?;
+        let vault_authority_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        let mut offer = Self::unpack_offer(program_id, offer_info, vault_info)?;
+        if offer.maker != *maker_info.key {
+            return Err(EscrowError::MakerMismatch.into());
+        }
+        if !maker_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let remaining_amount_a = offer.remaining_amount_a();
+        offer.is_cancelled = true;
+        Offer::pack(offer, &mut offer_info.data.borrow_mut())?;
+
+        Self::transfer_from_vault(
+            program_id,
+            &offer,
+            offer_info,
+            token_program_info,
+            vault_info,
+            mint_a_info,
+            refund_info,
+            vault_authority_info,
+            remaining_amount_a,
+        )
+    }
+
+    /// Processes an [`Instruction`](enum.Instruction.html).
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
+        let instruction = EscrowInstruction::unpack(input)?;
+
+        match instruction {
+            EscrowInstruction::MakeOffer {
+                amount_a,
+                amount_b,
+                expiry_timestamp,
+                taker,
+            } => {
+                msg!("Instruction: MakeOffer");
+                Self::process_make_offer(
+                    program_id,
+                    accounts,
+                    amount_a,
+                    amount_b,
+                    expiry_timestamp,
+                    taker,
+                )
+            }
+            EscrowInstruction::TakeOffer { amount_a } => {
+                msg!("Instruction: TakeOffer");
+                Self::process_take_offer(program_id, accounts, amount_a)
+            }
+            EscrowInstruction::CancelOffer => {
+                msg!("Instruction: CancelOffer");
+                Self::process_cancel_offer(program_id, accounts)
+            }
+        }
+    }
+
+    /// Checks that the account is owned by the given program
+    pub fn check_account_owner(program_id: &Pubkey, account_info: &AccountInfo) -> ProgramResult {
+        if program_id != account_info.owner {
+            Err(ProgramError::IncorrectProgramId)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Unpacks an open offer, checking the vault passed with it
+    fn unpack_offer(
+        program_id: &Pubkey,
+        offer_info: &AccountInfo,
+        vault_info: &AccountInfo,
+    ) -> Result<Offer, ProgramError> {
+        Self::check_account_owner(program_id, offer_info)?;
+        let offer = Offer::unpack(&offer_info.data.borrow())?;
+        if offer.vault != *vault_info.key {
+            return Err(EscrowError::InvalidVault.into());
+        }
+        if offer.is_closed() {
+            return Err(EscrowError::OfferClosed.into());
+        }
+        Ok(offer)
+    }
+
+    fn unpack_token_account(account_info: &AccountInfo) -> Result<Account, ProgramError> {
+        apl_token::check_program_account(account_info.owner)?;
+        Account::unpack_base(&account_info.data.borrow())
+    }
+
+    /// Checks that the mint is the one expected by the offer, returning its
+    /// decimals
+    fn unpack_mint(mint_info: &AccountInfo, expected_mint: &Pubkey) -> Result<u8, ProgramError> {
+        apl_token::check_program_account(mint_info.owner)?;
+        if mint_info.key != expected_mint {
+            return Err(EscrowError::MintMismatch.into());
+        }
+        let mint = Mint::unpack_base(&mint_info.data.borrow())?;
+        Ok(mint.decimals)
+    }
+
+    /// Transfers tokens out of the vault of an offer, signing as its vault
+    /// authority
+    #[allow(clippy::too_many_arguments)]
+    fn transfer_from_vault<'a>(
+        program_id: &Pubkey,
+        offer: &Offer,
+        offer_info: &AccountInfo<'a>,
+        token_program_info: &AccountInfo<'a>,
+        vault_info: &AccountInfo<'a>,
+        mint_a_info: &AccountInfo<'a>,
+        destination_info: &AccountInfo<'a>,
+        vault_authority_info: &AccountInfo<'a>,
+        amount: u64,
+    ) -> ProgramResult {
+        let offer_key = offer_info.key.serialize();
+        let vault_authority_seeds: &[&[u8]] = &[
+            VAULT_AUTHORITY_SEED,
+            &offer_key,
+            &[offer.vault_authority_bump],
+        ];
+        let vault_authority = Pubkey::create_program_address(vault_authority_seeds, program_id)?;
+        if vault_authority != *vault_authority_info.key {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        let decimals_a = Self::unpack_mint(mint_a_info, &offer.mint_a)?;
+
+        Self::transfer(
+            token_program_info,
+            vault_info,
+            mint_a_info,
+            destination_info,
+            vault_authority_info,
+            amount,
+            decimals_a,
+            &[vault_authority_seeds],
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn transfer<'a>(
+        token_program_info: &AccountInfo<'a>,
+        source_info: &AccountInfo<'a>,
+        mint_info: &AccountInfo<'a>,
+        destination_info: &AccountInfo<'a>,
+        authority_info: &AccountInfo<'a>,
+        amount: u64,
+        decimals: u8,
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        apl_token::check_program_account(token_program_info.key)?;
+        invoke_signed(
+            &apl_token::instruction::transfer_checked(
+                token_program_info.key,
+                source_info.key,
+                mint_info.key,
+                destination_info.key,
+                authority_info.key,
+                &[],
+                amount,
+                decimals,
+            )?,
+            &[
+                source_info.clone(),
+                mint_info.clone(),
+                destination_info.clone(),
+                authority_info.clone(),
+                token_program_info.clone(),
+            ],
+            signers_seeds,
+        )
+    }
+}
@@ -0,0 +1,254 @@
+//! State transition types
+
+use {
+    arch_program::{
+        program_error::ProgramError,
+        program_pack::{IsInitialized, Pack, Sealed},
+        pubkey::Pubkey,
+    },
+    arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs},
+};
+
+/// Offer data.
+///
+/// The maker's tokens of `mint_a` sit in the vault and are sold at the fixed
+/// price of `amount_b` for `amount_a`. Partial fills are charged so that the
+/// total paid for `filled_amount_a` is always that share of `amount_b`
+/// rounded up, which adds up to exactly `amount_b` once the offer is filled.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Offer {
+    /// Is `true` if this structure has been initialized
+    pub is_initialized: bool,
+    /// Bump seed of the vault authority
+    pub vault_authority_bump: u8,
+    /// Is `true` if the maker has cancelled the offer
+    pub is_cancelled: bool,
+    /// Account that made the offer
+    pub maker: Pubkey,
+    /// Only account that can take the offer, or the default pubkey if anyone
+    /// can
+    pub taker: Pubkey,
+    /// Mint of the offered tokens
+    pub mint_a: Pubkey,
+    /// Mint of the requested tokens
+    pub mint_b: Pubkey,
+    /// Token account holding the offered tokens
+    pub vault: Pubkey,
+    /// Maker's token account receiving the requested tokens
+    pub maker_receive: Pubkey,
+    /// Unix timestamp from which the offer can no longer be taken, or zero if
+    /// it never expires
+    pub expiry_timestamp: i64,
+    /// Amount of tokens offered
+    pub amount_a: u64,
+    /// Amount of tokens requested for all of the offered tokens
+    pub amount_b: u64,
+    /// Amount of offered tokens already taken
+    pub filled_amount_a: u64,
+}
+
+impl Offer {
+    /// Checks if the offer has been filled or cancelled
+    pub fn is_closed(&self) -> bool {
+        self.is_cancelled || self.filled_amount_a >= self.amount_a
+    }
+
+    /// Amount of offered tokens left in the vault
+    pub fn remaining_amount_a(&self) -> u64 {
+        self.amount_a.saturating_sub(self.filled_amount_a)
+    }
+
+    /// Checks if the offer can no longer be taken at `unix_timestamp`
+    pub fn is_expired(&self, unix_timestamp: i64) -> bool {
+        self.expiry_timestamp != 0 && unix_timestamp >= self.expiry_timestamp
+    }
+
+    /// Checks if `taker` is allowed to take the offer
+    pub fn is_taker(&self, taker: &Pubkey) -> bool {
+        self.taker == Pubkey::default() || self.taker == *taker
+    }
+
+    /// Takes `amount_a` offered tokens out of the offer, returning the amount
+    /// of requested tokens owed for them
+    pub fn fill(&mut self, amount_a: u64) -> Option<u64> {
+        if amount_a > self.remaining_amount_a() {
+            return None;
+        }
+        let paid_before = self.paid_amount_b(self.filled_amount_a)?;
+        self.filled_amount_a += amount_a;
+        self.paid_amount_b(self.filled_amount_a)?
+            .checked_sub(paid_before)
+    }
+
+    /// Total amount of requested tokens owed for `filled_amount_a` offered
+    /// tokens, rounded up in the maker's favour
+    fn paid_amount_b(&self, filled_amount_a: u64) -> Option<u64> {
+        if self.amount_a == 0 {
+            return None;
+        }
+        u64::try_from(
+            (filled_amount_a as u128 * self.amount_b as u128).div_ceil(self.amount_a as u128),
+        )
+        .ok()
+    }
+}
+
+impl Sealed for Offer {}
+impl IsInitialized for Offer {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+impl Pack for Offer {
+    const LEN: usize = 227;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 227];
+        let (
+            is_initialized,
+            vault_authority_bump,
+            is_cancelled,
+            maker,
+            taker,
+            mint_a,
+            mint_b,
+            vault,
+            maker_receive,
+            expiry_timestamp,
+            amount_a,
+            amount_b,
+            filled_amount_a,
+        ) = array_refs![src, 1, 1, 1, 32, 32, 32, 32, 32, 32, 8, 8, 8, 8];
+        Ok(Offer {
+            is_initialized: unpack_bool(is_initialized)?,
+            vault_authority_bump: vault_authority_bump[0],
+            is_cancelled: unpack_bool(is_cancelled)?,
+            maker: Pubkey::from_slice(maker),
+            taker: Pubkey::from_slice(taker),
+            mint_a: Pubkey::from_slice(mint_a),
+            mint_b: Pubkey::from_slice(mint_b),
+            vault: Pubkey::from_slice(vault),
+            maker_receive: Pubkey::from_slice(maker_receive),
+            expiry_timestamp: i64::from_le_bytes(*expiry_timestamp),
+            amount_a: u64::from_le_bytes(*amount_a),
+            amount_b: u64::from_le_bytes(*amount_b),
+            filled_amount_a: u64::from_le_bytes(*filled_amount_a),
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 227];
+        let (
+            is_initialized_dst,
+            vault_authority_bump_dst,
+            is_cancelled_dst,
+            maker_dst,
+            taker_dst,
+            mint_a_dst,
+            mint_b_dst,
+            vault_dst,
+            maker_receive_dst,
+            expiry_timestamp_dst,
+            amount_a_dst,
+            amount_b_dst,
+            filled_amount_a_dst,
+        ) = mut_array_refs![dst, 1, 1, 1, 32, 32, 32, 32, 32, 32, 8, 8, 8, 8];
+        *is_initialized_dst = [self.is_initialized as u8];
+        *vault_authority_bump_dst = [self.vault_authority_bump];
+        *is_cancelled_dst = [self.is_cancelled as u8];
+        maker_dst.copy_from_slice(self.maker.as_ref());
+        taker_dst.copy_from_slice(self.taker.as_ref());
+        mint_a_dst.copy_from_slice(self.mint_a.as_ref());
+        mint_b_dst.copy_from_slice(self.mint_b.as_ref());
+        vault_dst.copy_from_slice(self.vault.as_ref());
+        maker_receive_dst.copy_from_slice(self.maker_receive.as_ref());
+        *expiry_timestamp_dst = self.expiry_timestamp.to_le_bytes();
+        *amount_a_dst = self.amount_a.to_le_bytes();
+        *amount_b_dst = self.amount_b.to_le_bytes();
+        *filled_amount_a_dst = self.filled_amount_a.to_le_bytes();
+    }
+}
+
+fn unpack_bool(src: &[u8; 1]) -> Result<bool, ProgramError> {
+    match src {
+        [0] => Ok(false),
+        [1] => Ok(true),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_offer() -> Offer {
+        Offer {
+            is_initialized: true,
+            vault_authority_bump: 255,
+            is_cancelled: false,
+            maker: Pubkey::new_unique(),
+            taker: Pubkey::default(),
+            mint_a: Pubkey::new_unique(),
+            mint_b: Pubkey::new_unique(),
+            vault: Pubkey::new_unique(),
+            maker_receive: Pubkey::new_unique(),
+            expiry_timestamp: 1_000,
+            amount_a: 300,
+            amount_b: 1_000,
+            filled_amount_a: 0,
+        }
+    }
+
+    #[test]
+    fn test_pack_unpack() {
+        let offer = test_offer();
+        let mut packed = vec![0u8; Offer::LEN];
+        Offer::pack(offer, &mut packed).unwrap();
+        assert_eq!(Offer::unpack(&packed).unwrap(), offer);
+
+        packed[2] = 2;
+        assert_eq!(
+            Offer::unpack_from_slice(&packed),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn test_fill() {
+        let mut offer = test_offer();
+        assert_eq!(offer.fill(301), None);
+
+        // 100 * 1000 / 300 rounds up to 334
+        assert_eq!(offer.fill(100), Some(334));
+        assert_eq!(offer.remaining_amount_a(), 200);
+        assert!(!offer.is_closed());
+
+        // Rounding is not compounded over partial fills
+        assert_eq!(offer.fill(1), Some(3));
+        assert_eq!(offer.fill(1), Some(3));
+        assert_eq!(offer.fill(198), Some(660));
+        assert_eq!(offer.remaining_amount_a(), 0);
+        assert!(offer.is_closed());
+
+        // A cheap offer cannot be taken piecemeal for less than its price
+        let mut offer = Offer {
+            amount_b: 1,
+            ..test_offer()
+        };
+        assert_eq!(offer.fill(1), Some(1));
+        assert_eq!(offer.fill(299), Some(0));
+    }
+
+    #[test]
+    fn test_taker_and_expiry() {
+        let mut offer = test_offer();
+        assert!(offer.is_taker(&Pubkey::new_unique()));
+        assert!(!offer.is_expired(999));
+        assert!(offer.is_expired(1_000));
+
+        offer.taker = Pubkey::new_unique();
+        offer.expiry_timestamp = 0;
+        assert!(offer.is_taker(&offer.taker.clone()));
+        assert!(!offer.is_taker(&Pubkey::new_unique()));
+        assert!(!offer.is_expired(i64::MAX));
+    }
+}
@@ -0,0 +1,15 @@
+//! Program entrypoint
+
+use {
+    crate::processor::process_instruction,
+    arch_program::{account::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey},
+};
+
+arch_program::entrypoint!(program_entrypoint);
+fn program_entrypoint(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    process_instruction(program_id, accounts, instruction_data)
+}
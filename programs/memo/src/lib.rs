@@ -0,0 +1,53 @@
+#![deny(missing_docs)]
+#![cfg_attr(not(test), forbid(unsafe_code))]
+
+//! A program that validates a UTF-8 memo and records it in the program logs,
+//! optionally requiring the accounts passed to it to sign
+
+pub mod processor;
+
+#[cfg(not(feature = "no-entrypoint"))]
+mod entrypoint;
+
+// Export current sdk types for downstream users building with a different sdk
+// version
+pub use arch_program;
+use arch_program::{account::AccountMeta, instruction::Instruction, pubkey::Pubkey};
+
+/// The program id of the memo program
+pub fn id() -> Pubkey {
+    Pubkey::from_slice(b"apl-memo000000000000000000000000")
+}
+
+/// Creates a memo instruction.
+///
+/// Accounts expected by this instruction:
+///
+///   0. ..`N` `[signer]` Accounts that must sign the memo, if any.
+pub fn build_memo(memo: &[u8], signer_pubkeys: &[&Pubkey]) -> Instruction {
+    Instruction {
+        program_id: id(),
+        accounts: signer_pubkeys
+            .iter()
+            .map(|&pubkey| AccountMeta::new_readonly(*pubkey, true))
+            .collect(),
+        data: memo.to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_memo() {
+        let signer_pubkey = Pubkey::new_unique();
+        let instruction = build_memo(b"invoice 42", &[&signer_pubkey]);
+        assert_eq!(instruction.program_id, id());
+        assert_eq!(instruction.data, b"invoice 42");
+        assert_eq!(
+            instruction.accounts,
+            vec![AccountMeta::new_readonly(signer_pubkey, true)]
+        );
+    }
+}
@@ -0,0 +1,53 @@
+//! Program state processor
+
+use {
+    arch_program::{
+        account::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
+        pubkey::Pubkey,
+    },
+    std::str::from_utf8,
+};
+
+/// Instruction processor
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    input: &[u8],
+) -> ProgramResult {
+    let mut missing_required_signature = false;
+    for account_info in accounts.iter() {
+        if !account_info.is_signer {
+            msg!("Missing required signature: {}", account_info.key);
+            missing_required_signature = true;
+        }
+    }
+    if missing_required_signature {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let memo = from_utf8(input).map_err(|err| {
+        msg!("Invalid UTF-8, from byte {}", err.valid_up_to());
+        ProgramError::InvalidInstructionData
+    })?;
+    msg!("Memo (len {}): {:?}", memo.len(), memo);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf8_memo() {
+        let program_id = crate::id();
+        assert_eq!(
+            process_instruction(&program_id, &[], "🐆".as_bytes()),
+            Ok(())
+        );
+        assert_eq!(
+            process_instruction(&program_id, &[], &[0xF0, 0x9F, 0x90]),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
+}
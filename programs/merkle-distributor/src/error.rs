@@ -0,0 +1,99 @@
+//! Error types
+
+use {
+    arch_program::{
+        decode_error::DecodeError,
+        msg,
+        program_error::{PrintProgramError, ProgramError},
+    },
+    num_derive::FromPrimitive,
+    thiserror::Error,
+};
+
+/// Errors that may be returned by the MerkleDistributor program.
+#[derive(Clone, Debug, Eq, Error, FromPrimitive, PartialEq)]
+pub enum DistributorError {
+    // 0
+    /// Invalid instruction
+    #[error("Invalid instruction")]
+    InvalidInstruction,
+    /// The distributor cannot be initialized because it is already being
+    /// used.
+    #[error("Already in use")]
+    AlreadyInUse,
+    /// The distributor has no nodes or nothing to distribute.
+    #[error("Invalid distributor")]
+    InvalidDistributor,
+    /// The vault is not a token account owned by the distributor's vault
+    /// authority.
+    #[error("Invalid vault")]
+    InvalidVault,
+    /// The admin does not match the distributor.
+    #[error("Admin mismatch")]
+    AdminMismatch,
+
+    // 5
+    /// The proof does not lead to the distributor's root.
+    #[error("Invalid proof")]
+    InvalidProof,
+    /// The index has already been claimed.
+    #[error("Already claimed")]
+    AlreadyClaimed,
+    /// The index is beyond the number of nodes of the distributor.
+    #[error("Index out of range")]
+    IndexOutOfRange,
+    /// The claim would exceed the amount of the distributor.
+    #[error("Exceeds max claim")]
+    ExceedsMaxClaim,
+    /// The clawback timestamp has not been reached yet.
+    #[error("Clawback not started")]
+    ClawbackNotStarted,
+
+    // 10
+    /// The unclaimed tokens have been clawed back.
+    #[error("Clawed back")]
+    ClawedBack,
+    /// Operation overflowed
+    #[error("Operation overflowed")]
+    Overflow,
+    /// The mint account does not match the distributor.
+    #[error("Mint mismatch")]
+    MintMismatch,
+}
+impl From<DistributorError> for ProgramError {
+    fn from(e: DistributorError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+impl<T> DecodeError<T> for DistributorError {
+    fn type_of() -> &'static str {
+        "DistributorError"
+    }
+}
+
+impl PrintProgramError for DistributorError {
+    fn print<E>(&self)
+    where
+        E: 'static
+            + std::error::Error
+            + DecodeError<E>
+            + PrintProgramError
+            + num_traits::FromPrimitive,
+    {
+        match self {
+            DistributorError::InvalidInstruction => msg!("Error: Invalid instruction"),
+            DistributorError::AlreadyInUse => msg!("Error: distributor already in use"),
+            DistributorError::InvalidDistributor => msg!("Error: invalid distributor"),
+            DistributorError::InvalidVault => msg!("Error: invalid vault"),
+            DistributorError::AdminMismatch => msg!("Error: admin mismatch"),
+            DistributorError::InvalidProof => msg!("Error: invalid proof"),
+            DistributorError::AlreadyClaimed => msg!("Error: already claimed"),
+            DistributorError::IndexOutOfRange => msg!("Error: index out of range"),
+            DistributorError::ExceedsMaxClaim => msg!("Error: exceeds max claim"),
+            DistributorError::ClawbackNotStarted => msg!("Error: clawback not started"),
+            DistributorError::ClawedBack => msg!("Error: clawed back"),
+            DistributorError::Overflow => msg!("Error: Operation overflowed"),
+            DistributorError::MintMismatch => msg!("Error: mint mismatch"),
+        }
+    }
+}
@@ -0,0 +1,401 @@
+//! Instruction types
+
+use {
+    crate::{
+        check_program_account, error::DistributorError, get_claim_bitmap_address,
+        get_vault_authority_address,
+    },
+    arch_program::{
+        account::AccountMeta, instruction::Instruction, program_error::ProgramError, pubkey::Pubkey,
+    },
+    std::{convert::TryInto, mem::size_of},
+};
+
+/// Maximum number of nodes in a claim proof, enough for 2^32 leaves
+pub const MAX_PROOF_LEN: usize = 32;
+
+/// Instructions supported by the merkle distributor program.
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum DistributorInstruction {
+    /// Creates a distributor, creates its claim bitmap and moves the
+    /// distributed tokens from the admin into its vault. The distributor
+    /// account must be allocated and assigned to the program, and the vault
+    /// must be a token account owned by the distributor's vault authority.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The distributor account to initialize.
+    ///   1. `[writable]` The claim bitmap account, derived from the
+    ///      distributor.
+    ///   2. `[writable]` The vault token account.
+    ///   3. `[]` The token mint.
+    ///   4. `[writable]` The admin's source token account.
+    ///   5. `[signer]` The admin, owner of the source account.
+    ///   6. `[]` The system program.
+    ///   7. `[]` The token program.
+    NewDistributor {
+        /// Transaction id of the UTXO backing the claim bitmap account.
+        txid: [u8; 32],
+        /// Output index of the UTXO backing the claim bitmap account.
+        vout: u32,
+        /// Root of the merkle tree.
+        root: [u8; 32],
+        /// Amount of tokens distributed over all leaves.
+        max_total_claim: u64,
+        /// Number of leaves of the tree.
+        max_num_nodes: u64,
+        /// Unix timestamp from which the admin can claw back the unclaimed
+        /// tokens.
+        clawback_timestamp: i64,
+    },
+    /// Claims the tokens of a leaf for its recipient.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The distributor account.
+    ///   1. `[writable]` The claim bitmap account.
+    ///   2. `[writable]` The vault token account.
+    ///   3. `[]` The token mint.
+    ///   4. `[writable]` The destination token account.
+    ///   5. `[signer]` The recipient of the leaf.
+    ///   6. `[]` The vault authority.
+    ///   7. `[]` The token program.
+    Claim {
+        /// Index of the leaf.
+        index: u64,
+        /// Amount of tokens of the leaf.
+        amount: u64,
+        /// Siblings of the nodes from the leaf up to the root.
+        proof: Vec<[u8; 32]>,
+    },
+    /// Moves the unclaimed tokens out of the vault once the clawback
+    /// timestamp is reached, closing the distribution.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The distributor account.
+    ///   1. `[writable]` The vault token account.
+    ///   2. `[]` The token mint.
+    ///   3. `[writable]` The destination token account.
+    ///   4. `[signer]` The admin.
+    ///   5. `[]` The vault authority.
+    ///   6. `[]` The token program.
+    Clawback,
+}
+
+impl DistributorInstruction {
+    /// Unpacks a byte buffer into a
+    /// [`DistributorInstruction`](enum.DistributorInstruction.html).
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        use DistributorError::InvalidInstruction;
+
+        let (&tag, rest) = input.split_first().ok_or(InvalidInstruction)?;
+        Ok(match tag {
+            0 => {
+                let (txid, rest) = Self::unpack_hash(rest)?;
+                let (vout, rest) = Self::unpack_u32(rest)?;
+                let (root, rest) = Self::unpack_hash(rest)?;
+                let (max_total_claim, rest) = Self::unpack_u64(rest)?;
+                let (max_num_nodes, rest) = Self::unpack_u64(rest)?;
+                let (clawback_timestamp, _rest) = Self::unpack_i64(rest)?;
+                Self::NewDistributor {
+                    txid,
+                    vout,
+                    root,
+                    max_total_claim,
+                    max_num_nodes,
+                    clawback_timestamp,
+                }
+            }
+            1 => {
+                let (index, rest) = Self::unpack_u64(rest)?;
+                let (amount, rest) = Self::unpack_u64(rest)?;
+                let (&len, mut rest) = rest.split_first().ok_or(InvalidInstruction)?;
+                if len as usize > MAX_PROOF_LEN {
+                    return Err(InvalidInstruction.into());
+                }
+                let mut proof = Vec::with_capacity(len as usize);
+                for _ in 0..len {
+                    let (node, next) = Self::unpack_hash(rest)?;
+                    proof.push(node);
+                    rest = next;
+                }
+                Self::Claim {
+                    index,
+                    amount,
+                    proof,
+                }
+            }
+            2 => Self::Clawback,
+            _ => return Err(InvalidInstruction.into()),
+        })
+    }
+
+    /// Packs a [`DistributorInstruction`](enum.DistributorInstruction.html)
+    /// into a byte buffer.
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(size_of::<Self>());
+        match self {
+            Self::NewDistributor {
+                txid,
+                vout,
+                root,
+                max_total_claim,
+                max_num_nodes,
+                clawback_timestamp,
+            } => {
+                buf.push(0);
+                buf.extend_from_slice(txid);
+                buf.extend_from_slice(&vout.to_le_bytes());
+                buf.extend_from_slice(root);
+                buf.extend_from_slice(&max_total_claim.to_le_bytes());
+                buf.extend_from_slice(&max_num_nodes.to_le_bytes());
+                buf.extend_from_slice(&clawback_timestamp.to_le_bytes());
+            }
+            Self::Claim {
+                index,
+                amount,
+                proof,
+            } => {
+                buf.push(1);
+                buf.extend_from_slice(&index.to_le_bytes());
+                buf.extend_from_slice(&amount.to_le_bytes());
+                buf.push(proof.len() as u8);
+                for node in proof {
+                    buf.extend_from_slice(node);
+                }
+            }
+            Self::Clawback => buf.push(2),
+        };
+        buf
+    }
+
+    fn unpack_u32(input: &[u8]) -> Result<(u32, &[u8]), ProgramError> {
+        let value = input
+            .get(..4)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u32::from_le_bytes)
+            .ok_or(DistributorError::InvalidInstruction)?;
+        Ok((value, &input[4..]))
+    }
+
+    fn unpack_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
+        let value = input
+            .get(..8)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(DistributorError::InvalidInstruction)?;
+        Ok((value, &input[8..]))
+    }
+
+    fn unpack_i64(input: &[u8]) -> Result<(i64, &[u8]), ProgramError> {
+        let value = input
+            .get(..8)
+            .and_then(|slice| slice.try_into().ok())
+            .map(i64::from_le_bytes)
+            .ok_or(DistributorError::InvalidInstruction)?;
+        Ok((value, &input[8..]))
+    }
+
+    fn unpack_hash(input: &[u8]) -> Result<([u8; 32], &[u8]), ProgramError> {
+        let value = input
+            .get(..32)
+            .and_then(|slice| slice.try_into().ok())
+            .ok_or(DistributorError::InvalidInstruction)?;
+        Ok((value, &input[32..]))
+    }
+}
+
+/// Creates a `NewDistributor` instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn new_distributor(
+    distributor_program_id: &Pubkey,
+    distributor_pubkey: &Pubkey,
+    vault_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    source_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    txid: [u8; 32],
+    vout: u32,
+    root: [u8; 32],
+    max_total_claim: u64,
+    max_num_nodes: u64,
+    clawback_timestamp: i64,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(distributor_program_id)?;
+    let data = DistributorInstruction::NewDistributor {
+        txid,
+        vout,
+        root,
+        max_total_claim,
+        max_num_nodes,
+        clawback_timestamp,
+    }
+    .pack();
+
+    let accounts = vec![
+        AccountMeta::new(*distributor_pubkey, false),
+        AccountMeta::new(get_claim_bitmap_address(distributor_pubkey), false),
+        AccountMeta::new(*vault_pubkey, false),
+        AccountMeta::new_readonly(*mint_pubkey, false),
+        AccountMeta::new(*source_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+        AccountMeta::new_readonly(Pubkey::system_program(), false),
+        AccountMeta::new_readonly(apl_token::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *distributor_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `Claim` instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn claim(
+    distributor_program_id: &Pubkey,
+    distributor_pubkey: &Pubkey,
+    vault_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    recipient_pubkey: &Pubkey,
+    index: u64,
+    amount: u64,
+    proof: Vec<[u8; 32]>,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(distributor_program_id)?;
+    if proof.len() > MAX_PROOF_LEN {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    Ok(Instruction {
+        program_id: *distributor_program_id,
+        accounts: vec![
+            AccountMeta::new(*distributor_pubkey, false),
+            AccountMeta::new(get_claim_bitmap_address(distributor_pubkey), false),
+            AccountMeta::new(*vault_pubkey, false),
+            AccountMeta::new_readonly(*mint_pubkey, false),
+            AccountMeta::new(*destination_pubkey, false),
+            AccountMeta::new_readonly(*recipient_pubkey, true),
+            AccountMeta::new_readonly(get_vault_authority_address(distributor_pubkey), false),
+            AccountMeta::new_readonly(apl_token::id(), false),
+        ],
+        data: DistributorInstruction::Claim {
+            index,
+            amount,
+            proof,
+        }
+        .pack(),
+    })
+}
+
+/// Creates a `Clawback` instruction.
+pub fn clawback(
+    distributor_program_id: &Pubkey,
+    distributor_pubkey: &Pubkey,
+    vault_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(distributor_program_id)?;
+
+    Ok(Instruction {
+        program_id: *distributor_program_id,
+        accounts: vec![
+            AccountMeta::new(*distributor_pubkey, false),
+            AccountMeta::new(*vault_pubkey, false),
+            AccountMeta::new_readonly(*mint_pubkey, false),
+            AccountMeta::new(*destination_pubkey, false),
+            AccountMeta::new_readonly(*admin_pubkey, true),
+            AccountMeta::new_readonly(get_vault_authority_address(distributor_pubkey), false),
+            AccountMeta::new_readonly(apl_token::id(), false),
+        ],
+        data: DistributorInstruction::Clawback.pack(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_instruction_packing() {
+        let check = DistributorInstruction::NewDistributor {
+            txid: [1; 32],
+            vout: 2,
+            root: [3; 32],
+            max_total_claim: 4,
+            max_num_nodes: 5,
+            clawback_timestamp: 6,
+        };
+        let packed = check.pack();
+        let mut expect = vec![0u8];
+        expect.extend_from_slice(&[1; 32]);
+        expect.extend_from_slice(&2u32.to_le_bytes());
+        expect.extend_from_slice(&[3; 32]);
+        for value in [4u64, 5, 6] {
+            expect.extend_from_slice(&value.to_le_bytes());
+        }
+        assert_eq!(packed, expect);
+        let unpacked = DistributorInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = DistributorInstruction::Claim {
+            index: 1,
+            amount: 2,
+            proof: vec![[3; 32], [4; 32]],
+        };
+        let packed = check.pack();
+        let mut expect = vec![1u8];
+        expect.extend_from_slice(&1u64.to_le_bytes());
+        expect.extend_from_slice(&2u64.to_le_bytes());
+        expect.push(2);
+        expect.extend_from_slice(&[3; 32]);
+        expect.extend_from_slice(&[4; 32]);
+        assert_eq!(packed, expect);
+        let unpacked = DistributorInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = DistributorInstruction::Clawback;
+        let packed = check.pack();
+        let expect = vec![2u8];
+        assert_eq!(packed, expect);
+        let unpacked = DistributorInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn test_instruction_unpack_errors() {
+        let mut data = DistributorInstruction::Claim {
+            index: 1,
+            amount: 2,
+            proof: vec![[3; 32]],
+        }
+        .pack();
+        data.pop();
+        assert_eq!(
+            DistributorInstruction::unpack(&data),
+            Err(DistributorError::InvalidInstruction.into())
+        );
+
+        let mut data = DistributorInstruction::Claim {
+            index: 1,
+            amount: 2,
+            proof: vec![],
+        }
+        .pack();
+        *data.last_mut().unwrap() = MAX_PROOF_LEN as u8 + 1;
+        data.extend(vec![0; 32 * (MAX_PROOF_LEN + 1)]);
+        assert_eq!(
+            DistributorInstruction::unpack(&data),
+            Err(DistributorError::InvalidInstruction.into())
+        );
+        assert_eq!(
+            DistributorInstruction::unpack(&[3]),
+            Err(DistributorError::InvalidInstruction.into())
+        );
+    }
+}
@@ -0,0 +1,72 @@
+#![deny(missing_docs)]
+#![cfg_attr(not(test), forbid(unsafe_code))]
+
+//! A program that airdrops APL tokens to the recipients of a merkle tree,
+//! each claiming their own amount with a proof
+
+pub mod error;
+pub mod instruction;
+pub mod merkle;
+pub mod processor;
+pub mod state;
+#[cfg(feature = "host")]
+pub mod tree;
+
+#[cfg(not(feature = "no-entrypoint"))]
+mod entrypoint;
+
+// Export current sdk types for downstream users building with a different sdk
+// version
+pub use arch_program;
+use arch_program::{entrypoint::ProgramResult, program_error::ProgramError, pubkey::Pubkey};
+
+/// Seed of the authority owning the vault of a distributor, derived with the
+/// distributor address
+pub const VAULT_AUTHORITY_SEED: &[u8] = b"vault-authority";
+
+/// Seed of the bitmap of claimed indices of a distributor, derived with the
+/// distributor address
+pub const CLAIM_BITMAP_SEED: &[u8] = b"claim-bitmap";
+
+/// The program id of the merkle distributor program
+pub fn id() -> Pubkey {
+    Pubkey::from_slice(b"apl-merkle-distributor0000000000")
+}
+
+/// Checks that the supplied program ID is the correct one for
+/// APL-merkle-distributor
+pub fn check_program_account(distributor_program_id: &Pubkey) -> ProgramResult {
+    if distributor_program_id != &id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
+
+/// Derives the authority owning the vault of a distributor, and its bump seed
+pub fn get_vault_authority_address_and_bump_seed(
+    distributor: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[VAULT_AUTHORITY_SEED, &distributor.serialize()],
+        program_id,
+    )
+}
+
+/// Derives the authority owning the vault of a distributor
+pub fn get_vault_authority_address(distributor: &Pubkey) -> Pubkey {
+    get_vault_authority_address_and_bump_seed(distributor, &id()).0
+}
+
+/// Derives the claim bitmap of a distributor, and its bump seed
+pub fn get_claim_bitmap_address_and_bump_seed(
+    distributor: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CLAIM_BITMAP_SEED, &distributor.serialize()], program_id)
+}
+
+/// Derives the claim bitmap of a distributor
+pub fn get_claim_bitmap_address(distributor: &Pubkey) -> Pubkey {
+    get_claim_bitmap_address_and_bump_seed(distributor, &id()).0
+}
@@ -0,0 +1,64 @@
+//! Merkle tree hashing shared by the program and the tree builder
+//!
+//! Leaves and inner nodes are hashed with distinct prefixes so a leaf can
+//! never be passed off as a node. The two children of a node are sorted
+//! before hashing, so proofs are just the list of siblings from leaf to root.
+
+use arch_program::{
+    bitcoin::hashes::{sha256, Hash, HashEngine},
+    pubkey::Pubkey,
+};
+
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+/// Hashes the leaf granting `amount` tokens to `recipient` at `index`
+pub fn hash_leaf(index: u64, recipient: &Pubkey, amount: u64) -> [u8; 32] {
+    let mut engine = sha256::Hash::engine();
+    engine.input(LEAF_PREFIX);
+    engine.input(&index.to_le_bytes());
+    engine.input(recipient.as_ref());
+    engine.input(&amount.to_le_bytes());
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
+/// Hashes two sibling nodes into their parent
+pub fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let (first, second) = if left <= right {
+        (left, right)
+    } else {
+        (right, left)
+    };
+    let mut engine = sha256::Hash::engine();
+    engine.input(NODE_PREFIX);
+    engine.input(first);
+    engine.input(second);
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
+/// Checks that `proof` leads from `leaf` to `root`
+pub fn verify(proof: &[[u8; 32]], root: &[u8; 32], leaf: [u8; 32]) -> bool {
+    proof
+        .iter()
+        .fold(leaf, |node, sibling| hash_node(&node, sibling))
+        == *root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify() {
+        let recipient = Pubkey::new_unique();
+        let leaf = hash_leaf(0, &recipient, 100);
+        let sibling = hash_leaf(1, &Pubkey::new_unique(), 200);
+        let root = hash_node(&leaf, &sibling);
+        assert_eq!(root, hash_node(&sibling, &leaf));
+
+        assert!(verify(&[sibling], &root, leaf));
+        assert!(verify(&[], &leaf, leaf));
+        assert!(!verify(&[sibling], &root, hash_leaf(0, &recipient, 101)));
+        assert!(!verify(&[sibling], &root, hash_leaf(1, &recipient, 100)));
+    }
+}
@@ -0,0 +1,402 @@
+//! Program state processor
+
+use {
+    crate::{
+        error::DistributorError,
+        get_claim_bitmap_address_and_bump_seed, get_vault_authority_address_and_bump_seed,
+        instruction::DistributorInstruction,
+        merkle,
+        state::{ClaimBitmap, MerkleDistributor},
+        CLAIM_BITMAP_SEED, VAULT_AUTHORITY_SEED,
+    },
+    apl_token::{
+        extension::BaseState,
+        state::{Account, Mint},
+    },
+    arch_program::{
+        account::{next_account_info, AccountInfo},
+        entrypoint::ProgramResult,
+        msg,
+        program::{get_clock, invoke_signed},
+        program_error::ProgramError,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        system_instruction,
+    },
+};
+
+/// Program state handler.
+pub struct Processor {}
+impl Processor {
+    /// Processes a [`NewDistributor`](enum.DistributorInstruction.html)
+    /// instruction.
+    #[allow(clippy::too_many_arguments)]
+    pub fn process_new_distributor(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        txid: [u8; 32],
+        vout: u32,
+        root: [u8; 32],
+        max_total_claim: u64,
+        max_num_nodes: u64,
+        clawback_timestamp: i64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let distributor_info = next_account_info(account_info_iter)?;
+        let claim_bitmap_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let mint_info = next_account_info(account_info_iter)?;
+        let source_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        Self::check_account_owner(program_id, distributor_info)?;
+        let distributor = MerkleDistributor::unpack_unchecked(&distributor_info.data.borrow())?;
+        if distributor.is_initialized {
+            return Err(DistributorError::AlreadyInUse.into());
+        }
+        if !admin_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if max_total_claim == 0 || max_num_nodes == 0 {
+            return Err(DistributorError::InvalidDistributor.into());
+        }
+
+        let (vault_authority, vault_authority_bump) =
+            get_vault_authority_address_and_bump_seed(distributor_info.key, program_id);
+        apl_token::check_program_account(vault_info.owner)?;
+        let vault = Account::unpack_base(&vault_info.data.borrow())?;
+        if vault.owner != vault_authority {
+            return Err(DistributorError::InvalidVault.into());
+        }
+        let decimals = Self::unpack_mint(mint_info, &vault.mint)?;
+
+        let (claim_bitmap, claim_bitmap_bump) =
+            get_claim_bitmap_address_and_bump_seed(distributor_info.key, program_id);
+        if claim_bitmap != *claim_bitmap_info.key {
+            msg!("Error: Claim bitmap address does not match seed derivation");
+            return Err(ProgramError::InvalidSeeds);
+        }
+        let claim_bitmap_len = ClaimBitmap::len(max_num_nodes)
+            .and_then(|len| u32::try_from(len).ok())
+            .ok_or(DistributorError::InvalidDistributor)?;
+        let distributor_key = distributor_info.key.serialize();
+        let claim_bitmap_signer_seeds: &[&[u8]] =
+            &[CLAIM_BITMAP_SEED, &distributor_key, &[claim_bitmap_bump]];
+
+        invoke_signed(
+            &system_instruction::create_account(txid, vout, *claim_bitmap_info.key),
+            &[claim_bitmap_info.clone(), system_program_info.clone()],
+            &[claim_bitmap_signer_seeds],
+        )?;
+        invoke_signed(
+            &system_instruction::write_bytes(
+                0,
+                claim_bitmap_len,
+                vec![0; claim_bitmap_len as usize],
+                *claim_bitmap_info.key,
+            ),
+            &[claim_bitmap_info.clone(), system_program_info.clone()],
+            &[claim_bitmap_signer_seeds],
+        )?;
+        invoke_signed(
+            &system_instruction::assign(*claim_bitmap_info.key, *program_id),
+            &[claim_bitmap_info.clone(), system_program_info.clone()],
+            &[claim_bitmap_signer_seeds],
+        )?;
+
+        let distributor = MerkleDistributor {
+            is_initialized: true,
+            vault_authority_bump,
+            claim_bitmap_bump,
+            is_clawed_back: false,
+            admin: *admin_info.key,
+            mint: vault.mint,
+            vault: *vault_info.key,
+            root,
+            max_total_claim,
+            max_num_nodes,
+            total_amount_claimed: 0,
+            num_nodes_claimed: 0,
+            clawback_timestamp,
+        };
+        MerkleDistributor::pack(distributor, &mut distributor_info.data.borrow_mut())?;
+
+        Self::transfer(
+            token_program_info,
+            source_info,
+            mint_info,
+            vault_info,
+            admin_info,
+            max_total_claim,
+            decimals,
+            &[],
+        )
+    }
+
+    /// Processes a [`Claim`](enum.DistributorInstruction.html) instruction.
+    pub fn process_claim(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        index: u64,
+        amount: u64,
+        proof: &[[u8; 32]],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let distributor_info = next_account_info(account_info_iter)?;
+        let claim_bitmap_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let mint_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        let recipient_info = next_account_info(account_info_iter)?;
+        let vault_authority_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        let mut distributor = Self::unpack_distributor(program_id, distributor_info, vault_info)?;
+        if !recipient_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if index >= distributor.max_num_nodes {
+            return Err(DistributorError::IndexOutOfRange.into());
+        }
+
+        let distributor_key = distributor_info.key.serialize();
+        let claim_bitmap = Pubkey::create_program_address(
+            &[
+                CLAIM_BITMAP_SEED,
+                &distributor_key,
+                &[distributor.claim_bitmap_bump],
+            ],
+            program_id,
+        )?;
+        if claim_bitmap != *claim_bitmap_info.key {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Self::check_account_owner(program_id, claim_bitmap_info)?;
+        if ClaimBitmap::is_claimed(&claim_bitmap_info.data.borrow(), index) {
+            return Err(DistributorError::AlreadyClaimed.into());
+        }
+
+        let leaf = merkle::hash_leaf(index, recipient_info.key, amount);
+        if !merkle::verify(proof, &distributor.root, leaf) {
+            return Err(DistributorError::InvalidProof.into());
+        }
+
+        distributor.total_amount_claimed = distributor
+            .total_amount_claimed
+            .checked_add(amount)
+            .filter(|total| *total <= distributor.max_total_claim)
+            .ok_or(DistributorError::ExceedsMaxClaim)?;
+        distributor.num_nodes_claimed = distributor
+            .num_nodes_claimed
+            .checked_add(1)
+            .ok_or(DistributorError::Overflow)?;
+        ClaimBitmap::set_claimed(&mut claim_bitmap_info.data.borrow_mut(), index)?;
+        MerkleDistributor::pack(distributor, &mut distributor_info.data.borrow_mut())?;
+
+        Self::transfer_from_vault(
+            program_id,
+            &distributor,
+            distributor_info,
+            token_program_info,
+            vault_info,
+            mint_info,
+            destination_info,
+            vault_authority_info,
+            amount,
+        )
+    }
+
+    /// Processes a [`Clawback`](enum.DistributorInstruction.html)
+    /// instruction.
+    pub fn process_clawback(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let distributor_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let mint_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let vault_authority_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        let mut distributor = Self::unpack_distributor(program_id, distributor_info, vault_info)?;
+        if distributor.admin != *admin_info.key {
+            return Err(DistributorError::AdminMismatch.into());
+        }
+        if !admin_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if get_clock().unix_timestamp < distributor.clawback_timestamp {
+            return Err(DistributorError::ClawbackNotStarted.into());
+        }
+
+        distributor.is_clawed_back = true;
+        MerkleDistributor::pack(distributor, &mut distributor_info.data.borrow_mut())?;
+
+        let amount = Account::unpack_base(&vault_info.data.borrow())?.amount;
+        if amount == 0 {
+            return Ok(());
+        }
+        Self::transfer_from_vault(
+            program_id,
+            &distributor,
+            distributor_info,
+            token_program_info,
+            vault_info,
+            mint_info,
+            destination_info,
+            vault_authority_info,
+            amount,
+        )
+    }
+
+    /// Processes an [`Instruction`](enum.Instruction.html).
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
+        let instruction = DistributorInstruction::unpack(input)?;
+
+        match instruction {
+            DistributorInstruction::NewDistributor {
+                txid,
+                vout,
+                root,
+                max_total_claim,
+                max_num_nodes,
+                clawback_timestamp,
+            } => {
+                msg!("Instruction: NewDistributor");
+                Self::process_new_distributor(
+                    program_id,
+                    accounts,
+                    txid,
+                    vout,
+                    root,
+                    max_total_claim,
+                    max_num_nodes,
+                    clawback_timestamp,
+                )
+            }
+            DistributorInstruction::Claim {
+                index,
+                amount,
+                proof,
+            } => {
+                msg!("Instruction: Claim");
+                Self::process_claim(program_id, accounts, index, amount, &proof)
+            }
+            DistributorInstruction::Clawback => {
+                msg!("Instruction: Clawback");
+                Self::process_clawback(program_id, accounts)
+            }
+        }
+    }
+
+    /// Checks that the account is owned by the given program
+    pub fn check_account_owner(program_id: &Pubkey, account_info: &AccountInfo) -> ProgramResult {
+        if program_id != account_info.owner {
+            Err(ProgramError::IncorrectProgramId)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Unpacks a distributor still distributing, checking the vault passed
+    /// with it
+    fn unpack_distributor(
+        program_id: &Pubkey,
+        distributor_info: &AccountInfo,
+        vault_info: &AccountInfo,
+    ) -> Result<MerkleDistributor, ProgramError> {
+        Self::check_account_owner(program_id, distributor_info)?;
+        let distributor = MerkleDistributor::unpack(&distributor_info.data.borrow())?;
+        if distributor.vault != *vault_info.key {
+            return Err(DistributorError::InvalidVault.into());
+        }
+        if distributor.is_clawed_back {
+            return Err(DistributorError::ClawedBack.into());
+        }
+        Ok(distributor)
+    }
+
+    /// Checks that the mint is the distributed one, returning its decimals
+    fn unpack_mint(mint_info: &AccountInfo, expected_mint: &Pubkey) -> Result<u8, ProgramError> {
+        apl_token::check_program_account(mint_info.owner)?;
+        if mint_info.key != expected_mint {
+            return Err(DistributorError::MintMismatch.into());
+        }
+        let mint = Mint::unpack_base(&mint_info.data.borrow())?;
+        Ok(mint.decimals)
+    }
+
+    /// Transfers tokens out of the vault of a distributor, signing as its
+    /// vault authority
+    #[allow(clippy::too_many_arguments)]
+    fn transfer_from_vault<'a>(
+        program_id: &Pubkey,
+        distributor: &MerkleDistributor,
+        distributor_info: &AccountInfo<'a>,
+        token_program_info: &AccountInfo<'a>,
+        vault_info: &AccountInfo<'a>,
+        mint_info: &AccountInfo<'a>,
+        destination_info: &AccountInfo<'a>,
+        vault_authority_info: &AccountInfo<'a>,
+        amount: u64,
+    ) -> ProgramResult {
+        let distributor_key = distributor_info.key.serialize();
+        let vault_authority_seeds: &[&[u8]] = &[
+            VAULT_AUTHORITY_SEED,
+            &distributor_key,
+            &[distributor.vault_authority_bump],
+        ];
+        let vault_authority = Pubkey::create_program_address(vault_authority_seeds, program_id)?;
+        if vault_authority != *vault_authority_info.key {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        let decimals = Self::unpack_mint(mint_info, &distributor.mint)?;
+
+        Self::transfer(
+            token_program_info,
+            vault_info,
+            mint_info,
+            destination_info,
+            vault_authority_info,
+            amount,
+            decimals,
+            &[vault_authority_seeds],
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn transfer<'a>(
+        token_program_info: &AccountInfo<'a>,
+        source_info: &AccountInfo<'a>,
+        mint_info: &AccountInfo<'a>,
+        destination_info: &AccountInfo<'a>,
+        authority_info: &AccountInfo<'a>,
+        amount: u64,
+        decimals: u8,
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        apl_token::check_program_account(token_program_info.key)?;
+        invoke_signed(
+            &apl_token::instruction::transfer_checked(
+                token_program_info.key,
+                source_info.key,
+                mint_info.key,
+                destination_info.key,
+                authority_info.key,
+                &[],
+                amount,
+                decimals,
+            )?,
+            &[
+                source_info.clone(),
+                mint_info.clone(),
+                destination_info.clone(),
+                authority_info.clone(),
+                token_program_info.clone(),
+            ],
+            signers_seeds,
+        )
+    }
+}
@@ -0,0 +1,204 @@
+//! State transition types
+
+use {
+    arch_program::{
+        program_error::ProgramError,
+        program_pack::{IsInitialized, Pack, Sealed},
+        pubkey::Pubkey,
+    },
+    arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs},
+};
+
+/// Merkle distributor data.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MerkleDistributor {
+    /// Is `true` if this structure has been initialized
+    pub is_initialized: bool,
+    /// Bump seed of the vault authority
+    pub vault_authority_bump: u8,
+    /// Bump seed of the claim bitmap
+    pub claim_bitmap_bump: u8,
+    /// Is `true` if the unclaimed tokens have been clawed back
+    pub is_clawed_back: bool,
+    /// Account allowed to claw back the unclaimed tokens
+    pub admin: Pubkey,
+    /// Mint of the distributed tokens
+    pub mint: Pubkey,
+    /// Token account holding the unclaimed tokens
+    pub vault: Pubkey,
+    /// Root of the merkle tree of `(index, recipient, amount)` leaves
+    pub root: [u8; 32],
+    /// Amount of tokens distributed over all leaves
+    pub max_total_claim: u64,
+    /// Number of leaves of the tree
+    pub max_num_nodes: u64,
+    /// Amount of tokens claimed so far
+    pub total_amount_claimed: u64,
+    /// Number of leaves claimed so far
+    pub num_nodes_claimed: u64,
+    /// Unix timestamp from which the admin can claw back the unclaimed tokens
+    pub clawback_timestamp: i64,
+}
+
+impl Sealed for MerkleDistributor {}
+impl IsInitialized for MerkleDistributor {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+impl Pack for MerkleDistributor {
+    const LEN: usize = 172;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 172];
+        let (
+            is_initialized,
+            vault_authority_bump,
+            claim_bitmap_bump,
+            is_clawed_back,
+            admin,
+            mint,
+            vault,
+            root,
+            max_total_claim,
+            max_num_nodes,
+            total_amount_claimed,
+            num_nodes_claimed,
+            clawback_timestamp,
+        ) = array_refs![src, 1, 1, 1, 1, 32, 32, 32, 32, 8, 8, 8, 8, 8];
+        Ok(MerkleDistributor {
+            is_initialized: unpack_bool(is_initialized)?,
+            vault_authority_bump: vault_authority_bump[0],
+            claim_bitmap_bump: claim_bitmap_bump[0],
+            is_clawed_back: unpack_bool(is_clawed_back)?,
+            admin: Pubkey::from_slice(admin),
+            mint: Pubkey::from_slice(mint),
+            vault: Pubkey::from_slice(vault),
+            root: *root,
+            max_total_claim: u64::from_le_bytes(*max_total_claim),
+            max_num_nodes: u64::from_le_bytes(*max_num_nodes),
+            total_amount_claimed: u64::from_le_bytes(*total_amount_claimed),
+            num_nodes_claimed: u64::from_le_bytes(*num_nodes_claimed),
+            clawback_timestamp: i64::from_le_bytes(*clawback_timestamp),
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 172];
+        let (
+            is_initialized_dst,
+            vault_authority_bump_dst,
+            claim_bitmap_bump_dst,
+            is_clawed_back_dst,
+            admin_dst,
+            mint_dst,
+            vault_dst,
+            root_dst,
+            max_total_claim_dst,
+            max_num_nodes_dst,
+            total_amount_claimed_dst,
+            num_nodes_claimed_dst,
+            clawback_timestamp_dst,
+        ) = mut_array_refs![dst, 1, 1, 1, 1, 32, 32, 32, 32, 8, 8, 8, 8, 8];
+        *is_initialized_dst = [self.is_initialized as u8];
+        *vault_authority_bump_dst = [self.vault_authority_bump];
+        *claim_bitmap_bump_dst = [self.claim_bitmap_bump];
+        *is_clawed_back_dst = [self.is_clawed_back as u8];
+        admin_dst.copy_from_slice(self.admin.as_ref());
+        mint_dst.copy_from_slice(self.mint.as_ref());
+        vault_dst.copy_from_slice(self.vault.as_ref());
+        *root_dst = self.root;
+        *max_total_claim_dst = self.max_total_claim.to_le_bytes();
+        *max_num_nodes_dst = self.max_num_nodes.to_le_bytes();
+        *total_amount_claimed_dst = self.total_amount_claimed.to_le_bytes();
+        *num_nodes_claimed_dst = self.num_nodes_claimed.to_le_bytes();
+        *clawback_timestamp_dst = self.clawback_timestamp.to_le_bytes();
+    }
+}
+
+fn unpack_bool(src: &[u8; 1]) -> Result<bool, ProgramError> {
+    match src {
+        [0] => Ok(false),
+        [1] => Ok(true),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+/// Bitmap of the claimed indices of a distributor, one bit per leaf.
+pub struct ClaimBitmap;
+
+impl ClaimBitmap {
+    /// Length in bytes of the bitmap of `num_nodes` leaves
+    pub fn len(num_nodes: u64) -> Option<usize> {
+        usize::try_from(num_nodes.div_ceil(8)).ok()
+    }
+
+    /// Checks if `index` has been claimed
+    pub fn is_claimed(bitmap: &[u8], index: u64) -> bool {
+        let (byte, mask) = Self::position(index);
+        bitmap.get(byte).is_some_and(|byte| byte & mask != 0)
+    }
+
+    /// Marks `index` as claimed
+    pub fn set_claimed(bitmap: &mut [u8], index: u64) -> Result<(), ProgramError> {
+        let (byte, mask) = Self::position(index);
+        *bitmap
+            .get_mut(byte)
+            .ok_or(ProgramError::InvalidAccountData)? |= mask;
+        Ok(())
+    }
+
+    fn position(index: u64) -> (usize, u8) {
+        ((index / 8) as usize, 1 << (index % 8))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_unpack() {
+        let distributor = MerkleDistributor {
+            is_initialized: true,
+            vault_authority_bump: 255,
+            claim_bitmap_bump: 254,
+            is_clawed_back: false,
+            admin: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            vault: Pubkey::new_unique(),
+            root: [7; 32],
+            max_total_claim: 1_000,
+            max_num_nodes: 10,
+            total_amount_claimed: 100,
+            num_nodes_claimed: 1,
+            clawback_timestamp: 1_000_000,
+        };
+        let mut packed = vec![0u8; MerkleDistributor::LEN];
+        MerkleDistributor::pack(distributor, &mut packed).unwrap();
+        assert_eq!(MerkleDistributor::unpack(&packed).unwrap(), distributor);
+
+        packed[3] = 2;
+        assert_eq!(
+            MerkleDistributor::unpack(&packed),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn test_claim_bitmap() {
+        assert_eq!(ClaimBitmap::len(0), Some(0));
+        assert_eq!(ClaimBitmap::len(8), Some(1));
+        assert_eq!(ClaimBitmap::len(9), Some(2));
+
+        let mut bitmap = vec![0u8; 2];
+        assert!(!ClaimBitmap::is_claimed(&bitmap, 9));
+        ClaimBitmap::set_claimed(&mut bitmap, 9).unwrap();
+        assert!(ClaimBitmap::is_claimed(&bitmap, 9));
+        assert!(!ClaimBitmap::is_claimed(&bitmap, 8));
+        assert_eq!(bitmap, [0, 2]);
+        assert_eq!(
+            ClaimBitmap::set_claimed(&mut bitmap, 16),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+}
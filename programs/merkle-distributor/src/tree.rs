@@ -0,0 +1,189 @@
+//! Host-side merkle tree builder
+//!
+//! Builds the tree of a distribution and the proofs of its leaves, typically
+//! from a CSV file of `recipient,amount` rows. Recipients are hex-encoded
+//! public keys, and leaves are indexed in row order. Only built with the
+//! `host` feature.
+
+use {
+    crate::merkle::{hash_leaf, hash_node},
+    arch_program::pubkey::Pubkey,
+    std::io::{self, BufRead},
+};
+
+/// A leaf of the distribution.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Leaf {
+    /// Index of the leaf, also its bit in the claim bitmap
+    pub index: u64,
+    /// Account allowed to claim the leaf
+    pub recipient: Pubkey,
+    /// Amount of tokens granted to the recipient
+    pub amount: u64,
+}
+
+/// Merkle tree of a distribution, keeping every layer to build proofs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MerkleTree {
+    leaves: Vec<Leaf>,
+    layers: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    /// Builds the tree granting each `(recipient, amount)` pair in order,
+    /// or returns `None` if there are none
+    pub fn new(recipients: &[(Pubkey, u64)]) -> Option<Self> {
+        if recipients.is_empty() {
+            return None;
+        }
+        let leaves: Vec<Leaf> = recipients
+            .iter()
+            .zip(0..)
+            .map(|(&(recipient, amount), index)| Leaf {
+                index,
+                recipient,
+                amount,
+            })
+            .collect();
+
+        let mut layers = vec![leaves
+            .iter()
+            .map(|leaf| hash_leaf(leaf.index, &leaf.recipient, leaf.amount))
+            .collect::<Vec<_>>()];
+        while let Some(layer) = layers.last().filter(|layer| layer.len() > 1) {
+            // A node without a sibling moves up unchanged
+            let parents = layer
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_node(left, right),
+                    [node] => *node,
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(parents);
+        }
+
+        Some(MerkleTree { leaves, layers })
+    }
+
+    /// Builds the tree from CSV rows of hex-encoded recipient and amount,
+    /// skipping a `recipient,amount` header line if present
+    pub fn from_csv(reader: impl BufRead) -> io::Result<Self> {
+        let mut recipients = vec![];
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || (number == 0 && line.eq_ignore_ascii_case("recipient,amount")) {
+                continue;
+            }
+            recipients.push(
+                parse_row(line)
+                    .map_err(|reason| invalid_data(format!("line {}: {}", number + 1, reason)))?,
+            );
+        }
+        Self::new(&recipients).ok_or_else(|| invalid_data("no recipients".to_string()))
+    }
+
+    /// Root of the tree
+    pub fn root(&self) -> [u8; 32] {
+        self.layers.last().unwrap()[0]
+    }
+
+    /// Leaves of the tree, in index order
+    pub fn leaves(&self) -> &[Leaf] {
+        &self.leaves
+    }
+
+    /// Number of leaves, the `max_num_nodes` of the distributor
+    pub fn max_num_nodes(&self) -> u64 {
+        self.leaves.len() as u64
+    }
+
+    /// Sum of the leaf amounts, the `max_total_claim` of the distributor
+    pub fn max_total_claim(&self) -> Option<u64> {
+        self.leaves
+            .iter()
+            .try_fold(0u64, |total, leaf| total.checked_add(leaf.amount))
+    }
+
+    /// Proof of the leaf at `index`, the siblings from the leaf to the root
+    pub fn proof(&self, index: u64) -> Option<Vec<[u8; 32]>> {
+        let mut position = usize::try_from(index).ok()?;
+        if position >= self.leaves.len() {
+            return None;
+        }
+        let mut proof = vec![];
+        for layer in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = layer.get(position ^ 1) {
+                proof.push(*sibling);
+            }
+            position /= 2;
+        }
+        Some(proof)
+    }
+}
+
+fn parse_row(line: &str) -> Result<(Pubkey, u64), String> {
+    let (recipient, amount) = line
+        .split_once(',')
+        .ok_or_else(|| "expected recipient,amount".to_string())?;
+    let recipient = hex::decode(recipient.trim())
+        .ok()
+        .filter(|bytes| bytes.len() == 32)
+        .ok_or_else(|| format!("invalid recipient {}", recipient.trim()))?;
+    let amount = amount
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid amount {}", amount.trim()))?;
+    Ok((Pubkey::from_slice(&recipient), amount))
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::merkle::verify};
+
+    #[test]
+    fn test_proofs() {
+        for len in 1..=9 {
+            let recipients: Vec<_> = (0..len)
+                .map(|amount| (Pubkey::new_unique(), amount * 10))
+                .collect();
+            let tree = MerkleTree::new(&recipients).unwrap();
+            assert_eq!(tree.max_num_nodes(), len);
+            assert_eq!(tree.max_total_claim(), Some(len * (len - 1) * 5));
+
+            for leaf in tree.leaves() {
+                let proof = tree.proof(leaf.index).unwrap();
+                let hash = hash_leaf(leaf.index, &leaf.recipient, leaf.amount);
+                assert!(verify(&proof, &tree.root(), hash));
+                let wrong = hash_leaf(leaf.index, &leaf.recipient, leaf.amount + 1);
+                assert!(!verify(&proof, &tree.root(), wrong));
+            }
+            assert_eq!(tree.proof(len), None);
+        }
+        assert_eq!(MerkleTree::new(&[]), None);
+    }
+
+    #[test]
+    fn test_from_csv() {
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        let csv = format!("recipient,amount\n{:x},100\n\n{:x}, 250\n", first, second);
+        let tree = MerkleTree::from_csv(csv.as_bytes()).unwrap();
+        assert_eq!(
+            tree,
+            MerkleTree::new(&[(first, 100), (second, 250)]).unwrap()
+        );
+        assert_eq!(tree.leaves()[1].index, 1);
+
+        let error = MerkleTree::from_csv(format!("{:x},ten\n", first).as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "line 1: invalid amount ten");
+        assert!(MerkleTree::from_csv("0102,1\n".as_bytes()).is_err());
+        assert!(MerkleTree::from_csv("recipient,amount\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,89 @@
+//! Error types
+
+use {
+    arch_program::{
+        decode_error::DecodeError,
+        msg,
+        program_error::{PrintProgramError, ProgramError},
+    },
+    num_derive::FromPrimitive,
+    thiserror::Error,
+};
+
+/// Errors that may be returned by the Staking program.
+#[derive(Clone, Debug, Eq, Error, FromPrimitive, PartialEq)]
+pub enum StakingError {
+    // 0
+    /// Invalid instruction
+    #[error("Invalid instruction")]
+    InvalidInstruction,
+    /// The account cannot be initialized because it is already being used.
+    #[error("Already in use")]
+    AlreadyInUse,
+    /// The vault is not a token account owned by the pool's vault authority.
+    #[error("Invalid vault")]
+    InvalidVault,
+    /// The admin does not match the pool.
+    #[error("Admin mismatch")]
+    AdminMismatch,
+    /// The owner does not match the stake account.
+    #[error("Owner mismatch")]
+    OwnerMismatch,
+
+    // 5
+    /// The stake account belongs to another pool.
+    #[error("Pool mismatch")]
+    PoolMismatch,
+    /// The stake account holds less than the amount to unstake.
+    #[error("Insufficient stake")]
+    InsufficientStake,
+    /// The unstaked tokens are still cooling down.
+    #[error("Cooldown active")]
+    CooldownActive,
+    /// No unstaked tokens are waiting to be withdrawn.
+    #[error("Nothing to withdraw")]
+    NothingToWithdraw,
+    /// No rewards are owed to the stake account.
+    #[error("Nothing to claim")]
+    NothingToClaim,
+
+    // 10
+    /// Operation overflowed
+    #[error("Operation overflowed")]
+    Overflow,
+}
+impl From<StakingError> for ProgramError {
+    fn from(e: StakingError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+impl<T> DecodeError<T> for StakingError {
+    fn type_of() -> &'static str {
+        "StakingError"
+    }
+}
+
+impl PrintProgramError for StakingError {
+    fn print<E>(&self)
+    where
+        E: 'static
+            + std::error::Error
+            + DecodeError<E>
+            + PrintProgramError
+            + num_traits::FromPrimitive,
+    {
+        match self {
+            StakingError::InvalidInstruction => msg!("Error: Invalid instruction"),
+            StakingError::AlreadyInUse => msg!("Error: account already in use"),
+            StakingError::InvalidVault => msg!("Error: invalid vault"),
+            StakingError::AdminMismatch => msg!("Error: admin mismatch"),
+            StakingError::OwnerMismatch => msg!("Error: owner mismatch"),
+            StakingError::PoolMismatch => msg!("Error: pool mismatch"),
+            StakingError::InsufficientStake => msg!("Error: insufficient stake"),
+            StakingError::CooldownActive => msg!("Error: cooldown active"),
+            StakingError::NothingToWithdraw => msg!("Error: nothing to withdraw"),
+            StakingError::NothingToClaim => msg!("Error: nothing to claim"),
+            StakingError::Overflow => msg!("Error: Operation overflowed"),
+        }
+    }
+}
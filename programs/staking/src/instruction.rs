@@ -0,0 +1,509 @@
+//! Instruction types
+
+use {
+    crate::{check_program_account, error::StakingError, get_vault_authority_address},
+    arch_program::{
+        account::AccountMeta, instruction::Instruction, program_error::ProgramError, pubkey::Pubkey,
+    },
+    std::{convert::TryInto, mem::size_of},
+};
+
+/// Instructions supported by the staking program.
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum StakingInstruction {
+    /// Initializes a staking pool. The pool account must be allocated and
+    /// assigned to the staking program, and both vaults must be token
+    /// accounts owned by the pool's vault authority.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The pool account to initialize.
+    ///   1. `[]` The stake vault token account.
+    ///   2. `[]` The reward vault token account.
+    ///   3. `[signer]` The admin.
+    InitializePool {
+        /// Rewards emitted per second.
+        reward_rate: u64,
+        /// Seconds unstaked tokens wait before they can be withdrawn.
+        cooldown_seconds: i64,
+    },
+    /// Initializes a stake account in a pool. The stake account must be
+    /// allocated and assigned to the staking program.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The stake account to initialize.
+    ///   1. `[]` The pool account.
+    ///   2. `[]` The owner of the stake.
+    InitializeStakeAccount,
+    /// Moves tokens from the owner into the stake vault.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The pool account.
+    ///   1. `[writable]` The stake account.
+    ///   2. `[writable]` The stake vault token account.
+    ///   3. `[writable]` The owner's source token account.
+    ///   4. `[signer]` The owner of the stake and the source account.
+    ///   5. `[]` The token program.
+    Stake {
+        /// Amount of tokens to stake.
+        amount: u64,
+    },
+    /// Stops staking tokens. Without a cooldown they are moved to the
+    /// destination right away, otherwise they are withdrawn later with
+    /// `WithdrawUnstaked`, restarting the cooldown of any tokens pending.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The pool account.
+    ///   1. `[writable]` The stake account.
+    ///   2. `[writable]` The stake vault token account.
+    ///   3. `[writable]` The destination token account.
+    ///   4. `[signer]` The owner of the stake.
+    ///   5. `[]` The vault authority.
+    ///   6. `[]` The token program.
+    Unstake {
+        /// Amount of tokens to unstake.
+        amount: u64,
+    },
+    /// Moves the unstaked tokens to the destination once their cooldown is
+    /// over.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[]` The pool account.
+    ///   1. `[writable]` The stake account.
+    ///   2. `[writable]` The stake vault token account.
+    ///   3. `[writable]` The destination token account.
+    ///   4. `[signer]` The owner of the stake.
+    ///   5. `[]` The vault authority.
+    ///   6. `[]` The token program.
+    WithdrawUnstaked,
+    /// Moves the rewards owed to a stake to the destination.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The pool account.
+    ///   1. `[writable]` The stake account.
+    ///   2. `[writable]` The reward vault token account.
+    ///   3. `[writable]` The destination token account.
+    ///   4. `[signer]` The owner of the stake.
+    ///   5. `[]` The vault authority.
+    ///   6. `[]` The token program.
+    ClaimRewards,
+    /// Moves reward tokens from the admin into the reward vault, to be
+    /// emitted from now on.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The pool account.
+    ///   1. `[writable]` The reward vault token account.
+    ///   2. `[writable]` The admin's source token account.
+    ///   3. `[signer]` The admin, owner of the source account.
+    ///   4. `[]` The token program.
+    FundRewards {
+        /// Amount of reward tokens to add.
+        amount: u64,
+    },
+    /// Changes the rate at which rewards are emitted from now on.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The pool account.
+    ///   1. `[signer]` The admin.
+    SetRewardRate {
+        /// Rewards emitted per second.
+        reward_rate: u64,
+    },
+}
+
+impl StakingInstruction {
+    /// Unpacks a byte buffer into a
+    /// [`StakingInstruction`](enum.StakingInstruction.html).
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        use StakingError::InvalidInstruction;
+
+        let (&tag, rest) = input.split_first().ok_or(InvalidInstruction)?;
+        Ok(match tag {
+            0 => {
+                let (reward_rate, rest) = Self::unpack_u64(rest)?;
+                let (cooldown_seconds, _rest) = Self::unpack_i64(rest)?;
+                Self::InitializePool {
+                    reward_rate,
+                    cooldown_seconds,
+                }
+            }
+            1 => Self::InitializeStakeAccount,
+            2 => {
+                let (amount, _rest) = Self::unpack_u64(rest)?;
+                Self::Stake { amount }
+            }
+            3 => {
+                let (amount, _rest) = Self::unpack_u64(rest)?;
+                Self::Unstake { amount }
+            }
+            4 => Self::WithdrawUnstaked,
+            5 => Self::ClaimRewards,
+            6 => {
+                let (amount, _rest) = Self::unpack_u64(rest)?;
+                Self::FundRewards { amount }
+            }
+            7 => {
+                let (reward_rate, _rest) = Self::unpack_u64(rest)?;
+                Self::SetRewardRate { reward_rate }
+            }
+            _ => return Err(InvalidInstruction.into()),
+        })
+    }
+
+    /// Packs a [`StakingInstruction`](enum.StakingInstruction.html) into a
+    /// byte buffer.
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(size_of::<Self>());
+        match self {
+            &Self::InitializePool {
+                reward_rate,
+                cooldown_seconds,
+            } => {
+                buf.push(0);
+                buf.extend_from_slice(&reward_rate.to_le_bytes());
+                buf.extend_from_slice(&cooldown_seconds.to_le_bytes());
+            }
+            Self::InitializeStakeAccount => buf.push(1),
+            &Self::Stake { amount } => {
+                buf.push(2);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            &Self::Unstake { amount } => {
+                buf.push(3);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::WithdrawUnstaked => buf.push(4),
+            Self::ClaimRewards => buf.push(5),
+            &Self::FundRewards { amount } => {
+                buf.push(6);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            &Self::SetRewardRate { reward_rate } => {
+                buf.push(7);
+                buf.extend_from_slice(&reward_rate.to_le_bytes());
+            }
+        };
+        buf
+    }
+
+    fn unpack_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
+        let value = input
+            .get(..8)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(StakingError::InvalidInstruction)?;
+        Ok((value, &input[8..]))
+    }
+
+    fn unpack_i64(input: &[u8]) -> Result<(i64, &[u8]), ProgramError> {
+        let value = input
+            .get(..8)
+            .and_then(|slice| slice.try_into().ok())
+            .map(i64::from_le_bytes)
+            .ok_or(StakingError::InvalidInstruction)?;
+        Ok((value, &input[8..]))
+    }
+}
+
+/// Creates an `InitializePool` instruction.
+pub fn initialize_pool(
+    staking_program_id: &Pubkey,
+    pool_pubkey: &Pubkey,
+    stake_vault_pubkey: &Pubkey,
+    reward_vault_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    reward_rate: u64,
+    cooldown_seconds: i64,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(staking_program_id)?;
+
+    Ok(Instruction {
+        program_id: *staking_program_id,
+        accounts: vec![
+            AccountMeta::new(*pool_pubkey, false),
+            AccountMeta::new_readonly(*stake_vault_pubkey, false),
+            AccountMeta::new_readonly(*reward_vault_pubkey, false),
+            AccountMeta::new_readonly(*admin_pubkey, true),
+        ],
+        data: StakingInstruction::InitializePool {
+            reward_rate,
+            cooldown_seconds,
+        }
+        .pack(),
+    })
+}
+
+/// Creates an `InitializeStakeAccount` instruction.
+pub fn initialize_stake_account(
+    staking_program_id: &Pubkey,
+    stake_pubkey: &Pubkey,
+    pool_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(staking_program_id)?;
+
+    Ok(Instruction {
+        program_id: *staking_program_id,
+        accounts: vec![
+            AccountMeta::new(*stake_pubkey, false),
+            AccountMeta::new_readonly(*pool_pubkey, false),
+            AccountMeta::new_readonly(*owner_pubkey, false),
+        ],
+        data: StakingInstruction::InitializeStakeAccount.pack(),
+    })
+}
+
+/// Creates a `Stake` instruction.
+pub fn stake(
+    staking_program_id: &Pubkey,
+    pool_pubkey: &Pubkey,
+    stake_pubkey: &Pubkey,
+    stake_vault_pubkey: &Pubkey,
+    source_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(staking_program_id)?;
+
+    Ok(Instruction {
+        program_id: *staking_program_id,
+        accounts: vec![
+            AccountMeta::new(*pool_pubkey, false),
+            AccountMeta::new(*stake_pubkey, false),
+            AccountMeta::new(*stake_vault_pubkey, false),
+            AccountMeta::new(*source_pubkey, false),
+            AccountMeta::new_readonly(*owner_pubkey, true),
+            AccountMeta::new_readonly(apl_token::id(), false),
+        ],
+        data: StakingInstruction::Stake { amount }.pack(),
+    })
+}
+
+/// Creates an `Unstake` instruction.
+pub fn unstake(
+    staking_program_id: &Pubkey,
+    pool_pubkey: &Pubkey,
+    stake_pubkey: &Pubkey,
+    stake_vault_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(staking_program_id)?;
+
+    Ok(Instruction {
+        program_id: *staking_program_id,
+        accounts: vault_transfer_accounts(
+            pool_pubkey,
+            true,
+            stake_pubkey,
+            stake_vault_pubkey,
+            destination_pubkey,
+            owner_pubkey,
+        ),
+        data: StakingInstruction::Unstake { amount }.pack(),
+    })
+}
+
+/// Creates a `WithdrawUnstaked` instruction.
+pub fn withdraw_unstaked(
+    staking_program_id: &Pubkey,
+    pool_pubkey: &Pubkey,
+    stake_pubkey: &Pubkey,
+    stake_vault_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(staking_program_id)?;
+
+    Ok(Instruction {
+        program_id: *staking_program_id,
+        accounts: vault_transfer_accounts(
+            pool_pubkey,
+            false,
+            stake_pubkey,
+            stake_vault_pubkey,
+            destination_pubkey,
+            owner_pubkey,
+        ),
+        data: StakingInstruction::WithdrawUnstaked.pack(),
+    })
+}
+
+/// Creates a `ClaimRewards` instruction.
+pub fn claim_rewards(
+    staking_program_id: &Pubkey,
+    pool_pubkey: &Pubkey,
+    stake_pubkey: &Pubkey,
+    reward_vault_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(staking_program_id)?;
+
+    Ok(Instruction {
+        program_id: *staking_program_id,
+        accounts: vault_transfer_accounts(
+            pool_pubkey,
+            true,
+            stake_pubkey,
+            reward_vault_pubkey,
+            destination_pubkey,
+            owner_pubkey,
+        ),
+        data: StakingInstruction::ClaimRewards.pack(),
+    })
+}
+
+/// Creates a `FundRewards` instruction.
+pub fn fund_rewards(
+    staking_program_id: &Pubkey,
+    pool_pubkey: &Pubkey,
+    reward_vault_pubkey: &Pubkey,
+    source_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(staking_program_id)?;
+
+    Ok(Instruction {
+        program_id: *staking_program_id,
+        accounts: vec![
+            AccountMeta::new(*pool_pubkey, false),
+            AccountMeta::new(*reward_vault_pubkey, false),
+            AccountMeta::new(*source_pubkey, false),
+            AccountMeta::new_readonly(*admin_pubkey, true),
+            AccountMeta::new_readonly(apl_token::id(), false),
+        ],
+        data: StakingInstruction::FundRewards { amount }.pack(),
+    })
+}
+
+/// Creates a `SetRewardRate` instruction.
+pub fn set_reward_rate(
+    staking_program_id: &Pubkey,
+    pool_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    reward_rate: u64,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(staking_program_id)?;
+
+    Ok(Instruction {
+        program_id: *staking_program_id,
+        accounts: vec![
+            AccountMeta::new(*pool_pubkey, false),
+            AccountMeta::new_readonly(*admin_pubkey, true),
+        ],
+        data: StakingInstruction::SetRewardRate { reward_rate }.pack(),
+    })
+}
+
+/// Accounts of the instructions moving tokens out of a pool vault
+fn vault_transfer_accounts(
+    pool_pubkey: &Pubkey,
+    pool_is_writable: bool,
+    stake_pubkey: &Pubkey,
+    vault_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+) -> Vec<AccountMeta> {
+    vec![
+        if pool_is_writable {
+            AccountMeta::new(*pool_pubkey, false)
+        } else {
+            AccountMeta::new_readonly(*pool_pubkey, false)
+        },
+        AccountMeta::new(*stake_pubkey, false),
+        AccountMeta::new(*vault_pubkey, false),
+        AccountMeta::new(*destination_pubkey, false),
+        AccountMeta::new_readonly(*owner_pubkey, true),
+        AccountMeta::new_readonly(get_vault_authority_address(pool_pubkey), false),
+        AccountMeta::new_readonly(apl_token::id(), false),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_instruction_packing() {
+        let check = StakingInstruction::InitializePool {
+            reward_rate: 1,
+            cooldown_seconds: 2,
+        };
+        let packed = check.pack();
+        let mut expect = vec![0u8];
+        expect.extend_from_slice(&1u64.to_le_bytes());
+        expect.extend_from_slice(&2u64.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = StakingInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        for (check, tag) in [
+            (StakingInstruction::InitializeStakeAccount, 1u8),
+            (StakingInstruction::WithdrawUnstaked, 4),
+            (StakingInstruction::ClaimRewards, 5),
+        ] {
+            let packed = check.pack();
+            let expect = vec![tag];
+            assert_eq!(packed, expect);
+            let unpacked = StakingInstruction::unpack(&expect).unwrap();
+            assert_eq!(unpacked, check);
+        }
+
+        for (check, tag) in [
+            (StakingInstruction::Stake { amount: 3 }, 2u8),
+            (StakingInstruction::Unstake { amount: 3 }, 3),
+            (StakingInstruction::FundRewards { amount: 3 }, 6),
+            (StakingInstruction::SetRewardRate { reward_rate: 3 }, 7),
+        ] {
+            let packed = check.pack();
+            let mut expect = vec![tag];
+            expect.extend_from_slice(&3u64.to_le_bytes());
+            assert_eq!(packed, expect);
+            let unpacked = StakingInstruction::unpack(&expect).unwrap();
+            assert_eq!(unpacked, check);
+        }
+
+        assert_eq!(
+            StakingInstruction::unpack(&[2, 0]),
+            Err(StakingError::InvalidInstruction.into())
+        );
+        assert_eq!(
+            StakingInstruction::unpack(&[8]),
+            Err(StakingError::InvalidInstruction.into())
+        );
+    }
+
+    #[test]
+    fn test_instruction_builders() {
+        let pool = Pubkey::new_unique();
+        let instruction = withdraw_unstaked(
+            &crate::id(),
+            &pool,
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+        )
+        .unwrap();
+        assert!(!instruction.accounts[0].is_writable);
+        assert_eq!(
+            instruction.accounts[5].pubkey,
+            get_vault_authority_address(&pool)
+        );
+        assert_eq!(
+            set_reward_rate(&Pubkey::new_unique(), &pool, &Pubkey::new_unique(), 1),
+            Err(ProgramError::IncorrectProgramId)
+        );
+    }
+}
@@ -0,0 +1,49 @@
+#![deny(missing_docs)]
+#![cfg_attr(not(test), forbid(unsafe_code))]
+
+//! A program that pays rewards per second to the holders staking an APL
+//! token, shared in proportion to their stake
+
+pub mod error;
+pub mod instruction;
+pub mod math;
+pub mod processor;
+pub mod state;
+
+#[cfg(not(feature = "no-entrypoint"))]
+mod entrypoint;
+
+// Export current sdk types for downstream users building with a different sdk
+// version
+pub use arch_program;
+use arch_program::{entrypoint::ProgramResult, program_error::ProgramError, pubkey::Pubkey};
+
+/// Seed of the authority owning the stake and reward vaults of a pool,
+/// derived with the pool address
+pub const VAULT_AUTHORITY_SEED: &[u8] = b"vault-authority";
+
+/// The program id of the staking program
+pub fn id() -> Pubkey {
+    Pubkey::from_slice(b"apl-staking000000000000000000000")
+}
+
+/// Checks that the supplied program ID is the correct one for APL-staking
+pub fn check_program_account(staking_program_id: &Pubkey) -> ProgramResult {
+    if staking_program_id != &id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
+
+/// Derives the authority owning the vaults of a pool, and its bump seed
+pub fn get_vault_authority_address_and_bump_seed(
+    pool: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[VAULT_AUTHORITY_SEED, &pool.serialize()], program_id)
+}
+
+/// Derives the authority owning the vaults of a pool
+pub fn get_vault_authority_address(pool: &Pubkey) -> Pubkey {
+    get_vault_authority_address_and_bump_seed(pool, &id()).0
+}
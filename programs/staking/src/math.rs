@@ -0,0 +1,131 @@
+//! Math utilities for reward accrual
+
+use {crate::error::StakingError, arch_program::program_error::ProgramError};
+
+/// Scale of the reward-per-token accumulator
+pub const REWARD_PER_TOKEN_PRECISION: u128 = 1_000_000_000_000;
+
+/// Calculates the rewards emitted over `elapsed` seconds, capped at the
+/// rewards left to distribute
+pub fn calculate_emission(
+    reward_rate: u64,
+    elapsed: i64,
+    undistributed_rewards: u64,
+) -> Result<u64, ProgramError> {
+    if elapsed <= 0 {
+        return Ok(0);
+    }
+    let emission = (reward_rate as u128)
+        .checked_mul(elapsed as u128)
+        .ok_or(StakingError::Overflow)?;
+
+    Ok(emission.min(undistributed_rewards as u128) as u64)
+}
+
+/// Calculates the reward-per-token accumulator after sharing `emission`
+/// among `total_staked` tokens
+pub fn calculate_reward_per_token(
+    reward_per_token: u128,
+    emission: u64,
+    total_staked: u64,
+) -> Result<u128, ProgramError> {
+    let increase = (emission as u128)
+        .checked_mul(REWARD_PER_TOKEN_PRECISION)
+        .ok_or(StakingError::Overflow)?
+        .checked_div(total_staked as u128)
+        .ok_or(StakingError::Overflow)?;
+
+    reward_per_token
+        .checked_add(increase)
+        .ok_or(StakingError::Overflow.into())
+}
+
+/// Calculates the rewards owed to a stake once it is brought up to date with
+/// the accumulator
+pub fn calculate_rewards_owed(
+    staked_amount: u64,
+    reward_per_token: u128,
+    reward_per_token_paid: u128,
+    rewards_owed: u64,
+) -> Result<u64, ProgramError> {
+    let earned = reward_per_token
+        .checked_sub(reward_per_token_paid)
+        .ok_or(StakingError::Overflow)?
+        .checked_mul(staked_amount as u128)
+        .ok_or(StakingError::Overflow)?
+        .checked_div(REWARD_PER_TOKEN_PRECISION)
+        .ok_or(StakingError::Overflow)?;
+
+    let rewards_owed = earned
+        .checked_add(rewards_owed as u128)
+        .ok_or(StakingError::Overflow)?;
+
+    if rewards_owed > u64::MAX as u128 {
+        return Err(StakingError::Overflow.into());
+    }
+
+    Ok(rewards_owed as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calculate_emission() {
+        assert_eq!(calculate_emission(10, 100, 5_000).unwrap(), 1_000);
+        // Capped by the rewards left
+        assert_eq!(calculate_emission(10, 100, 500).unwrap(), 500);
+        // Clock going backwards emits nothing
+        assert_eq!(calculate_emission(10, -5, 500).unwrap(), 0);
+        assert_eq!(
+            calculate_emission(u64::MAX, i64::MAX, u64::MAX).unwrap(),
+            u64::MAX
+        );
+    }
+
+    #[test]
+    fn test_rewards_shared_by_stake() {
+        // 1_000 rewards over 300 + 100 staked tokens
+        let reward_per_token = calculate_reward_per_token(0, 1_000, 400).unwrap();
+        assert_eq!(reward_per_token, 2_500_000_000_000);
+        assert_eq!(
+            calculate_rewards_owed(300, reward_per_token, 0, 0).unwrap(),
+            750
+        );
+        assert_eq!(
+            calculate_rewards_owed(100, reward_per_token, 0, 5).unwrap(),
+            255
+        );
+
+        // A stake joining later earns only what accrues afterwards
+        let later = calculate_reward_per_token(reward_per_token, 400, 400).unwrap();
+        assert_eq!(
+            calculate_rewards_owed(100, later, reward_per_token, 0).unwrap(),
+            100
+        );
+
+        assert!(calculate_reward_per_token(0, 1, 0).is_err());
+        assert!(calculate_rewards_owed(1, 0, 1, 0).is_err());
+    }
+
+    #[test]
+    fn test_rewards_never_exceed_emission() {
+        for total_staked in [1u64, 3, 7, 1_000_003, u64::MAX] {
+            for emission in [0u64, 1, 999, 123_456_789] {
+                let reward_per_token =
+                    calculate_reward_per_token(0, emission, total_staked).unwrap();
+                let stakes = [
+                    total_staked / 3,
+                    total_staked / 3,
+                    total_staked - 2 * (total_staked / 3),
+                ];
+                let owed: u64 = stakes
+                    .iter()
+                    .map(|stake| calculate_rewards_owed(*stake, reward_per_token, 0, 0).unwrap())
+                    .sum();
+                assert!(owed <= emission);
+            }
+        }
+    }
+}
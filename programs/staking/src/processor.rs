@@ -0,0 +1,556 @@
+//! Program state processor
+
+use {
+    crate::{
+        error::StakingError,
+        get_vault_authority_address_and_bump_seed,
+        instruction::StakingInstruction,
+        math::{calculate_emission, calculate_reward_per_token, calculate_rewards_owed},
+        state::{StakeAccount, StakePool},
+        VAULT_AUTHORITY_SEED,
+    },
+    apl_token::{extension::BaseState, state::Account},
+    arch_program::{
+        account::{next_account_info, AccountInfo},
+        entrypoint::ProgramResult,
+        msg,
+        program::{get_clock, invoke_signed},
+        program_error::ProgramError,
+        program_pack::Pack,
+        pubkey::Pubkey,
+    },
+};
+
+/// Program state handler.
+pub struct Processor {}
+impl Processor {
+    /// Processes an [`InitializePool`](enum.StakingInstruction.html)
+    /// instruction.
+    pub fn process_initialize_pool(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        reward_rate: u64,
+        cooldown_seconds: i64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let pool_info = next_account_info(account_info_iter)?;
+        let stake_vault_info = next_account_info(account_info_iter)?;
+        let reward_vault_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+
+        Self::check_account_owner(program_id, pool_info)?;
+        let pool = StakePool::unpack_unchecked(&pool_info.data.borrow())?;
+        if pool.is_initialized {
+            return Err(StakingError::AlreadyInUse.into());
+        }
+        if !admin_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if cooldown_seconds < 0 {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let (vault_authority, vault_authority_bump) =
+            get_vault_authority_address_and_bump_seed(pool_info.key, program_id);
+        if stake_vault_info.key == reward_vault_info.key {
+            return Err(StakingError::InvalidVault.into());
+        }
+        let stake_vault = Self::unpack_vault(stake_vault_info, &vault_authority)?;
+        let reward_vault = Self::unpack_vault(reward_vault_info, &vault_authority)?;
+
+        let pool = StakePool {
+            is_initialized: true,
+            vault_authority_bump,
+            admin: *admin_info.key,
+            stake_mint: stake_vault.mint,
+            reward_mint: reward_vault.mint,
+            stake_vault: *stake_vault_info.key,
+            reward_vault: *reward_vault_info.key,
+            reward_rate,
+            cooldown_seconds,
+            last_update_timestamp: get_clock().unix_timestamp,
+            total_staked: 0,
+            undistributed_rewards: 0,
+            reward_per_token: 0,
+        };
+        StakePool::pack(pool, &mut pool_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Processes an [`InitializeStakeAccount`](enum.StakingInstruction.html)
+    /// instruction.
+    pub fn process_initialize_stake_account(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let stake_info = next_account_info(account_info_iter)?;
+        let pool_info = next_account_info(account_info_iter)?;
+        let owner_info = next_account_info(account_info_iter)?;
+
+        Self::check_account_owner(program_id, stake_info)?;
+        let stake = StakeAccount::unpack_unchecked(&stake_info.data.borrow())?;
+        if stake.is_initialized {
+            return Err(StakingError::AlreadyInUse.into());
+        }
+        let pool = Self::unpack_pool(program_id, pool_info)?;
+
+        let stake = StakeAccount {
+            is_initialized: true,
+            pool: *pool_info.key,
+            owner: *owner_info.key,
+            reward_per_token_paid: pool.reward_per_token,
+            ..StakeAccount::default()
+        };
+        StakeAccount::pack(stake, &mut stake_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Processes a [`Stake`](enum.StakingInstruction.html) instruction.
+    pub fn process_stake(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let pool_info = next_account_info(account_info_iter)?;
+        let stake_info = next_account_info(account_info_iter)?;
+        let stake_vault_info = next_account_info(account_info_iter)?;
+        let source_info = next_account_info(account_info_iter)?;
+        let owner_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        let mut pool = Self::unpack_pool(program_id, pool_info)?;
+        let mut stake = Self::unpack_stake(program_id, stake_info, pool_info, owner_info)?;
+        if pool.stake_vault != *stake_vault_info.key {
+            return Err(StakingError::InvalidVault.into());
+        }
+
+        Self::update_rewards(&mut pool, &mut stake)?;
+        stake.staked_amount = stake
+            .staked_amount
+            .checked_add(amount)
+            .ok_or(StakingError::Overflow)?;
+        pool.total_staked = pool
+            .total_staked
+            .checked_add(amount)
+            .ok_or(StakingError::Overflow)?;
+        StakePool::pack(pool, &mut pool_info.data.borrow_mut())?;
+        StakeAccount::pack(stake, &mut stake_info.data.borrow_mut())?;
+
+        Self::transfer(
+            token_program_info,
+            source_info,
+            stake_vault_info,
+            owner_info,
+            amount,
+            &[],
+        )
+    }
+
+    /// Processes an [`Unstake`](enum.StakingInstruction.html) instruction.
+    pub fn process_unstake(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let pool_info = next_account_info(account_info_iter)?;
+        let stake_info = next_account_info(account_info_iter)?;
+        let stake_vault_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        let owner_info = next_account_info(account_info_iter)?;
+        let vault_authority_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        let mut pool = Self::unpack_pool(program_id, pool_info)?;
+        let mut stake = Self::unpack_stake(program_id, stake_info, pool_info, owner_info)?;
+        if pool.stake_vault != *stake_vault_info.key {
+            return Err(StakingError::InvalidVault.into());
+        }
+
+        Self::update_rewards(&mut pool, &mut stake)?;
+        stake.staked_amount = stake
+            .staked_amount
+            .checked_sub(amount)
+            .ok_or(StakingError::InsufficientStake)?;
+        pool.total_staked = pool
+            .total_staked
+            .checked_sub(amount)
+            .ok_or(StakingError::Overflow)?;
+        let withdraw_now = pool.cooldown_seconds == 0;
+        if !withdraw_now {
+            stake.pending_unstake_amount = stake
+                .pending_unstake_amount
+                .checked_add(amount)
+                .ok_or(StakingError::Overflow)?;
+            stake.unstake_available_timestamp = get_clock()
+                .unix_timestamp
+                .checked_add(pool.cooldown_seconds)
+                .ok_or(StakingError::Overflow)?;
+        }
+        StakePool::pack(pool, &mut pool_info.data.borrow_mut())?;
+        StakeAccount::pack(stake, &mut stake_info.data.borrow_mut())?;
+
+        if !withdraw_now {
+            return Ok(());
+        }
+        Self::transfer_from_vault(
+            program_id,
+            &pool,
+            pool_info,
+            token_program_info,
+            stake_vault_info,
+            destination_info,
+            vault_authority_info,
+            amount,
+        )
+    }
+
+    /// Processes a [`WithdrawUnstaked`](enum.StakingInstruction.html)
+    /// instruction.
+    pub fn process_withdraw_unstaked(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let pool_info = next_account_info(account_info_iter)?;
+        let stake_info = next_account_info(account_info_iter)?;
+        let stake_vault_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        let owner_info = next_account_info(account_info_iter)?;
+        let vault_authority_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        let pool = Self::unpack_pool(program_id, pool_info)?;
+        let mut stake = Self::unpack_stake(program_id, stake_info, pool_info, owner_info)?;
+        if pool.stake_vault != *stake_vault_info.key {
+            return Err(StakingError::InvalidVault.into());
+        }
+
+        let amount = stake.pending_unstake_amount;
+        if amount == 0 {
+            return Err(StakingError::NothingToWithdraw.into());
+        }
+        if get_clock().unix_timestamp < stake.unstake_available_timestamp {
+            return Err(StakingError::CooldownActive.into());
+        }
+        stake.pending_unstake_amount = 0;
+        StakeAccount::pack(stake, &mut stake_info.data.borrow_mut())?;
+
+        Self::transfer_from_vault(
+            program_id,
+            &pool,
+            pool_info,
+            token_program_info,
+            stake_vault_info,
+            destination_info,
+            vault_authority_info,
+            amount,
+        )
+    }
+
+    /// Processes a [`ClaimRewards`](enum.StakingInstruction.html)
+    /// instruction.
+    pub fn process_claim_rewards(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let pool_info = next_account_info(account_info_iter)?;
+        let stake_info = next_account_info(account_info_iter)?;
+        let reward_vault_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        let owner_info = next_account_info(account_info_iter)?;
+        let vault_authority_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        let mut pool = Self::unpack_pool(program_id, pool_info)?;
+        let mut stake = Self::unpack_stake(program_id, stake_info, pool_info, owner_info)?;
+        if pool.reward_vault != *reward_vault_info.key {
+            return Err(StakingError::InvalidVault.into());
+        }
+
+        Self::update_rewards(&mut pool, &mut stake)?;
+        let amount = stake.rewards_owed;
+        if amount == 0 {
+            return Err(StakingError::NothingToClaim.into());
+        }
+        stake.rewards_owed = 0;
+        StakePool::pack(pool, &mut pool_info.data.borrow_mut())?;
+        StakeAccount::pack(stake, &mut stake_info.data.borrow_mut())?;
+
+        Self::transfer_from_vault(
+            program_id,
+            &pool,
+            pool_info,
+            token_program_info,
+            reward_vault_info,
+            destination_info,
+            vault_authority_info,
+            amount,
+        )
+    }
+
+    /// Processes a [`FundRewards`](enum.StakingInstruction.html)
+    /// instruction.
+    pub fn process_fund_rewards(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let pool_info = next_account_info(account_info_iter)?;
+        let reward_vault_info = next_account_info(account_info_iter)?;
+        let source_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        let mut pool = Self::unpack_pool(program_id, pool_info)?;
+        Self::check_admin(&pool, admin_info)?;
+        if pool.reward_vault != *reward_vault_info.key {
+            return Err(StakingError::InvalidVault.into());
+        }
+
+        Self::accrue_rewards(&mut pool)?;
+        pool.undistributed_rewards = pool
+            .undistributed_rewards
+            .checked_add(amount)
+            .ok_or(StakingError::Overflow)?;
+        StakePool::pack(pool, &mut pool_info.data.borrow_mut())?;
+
+        Self::transfer(
+            token_program_info,
+            source_info,
+            reward_vault_info,
+            admin_info,
+            amount,
+            &[],
+        )
+    }
+
+    /// Processes a [`SetRewardRate`](enum.StakingInstruction.html)
+    /// instruction.
+    pub fn process_set_reward_rate(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        reward_rate: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let pool_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+
+        let mut pool = Self::unpack_pool(program_id, pool_info)?;
+        Self::check_admin(&pool, admin_info)?;
+
+        // Rewards up to now are emitted at the previous rate
+        Self::accrue_rewards(&mut pool)?;
+        pool.reward_rate = reward_rate;
+        StakePool::pack(pool, &mut pool_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Processes an [`Instruction`](enum.Instruction.html).
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
+        let instruction = StakingInstruction::unpack(input)?;
+
+        match instruction {
+            StakingInstruction::InitializePool {
+                reward_rate,
+                cooldown_seconds,
+            } => {
+                msg!("Instruction: InitializePool");
+                Self::process_initialize_pool(program_id, accounts, reward_rate, cooldown_seconds)
+            }
+            StakingInstruction::InitializeStakeAccount => {
+                msg!("Instruction: InitializeStakeAccount");
+                Self::process_initialize_stake_account(program_id, accounts)
+            }
+            StakingInstruction::Stake { amount } => {
+                msg!("Instruction: Stake");
+                Self::process_stake(program_id, accounts, amount)
+            }
+            StakingInstruction::Unstake { amount } => {
+                msg!("Instruction: Unstake");
+                Self::process_unstake(program_id, accounts, amount)
+            }
+            StakingInstruction::WithdrawUnstaked => {
+                msg!("Instruction: WithdrawUnstaked");
+                Self::process_withdraw_unstaked(program_id, accounts)
+            }
+            StakingInstruction::ClaimRewards => {
+                msg!("Instruction: ClaimRewards");
+                Self::process_claim_rewards(program_id, accounts)
+            }
+            StakingInstruction::FundRewards { amount } => {
+                msg!("Instruction: FundRewards");
+                Self::process_fund_rewards(program_id, accounts, amount)
+            }
+            StakingInstruction::SetRewardRate { reward_rate } => {
+                msg!("Instruction: SetRewardRate");
+                Self::process_set_reward_rate(program_id, accounts, reward_rate)
+            }
+        }
+    }
+
+    /// Accrues the rewards emitted since the last update into the pool's
+    /// reward-per-token accumulator
+    pub fn accrue_rewards(pool: &mut StakePool) -> ProgramResult {
+        let now = get_clock().unix_timestamp;
+        if now <= pool.last_update_timestamp {
+            return Ok(());
+        }
+        // Nothing is emitted while nothing is staked
+        if pool.total_staked > 0 {
+            let emission = calculate_emission(
+                pool.reward_rate,
+                now - pool.last_update_timestamp,
+                pool.undistributed_rewards,
+            )?;
+            pool.reward_per_token =
+                calculate_reward_per_token(pool.reward_per_token, emission, pool.total_staked)?;
+            pool.undistributed_rewards -= emission;
+        }
+        pool.last_update_timestamp = now;
+        Ok(())
+    }
+
+    /// Accrues the pool rewards and brings the rewards owed to a stake up to
+    /// date, before its amount changes
+    pub fn update_rewards(pool: &mut StakePool, stake: &mut StakeAccount) -> ProgramResult {
+        Self::accrue_rewards(pool)?;
+        stake.rewards_owed = calculate_rewards_owed(
+            stake.staked_amount,
+            pool.reward_per_token,
+            stake.reward_per_token_paid,
+            stake.rewards_owed,
+        )?;
+        stake.reward_per_token_paid = pool.reward_per_token;
+        Ok(())
+    }
+
+    /// Checks that the account is owned by the given program
+    pub fn check_account_owner(program_id: &Pubkey, account_info: &AccountInfo) -> ProgramResult {
+        if program_id != account_info.owner {
+            Err(ProgramError::IncorrectProgramId)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_admin(pool: &StakePool, admin_info: &AccountInfo) -> ProgramResult {
+        if pool.admin != *admin_info.key {
+            return Err(StakingError::AdminMismatch.into());
+        }
+        if !admin_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        Ok(())
+    }
+
+    fn unpack_pool(
+        program_id: &Pubkey,
+        pool_info: &AccountInfo,
+    ) -> Result<StakePool, ProgramError> {
+        Self::check_account_owner(program_id, pool_info)?;
+        StakePool::unpack(&pool_info.data.borrow())
+    }
+
+    /// Unpacks a stake account, checking that it belongs to the pool and is
+    /// signed for by its owner
+    fn unpack_stake(
+        program_id: &Pubkey,
+        stake_info: &AccountInfo,
+        pool_info: &AccountInfo,
+        owner_info: &AccountInfo,
+    ) -> Result<StakeAccount, ProgramError> {
+        Self::check_account_owner(program_id, stake_info)?;
+        let stake = StakeAccount::unpack(&stake_info.data.borrow())?;
+        if stake.pool != *pool_info.key {
+            return Err(StakingError::PoolMismatch.into());
+        }
+        if stake.owner != *owner_info.key {
+            return Err(StakingError::OwnerMismatch.into());
+        }
+        if !owner_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        Ok(stake)
+    }
+
+    /// Unpacks a vault, checking that it is a token account owned by the
+    /// vault authority
+    fn unpack_vault(
+        vault_info: &AccountInfo,
+        vault_authority: &Pubkey,
+    ) -> Result<Account, ProgramError> {
+        apl_token::check_program_account(vault_info.owner)?;
+        let vault = Account::unpack_base(&vault_info.data.borrow())?;
+        if vault.owner != *vault_authority {
+            return Err(StakingError::InvalidVault.into());
+        }
+        Ok(vault)
+    }
+
+    /// Transfers tokens out of a vault of the pool, signing as its vault
+    /// authority
+    #[allow(clippy::too_many_arguments)]
+    fn transfer_from_vault<'a>(
+        program_id: &Pubkey,
+        pool: &StakePool,
+        pool_info: &AccountInfo<'a>,
+        token_program_info: &AccountInfo<'a>,
+        vault_info: &AccountInfo<'a>,
+        destination_info: &AccountInfo<'a>,
+        vault_authority_info: &AccountInfo<'a>,
+        amount: u64,
+    ) -> ProgramResult {
+        let pool_key = pool_info.key.serialize();
+        let vault_authority_seeds: &[&[u8]] = &[
+            VAULT_AUTHORITY_SEED,
+            &pool_key,
+            &[pool.vault_authority_bump],
+        ];
+        let vault_authority = Pubkey::create_program_address(vault_authority_seeds, program_id)?;
+        if vault_authority != *vault_authority_info.key {
+            return Err(ProgramError::InvalidSeeds);
+        }
+
+        Self::transfer(
+            token_program_info,
+            vault_info,
+            destination_info,
+            vault_authority_info,
+            amount,
+            &[vault_authority_seeds],
+        )
+    }
+
+    fn transfer<'a>(
+        token_program_info: &AccountInfo<'a>,
+        source_info: &AccountInfo<'a>,
+        destination_info: &AccountInfo<'a>,
+        authority_info: &AccountInfo<'a>,
+        amount: u64,
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        apl_token::check_program_account(token_program_info.key)?;
+        invoke_signed(
+            &apl_token::instruction::transfer(
+                token_program_info.key,
+                source_info.key,
+                destination_info.key,
+                authority_info.key,
+                &[],
+                amount,
+            )?,
+            &[
+                source_info.clone(),
+                destination_info.clone(),
+                authority_info.clone(),
+                token_program_info.clone(),
+            ],
+            signers_seeds,
+        )
+    }
+}
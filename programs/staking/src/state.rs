@@ -0,0 +1,253 @@
+//! State transition types
+
+use {
+    arch_program::{
+        program_error::ProgramError,
+        program_pack::{IsInitialized, Pack, Sealed},
+        pubkey::Pubkey,
+    },
+    arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs},
+};
+
+/// Staking pool data.
+///
+/// Rewards are emitted at `reward_rate` per second while tokens are staked
+/// and funded rewards remain, and are shared through `reward_per_token`, the
+/// rewards accrued per staked token scaled by
+/// [`REWARD_PER_TOKEN_PRECISION`](../math/constant.REWARD_PER_TOKEN_PRECISION.html).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StakePool {
+    /// Is `true` if this structure has been initialized
+    pub is_initialized: bool,
+    /// Bump seed of the vault authority
+    pub vault_authority_bump: u8,
+    /// Account allowed to fund rewards and set their rate
+    pub admin: Pubkey,
+    /// Mint of the staked tokens
+    pub stake_mint: Pubkey,
+    /// Mint of the reward tokens
+    pub reward_mint: Pubkey,
+    /// Token account holding the staked tokens
+    pub stake_vault: Pubkey,
+    /// Token account holding the reward tokens
+    pub reward_vault: Pubkey,
+    /// Rewards emitted per second
+    pub reward_rate: u64,
+    /// Seconds unstaked tokens wait before they can be withdrawn
+    pub cooldown_seconds: i64,
+    /// Unix timestamp up to which rewards have been accrued
+    pub last_update_timestamp: i64,
+    /// Amount of tokens staked
+    pub total_staked: u64,
+    /// Funded rewards not emitted yet
+    pub undistributed_rewards: u64,
+    /// Rewards accrued per staked token since the pool was created
+    pub reward_per_token: u128,
+}
+
+impl Sealed for StakePool {}
+impl IsInitialized for StakePool {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+impl Pack for StakePool {
+    const LEN: usize = 218;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 218];
+        let (
+            is_initialized,
+            vault_authority_bump,
+            admin,
+            stake_mint,
+            reward_mint,
+            stake_vault,
+            reward_vault,
+            reward_rate,
+            cooldown_seconds,
+            last_update_timestamp,
+            total_staked,
+            undistributed_rewards,
+            reward_per_token,
+        ) = array_refs![src, 1, 1, 32, 32, 32, 32, 32, 8, 8, 8, 8, 8, 16];
+        Ok(StakePool {
+            is_initialized: unpack_bool(is_initialized)?,
+            vault_authority_bump: vault_authority_bump[0],
+            admin: Pubkey::from_slice(admin),
+            stake_mint: Pubkey::from_slice(stake_mint),
+            reward_mint: Pubkey::from_slice(reward_mint),
+            stake_vault: Pubkey::from_slice(stake_vault),
+            reward_vault: Pubkey::from_slice(reward_vault),
+            reward_rate: u64::from_le_bytes(*reward_rate),
+            cooldown_seconds: i64::from_le_bytes(*cooldown_seconds),
+            last_update_timestamp: i64::from_le_bytes(*last_update_timestamp),
+            total_staked: u64::from_le_bytes(*total_staked),
+            undistributed_rewards: u64::from_le_bytes(*undistributed_rewards),
+            reward_per_token: u128::from_le_bytes(*reward_per_token),
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 218];
+        let (
+            is_initialized_dst,
+            vault_authority_bump_dst,
+            admin_dst,
+            stake_mint_dst,
+            reward_mint_dst,
+            stake_vault_dst,
+            reward_vault_dst,
+            reward_rate_dst,
+            cooldown_seconds_dst,
+            last_update_timestamp_dst,
+            total_staked_dst,
+            undistributed_rewards_dst,
+            reward_per_token_dst,
+        ) = mut_array_refs![dst, 1, 1, 32, 32, 32, 32, 32, 8, 8, 8, 8, 8, 16];
+        *is_initialized_dst = [self.is_initialized as u8];
+        *vault_authority_bump_dst = [self.vault_authority_bump];
+        admin_dst.copy_from_slice(self.admin.as_ref());
+        stake_mint_dst.copy_from_slice(self.stake_mint.as_ref());
+        reward_mint_dst.copy_from_slice(self.reward_mint.as_ref());
+        stake_vault_dst.copy_from_slice(self.stake_vault.as_ref());
+        reward_vault_dst.copy_from_slice(self.reward_vault.as_ref());
+        *reward_rate_dst = self.reward_rate.to_le_bytes();
+        *cooldown_seconds_dst = self.cooldown_seconds.to_le_bytes();
+        *last_update_timestamp_dst = self.last_update_timestamp.to_le_bytes();
+        *total_staked_dst = self.total_staked.to_le_bytes();
+        *undistributed_rewards_dst = self.undistributed_rewards.to_le_bytes();
+        *reward_per_token_dst = self.reward_per_token.to_le_bytes();
+    }
+}
+
+/// Stake account data.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StakeAccount {
+    /// Is `true` if this structure has been initialized
+    pub is_initialized: bool,
+    /// Pool the tokens are staked in
+    pub pool: Pubkey,
+    /// Owner of the stake
+    pub owner: Pubkey,
+    /// Amount of tokens staked and earning rewards
+    pub staked_amount: u64,
+    /// Amount of unstaked tokens waiting for the cooldown
+    pub pending_unstake_amount: u64,
+    /// Unix timestamp from which the pending tokens can be withdrawn
+    pub unstake_available_timestamp: i64,
+    /// Rewards earned and not claimed yet, as of `reward_per_token_paid`
+    pub rewards_owed: u64,
+    /// Value of the pool's reward-per-token accumulator when the rewards
+    /// owed were last updated
+    pub reward_per_token_paid: u128,
+}
+
+impl Sealed for StakeAccount {}
+impl IsInitialized for StakeAccount {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+impl Pack for StakeAccount {
+    const LEN: usize = 113;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 113];
+        let (
+            is_initialized,
+            pool,
+            owner,
+            staked_amount,
+            pending_unstake_amount,
+            unstake_available_timestamp,
+            rewards_owed,
+            reward_per_token_paid,
+        ) = array_refs![src, 1, 32, 32, 8, 8, 8, 8, 16];
+        Ok(StakeAccount {
+            is_initialized: unpack_bool(is_initialized)?,
+            pool: Pubkey::from_slice(pool),
+            owner: Pubkey::from_slice(owner),
+            staked_amount: u64::from_le_bytes(*staked_amount),
+            pending_unstake_amount: u64::from_le_bytes(*pending_unstake_amount),
+            unstake_available_timestamp: i64::from_le_bytes(*unstake_available_timestamp),
+            rewards_owed: u64::from_le_bytes(*rewards_owed),
+            reward_per_token_paid: u128::from_le_bytes(*reward_per_token_paid),
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 113];
+        let (
+            is_initialized_dst,
+            pool_dst,
+            owner_dst,
+            staked_amount_dst,
+            pending_unstake_amount_dst,
+            unstake_available_timestamp_dst,
+            rewards_owed_dst,
+            reward_per_token_paid_dst,
+        ) = mut_array_refs![dst, 1, 32, 32, 8, 8, 8, 8, 16];
+        *is_initialized_dst = [self.is_initialized as u8];
+        pool_dst.copy_from_slice(self.pool.as_ref());
+        owner_dst.copy_from_slice(self.owner.as_ref());
+        *staked_amount_dst = self.staked_amount.to_le_bytes();
+        *pending_unstake_amount_dst = self.pending_unstake_amount.to_le_bytes();
+        *unstake_available_timestamp_dst = self.unstake_available_timestamp.to_le_bytes();
+        *rewards_owed_dst = self.rewards_owed.to_le_bytes();
+        *reward_per_token_paid_dst = self.reward_per_token_paid.to_le_bytes();
+    }
+}
+
+fn unpack_bool(src: &[u8; 1]) -> Result<bool, ProgramError> {
+    match src {
+        [0] => Ok(false),
+        [1] => Ok(true),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_unpack() {
+        let pool = StakePool {
+            is_initialized: true,
+            vault_authority_bump: 255,
+            admin: Pubkey::new_unique(),
+            stake_mint: Pubkey::new_unique(),
+            reward_mint: Pubkey::new_unique(),
+            stake_vault: Pubkey::new_unique(),
+            reward_vault: Pubkey::new_unique(),
+            reward_rate: 1,
+            cooldown_seconds: 2,
+            last_update_timestamp: 3,
+            total_staked: 4,
+            undistributed_rewards: 5,
+            reward_per_token: u128::MAX - 6,
+        };
+        let mut packed = vec![0u8; StakePool::LEN];
+        StakePool::pack(pool, &mut packed).unwrap();
+        assert_eq!(StakePool::unpack(&packed).unwrap(), pool);
+
+        let stake = StakeAccount {
+            is_initialized: true,
+            pool: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            staked_amount: 1,
+            pending_unstake_amount: 2,
+            unstake_available_timestamp: 3,
+            rewards_owed: 4,
+            reward_per_token_paid: u128::MAX - 5,
+        };
+        let mut packed = vec![0u8; StakeAccount::LEN];
+        StakeAccount::pack(stake, &mut packed).unwrap();
+        assert_eq!(StakeAccount::unpack(&packed).unwrap(), stake);
+
+        packed[0] = 2;
+        assert_eq!(
+            StakeAccount::unpack_from_slice(&packed),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+}
@@ -80,6 +80,57 @@ pub enum TokenError {
     /// Instruction does not support non-native tokens
     #[error("Instruction does not support non-native tokens")]
     NonNativeNotSupported,
+
+    // 20
+    /// Minting would take the supply past the mint's supply cap
+    #[error("Supply cap exceeded")]
+    SupplyCapExceeded,
+    /// The supply cap of a mint can only be lowered
+    #[error("Supply cap cannot be raised")]
+    SupplyCapIncrease,
+    /// The delegate's allowance has expired
+    #[error("Allowance expired")]
+    AllowanceExpired,
+    /// The account has no room for another allowance
+    #[error("Too many allowances")]
+    TooManyAllowances,
+    /// The permit's deadline has passed
+    #[error("Permit expired")]
+    PermitExpired,
+
+    // 25
+    /// The permit's nonce is not the owner's next nonce
+    #[error("Invalid permit nonce")]
+    InvalidPermitNonce,
+    /// The permit was not signed by the owner
+    #[error("Invalid permit signature")]
+    InvalidPermitSignature,
+    /// Transfers of the mint must name the mint, using a `Checked` instruction
+    #[error("Mint required for this transfer")]
+    MintRequiredForTransfer,
+    /// The mint is already in the middle of a transfer hook
+    #[error("Transfer hook reentrancy")]
+    TransferHookReentrancy,
+    /// Tokens of the mint cannot be transferred
+    #[error("Transfer is disabled for this mint")]
+    NonTransferable,
+
+    // 30
+    /// The signer is already a member of the multisignature account
+    #[error("Multisig signer already exists")]
+    MultisigSignerExists,
+    /// The signer is not a member of the multisignature account
+    #[error("Multisig signer not found")]
+    MultisigSignerNotFound,
+    /// No checkpoint is held at or before the requested slot
+    #[error("Checkpoint not found")]
+    CheckpointNotFound,
+    /// The destination account requires a memo on incoming transfers
+    #[error("No memo with transfer")]
+    NoMemo,
+    /// The mint must be writable to run its transfer hook
+    #[error("Mint not writable")]
+    MintNotWritable,
 }
 impl From<TokenError> for ProgramError {
     fn from(e: TokenError) -> Self {
@@ -136,6 +187,21 @@ impl PrintProgramError for TokenError {
             TokenError::NonNativeNotSupported => {
                 msg!("Error: Instruction does not support non-native tokens")
             }
+            TokenError::SupplyCapExceeded => msg!("Error: supply cap exceeded"),
+            TokenError::SupplyCapIncrease => msg!("Error: supply cap cannot be raised"),
+            TokenError::AllowanceExpired => msg!("Error: allowance expired"),
+            TokenError::TooManyAllowances => msg!("Error: too many allowances"),
+            TokenError::PermitExpired => msg!("Error: permit expired"),
+            TokenError::InvalidPermitNonce => msg!("Error: invalid permit nonce"),
+            TokenError::InvalidPermitSignature => msg!("Error: invalid permit signature"),
+            TokenError::MintRequiredForTransfer => msg!("Error: mint required for this transfer"),
+            TokenError::TransferHookReentrancy => msg!("Error: transfer hook reentrancy"),
+            TokenError::NonTransferable => msg!("Error: transfer is disabled for this mint"),
+            TokenError::MultisigSignerExists => msg!("Error: multisig signer already exists"),
+            TokenError::MultisigSignerNotFound => msg!("Error: multisig signer not found"),
+            TokenError::CheckpointNotFound => msg!("Error: checkpoint not found"),
+            TokenError::NoMemo => msg!("Error: no memo with transfer"),
+            TokenError::MintNotWritable => msg!("Error: mint not writable"),
         }
     }
 }
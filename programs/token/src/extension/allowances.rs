@@ -0,0 +1,270 @@
+//! Allowances extension
+
+use {
+    crate::{
+        error::TokenError,
+        extension::{Extension, ExtensionType},
+    },
+    arch_program::{
+        clock::Clock,
+        program_error::ProgramError,
+        program_pack::{Pack, Sealed},
+        pubkey::Pubkey,
+    },
+    arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs},
+};
+
+/// Maximum number of delegates an account can approve through allowances
+pub const MAX_ALLOWANCES: usize = 4;
+
+/// Point in time after which an allowance can no longer be spent.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AllowanceExpiry {
+    /// The allowance never expires
+    #[default]
+    Never,
+    /// The allowance can be spent up to and including this slot
+    Slot(u64),
+    /// The allowance can be spent up to and including this unix timestamp
+    UnixTimestamp(i64),
+}
+
+impl AllowanceExpiry {
+    /// Serialized length of an expiry: a tag and a `u64` value
+    pub const LEN: usize = 9;
+
+    /// Checks if the expiry has passed at the given clock
+    pub fn is_expired(&self, clock: &Clock) -> bool {
+        match *self {
+            AllowanceExpiry::Never => false,
+            AllowanceExpiry::Slot(slot) => clock.slot > slot,
+            AllowanceExpiry::UnixTimestamp(unix_timestamp) => clock.unix_timestamp > unix_timestamp,
+        }
+    }
+
+    /// Unpacks an expiry from its tag and little-endian value
+    pub fn unpack(src: &[u8; Self::LEN]) -> Result<Self, ProgramError> {
+        let (tag, value) = array_refs![src, 1, 8];
+        match tag[0] {
+            0 => Ok(AllowanceExpiry::Never),
+            1 => Ok(AllowanceExpiry::Slot(u64::from_le_bytes(*value))),
+            2 => Ok(AllowanceExpiry::UnixTimestamp(i64::from_le_bytes(*value))),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+
+    /// Packs an expiry as its tag and little-endian value
+    pub fn pack(&self, dst: &mut [u8; Self::LEN]) {
+        let (tag, value) = mut_array_refs![dst, 1, 8];
+        match *self {
+            AllowanceExpiry::Never => {
+                tag[0] = 0;
+                *value = [0; 8];
+            }
+            AllowanceExpiry::Slot(slot) => {
+                tag[0] = 1;
+                *value = slot.to_le_bytes();
+            }
+            AllowanceExpiry::UnixTimestamp(unix_timestamp) => {
+                tag[0] = 2;
+                *value = unix_timestamp.to_le_bytes();
+            }
+        }
+    }
+}
+
+/// Amount a single delegate may transfer or burn from an account.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Allowance {
+    /// The delegate, or the default pubkey for an unused slot
+    pub delegate: Pubkey,
+    /// The amount the delegate may still spend
+    pub amount: u64,
+    /// When the allowance stops being spendable
+    pub expiry: AllowanceExpiry,
+}
+
+impl Allowance {
+    /// Serialized length of an allowance
+    pub const LEN: usize = 32 + 8 + AllowanceExpiry::LEN;
+}
+
+/// Per-delegate allowances of a token account, held alongside the single
+/// `delegate` of the base state.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Allowances {
+    /// Allowance slots, unused ones having the default delegate
+    pub allowances: [Allowance; MAX_ALLOWANCES],
+}
+
+impl Allowances {
+    /// Finds the allowance of `delegate`
+    pub fn get(&self, delegate: &Pubkey) -> Option<&Allowance> {
+        self.allowances.iter().find(|allowance| {
+            allowance.delegate != Pubkey::default() && allowance.delegate == *delegate
+        })
+    }
+
+    /// Sets the allowance of `delegate`, replacing its existing one or taking
+    /// an unused slot. Approving zero removes the allowance.
+    pub fn approve(
+        &mut self,
+        delegate: &Pubkey,
+        amount: u64,
+        expiry: AllowanceExpiry,
+    ) -> Result<(), ProgramError> {
+        if *delegate == Pubkey::default() {
+            return Err(ProgramError::InvalidArgument);
+        }
+        if amount == 0 {
+            self.revoke(delegate);
+            return Ok(());
+        }
+        let position = self
+            .position(delegate)
+            .or_else(|| self.position(&Pubkey::default()))
+            .ok_or(TokenError::TooManyAllowances)?;
+        self.allowances[position] = Allowance {
+            delegate: *delegate,
+            amount,
+            expiry,
+        };
+        Ok(())
+    }
+
+    /// Removes the allowance of `delegate`, returning whether it had one
+    pub fn revoke(&mut self, delegate: &Pubkey) -> bool {
+        match self.position(delegate) {
+            Some(position) if *delegate != Pubkey::default() => {
+                self.allowances[position] = Allowance::default();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Spends `amount` of the allowance of `delegate`, removing it once
+    /// exhausted
+    pub fn spend(
+        &mut self,
+        delegate: &Pubkey,
+        amount: u64,
+        clock: &Clock,
+    ) -> Result<(), ProgramError> {
+        let position = self
+            .position(delegate)
+            .ok_or(ProgramError::InvalidArgument)?;
+        let allowance = &mut self.allowances[position];
+        if allowance.expiry.is_expired(clock) {
+            return Err(TokenError::AllowanceExpired.into());
+        }
+        allowance.amount = allowance
+            .amount
+            .checked_sub(amount)
+            .ok_or(TokenError::InsufficientFunds)?;
+        if allowance.amount == 0 {
+            *allowance = Allowance::default();
+        }
+        Ok(())
+    }
+
+    fn position(&self, delegate: &Pubkey) -> Option<usize> {
+        self.allowances
+            .iter()
+            .position(|allowance| allowance.delegate == *delegate)
+    }
+}
+
+impl Sealed for Allowances {}
+impl Pack for Allowances {
+    const LEN: usize = Allowance::LEN * MAX_ALLOWANCES;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let mut allowances = [Allowance::default(); MAX_ALLOWANCES];
+        for (allowance, src) in allowances.iter_mut().zip(src.chunks_exact(Allowance::LEN)) {
+            let src = array_ref![src, 0, Allowance::LEN];
+            let (delegate, amount, expiry) = array_refs![src, 32, 8, AllowanceExpiry::LEN];
+            *allowance = Allowance {
+                delegate: Pubkey::from_slice(delegate),
+                amount: u64::from_le_bytes(*amount),
+                expiry: AllowanceExpiry::unpack(expiry)?,
+            };
+        }
+        Ok(Allowances { allowances })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        for (allowance, dst) in self
+            .allowances
+            .iter()
+            .zip(dst.chunks_exact_mut(Allowance::LEN))
+        {
+            let dst = array_mut_ref![dst, 0, Allowance::LEN];
+            let (delegate, amount, expiry) = mut_array_refs![dst, 32, 8, AllowanceExpiry::LEN];
+            delegate.copy_from_slice(allowance.delegate.as_ref());
+            *amount = allowance.amount.to_le_bytes();
+            allowance.expiry.pack(expiry);
+        }
+    }
+}
+
+impl Extension for Allowances {
+    const TYPE: ExtensionType = ExtensionType::Allowances;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowances() {
+        let mut allowances = Allowances::default();
+        let delegates: Vec<Pubkey> = (0..=MAX_ALLOWANCES).map(|_| Pubkey::new_unique()).collect();
+        for delegate in &delegates[..MAX_ALLOWANCES] {
+            allowances
+                .approve(delegate, 10, AllowanceExpiry::Slot(5))
+                .unwrap();
+        }
+        assert_eq!(
+            allowances.approve(&delegates[MAX_ALLOWANCES], 10, AllowanceExpiry::Never),
+            Err(TokenError::TooManyAllowances.into())
+        );
+
+        let clock = Clock {
+            slot: 5,
+            ..Clock::default()
+        };
+        allowances.spend(&delegates[0], 4, &clock).unwrap();
+        assert_eq!(allowances.get(&delegates[0]).unwrap().amount, 6);
+        assert_eq!(
+            allowances.spend(&delegates[0], 7, &clock),
+            Err(TokenError::InsufficientFunds.into())
+        );
+        allowances.spend(&delegates[0], 6, &clock).unwrap();
+        assert_eq!(allowances.get(&delegates[0]), None);
+
+        let late = Clock {
+            slot: 6,
+            ..Clock::default()
+        };
+        assert_eq!(
+            allowances.spend(&delegates[1], 1, &late),
+            Err(TokenError::AllowanceExpired.into())
+        );
+
+        assert!(allowances.revoke(&delegates[1]));
+        assert!(!allowances.revoke(&delegates[1]));
+        allowances
+            .approve(
+                &delegates[MAX_ALLOWANCES],
+                1,
+                AllowanceExpiry::UnixTimestamp(-1),
+            )
+            .unwrap();
+
+        let mut packed = vec![0; Allowances::LEN];
+        allowances.pack_into_slice(&mut packed);
+        assert_eq!(Allowances::unpack_from_slice(&packed).unwrap(), allowances);
+    }
+}
@@ -0,0 +1,287 @@
+//! Balance and supply checkpoints extensions
+//!
+//! Accounts and mints carrying checkpoints record their balance or supply,
+//! keyed by slot, every time it changes. Only the latest `MAX_CHECKPOINTS`
+//! changes are kept, so queries can look back as far as the oldest of them.
+//! Accounts skip credits below their own dust threshold, so that others
+//! cannot cheaply evict their history with tiny transfers.
+
+use {
+    crate::extension::{Extension, ExtensionType},
+    arch_program::{
+        program_error::ProgramError,
+        program_pack::{Pack, Sealed},
+    },
+    arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs},
+};
+
+/// Maximum number of checkpoints kept per account or mint
+pub const MAX_CHECKPOINTS: usize = 32;
+
+/// Balance or supply as of the end of a slot.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Checkpoint {
+    /// Slot of the last change recorded in the checkpoint
+    pub slot: u64,
+    /// Balance or supply after the change
+    pub amount: u64,
+}
+
+impl Checkpoint {
+    /// Serialized length of a checkpoint
+    pub const LEN: usize = 16;
+}
+
+/// Ring buffer of the latest checkpoints, oldest overwritten first.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Checkpoints {
+    /// Number of checkpoints held
+    pub len: u8,
+    /// Index the next checkpoint is written to
+    pub next: u8,
+    /// Checkpoint slots, of which `len` ending before `next` are in use
+    pub checkpoints: [Checkpoint; MAX_CHECKPOINTS],
+}
+
+impl Checkpoints {
+    /// Serialized length of the checkpoints
+    pub const LEN: usize = 2 + Checkpoint::LEN * MAX_CHECKPOINTS;
+
+    /// Iterates over the checkpoints held, newest first
+    pub fn iter(&self) -> impl Iterator<Item = &Checkpoint> {
+        let next = self.next as usize;
+        let (older, newer) = self.checkpoints.split_at(next);
+        older
+            .iter()
+            .rev()
+            .chain(newer.iter().rev())
+            .take(self.len as usize)
+    }
+
+    /// Newest checkpoint held
+    pub fn latest(&self) -> Option<&Checkpoint> {
+        self.iter().next()
+    }
+
+    /// Records `amount` at `slot`, replacing the newest checkpoint if it is
+    /// for the same slot. Unchanged amounts are not recorded again.
+    pub fn record(&mut self, slot: u64, amount: u64) {
+        if self.latest().is_some_and(|latest| latest.amount == amount) {
+            return;
+        }
+        let newest = (self.next as usize + MAX_CHECKPOINTS - 1) % MAX_CHECKPOINTS;
+        if self.len > 0 && self.checkpoints[newest].slot == slot {
+            self.checkpoints[newest].amount = amount;
+            return;
+        }
+        self.checkpoints[self.next as usize] = Checkpoint { slot, amount };
+        self.next = ((self.next as usize + 1) % MAX_CHECKPOINTS) as u8;
+        self.len = self.len.saturating_add(1).min(MAX_CHECKPOINTS as u8);
+    }
+
+    /// Finds the amount as of the end of `slot`, if a checkpoint at or
+    /// before it is still held
+    pub fn get_at(&self, slot: u64) -> Option<u64> {
+        self.iter()
+            .find(|checkpoint| checkpoint.slot <= slot)
+            .map(|checkpoint| checkpoint.amount)
+    }
+
+    fn unpack(src: &[u8; Self::LEN]) -> Result<Self, ProgramError> {
+        let (len, next, checkpoints_flat) =
+            array_refs![src, 1, 1, Checkpoint::LEN * MAX_CHECKPOINTS];
+        if len[0] as usize > MAX_CHECKPOINTS || next[0] as usize >= MAX_CHECKPOINTS {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mut checkpoints = [Checkpoint::default(); MAX_CHECKPOINTS];
+        for (checkpoint, src) in checkpoints
+            .iter_mut()
+            .zip(checkpoints_flat.chunks_exact(Checkpoint::LEN))
+        {
+            let (slot, amount) = array_refs![array_ref![src, 0, Checkpoint::LEN], 8, 8];
+            *checkpoint = Checkpoint {
+                slot: u64::from_le_bytes(*slot),
+                amount: u64::from_le_bytes(*amount),
+            };
+        }
+        Ok(Checkpoints {
+            len: len[0],
+            next: next[0],
+            checkpoints,
+        })
+    }
+
+    fn pack(&self, dst: &mut [u8; Self::LEN]) {
+        let (len, next, checkpoints_flat) =
+            mut_array_refs![dst, 1, 1, Checkpoint::LEN * MAX_CHECKPOINTS];
+        *len = [self.len];
+        *next = [self.next];
+        for (checkpoint, dst) in self
+            .checkpoints
+            .iter()
+            .zip(checkpoints_flat.chunks_exact_mut(Checkpoint::LEN))
+        {
+            let (slot, amount) = mut_array_refs![array_mut_ref![dst, 0, Checkpoint::LEN], 8, 8];
+            *slot = checkpoint.slot.to_le_bytes();
+            *amount = checkpoint.amount.to_le_bytes();
+        }
+    }
+}
+
+/// Balance checkpoints of a token account.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BalanceCheckpoints {
+    /// Smallest increase of the balance over the newest checkpoint that is
+    /// recorded. Balances looked up may fall short by less than this.
+    pub min_credit: u64,
+    /// Checkpoints of the account balance
+    pub checkpoints: Checkpoints,
+}
+
+impl BalanceCheckpoints {
+    /// Records `balance` at `slot`, unless it grew by less than `min_credit`
+    /// since the newest checkpoint
+    pub fn record(&mut self, slot: u64, balance: u64) {
+        if let Some(latest) = self.checkpoints.latest() {
+            if balance > latest.amount && balance - latest.amount < self.min_credit {
+                return;
+            }
+        }
+        self.checkpoints.record(slot, balance);
+    }
+}
+
+impl Sealed for BalanceCheckpoints {}
+impl Pack for BalanceCheckpoints {
+    const LEN: usize = 8 + Checkpoints::LEN;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, BalanceCheckpoints::LEN];
+        let (min_credit, checkpoints) = array_refs![src, 8, Checkpoints::LEN];
+        Ok(BalanceCheckpoints {
+            min_credit: u64::from_le_bytes(*min_credit),
+            checkpoints: Checkpoints::unpack(checkpoints)?,
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, BalanceCheckpoints::LEN];
+        let (min_credit, checkpoints) = mut_array_refs![dst, 8, Checkpoints::LEN];
+        *min_credit = self.min_credit.to_le_bytes();
+        self.checkpoints.pack(checkpoints);
+    }
+}
+
+impl Extension for BalanceCheckpoints {
+    const TYPE: ExtensionType = ExtensionType::BalanceCheckpoints;
+}
+
+/// Supply checkpoints of a mint.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SupplyCheckpoints {
+    /// Checkpoints of the mint supply
+    pub checkpoints: Checkpoints,
+}
+
+impl Sealed for SupplyCheckpoints {}
+impl Pack for SupplyCheckpoints {
+    const LEN: usize = Checkpoints::LEN;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        Ok(SupplyCheckpoints {
+            checkpoints: Checkpoints::unpack(array_ref![src, 0, Checkpoints::LEN])?,
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        self.checkpoints
+            .pack(array_mut_ref![dst, 0, Checkpoints::LEN]);
+    }
+}
+
+impl Extension for SupplyCheckpoints {
+    const TYPE: ExtensionType = ExtensionType::SupplyCheckpoints;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoints() {
+        let mut checkpoints = Checkpoints::default();
+        assert_eq!(checkpoints.get_at(u64::MAX), None);
+
+        checkpoints.record(10, 0);
+        checkpoints.record(20, 5);
+        checkpoints.record(20, 7);
+        assert_eq!(checkpoints.len, 2);
+        assert_eq!(checkpoints.get_at(9), None);
+        assert_eq!(checkpoints.get_at(10), Some(0));
+        assert_eq!(checkpoints.get_at(19), Some(0));
+        assert_eq!(checkpoints.get_at(20), Some(7));
+        assert_eq!(checkpoints.get_at(u64::MAX), Some(7));
+
+        // Unchanged amounts take no checkpoint
+        checkpoints.record(25, 7);
+        assert_eq!(checkpoints.len, 2);
+        assert_eq!(checkpoints.latest().unwrap().slot, 20);
+
+        // Wrapping around drops the oldest checkpoints
+        for slot in 0..MAX_CHECKPOINTS as u64 {
+            checkpoints.record(30 + slot, slot);
+        }
+        assert_eq!(checkpoints.len as usize, MAX_CHECKPOINTS);
+        assert_eq!(checkpoints.get_at(29), None);
+        assert_eq!(checkpoints.get_at(30), Some(0));
+        assert_eq!(
+            checkpoints.get_at(u64::MAX),
+            Some(MAX_CHECKPOINTS as u64 - 1)
+        );
+        assert!(checkpoints
+            .iter()
+            .zip(checkpoints.iter().skip(1))
+            .all(|(newer, older)| newer.slot > older.slot));
+
+        let balance_checkpoints = BalanceCheckpoints {
+            min_credit: 100,
+            checkpoints,
+        };
+        let mut packed = vec![0; BalanceCheckpoints::LEN];
+        balance_checkpoints.pack_into_slice(&mut packed);
+        assert_eq!(
+            BalanceCheckpoints::unpack_from_slice(&packed).unwrap(),
+            balance_checkpoints
+        );
+
+        packed[9] = MAX_CHECKPOINTS as u8;
+        assert_eq!(
+            BalanceCheckpoints::unpack_from_slice(&packed),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn test_balance_checkpoints_dust() {
+        let mut balance_checkpoints = BalanceCheckpoints {
+            min_credit: 100,
+            ..BalanceCheckpoints::default()
+        };
+        balance_checkpoints.record(10, 1_000);
+
+        // Dust credits are skipped until they add up to the minimum credit
+        for slot in 11..11 + MAX_CHECKPOINTS as u64 {
+            balance_checkpoints.record(slot, 1_000 + slot - 10);
+        }
+        let checkpoints = balance_checkpoints.checkpoints;
+        assert_eq!(checkpoints.len, 1);
+        assert_eq!(checkpoints.get_at(10), Some(1_000));
+        balance_checkpoints.record(200, 1_100);
+        assert_eq!(balance_checkpoints.checkpoints.get_at(200), Some(1_100));
+
+        // Debits are always recorded
+        balance_checkpoints.record(201, 1_099);
+        assert_eq!(balance_checkpoints.checkpoints.get_at(201), Some(1_099));
+        assert_eq!(balance_checkpoints.checkpoints.get_at(10), Some(1_000));
+    }
+}
@@ -0,0 +1,40 @@
+//! Default account state extension
+
+use {
+    crate::{
+        extension::{Extension, ExtensionType},
+        state::AccountState,
+    },
+    arch_program::{
+        program_error::ProgramError,
+        program_pack::{Pack, Sealed},
+    },
+    num_enum::TryFromPrimitive,
+};
+
+/// State new token accounts of a mint start in, letting permissioned mints
+/// create accounts frozen until the freeze authority thaws them.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DefaultAccountState {
+    /// The state of new accounts, `Initialized` or `Frozen`
+    pub state: AccountState,
+}
+
+impl Sealed for DefaultAccountState {}
+impl Pack for DefaultAccountState {
+    const LEN: usize = 1;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        Ok(DefaultAccountState {
+            state: AccountState::try_from_primitive(src[0])
+                .or(Err(ProgramError::InvalidAccountData))?,
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[0] = self.state as u8;
+    }
+}
+
+impl Extension for DefaultAccountState {
+    const TYPE: ExtensionType = ExtensionType::DefaultAccountState;
+}
@@ -0,0 +1,235 @@
+//! Interest-bearing extension
+//!
+//! Raw balances of an interest-bearing mint never change; only their UI
+//! representation accrues interest, compounding continuously at the mint's
+//! rate. Rate changes are folded into a time-weighted average of every rate
+//! in force before the latest change, so the full history is accounted for
+//! without storing it.
+
+use {
+    crate::extension::{Extension, ExtensionType},
+    arch_program::{
+        program_error::ProgramError,
+        program_pack::{Pack, Sealed},
+        pubkey::Pubkey,
+    },
+    arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs},
+};
+
+/// Average number of seconds in a year, accounting for leap years
+const SECONDS_PER_YEAR: f64 = 60. * 60. * 24. * 365.24;
+/// Basis points in one
+const ONE_IN_BASIS_POINTS: f64 = 10_000.;
+
+/// Interest rate configuration of a mint. A default rate authority stands
+/// for `None`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct InterestBearingConfig {
+    /// Authority that can update the rate
+    pub rate_authority: Pubkey,
+    /// Unix timestamp from which interest accrues
+    pub initialization_timestamp: i64,
+    /// Time-weighted average rate, in basis points, from initialization to
+    /// the latest rate change
+    pub pre_update_average_rate: i16,
+    /// Unix timestamp of the latest rate change
+    pub last_update_timestamp: i64,
+    /// Current rate, in basis points
+    pub current_rate: i16,
+}
+
+impl InterestBearingConfig {
+    /// Creates a configuration accruing `rate` from `unix_timestamp`
+    pub fn new(rate_authority: Option<Pubkey>, rate: i16, unix_timestamp: i64) -> Self {
+        InterestBearingConfig {
+            rate_authority: rate_authority.unwrap_or_default(),
+            initialization_timestamp: unix_timestamp,
+            pre_update_average_rate: rate,
+            last_update_timestamp: unix_timestamp,
+            current_rate: rate,
+        }
+    }
+
+    /// Authority that can update the rate, if any
+    pub fn rate_authority(&self) -> Option<Pubkey> {
+        Some(self.rate_authority).filter(|authority| *authority != Pubkey::default())
+    }
+
+    /// Time-weighted average of all rates in force between initialization
+    /// and `unix_timestamp`
+    pub fn time_weighted_average_rate(&self, unix_timestamp: i64) -> Option<i16> {
+        let initialization_timespan = unix_timestamp.checked_sub(self.initialization_timestamp)?;
+        if initialization_timespan <= 0 {
+            return Some(self.current_rate);
+        }
+        let pre_update_timespan = self
+            .last_update_timestamp
+            .checked_sub(self.initialization_timestamp)?;
+        let post_update_timespan = unix_timestamp.checked_sub(self.last_update_timestamp)?;
+        let numerator = (self.pre_update_average_rate as i128)
+            .checked_mul(pre_update_timespan as i128)?
+            .checked_add((self.current_rate as i128).checked_mul(post_update_timespan as i128)?)?;
+        i16::try_from(numerator.checked_div(initialization_timespan as i128)?).ok()
+    }
+
+    /// Changes the rate at `unix_timestamp`, folding the outgoing rate into
+    /// the pre-update average
+    pub fn update_rate(&mut self, rate: i16, unix_timestamp: i64) -> Option<()> {
+        self.pre_update_average_rate = self.time_weighted_average_rate(unix_timestamp)?;
+        self.last_update_timestamp = unix_timestamp;
+        self.current_rate = rate;
+        Some(())
+    }
+
+    fn exp(rate: i16, timespan: i64) -> f64 {
+        let exponent = (rate as f64) * (timespan as f64) / SECONDS_PER_YEAR / ONE_IN_BASIS_POINTS;
+        exponent.exp()
+    }
+
+    /// Factor converting a raw amount into a UI amount at `unix_timestamp`
+    fn total_scale(&self, decimals: u8, unix_timestamp: i64) -> Option<f64> {
+        let pre_update_timespan = self
+            .last_update_timestamp
+            .checked_sub(self.initialization_timestamp)?;
+        let post_update_timespan = unix_timestamp.checked_sub(self.last_update_timestamp)?;
+        Some(
+            Self::exp(self.pre_update_average_rate, pre_update_timespan)
+                * Self::exp(self.current_rate, post_update_timespan)
+                / 10_f64.powi(decimals as i32),
+        )
+    }
+
+    /// Converts a raw amount to its UI representation with interest accrued
+    /// up to `unix_timestamp`. Excess zeroes or unneeded decimal point are
+    /// trimmed.
+    pub fn amount_to_ui_amount(
+        &self,
+        amount: u64,
+        decimals: u8,
+        unix_timestamp: i64,
+    ) -> Option<String> {
+        let scaled_amount = (amount as f64) * self.total_scale(decimals, unix_timestamp)?;
+        if !scaled_amount.is_finite() {
+            return None;
+        }
+        let mut ui_amount = format!("{:.*}", decimals as usize, scaled_amount);
+        if decimals > 0 {
+            let zeros_trimmed = ui_amount.trim_end_matches('0');
+            ui_amount = zeros_trimmed.trim_end_matches('.').to_string();
+        }
+        Some(ui_amount)
+    }
+
+    /// Converts a UI amount with interest accrued up to `unix_timestamp` back
+    /// to its raw amount
+    pub fn try_ui_amount_into_amount(
+        &self,
+        ui_amount: &str,
+        decimals: u8,
+        unix_timestamp: i64,
+    ) -> Result<u64, ProgramError> {
+        let scaled_amount = ui_amount
+            .parse::<f64>()
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        let amount = scaled_amount
+            / self
+                .total_scale(decimals, unix_timestamp)
+                .ok_or(ProgramError::InvalidArgument)?;
+        if !amount.is_finite() || amount < 0. || amount > u64::MAX as f64 {
+            return Err(ProgramError::InvalidArgument);
+        }
+        Ok(amount.round() as u64)
+    }
+}
+
+impl Sealed for InterestBearingConfig {}
+impl Pack for InterestBearingConfig {
+    const LEN: usize = 52;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 52];
+        let (
+            rate_authority,
+            initialization_timestamp,
+            pre_update_average_rate,
+            last_update_timestamp,
+            current_rate,
+        ) = array_refs![src, 32, 8, 2, 8, 2];
+        Ok(InterestBearingConfig {
+            rate_authority: Pubkey::from_slice(rate_authority),
+            initialization_timestamp: i64::from_le_bytes(*initialization_timestamp),
+            pre_update_average_rate: i16::from_le_bytes(*pre_update_average_rate),
+            last_update_timestamp: i64::from_le_bytes(*last_update_timestamp),
+            current_rate: i16::from_le_bytes(*current_rate),
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 52];
+        let (
+            rate_authority,
+            initialization_timestamp,
+            pre_update_average_rate,
+            last_update_timestamp,
+            current_rate,
+        ) = mut_array_refs![dst, 32, 8, 2, 8, 2];
+        rate_authority.copy_from_slice(self.rate_authority.as_ref());
+        *initialization_timestamp = self.initialization_timestamp.to_le_bytes();
+        *pre_update_average_rate = self.pre_update_average_rate.to_le_bytes();
+        *last_update_timestamp = self.last_update_timestamp.to_le_bytes();
+        *current_rate = self.current_rate.to_le_bytes();
+    }
+}
+
+impl Extension for InterestBearingConfig {
+    const TYPE: ExtensionType = ExtensionType::InterestBearingConfig;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE_YEAR: i64 = SECONDS_PER_YEAR as i64;
+
+    #[test]
+    fn test_amount_to_ui_amount() {
+        let config = InterestBearingConfig::new(None, 500, 0);
+        assert_eq!(config.amount_to_ui_amount(100, 0, 0).unwrap(), "100");
+        // e^0.05 = 1.051271...
+        assert_eq!(
+            config.amount_to_ui_amount(1_000_000, 6, ONE_YEAR).unwrap(),
+            "1.051271"
+        );
+        assert_eq!(
+            config
+                .try_ui_amount_into_amount("1.051271", 6, ONE_YEAR)
+                .unwrap(),
+            1_000_000
+        );
+        assert_eq!(
+            config.try_ui_amount_into_amount("-1", 6, ONE_YEAR),
+            Err(ProgramError::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn test_update_rate() {
+        let mut config = InterestBearingConfig::new(None, 1_000, 0);
+        config.update_rate(-1_000, ONE_YEAR).unwrap();
+        assert_eq!(config.pre_update_average_rate, 1_000);
+        // One year at 10% then one year at -10% averages out to zero
+        assert_eq!(config.time_weighted_average_rate(2 * ONE_YEAR), Some(0));
+        assert_eq!(
+            config
+                .amount_to_ui_amount(1_000_000, 6, 2 * ONE_YEAR)
+                .unwrap(),
+            "1"
+        );
+
+        let mut packed = [0u8; InterestBearingConfig::LEN];
+        config.pack_into_slice(&mut packed);
+        assert_eq!(
+            InterestBearingConfig::unpack_from_slice(&packed).unwrap(),
+            config
+        );
+    }
+}
@@ -0,0 +1,42 @@
+//! Memo transfer extension
+
+use {
+    crate::extension::{Extension, ExtensionType},
+    arch_program::{
+        program_error::ProgramError,
+        program_pack::{Pack, Sealed},
+    },
+};
+
+/// Memo setting of a token account. Exchanges crediting deposits to users
+/// can require every incoming transfer to carry a memo identifying the
+/// depositor, sent with `TransferWithMemo`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MemoTransfer {
+    /// Is `true` if incoming transfers must carry a memo
+    pub require_incoming_transfer_memos: bool,
+}
+
+impl Sealed for MemoTransfer {}
+impl Pack for MemoTransfer {
+    const LEN: usize = 1;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        match src[0] {
+            0 => Ok(MemoTransfer {
+                require_incoming_transfer_memos: false,
+            }),
+            1 => Ok(MemoTransfer {
+                require_incoming_transfer_memos: true,
+            }),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[0] = self.require_incoming_transfer_memos as u8;
+    }
+}
+
+impl Extension for MemoTransfer {
+    const TYPE: ExtensionType = ExtensionType::MemoTransfer;
+}
@@ -0,0 +1,451 @@
+//! Optional state attached to mints and token accounts
+//!
+//! A mint or account allocated with more space than its base state needs
+//! carries extensions. The base state is padded up to `BASE_ACCOUNT_LENGTH`,
+//! followed by a one byte [`AccountType`] and a list of type-length-value
+//! entries, one per extension. Accounts of exactly `Mint::LEN` or
+//! `Account::LEN` bytes have no extensions and keep the original layout.
+
+use {
+    crate::state::{Account, Mint, Multisig, WeightedMultisig},
+    arch_program::{
+        program_error::ProgramError,
+        program_pack::{IsInitialized, Pack},
+    },
+    arrayref::{array_mut_ref, array_ref},
+    num_enum::TryFromPrimitive,
+};
+
+pub mod allowances;
+pub mod checkpoints;
+pub mod default_account_state;
+pub mod interest_bearing;
+pub mod memo_transfer;
+pub mod non_transferable;
+pub mod permanent_delegate;
+pub mod supply_cap;
+pub mod transfer_hook;
+
+use {
+    allowances::Allowances,
+    checkpoints::{BalanceCheckpoints, SupplyCheckpoints},
+    default_account_state::DefaultAccountState,
+    interest_bearing::InterestBearingConfig,
+    memo_transfer::MemoTransfer,
+    non_transferable::{NonTransferable, NonTransferableAccount},
+    permanent_delegate::PermanentDelegate,
+    supply_cap::SupplyCap,
+    transfer_hook::{TransferHook, TransferHookAccount},
+};
+
+/// Length of the base state region of an extended mint or account. Mints are
+/// padded up to the account length so both store their type at the same index.
+pub const BASE_ACCOUNT_LENGTH: usize = Account::LEN;
+/// Index of the [`AccountType`] byte in extended account data
+const ACCOUNT_TYPE_INDEX: usize = BASE_ACCOUNT_LENGTH;
+/// Start of the type-length-value entries in extended account data
+const TLV_START_INDEX: usize = ACCOUNT_TYPE_INDEX + 1;
+/// Serialized length of an entry header: a `u16` type and a `u16` length
+const TLV_HEADER_BYTES: usize = 4;
+
+/// Kind of base state stored in extended account data.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, TryFromPrimitive)]
+pub enum AccountType {
+    /// No base state has been written yet
+    #[default]
+    Uninitialized,
+    /// Mint base state
+    Mint,
+    /// Token account base state
+    Account,
+}
+
+/// Extensions that may be attached to a mint or account.
+#[repr(u16)]
+#[derive(Clone, Copy, Debug, PartialEq, TryFromPrimitive)]
+pub enum ExtensionType {
+    /// Marks the unused space after the last extension
+    Uninitialized,
+    /// Upper bound on the supply of a mint
+    SupplyCap,
+    /// Per-delegate allowances of a token account
+    Allowances,
+    /// Program invoked on every transfer of a mint's tokens
+    TransferHook,
+    /// Marks an account of a mint with a transfer hook
+    TransferHookAccount,
+    /// Disables transfers of a mint's tokens
+    NonTransferable,
+    /// Marks an account of a non-transferable mint
+    NonTransferableAccount,
+    /// Delegate of a mint over all of its accounts
+    PermanentDelegate,
+    /// State new accounts of a mint start in
+    DefaultAccountState,
+    /// Interest accrued on the UI amounts of a mint
+    InterestBearingConfig,
+    /// Balance history of a token account
+    BalanceCheckpoints,
+    /// Supply history of a mint
+    SupplyCheckpoints,
+    /// Memo requirement of a token account
+    MemoTransfer,
+}
+
+impl ExtensionType {
+    /// Packed length of the extension value
+    pub fn get_type_len(&self) -> usize {
+        match self {
+            ExtensionType::Uninitialized => 0,
+            ExtensionType::SupplyCap => SupplyCap::LEN,
+            ExtensionType::Allowances => Allowances::LEN,
+            ExtensionType::TransferHook => TransferHook::LEN,
+            ExtensionType::TransferHookAccount => TransferHookAccount::LEN,
+            ExtensionType::NonTransferable => NonTransferable::LEN,
+            ExtensionType::NonTransferableAccount => NonTransferableAccount::LEN,
+            ExtensionType::PermanentDelegate => PermanentDelegate::LEN,
+            ExtensionType::DefaultAccountState => DefaultAccountState::LEN,
+            ExtensionType::InterestBearingConfig => InterestBearingConfig::LEN,
+            ExtensionType::BalanceCheckpoints => BalanceCheckpoints::LEN,
+            ExtensionType::SupplyCheckpoints => SupplyCheckpoints::LEN,
+            ExtensionType::MemoTransfer => MemoTransfer::LEN,
+        }
+    }
+
+    /// Kind of base state the extension can be attached to
+    pub fn get_account_type(&self) -> AccountType {
+        match self {
+            ExtensionType::Uninitialized => AccountType::Uninitialized,
+            ExtensionType::SupplyCap => AccountType::Mint,
+            ExtensionType::Allowances => AccountType::Account,
+            ExtensionType::TransferHook => AccountType::Mint,
+            ExtensionType::TransferHookAccount => AccountType::Account,
+            ExtensionType::NonTransferable => AccountType::Mint,
+            ExtensionType::NonTransferableAccount => AccountType::Account,
+            ExtensionType::PermanentDelegate => AccountType::Mint,
+            ExtensionType::DefaultAccountState => AccountType::Mint,
+            ExtensionType::InterestBearingConfig => AccountType::Mint,
+            ExtensionType::BalanceCheckpoints => AccountType::Account,
+            ExtensionType::SupplyCheckpoints => AccountType::Mint,
+            ExtensionType::MemoTransfer => AccountType::Account,
+        }
+    }
+
+    /// Computes the data length of a mint or account holding the given
+    /// extensions
+    pub fn try_calculate_account_len(
+        account_type: AccountType,
+        extension_types: &[ExtensionType],
+    ) -> Result<usize, ProgramError> {
+        let base_len = match account_type {
+            AccountType::Mint => Mint::LEN,
+            AccountType::Account => Account::LEN,
+            AccountType::Uninitialized => return Err(ProgramError::InvalidArgument),
+        };
+        if extension_types.is_empty() {
+            return Ok(base_len);
+        }
+        let mut account_len = TLV_START_INDEX;
+        for extension_type in extension_types {
+            if extension_type.get_account_type() != account_type {
+                return Err(ProgramError::InvalidArgument);
+            }
+            account_len += TLV_HEADER_BYTES + extension_type.get_type_len();
+        }
+        // Extended accounts are told apart from multisig accounts by length
+        if account_len == Multisig::LEN || account_len == WeightedMultisig::LEN {
+            account_len += 1;
+        }
+        Ok(account_len)
+    }
+}
+
+/// State stored in a type-length-value entry after the base state.
+pub trait Extension: Pack {
+    /// Type tag of the entry holding this extension
+    const TYPE: ExtensionType;
+}
+
+/// Base state that extensions can be attached to.
+pub trait BaseState: Pack + IsInitialized {
+    /// Type tag written to extended account data holding this state
+    const ACCOUNT_TYPE: AccountType;
+
+    /// Unpacks the base state from account data that may carry extensions,
+    /// without checking that it is initialized
+    fn unpack_base_unchecked(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() == Self::LEN {
+            return Self::unpack_unchecked(input);
+        }
+        match get_account_type(input)? {
+            AccountType::Uninitialized => {}
+            account_type if account_type == Self::ACCOUNT_TYPE => {}
+            _ => return Err(ProgramError::InvalidAccountData),
+        }
+        Self::unpack_from_slice(&input[..Self::LEN])
+    }
+
+    /// Unpacks the base state from account data that may carry extensions,
+    /// checking that it is initialized
+    fn unpack_base(input: &[u8]) -> Result<Self, ProgramError> {
+        let value = Self::unpack_base_unchecked(input)?;
+        if !value.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if input.len() != Self::LEN && get_account_type(input)? != Self::ACCOUNT_TYPE {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(value)
+    }
+
+    /// Packs the base state into account data that may carry extensions,
+    /// tagging extended data with the account type
+    fn pack_base(src: Self, dst: &mut [u8]) -> Result<(), ProgramError> {
+        if dst.len() == Self::LEN {
+            return Self::pack(src, dst);
+        }
+        match get_account_type(dst)? {
+            AccountType::Uninitialized => {}
+            account_type if account_type == Self::ACCOUNT_TYPE => {}
+            _ => return Err(ProgramError::InvalidAccountData),
+        }
+        src.pack_into_slice(&mut dst[..Self::LEN]);
+        dst[ACCOUNT_TYPE_INDEX] = Self::ACCOUNT_TYPE as u8;
+        Ok(())
+    }
+}
+
+impl BaseState for Mint {
+    const ACCOUNT_TYPE: AccountType = AccountType::Mint;
+}
+
+impl BaseState for Account {
+    const ACCOUNT_TYPE: AccountType = AccountType::Account;
+}
+
+/// Checks if the account data has room for extensions
+fn is_extended(input: &[u8]) -> bool {
+    input.len() > BASE_ACCOUNT_LENGTH
+        && input.len() != Multisig::LEN
+        && input.len() != WeightedMultisig::LEN
+}
+
+/// Determines the kind of base state held by mint or account data.
+pub fn get_account_type(input: &[u8]) -> Result<AccountType, ProgramError> {
+    if input.len() == Mint::LEN {
+        Ok(AccountType::Mint)
+    } else if input.len() == Account::LEN {
+        Ok(AccountType::Account)
+    } else if is_extended(input) {
+        AccountType::try_from_primitive(input[ACCOUNT_TYPE_INDEX])
+            .or(Err(ProgramError::InvalidAccountData))
+    } else {
+        Err(ProgramError::InvalidAccountData)
+    }
+}
+
+/// Determines the extensions every token account of a mint must carry.
+pub fn get_required_account_extensions(
+    mint_data: &[u8],
+) -> Result<Vec<ExtensionType>, ProgramError> {
+    let mut extension_types = vec![];
+    if get_extension::<TransferHook>(mint_data)?.is_some() {
+        extension_types.push(ExtensionType::TransferHookAccount);
+    }
+    if get_extension::<NonTransferable>(mint_data)?.is_some() {
+        extension_types.push(ExtensionType::NonTransferableAccount);
+    }
+    Ok(extension_types)
+}
+
+/// Finds the start of the entry for `extension_type`. With `init`, the first
+/// unused entry is returned if the extension is not present.
+fn find_entry(
+    tlv_data: &[u8],
+    extension_type: ExtensionType,
+    init: bool,
+) -> Result<Option<usize>, ProgramError> {
+    let mut start = 0;
+    while start + TLV_HEADER_BYTES <= tlv_data.len() {
+        let header = array_ref![tlv_data, start, TLV_HEADER_BYTES];
+        let entry_type =
+            ExtensionType::try_from_primitive(u16::from_le_bytes([header[0], header[1]]))
+                .or(Err(ProgramError::InvalidAccountData))?;
+        if entry_type == ExtensionType::Uninitialized {
+            return Ok(if init { Some(start) } else { None });
+        }
+        if entry_type == extension_type {
+            return Ok(Some(start));
+        }
+        let length = u16::from_le_bytes([header[2], header[3]]) as usize;
+        start += TLV_HEADER_BYTES + length;
+    }
+    Ok(None)
+}
+
+/// Unpacks an extension from mint or account data, returning `None` if it is
+/// not present.
+pub fn get_extension<V: Extension>(input: &[u8]) -> Result<Option<V>, ProgramError> {
+    if !is_extended(input) {
+        return Ok(None);
+    }
+    match get_account_type(input)? {
+        // Writing an extension tags the data, so untagged data holds none
+        AccountType::Uninitialized => return Ok(None),
+        account_type if account_type == V::TYPE.get_account_type() => {}
+        _ => return Err(ProgramError::InvalidAccountData),
+    }
+    let tlv_data = &input[TLV_START_INDEX..];
+    let Some(start) = find_entry(tlv_data, V::TYPE, false)? else {
+        return Ok(None);
+    };
+    let value_start = start + TLV_HEADER_BYTES;
+    let length = u16::from_le_bytes([tlv_data[start + 2], tlv_data[start + 3]]) as usize;
+    if length != V::LEN || value_start + length > tlv_data.len() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    V::unpack_from_slice(&tlv_data[value_start..value_start + length]).map(Some)
+}
+
+/// Writes an extension into mint or account data, replacing the existing
+/// value or taking the first unused entry.
+pub fn set_extension<V: Extension>(input: &mut [u8], value: V) -> Result<(), ProgramError> {
+    if !is_extended(input) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let account_type = V::TYPE.get_account_type();
+    match get_account_type(input)? {
+        AccountType::Uninitialized => input[ACCOUNT_TYPE_INDEX] = account_type as u8,
+        existing if existing == account_type => {}
+        _ => return Err(ProgramError::InvalidAccountData),
+    }
+    let tlv_data = &mut input[TLV_START_INDEX..];
+    let start = find_entry(tlv_data, V::TYPE, true)?.ok_or(ProgramError::InvalidAccountData)?;
+    let value_start = start + TLV_HEADER_BYTES;
+    if value_start + V::LEN > tlv_data.len() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let header = array_mut_ref![tlv_data, start, TLV_HEADER_BYTES];
+    let length = u16::from_le_bytes([header[2], header[3]]) as usize;
+    if header[..2] != [0, 0] && length != V::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    header[..2].copy_from_slice(&(V::TYPE as u16).to_le_bytes());
+    header[2..].copy_from_slice(&(V::LEN as u16).to_le_bytes());
+    value.pack_into_slice(&mut tlv_data[value_start..value_start + V::LEN]);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, arch_program::program_option::COption, arch_program::pubkey::Pubkey};
+
+    fn test_mint() -> Mint {
+        Mint {
+            mint_authority: COption::Some(Pubkey::from_slice(&[1u8; 32])),
+            supply: 42,
+            decimals: 2,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        }
+    }
+
+    #[test]
+    fn test_try_calculate_account_len() {
+        let len = ExtensionType::try_calculate_account_len(AccountType::Mint, &[]).unwrap();
+        assert_eq!(len, Mint::LEN);
+        let len = ExtensionType::try_calculate_account_len(AccountType::Account, &[]).unwrap();
+        assert_eq!(len, Account::LEN);
+
+        let len = ExtensionType::try_calculate_account_len(
+            AccountType::Mint,
+            &[ExtensionType::SupplyCap],
+        )
+        .unwrap();
+        assert_eq!(
+            len,
+            BASE_ACCOUNT_LENGTH + 1 + TLV_HEADER_BYTES + SupplyCap::LEN
+        );
+
+        let err = ExtensionType::try_calculate_account_len(
+            AccountType::Account,
+            &[ExtensionType::SupplyCap],
+        )
+        .unwrap_err();
+        assert_eq!(err, ProgramError::InvalidArgument);
+    }
+
+    #[test]
+    fn test_base_state_roundtrip() {
+        // Unextended data keeps the original layout
+        let mut data = vec![0u8; Mint::LEN];
+        Mint::pack_base(test_mint(), &mut data).unwrap();
+        assert_eq!(Mint::unpack(&data).unwrap(), test_mint());
+        assert_eq!(Mint::unpack_base(&data).unwrap(), test_mint());
+
+        // Extended data is tagged with its account type
+        let len = ExtensionType::try_calculate_account_len(
+            AccountType::Mint,
+            &[ExtensionType::SupplyCap],
+        )
+        .unwrap();
+        let mut data = vec![0u8; len];
+        assert!(!Mint::unpack_base_unchecked(&data).unwrap().is_initialized);
+        Mint::pack_base(test_mint(), &mut data).unwrap();
+        assert_eq!(get_account_type(&data).unwrap(), AccountType::Mint);
+        assert_eq!(Mint::unpack_base(&data).unwrap(), test_mint());
+        assert_eq!(
+            Account::unpack_base(&data).unwrap_err(),
+            ProgramError::InvalidAccountData
+        );
+
+        // Data of no known length is rejected
+        let data = vec![0u8; Mint::LEN + 1];
+        assert_eq!(
+            Mint::unpack_base(&data).unwrap_err(),
+            ProgramError::InvalidAccountData
+        );
+    }
+
+    #[test]
+    fn test_get_set_extension() {
+        let len = ExtensionType::try_calculate_account_len(
+            AccountType::Mint,
+            &[ExtensionType::SupplyCap],
+        )
+        .unwrap();
+        let mut data = vec![0u8; len];
+        assert_eq!(get_extension::<SupplyCap>(&data).unwrap(), None);
+
+        set_extension(&mut data, SupplyCap { max_supply: 100 }).unwrap();
+        assert_eq!(get_account_type(&data).unwrap(), AccountType::Mint);
+        assert_eq!(
+            get_extension::<SupplyCap>(&data).unwrap(),
+            Some(SupplyCap { max_supply: 100 })
+        );
+
+        // Setting again replaces the existing entry
+        set_extension(&mut data, SupplyCap { max_supply: 50 }).unwrap();
+        assert_eq!(
+            get_extension::<SupplyCap>(&data).unwrap(),
+            Some(SupplyCap { max_supply: 50 })
+        );
+
+        // Base state can be written around the extension
+        Mint::pack_base(test_mint(), &mut data).unwrap();
+        assert_eq!(Mint::unpack_base(&data).unwrap(), test_mint());
+        assert_eq!(
+            get_extension::<SupplyCap>(&data).unwrap(),
+            Some(SupplyCap { max_supply: 50 })
+        );
+
+        // Unextended data holds no extensions and has no room for them
+        let mut data = vec![0u8; Mint::LEN];
+        assert_eq!(get_extension::<SupplyCap>(&data).unwrap(), None);
+        assert_eq!(
+            set_extension(&mut data, SupplyCap { max_supply: 1 }).unwrap_err(),
+            ProgramError::InvalidAccountData
+        );
+    }
+}
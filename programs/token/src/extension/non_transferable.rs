@@ -0,0 +1,66 @@
+//! Non-transferable extension
+
+use {
+    crate::extension::{Extension, ExtensionType},
+    arch_program::{
+        program_error::ProgramError,
+        program_pack::{Pack, Sealed},
+        pubkey::Pubkey,
+    },
+    arrayref::{array_mut_ref, array_ref},
+};
+
+/// Marks a mint whose tokens cannot be transferred once minted, only burned.
+/// The authority can be rotated or cleared with `SetAuthority`, but has no
+/// power over transfers. A default pubkey stands for no authority.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NonTransferable {
+    /// Authority over the non-transferable mode
+    pub authority: Pubkey,
+}
+
+impl NonTransferable {
+    /// Authority over the non-transferable mode, if any
+    pub fn authority(&self) -> Option<Pubkey> {
+        Some(self.authority).filter(|authority| *authority != Pubkey::default())
+    }
+}
+
+impl Sealed for NonTransferable {}
+impl Pack for NonTransferable {
+    const LEN: usize = 32;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 32];
+        Ok(NonTransferable {
+            authority: Pubkey::from_slice(src),
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 32];
+        dst.copy_from_slice(self.authority.as_ref());
+    }
+}
+
+impl Extension for NonTransferable {
+    const TYPE: ExtensionType = ExtensionType::NonTransferable;
+}
+
+/// Marks a token account of a non-transferable mint, so transfers that do
+/// not name the mint are refused as well.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NonTransferableAccount;
+
+impl Sealed for NonTransferableAccount {}
+impl Pack for NonTransferableAccount {
+    const LEN: usize = 0;
+    fn unpack_from_slice(_src: &[u8]) -> Result<Self, ProgramError> {
+        Ok(NonTransferableAccount)
+    }
+    fn pack_into_slice(&self, _dst: &mut [u8]) {}
+}
+
+impl Extension for NonTransferableAccount {
+    const TYPE: ExtensionType = ExtensionType::NonTransferableAccount;
+}
@@ -0,0 +1,46 @@
+//! Permanent delegate extension
+
+use {
+    crate::extension::{Extension, ExtensionType},
+    arch_program::{
+        program_error::ProgramError,
+        program_pack::{Pack, Sealed},
+        pubkey::Pubkey,
+    },
+    arrayref::{array_mut_ref, array_ref},
+};
+
+/// Delegate of a mint that may transfer or burn any amount from any account
+/// of the mint. A default pubkey stands for no delegate.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PermanentDelegate {
+    /// The permanent delegate
+    pub delegate: Pubkey,
+}
+
+impl PermanentDelegate {
+    /// The permanent delegate, if any
+    pub fn delegate(&self) -> Option<Pubkey> {
+        Some(self.delegate).filter(|delegate| *delegate != Pubkey::default())
+    }
+}
+
+impl Sealed for PermanentDelegate {}
+impl Pack for PermanentDelegate {
+    const LEN: usize = 32;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 32];
+        Ok(PermanentDelegate {
+            delegate: Pubkey::from_slice(src),
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 32];
+        dst.copy_from_slice(self.delegate.as_ref());
+    }
+}
+
+impl Extension for PermanentDelegate {
+    const TYPE: ExtensionType = ExtensionType::PermanentDelegate;
+}
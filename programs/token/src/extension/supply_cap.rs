@@ -0,0 +1,45 @@
+//! Supply cap extension
+
+use {
+    crate::extension::{Extension, ExtensionType},
+    arch_program::{
+        program_error::ProgramError,
+        program_pack::{Pack, Sealed},
+    },
+    arrayref::{array_mut_ref, array_ref},
+};
+
+/// Upper bound on the total supply of a mint. `MintTo` fails once the supply
+/// would exceed `max_supply`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SupplyCap {
+    /// Maximum total supply of the mint
+    pub max_supply: u64,
+}
+
+impl SupplyCap {
+    /// Number of tokens that can still be minted given the current supply
+    pub fn remaining(&self, supply: u64) -> u64 {
+        self.max_supply.saturating_sub(supply)
+    }
+}
+
+impl Sealed for SupplyCap {}
+impl Pack for SupplyCap {
+    const LEN: usize = 8;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 8];
+        Ok(SupplyCap {
+            max_supply: u64::from_le_bytes(*src),
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 8];
+        *dst = self.max_supply.to_le_bytes();
+    }
+}
+
+impl Extension for SupplyCap {
+    const TYPE: ExtensionType = ExtensionType::SupplyCap;
+}
@@ -0,0 +1,252 @@
+//! Transfer hook extension
+//!
+//! A mint with a transfer hook has its hook program invoked after every
+//! transfer of its tokens, with the instruction built by
+//! [`execute_instruction`]. Beyond the fixed accounts, the hook receives the
+//! accounts listed in its validation account, an account owned by the hook
+//! program at the address derived by [`get_extra_account_metas_address`].
+
+use {
+    crate::extension::{Extension, ExtensionType},
+    arch_program::{
+        account::AccountMeta,
+        instruction::Instruction,
+        program_error::ProgramError,
+        program_pack::{Pack, Sealed},
+        pubkey::Pubkey,
+    },
+    arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs},
+};
+
+/// Seed of the validation account of a mint, derived under the hook program
+pub const EXTRA_ACCOUNT_METAS_SEED: &[u8] = b"extra-account-metas";
+
+/// First 8 bytes of the sha256 hash of `apl-transfer-hook-interface:execute`,
+/// prefixed to the amount in the data of the hook instruction
+pub const EXECUTE_DISCRIMINATOR: [u8; 8] = [162, 198, 98, 193, 144, 205, 138, 64];
+
+/// Transfer hook of a mint. A default pubkey stands for `None`, so the
+/// extension keeps a fixed length. The mint is marked as transferring while
+/// the hook runs, so the hook cannot move any tokens of the mint.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TransferHook {
+    /// Authority that can change the hook program
+    pub authority: Pubkey,
+    /// Program invoked on every transfer
+    pub program_id: Pubkey,
+    /// Is `true` while the hook of a transfer of the mint runs
+    pub transferring: bool,
+}
+
+impl TransferHook {
+    /// Authority that can change the hook program, if any
+    pub fn authority(&self) -> Option<Pubkey> {
+        Some(self.authority).filter(|authority| *authority != Pubkey::default())
+    }
+
+    /// Program invoked on every transfer, if any
+    pub fn program_id(&self) -> Option<Pubkey> {
+        Some(self.program_id).filter(|program_id| *program_id != Pubkey::default())
+    }
+}
+
+impl Sealed for TransferHook {}
+impl Pack for TransferHook {
+    const LEN: usize = 65;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 65];
+        let (authority, program_id, transferring) = array_refs![src, 32, 32, 1];
+        Ok(TransferHook {
+            authority: Pubkey::from_slice(authority),
+            program_id: Pubkey::from_slice(program_id),
+            transferring: unpack_bool(transferring)?,
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 65];
+        let (authority, program_id, transferring) = mut_array_refs![dst, 32, 32, 1];
+        authority.copy_from_slice(self.authority.as_ref());
+        program_id.copy_from_slice(self.program_id.as_ref());
+        transferring[0] = self.transferring as u8;
+    }
+}
+
+impl Extension for TransferHook {
+    const TYPE: ExtensionType = ExtensionType::TransferHook;
+}
+
+/// Marks a token account of a mint with a transfer hook, so transfers that
+/// do not name the mint, and would skip both the hook and its reentrancy
+/// guard, are refused.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TransferHookAccount;
+
+impl Sealed for TransferHookAccount {}
+impl Pack for TransferHookAccount {
+    const LEN: usize = 0;
+    fn unpack_from_slice(_src: &[u8]) -> Result<Self, ProgramError> {
+        Ok(TransferHookAccount)
+    }
+    fn pack_into_slice(&self, _dst: &mut [u8]) {}
+}
+
+impl Extension for TransferHookAccount {
+    const TYPE: ExtensionType = ExtensionType::TransferHookAccount;
+}
+
+/// Account passed to the hook program in addition to the fixed accounts.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ExtraAccountMeta {
+    /// The account
+    pub pubkey: Pubkey,
+    /// Is `true` if the account must sign
+    pub is_signer: bool,
+    /// Is `true` if the account is writable
+    pub is_writable: bool,
+}
+
+impl ExtraAccountMeta {
+    /// Serialized length of an account meta
+    pub const LEN: usize = 34;
+
+    /// Unpacks the list of account metas held by a validation account: a
+    /// little-endian `u32` count followed by that many metas
+    pub fn unpack_list(input: &[u8]) -> Result<Vec<Self>, ProgramError> {
+        let count = input
+            .get(..4)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u32::from_le_bytes)
+            .ok_or(ProgramError::InvalidAccountData)? as usize;
+        let metas = input
+            .get(4..4 + count * Self::LEN)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        metas
+            .chunks_exact(Self::LEN)
+            .map(|meta| {
+                let meta = array_ref![meta, 0, ExtraAccountMeta::LEN];
+                let (pubkey, is_signer, is_writable) = array_refs![meta, 32, 1, 1];
+                Ok(ExtraAccountMeta {
+                    pubkey: Pubkey::from_slice(pubkey),
+                    is_signer: unpack_bool(is_signer)?,
+                    is_writable: unpack_bool(is_writable)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Packs a list of account metas in the layout read by
+    /// [`unpack_list`](Self::unpack_list)
+    pub fn pack_list(metas: &[Self]) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(4 + metas.len() * Self::LEN);
+        buf.extend_from_slice(&(metas.len() as u32).to_le_bytes());
+        for meta in metas {
+            buf.extend_from_slice(meta.pubkey.as_ref());
+            buf.push(meta.is_signer as u8);
+            buf.push(meta.is_writable as u8);
+        }
+        buf
+    }
+}
+
+impl From<&ExtraAccountMeta> for AccountMeta {
+    fn from(meta: &ExtraAccountMeta) -> Self {
+        AccountMeta {
+            pubkey: meta.pubkey,
+            is_signer: meta.is_signer,
+            is_writable: meta.is_writable,
+        }
+    }
+}
+
+fn unpack_bool(src: &[u8; 1]) -> Result<bool, ProgramError> {
+    match src {
+        [0] => Ok(false),
+        [1] => Ok(true),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+/// Derives the address of the validation account of `mint` for a hook program
+pub fn get_extra_account_metas_address(mint: &Pubkey, hook_program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[EXTRA_ACCOUNT_METAS_SEED, &mint.serialize()],
+        hook_program_id,
+    )
+    .0
+}
+
+/// Creates the instruction a hook program receives on every transfer.
+///
+///   0. `[]` The source account.
+///   1. `[]` The token mint.
+///   2. `[]` The destination account.
+///   3. `[]` The source account's owner/delegate.
+///   4. `[]` The validation account.
+///   5. ..`5+N` The N accounts listed in the validation account.
+pub fn execute_instruction(
+    hook_program_id: &Pubkey,
+    source_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    extra_account_metas: &[ExtraAccountMeta],
+    amount: u64,
+) -> Instruction {
+    let mut data = Vec::with_capacity(EXECUTE_DISCRIMINATOR.len() + 8);
+    data.extend_from_slice(&EXECUTE_DISCRIMINATOR);
+    data.extend_from_slice(&amount.to_le_bytes());
+
+    let mut accounts = Vec::with_capacity(5 + extra_account_metas.len());
+    accounts.push(AccountMeta::new_readonly(*source_pubkey, false));
+    accounts.push(AccountMeta::new_readonly(*mint_pubkey, false));
+    accounts.push(AccountMeta::new_readonly(*destination_pubkey, false));
+    accounts.push(AccountMeta::new_readonly(*authority_pubkey, false));
+    accounts.push(AccountMeta::new_readonly(
+        get_extra_account_metas_address(mint_pubkey, hook_program_id),
+        false,
+    ));
+    accounts.extend(extra_account_metas.iter().map(AccountMeta::from));
+
+    Instruction {
+        program_id: *hook_program_id,
+        accounts,
+        data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extra_account_metas_pack_unpack() {
+        let metas = vec![
+            ExtraAccountMeta {
+                pubkey: Pubkey::new_unique(),
+                is_signer: false,
+                is_writable: true,
+            },
+            ExtraAccountMeta {
+                pubkey: Pubkey::new_unique(),
+                is_signer: true,
+                is_writable: false,
+            },
+        ];
+        let mut packed = ExtraAccountMeta::pack_list(&metas);
+        assert_eq!(packed.len(), 4 + 2 * ExtraAccountMeta::LEN);
+        assert_eq!(ExtraAccountMeta::unpack_list(&packed).unwrap(), metas);
+
+        // Trailing space left by the hook program is ignored
+        packed.extend_from_slice(&[0; 8]);
+        assert_eq!(ExtraAccountMeta::unpack_list(&packed).unwrap(), metas);
+
+        packed[0] = 3;
+        assert_eq!(
+            ExtraAccountMeta::unpack_list(&packed),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+}
@@ -1,11 +1,21 @@
 //! Instruction types
 
 use {
-    crate::{check_program_account, error::TokenError},
+    crate::{
+        check_program_account,
+        error::TokenError,
+        extension::{
+            allowances::AllowanceExpiry,
+            transfer_hook::{get_extra_account_metas_address, ExtraAccountMeta},
+        },
+        permit::get_permit_nonce_address,
+        state::AccountState,
+    },
     arch_program::{
         account::AccountMeta, instruction::Instruction, msg, program_error::ProgramError,
         program_option::COption, pubkey::Pubkey,
     },
+    num_enum::TryFromPrimitive,
     std::{convert::TryInto, mem::size_of},
 };
 
@@ -13,6 +23,8 @@ use {
 pub const MIN_SIGNERS: usize = 1;
 /// Maximum number of multisignature signers (max N)
 pub const MAX_SIGNERS: usize = 11;
+/// Maximum number of weighted multisignature signers
+pub const MAX_WEIGHTED_SIGNERS: usize = 32;
 /// Serialized length of a `u64`, for unpacking
 const U64_BYTES: usize = 8;
 
@@ -84,7 +96,9 @@ pub enum TokenInstruction<'a> {
     /// Transfers tokens from one account to another either directly or via a
     /// delegate.  If this account is associated with the native mint then equal
     /// amounts of SOL and Tokens will be transferred to the destination
-    /// account.
+    /// account. Tokens of a mint with a transfer hook can only be moved with
+    /// `TransferChecked`. The mint's permanent delegate is not recognized here,
+    /// as the mint is not passed; it must use `TransferChecked` instead.
     ///
     /// Accounts expected by this instruction:
     ///
@@ -133,7 +147,12 @@ pub enum TokenInstruction<'a> {
     ///   0. `[writable]` The source account.
     ///   1. `[]` The source account's multisignature owner.
     ///   2. ..`2+M` `[signer]` M signer accounts
-    Revoke,
+    Revoke {
+        /// The delegate to revoke. `None` revokes the account's single
+        /// `delegate`; otherwise the delegate loses its allowance, and is also
+        /// cleared if it is the account's single `delegate`.
+        delegate: COption<Pubkey>,
+    },
     /// Sets a new authority of a mint or account.
     ///
     /// Accounts expected by this instruction:
@@ -247,17 +266,21 @@ pub enum TokenInstruction<'a> {
     /// decimals value is checked by the caller.  This may be useful when
     /// creating transactions offline or within a hardware wallet.
     ///
+    /// If the mint has a transfer hook, the mint must be writable, and the hook
+    /// program, its validation account and the accounts listed there must
+    /// follow the accounts below.
+    ///
     /// Accounts expected by this instruction:
     ///
     ///   * Single owner/delegate
     ///   0. `[writable]` The source account.
-    ///   1. `[]` The token mint.
+    ///   1. `[writable]` The token mint, only written if it has a transfer hook.
     ///   2. `[writable]` The destination account.
     ///   3. `[signer]` The source account's owner/delegate.
     ///
     ///   * Multisignature owner/delegate
     ///   0. `[writable]` The source account.
-    ///   1. `[]` The token mint.
+    ///   1. `[writable]` The token mint, only written if it has a transfer hook.
     ///   2. `[writable]` The destination account.
     ///   3. `[]` The source account's multisignature owner/delegate.
     ///   4. ..`4+M` `[signer]` M signer accounts.
@@ -371,7 +394,10 @@ pub enum TokenInstruction<'a> {
         owner: Pubkey,
     },
     /// Like [`InitializeMint`], but does not require the Rent sysvar to be
-    /// provided
+    /// provided, and optionally caps the supply of the mint.
+    ///
+    /// If `max_supply` is set, the mint must be allocated with room for the
+    /// `SupplyCap` extension.
     ///
     /// Accounts expected by this instruction:
     ///
@@ -383,6 +409,8 @@ pub enum TokenInstruction<'a> {
         mint_authority: Pubkey,
         /// The freeze authority/multisignature of the mint.
         freeze_authority: COption<Pubkey>,
+        /// The maximum total supply of the mint.
+        max_supply: COption<u64>,
     },
     /// Gets the required size of an account for the given mint as a
     /// little-endian `u64`.
@@ -439,6 +467,412 @@ pub enum TokenInstruction<'a> {
         /// The `ui_amount` of tokens to reformat.
         ui_amount: &'a str,
     },
+    /// Sets the supply cap of a mint. A mint without a cap may be given one
+    /// if it was allocated with room for the `SupplyCap` extension; an
+    /// existing cap can only be lowered, and never below the current supply.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single authority
+    ///   0. `[writable]` The mint.
+    ///   1. `[signer]` The mint's minting authority.
+    ///
+    ///   * Multisignature authority
+    ///   0. `[writable]` The mint.
+    ///   1. `[]` The mint's multisignature mint-tokens authority.
+    ///   2. ..`2+M` `[signer]` M signer accounts.
+    SetMaxSupply {
+        /// The new maximum total supply of the mint.
+        max_supply: u64,
+    },
+    /// Gets the number of tokens that can still be minted as a little-endian
+    /// `u64`. This is zero for a mint without a minting authority, and
+    /// otherwise bounded by the mint's supply cap, if any.
+    ///
+    /// Return data can be fetched using `sol_get_return_data` and deserializing
+    /// the return data as a little-endian `u64`.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[]` The mint to calculate for
+    GetMintableSupply,
+    /// Transfers tokens from one account to many others, debiting the source
+    /// once for the total. Each destination receives the amount at the same
+    /// position in `amounts`, and must hold the same mint as the source. A
+    /// destination equal to the source is a no-op, as with `Transfer`. Like
+    /// `Transfer`, the mint's permanent delegate can only spend with the
+    /// checked variant.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner/delegate
+    ///   0. `[writable]` The source account.
+    ///   1. ..`1+N` `[writable]` The N destination accounts.
+    ///   * `1+N`. `[signer]` The source account's owner/delegate.
+    ///
+    ///   * Multisignature owner/delegate
+    ///   0. `[writable]` The source account.
+    ///   1. ..`1+N` `[writable]` The N destination accounts.
+    ///   * `1+N`. `[]` The source account's multisignature owner/delegate.
+    ///   * `2+N`. ..`2+N+M` `[signer]` M signer accounts.
+    TransferBatch {
+        /// The amount of tokens to transfer to each destination.
+        amounts: Vec<u64>,
+    },
+    /// Transfers tokens from one account to many others, as `TransferBatch`.
+    ///
+    /// This instruction differs from `TransferBatch` in that the token mint
+    /// and decimals value is checked by the caller.  This may be useful when
+    /// creating transactions offline or within a hardware wallet.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner/delegate
+    ///   0. `[writable]` The source account.
+    ///   1. `[writable]` The token mint, only written if it has a transfer hook.
+    ///   2. ..`2+N` `[writable]` The N destination accounts.
+    ///   * `2+N`. `[signer]` The source account's owner/delegate.
+    ///
+    ///   * Multisignature owner/delegate
+    ///   0. `[writable]` The source account.
+    ///   1. `[writable]` The token mint, only written if it has a transfer hook.
+    ///   2. ..`2+N` `[writable]` The N destination accounts.
+    ///   * `2+N`. `[]` The source account's multisignature owner/delegate.
+    ///   * `3+N`. ..`3+N+M` `[signer]` M signer accounts.
+    TransferBatchChecked {
+        /// The amount of tokens to transfer to each destination.
+        amounts: Vec<u64>,
+        /// Expected number of base 10 digits to the right of the decimal place.
+        decimals: u8,
+    },
+    /// Approves a delegate for an amount of its own, alongside any other
+    /// delegates of the account, until an optional expiry. The account must
+    /// have been allocated with room for the `Allowances` extension.
+    /// Approving zero removes the delegate's allowance.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner
+    ///   0. `[writable]` The source account.
+    ///   1. `[]` The delegate.
+    ///   2. `[signer]` The source account owner.
+    ///
+    ///   * Multisignature owner
+    ///   0. `[writable]` The source account.
+    ///   1. `[]` The delegate.
+    ///   2. `[]` The source account's multisignature owner.
+    ///   3. ..`3+M` `[signer]` M signer accounts
+    ApproveAllowance {
+        /// The amount of tokens the delegate is approved for.
+        amount: u64,
+        /// When the allowance stops being spendable.
+        expiry: AllowanceExpiry,
+    },
+    /// Creates the permit nonce account of an owner, at the address derived
+    /// by `get_permit_nonce_address`. The account is funded by the given UTXO.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The permit nonce account.
+    ///   1. `[]` The owner.
+    ///   2. `[]` The system program.
+    CreatePermitNonce {
+        /// Transaction id of the UTXO funding the account.
+        txid: [u8; 32],
+        /// Output index of the UTXO funding the account.
+        vout: u32,
+    },
+    /// Sets the delegate of an account, as `Approve` does, on the strength of
+    /// a message signed off-chain by the account owner rather than the owner
+    /// signing the transaction. The signed message is the hash computed by
+    /// `permit_message_hash`, and the owner's nonce is advanced so each
+    /// permit can be used once. Multisignature owners cannot sign permits.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The source account.
+    ///   1. `[writable]` The owner's permit nonce account.
+    Permit {
+        /// The source account owner that signed the permit.
+        owner: Pubkey,
+        /// The delegate being approved.
+        spender: Pubkey,
+        /// The amount of tokens the delegate is approved for.
+        amount: u64,
+        /// The owner's next permit nonce.
+        nonce: u64,
+        /// Unix timestamp after which the permit can no longer be used.
+        deadline: i64,
+        /// Recovery id of the signature.
+        recovery_id: u8,
+        /// Compact secp256k1 signature of the permit message.
+        signature: [u8; 64],
+    },
+    /// Gives an uninitialized mint a transfer hook. The mint must have been
+    /// allocated with room for the `TransferHook` extension, and this
+    /// instruction must precede `InitializeMint` in the same transaction.
+    /// Token accounts of the mint must then be allocated with room for the
+    /// `TransferHookAccount` extension.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The mint to initialize.
+    InitializeTransferHook {
+        /// The authority that can change the hook program.
+        authority: COption<Pubkey>,
+        /// The program invoked on every transfer.
+        program_id: COption<Pubkey>,
+    },
+    /// Changes the program invoked on every transfer of a mint's tokens.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single authority
+    ///   0. `[writable]` The mint.
+    ///   1. `[signer]` The transfer hook authority.
+    ///
+    ///   * Multisignature authority
+    ///   0. `[writable]` The mint.
+    ///   1. `[]` The mint's multisignature transfer hook authority.
+    ///   2. ..`2+M` `[signer]` M signer accounts.
+    UpdateTransferHook {
+        /// The new program invoked on every transfer, if any.
+        program_id: COption<Pubkey>,
+    },
+    /// Makes an uninitialized mint non-transferable: its tokens can be minted,
+    /// burned and their accounts closed, but never transferred. The mint must
+    /// have been allocated with room for the `NonTransferable` extension, and
+    /// this instruction must precede `InitializeMint` in the same transaction.
+    /// Token accounts of the mint must then be allocated with room for the
+    /// `NonTransferableAccount` extension.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The mint to initialize.
+    InitializeNonTransferableMint {
+        /// The authority over the non-transferable mode.
+        authority: COption<Pubkey>,
+    },
+    /// Gives an uninitialized mint a permanent delegate, which may transfer or
+    /// burn any amount from any account of the mint. The mint must have been
+    /// allocated with room for the `PermanentDelegate` extension, and this
+    /// instruction must precede `InitializeMint` in the same transaction.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The mint to initialize.
+    InitializePermanentDelegate {
+        /// The permanent delegate.
+        delegate: Pubkey,
+    },
+    /// Sets the state new token accounts of an uninitialized mint start in,
+    /// so that accounts can be created frozen and thawed by the freeze
+    /// authority once approved. The mint must have been allocated with room
+    /// for the `DefaultAccountState` extension, and this instruction must
+    /// precede `InitializeMint` in the same transaction. A `Frozen` default
+    /// requires the mint to have a freeze authority.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The mint to initialize.
+    InitializeDefaultAccountState {
+        /// The state of new accounts, `Initialized` or `Frozen`.
+        state: AccountState,
+    },
+    /// Changes the state new token accounts of a mint start in.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single authority
+    ///   0. `[writable]` The mint.
+    ///   1. `[signer]` The mint freeze authority.
+    ///
+    ///   * Multisignature authority
+    ///   0. `[writable]` The mint.
+    ///   1. `[]` The mint's multisignature freeze authority.
+    ///   2. ..`2+M` `[signer]` M signer accounts.
+    UpdateDefaultAccountState {
+        /// The state of new accounts, `Initialized` or `Frozen`.
+        state: AccountState,
+    },
+    /// Makes an uninitialized mint interest-bearing: UI amounts of its tokens
+    /// accrue interest, compounding continuously from the current unix
+    /// timestamp, while raw amounts are unchanged. The mint must have been
+    /// allocated with room for the `InterestBearingConfig` extension, and this
+    /// instruction must precede `InitializeMint` in the same transaction.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The mint to initialize.
+    InitializeInterestBearingMint {
+        /// The authority that can update the rate.
+        rate_authority: COption<Pubkey>,
+        /// The annual rate, in basis points.
+        rate: i16,
+    },
+    /// Changes the rate of an interest-bearing mint. Interest accrued so far
+    /// at the previous rates is kept.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single authority
+    ///   0. `[writable]` The mint.
+    ///   1. `[signer]` The mint rate authority.
+    ///
+    ///   * Multisignature authority
+    ///   0. `[writable]` The mint.
+    ///   1. `[]` The mint's multisignature rate authority.
+    ///   2. ..`2+M` `[signer]` M signer accounts.
+    UpdateInterestRate {
+        /// The new annual rate, in basis points.
+        rate: i16,
+    },
+    /// Initializes a weighted multisignature account, where each signer
+    /// carries a weight and a set of signers may act for the account once
+    /// their weights add up to the threshold. Like `InitializeMultisig`, this
+    /// instruction must be included in the same transaction as the account's
+    /// creation.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The weighted multisignature account to initialize.
+    ///   1. ..`1+N`. `[]` The signer accounts, must equal to N where `1 <= N <=
+    ///      32`.
+    InitializeWeightedMultisig {
+        /// The total weight of signers required, at most the sum of all
+        /// weights.
+        threshold: u16,
+        /// The non-zero weight of each signer account, in order.
+        weights: Vec<u8>,
+    },
+    /// Adds a signer to a weighted multisignature account, approved by the
+    /// account's current threshold.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The weighted multisignature account.
+    ///   1. `[]` The signer to add.
+    ///   2. ..`2+M` `[signer]` M current signer accounts.
+    AddSigner {
+        /// The non-zero weight of the new signer.
+        weight: u8,
+    },
+    /// Removes a signer from a weighted multisignature account, approved by
+    /// the account's current threshold. The remaining signers must still be
+    /// able to reach the threshold.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The weighted multisignature account.
+    ///   1. `[]` The signer to remove.
+    ///   2. ..`2+M` `[signer]` M current signer accounts.
+    RemoveSigner,
+    /// Changes the threshold of a weighted multisignature account, approved
+    /// by the account's current threshold.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The weighted multisignature account.
+    ///   1. ..`1+M` `[signer]` M current signer accounts.
+    SetThreshold {
+        /// The new total weight of signers required.
+        threshold: u16,
+    },
+    /// Makes an uninitialized token account record its balance at every
+    /// change, for `GetBalanceAt`. The account must have been allocated with
+    /// room for the `BalanceCheckpoints` extension, and this instruction must
+    /// precede `InitializeAccount` in the same transaction.
+    ///
+    /// Only the latest `MAX_CHECKPOINTS` changes are kept. Credits that raise
+    /// the balance by less than `min_credit` over the newest checkpoint are
+    /// not recorded, so dust transfers cannot evict the history.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The account to initialize.
+    InitializeBalanceCheckpoints {
+        /// The smallest credit recorded, in base units.
+        min_credit: u64,
+    },
+    /// Makes an uninitialized mint record its supply at every change, for
+    /// `GetBalanceAt`. The mint must have been allocated with room for the
+    /// `SupplyCheckpoints` extension, and this instruction must precede
+    /// `InitializeMint` in the same transaction. Only the latest
+    /// `MAX_CHECKPOINTS` changes are kept.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The mint to initialize.
+    InitializeSupplyCheckpoints,
+    /// Gets the balance of a token account, or the supply of a mint, as of
+    /// the end of a past slot. Fails with `CheckpointNotFound` if the account
+    /// has no checkpoint at or before the slot, which happens once more than
+    /// `MAX_CHECKPOINTS` changes were recorded since. Balances may fall short
+    /// by less than the account's `min_credit`.
+    ///
+    /// Return data can be fetched using `sol_get_return_data` and deserializing
+    /// the return data as a little-endian `u64`.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[]` The token account or mint, with checkpoints.
+    GetBalanceAt {
+        /// The slot to look up.
+        slot: u64,
+    },
+    /// Requires incoming transfers to a token account to carry a memo, sent
+    /// with `TransferWithMemo`. The account must have been allocated with
+    /// room for the `MemoTransfer` extension.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner
+    ///   0. `[writable]` The account.
+    ///   1. `[signer]` The account's owner.
+    ///
+    ///   * Multisignature owner
+    ///   0. `[writable]` The account.
+    ///   1. `[]` The account's multisignature owner.
+    ///   2. ..`2+M` `[signer]` M signer accounts.
+    EnableRequiredTransferMemos,
+    /// Stops requiring incoming transfers to a token account to carry a memo.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner
+    ///   0. `[writable]` The account.
+    ///   1. `[signer]` The account's owner.
+    ///
+    ///   * Multisignature owner
+    ///   0. `[writable]` The account.
+    ///   1. `[]` The account's multisignature owner.
+    ///   2. ..`2+M` `[signer]` M signer accounts.
+    DisableRequiredTransferMemos,
+    /// Transfers tokens as `TransferChecked`, logging a UTF-8 memo. This is
+    /// the only way to transfer into an account requiring memos.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner/delegate
+    ///   0. `[writable]` The source account.
+    ///   1. `[writable]` The token mint, only written if it has a transfer hook.
+    ///   2. `[writable]` The destination account.
+    ///   3. `[signer]` The source account's owner/delegate.
+    ///
+    ///   * Multisignature owner/delegate
+    ///   0. `[writable]` The source account.
+    ///   1. `[writable]` The token mint, only written if it has a transfer hook.
+    ///   2. `[writable]` The destination account.
+    ///   3. `[]` The source account's multisignature owner/delegate.
+    ///   4. ..`4+M` `[signer]` M signer accounts.
+    TransferWithMemo {
+        /// The amount of tokens to transfer.
+        amount: u64,
+        /// Expected number of base 10 digits to the right of the decimal place.
+        decimals: u8,
+        /// The memo, taking up the rest of the instruction data.
+        memo: &'a str,
+    },
     // Any new variants also need to be added to program-2022 `TokenInstruction`, so that the
     // latter remains a superset of this instruction set. New variants also need to be added to
     // token/js/src/instructions/types.ts to maintain @solana/spl-token compatibility
@@ -483,7 +917,14 @@ impl<'a> TokenInstruction<'a> {
                     _ => unreachable!(),
                 }
             }
-            5 => Self::Revoke,
+            5 => {
+                let delegate = if rest.is_empty() {
+                    COption::None
+                } else {
+                    Self::unpack_pubkey_option(rest)?.0
+                };
+                Self::Revoke { delegate }
+            }
             6 => {
                 let (authority_type, rest) = rest
                     .split_first()
@@ -526,11 +967,13 @@ impl<'a> TokenInstruction<'a> {
             18 => {
                 let (&decimals, rest) = rest.split_first().ok_or(InvalidInstruction)?;
                 let (mint_authority, rest) = Self::unpack_pubkey(rest)?;
-                let (freeze_authority, _rest) = Self::unpack_pubkey_option(rest)?;
+                let (freeze_authority, rest) = Self::unpack_pubkey_option(rest)?;
+                let (max_supply, _rest) = Self::unpack_u64_option(rest)?;
                 Self::InitializeMint2 {
                     mint_authority,
                     freeze_authority,
                     decimals,
+                    max_supply,
                 }
             }
             19 => Self::GetAccountDataSize,
@@ -543,6 +986,145 @@ impl<'a> TokenInstruction<'a> {
                 let ui_amount = std::str::from_utf8(rest).map_err(|_| InvalidInstruction)?;
                 Self::UiAmountToAmount { ui_amount }
             }
+            23 => {
+                let (max_supply, _rest) = Self::unpack_u64(rest)?;
+                Self::SetMaxSupply { max_supply }
+            }
+            24 => Self::GetMintableSupply,
+            25 => {
+                let (amounts, _rest) = Self::unpack_amounts(rest)?;
+                Self::TransferBatch { amounts }
+            }
+            26 => {
+                let (amounts, rest) = Self::unpack_amounts(rest)?;
+                let (&decimals, _rest) = rest.split_first().ok_or(InvalidInstruction)?;
+                Self::TransferBatchChecked { amounts, decimals }
+            }
+            27 => {
+                let (amount, rest) = Self::unpack_u64(rest)?;
+                let expiry = rest
+                    .get(..AllowanceExpiry::LEN)
+                    .and_then(|slice| slice.try_into().ok())
+                    .ok_or(InvalidInstruction)
+                    .and_then(|expiry| {
+                        AllowanceExpiry::unpack(expiry).map_err(|_| InvalidInstruction)
+                    })?;
+                Self::ApproveAllowance { amount, expiry }
+            }
+            28 => {
+                let txid = rest
+                    .get(..32)
+                    .and_then(|slice| slice.try_into().ok())
+                    .ok_or(InvalidInstruction)?;
+                let vout = rest
+                    .get(32..36)
+                    .and_then(|slice| slice.try_into().ok())
+                    .map(u32::from_le_bytes)
+                    .ok_or(InvalidInstruction)?;
+                Self::CreatePermitNonce { txid, vout }
+            }
+            29 => {
+                let (owner, rest) = Self::unpack_pubkey(rest)?;
+                let (spender, rest) = Self::unpack_pubkey(rest)?;
+                let (amount, rest) = Self::unpack_u64(rest)?;
+                let (nonce, rest) = Self::unpack_u64(rest)?;
+                let (deadline, rest) = Self::unpack_u64(rest)?;
+                let (&recovery_id, rest) = rest.split_first().ok_or(InvalidInstruction)?;
+                let signature = rest
+                    .get(..64)
+                    .and_then(|slice| slice.try_into().ok())
+                    .ok_or(InvalidInstruction)?;
+                Self::Permit {
+                    owner,
+                    spender,
+                    amount,
+                    nonce,
+                    deadline: deadline as i64,
+                    recovery_id,
+                    signature,
+                }
+            }
+            30 => {
+                let (authority, rest) = Self::unpack_pubkey_option(rest)?;
+                let (program_id, _rest) = Self::unpack_pubkey_option(rest)?;
+                Self::InitializeTransferHook {
+                    authority,
+                    program_id,
+                }
+            }
+            31 => {
+                let (program_id, _rest) = Self::unpack_pubkey_option(rest)?;
+                Self::UpdateTransferHook { program_id }
+            }
+            32 => {
+                let (authority, _rest) = Self::unpack_pubkey_option(rest)?;
+                Self::InitializeNonTransferableMint { authority }
+            }
+            33 => {
+                let (delegate, _rest) = Self::unpack_pubkey(rest)?;
+                Self::InitializePermanentDelegate { delegate }
+            }
+            34 | 35 => {
+                let state = rest
+                    .first()
+                    .and_then(|&state| AccountState::try_from_primitive(state).ok())
+                    .ok_or(InvalidInstruction)?;
+                match tag {
+                    34 => Self::InitializeDefaultAccountState { state },
+                    35 => Self::UpdateDefaultAccountState { state },
+                    _ => unreachable!(),
+                }
+            }
+            36 => {
+                let (rate_authority, rest) = Self::unpack_pubkey_option(rest)?;
+                let (rate, _rest) = Self::unpack_i16(rest)?;
+                Self::InitializeInterestBearingMint {
+                    rate_authority,
+                    rate,
+                }
+            }
+            37 => {
+                let (rate, _rest) = Self::unpack_i16(rest)?;
+                Self::UpdateInterestRate { rate }
+            }
+            38 => {
+                let (threshold, rest) = Self::unpack_u16(rest)?;
+                let (&count, rest) = rest.split_first().ok_or(InvalidInstruction)?;
+                let weights = rest.get(..count as usize).ok_or(InvalidInstruction)?;
+                Self::InitializeWeightedMultisig {
+                    threshold,
+                    weights: weights.to_vec(),
+                }
+            }
+            39 => {
+                let &weight = rest.first().ok_or(InvalidInstruction)?;
+                Self::AddSigner { weight }
+            }
+            40 => Self::RemoveSigner,
+            41 => {
+                let (threshold, _rest) = Self::unpack_u16(rest)?;
+                Self::SetThreshold { threshold }
+            }
+            42 => {
+                let (min_credit, _rest) = Self::unpack_u64(rest)?;
+                Self::InitializeBalanceCheckpoints { min_credit }
+            }
+            43 => Self::InitializeSupplyCheckpoints,
+            44 => {
+                let (slot, _rest) = Self::unpack_u64(rest)?;
+                Self::GetBalanceAt { slot }
+            }
+            45 => Self::EnableRequiredTransferMemos,
+            46 => Self::DisableRequiredTransferMemos,
+            47 => {
+                let (amount, decimals, rest) = Self::unpack_amount_decimals(rest)?;
+                let memo = std::str::from_utf8(rest).map_err(|_| InvalidInstruction)?;
+                Self::TransferWithMemo {
+                    amount,
+                    decimals,
+                    memo,
+                }
+            }
             _ => return Err(TokenError::InvalidInstruction.into()),
         })
     }
@@ -583,7 +1165,14 @@ impl<'a> TokenInstruction<'a> {
                 buf.push(8);
                 buf.extend_from_slice(&amount.to_le_bytes());
             }
-            Self::Revoke => buf.push(5),
+            Self::Revoke { ref delegate } => {
+                buf.push(5);
+                // Nothing follows the tag when revoking the single delegate,
+                // matching the layout used before allowances were added
+                if delegate.is_some() {
+                    Self::pack_pubkey_option(delegate, &mut buf);
+                }
+            }
             Self::SetAuthority {
                 authority_type,
                 ref new_authority,
@@ -627,11 +1216,13 @@ impl<'a> TokenInstruction<'a> {
                 ref mint_authority,
                 ref freeze_authority,
                 decimals,
+                ref max_supply,
             } => {
                 buf.push(18);
                 buf.push(decimals);
                 buf.extend_from_slice(mint_authority.as_ref());
                 Self::pack_pubkey_option(freeze_authority, &mut buf);
+                Self::pack_u64_option(max_supply, &mut buf);
             }
             &Self::GetAccountDataSize => {
                 buf.push(19);
@@ -647,35 +1238,163 @@ impl<'a> TokenInstruction<'a> {
                 buf.push(22);
                 buf.extend_from_slice(ui_amount.as_bytes());
             }
-        };
-        buf
-    }
-
-    fn unpack_pubkey(input: &[u8]) -> Result<(Pubkey, &[u8]), ProgramError> {
-        if input.len() >= 32 {
-            let (key, rest) = input.split_at(32);
-            let pk = Pubkey::from_slice(key);
-            Ok((pk, rest))
-        } else {
-            Err(TokenError::InvalidInstruction.into())
-        }
-    }
-
-    fn unpack_pubkey_option(input: &[u8]) -> Result<(COption<Pubkey>, &[u8]), ProgramError> {
-        match input.split_first() {
-            Option::Some((&0, rest)) => Ok((COption::None, rest)),
-            Option::Some((&1, rest)) if rest.len() >= 32 => {
-                let (key, rest) = rest.split_at(32);
-                let pk = Pubkey::from_slice(key);
-                Ok((COption::Some(pk), rest))
+            &Self::SetMaxSupply { max_supply } => {
+                buf.push(23);
+                buf.extend_from_slice(&max_supply.to_le_bytes());
             }
-            _ => Err(TokenError::InvalidInstruction.into()),
-        }
-    }
-
-    fn pack_pubkey_option(value: &COption<Pubkey>, buf: &mut Vec<u8>) {
-        match *value {
-            COption::Some(ref key) => {
+            &Self::GetMintableSupply => {
+                buf.push(24);
+            }
+            Self::TransferBatch { amounts } => {
+                buf.push(25);
+                Self::pack_amounts(amounts, &mut buf);
+            }
+            &Self::TransferBatchChecked {
+                ref amounts,
+                decimals,
+            } => {
+                buf.push(26);
+                Self::pack_amounts(amounts, &mut buf);
+                buf.push(decimals);
+            }
+            &Self::ApproveAllowance { amount, ref expiry } => {
+                buf.push(27);
+                buf.extend_from_slice(&amount.to_le_bytes());
+                let mut packed_expiry = [0u8; AllowanceExpiry::LEN];
+                expiry.pack(&mut packed_expiry);
+                buf.extend_from_slice(&packed_expiry);
+            }
+            &Self::CreatePermitNonce { ref txid, vout } => {
+                buf.push(28);
+                buf.extend_from_slice(txid);
+                buf.extend_from_slice(&vout.to_le_bytes());
+            }
+            &Self::Permit {
+                ref owner,
+                ref spender,
+                amount,
+                nonce,
+                deadline,
+                recovery_id,
+                ref signature,
+            } => {
+                buf.push(29);
+                buf.extend_from_slice(owner.as_ref());
+                buf.extend_from_slice(spender.as_ref());
+                buf.extend_from_slice(&amount.to_le_bytes());
+                buf.extend_from_slice(&nonce.to_le_bytes());
+                buf.extend_from_slice(&deadline.to_le_bytes());
+                buf.push(recovery_id);
+                buf.extend_from_slice(signature);
+            }
+            Self::InitializeTransferHook {
+                ref authority,
+                ref program_id,
+            } => {
+                buf.push(30);
+                Self::pack_pubkey_option(authority, &mut buf);
+                Self::pack_pubkey_option(program_id, &mut buf);
+            }
+            Self::UpdateTransferHook { ref program_id } => {
+                buf.push(31);
+                Self::pack_pubkey_option(program_id, &mut buf);
+            }
+            Self::InitializeNonTransferableMint { ref authority } => {
+                buf.push(32);
+                Self::pack_pubkey_option(authority, &mut buf);
+            }
+            Self::InitializePermanentDelegate { delegate } => {
+                buf.push(33);
+                buf.extend_from_slice(delegate.as_ref());
+            }
+            &Self::InitializeDefaultAccountState { state } => {
+                buf.push(34);
+                buf.push(state as u8);
+            }
+            &Self::UpdateDefaultAccountState { state } => {
+                buf.push(35);
+                buf.push(state as u8);
+            }
+            Self::InitializeInterestBearingMint {
+                ref rate_authority,
+                rate,
+            } => {
+                buf.push(36);
+                Self::pack_pubkey_option(rate_authority, &mut buf);
+                buf.extend_from_slice(&rate.to_le_bytes());
+            }
+            &Self::UpdateInterestRate { rate } => {
+                buf.push(37);
+                buf.extend_from_slice(&rate.to_le_bytes());
+            }
+            Self::InitializeWeightedMultisig {
+                threshold,
+                ref weights,
+            } => {
+                buf.push(38);
+                buf.extend_from_slice(&threshold.to_le_bytes());
+                buf.push(weights.len() as u8);
+                buf.extend_from_slice(weights);
+            }
+            &Self::AddSigner { weight } => {
+                buf.push(39);
+                buf.push(weight);
+            }
+            Self::RemoveSigner => buf.push(40),
+            &Self::SetThreshold { threshold } => {
+                buf.push(41);
+                buf.extend_from_slice(&threshold.to_le_bytes());
+            }
+            &Self::InitializeBalanceCheckpoints { min_credit } => {
+                buf.push(42);
+                buf.extend_from_slice(&min_credit.to_le_bytes());
+            }
+            Self::InitializeSupplyCheckpoints => buf.push(43),
+            &Self::GetBalanceAt { slot } => {
+                buf.push(44);
+                buf.extend_from_slice(&slot.to_le_bytes());
+            }
+            Self::EnableRequiredTransferMemos => buf.push(45),
+            Self::DisableRequiredTransferMemos => buf.push(46),
+            Self::TransferWithMemo {
+                amount,
+                decimals,
+                memo,
+            } => {
+                buf.push(47);
+                buf.extend_from_slice(&amount.to_le_bytes());
+                buf.push(*decimals);
+                buf.extend_from_slice(memo.as_bytes());
+            }
+        };
+        buf
+    }
+
+    fn unpack_pubkey(input: &[u8]) -> Result<(Pubkey, &[u8]), ProgramError> {
+        if input.len() >= 32 {
+            let (key, rest) = input.split_at(32);
+            let pk = Pubkey::from_slice(key);
+            Ok((pk, rest))
+        } else {
+            Err(TokenError::InvalidInstruction.into())
+        }
+    }
+
+    fn unpack_pubkey_option(input: &[u8]) -> Result<(COption<Pubkey>, &[u8]), ProgramError> {
+        match input.split_first() {
+            Option::Some((&0, rest)) => Ok((COption::None, rest)),
+            Option::Some((&1, rest)) if rest.len() >= 32 => {
+                let (key, rest) = rest.split_at(32);
+                let pk = Pubkey::from_slice(key);
+                Ok((COption::Some(pk), rest))
+            }
+            _ => Err(TokenError::InvalidInstruction.into()),
+        }
+    }
+
+    fn pack_pubkey_option(value: &COption<Pubkey>, buf: &mut Vec<u8>) {
+        match *value {
+            COption::Some(ref key) => {
                 buf.push(1);
                 buf.extend_from_slice(&key.serialize());
             }
@@ -683,6 +1402,47 @@ impl<'a> TokenInstruction<'a> {
         }
     }
 
+    /// A missing option is read as `None`, so data packed before the option
+    /// was added still unpacks
+    fn unpack_u64_option(input: &[u8]) -> Result<(COption<u64>, &[u8]), ProgramError> {
+        match input.split_first() {
+            Option::None => Ok((COption::None, input)),
+            Option::Some((&0, rest)) => Ok((COption::None, rest)),
+            Option::Some((&1, rest)) => {
+                let (value, rest) = Self::unpack_u64(rest)?;
+                Ok((COption::Some(value), rest))
+            }
+            _ => Err(TokenError::InvalidInstruction.into()),
+        }
+    }
+
+    /// `None` is packed as nothing at all, matching the layout used before
+    /// the option was added
+    fn pack_u64_option(value: &COption<u64>, buf: &mut Vec<u8>) {
+        if let COption::Some(value) = *value {
+            buf.push(1);
+            buf.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    fn unpack_u16(input: &[u8]) -> Result<(u16, &[u8]), ProgramError> {
+        let value = input
+            .get(..2)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u16::from_le_bytes)
+            .ok_or(TokenError::InvalidInstruction)?;
+        Ok((value, &input[2..]))
+    }
+
+    fn unpack_i16(input: &[u8]) -> Result<(i16, &[u8]), ProgramError> {
+        let value = input
+            .get(..2)
+            .and_then(|slice| slice.try_into().ok())
+            .map(i16::from_le_bytes)
+            .ok_or(TokenError::InvalidInstruction)?;
+        Ok((value, &input[2..]))
+    }
+
     fn unpack_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
         let value = input
             .get(..U64_BYTES)
@@ -692,6 +1452,25 @@ impl<'a> TokenInstruction<'a> {
         Ok((value, &input[U64_BYTES..]))
     }
 
+    /// Amounts are prefixed by their count as a `u8`
+    fn unpack_amounts(input: &[u8]) -> Result<(Vec<u64>, &[u8]), ProgramError> {
+        let (&count, mut rest) = input.split_first().ok_or(TokenError::InvalidInstruction)?;
+        let mut amounts = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let (amount, next) = Self::unpack_u64(rest)?;
+            amounts.push(amount);
+            rest = next;
+        }
+        Ok((amounts, rest))
+    }
+
+    fn pack_amounts(amounts: &[u64], buf: &mut Vec<u8>) {
+        buf.push(amounts.len() as u8);
+        for amount in amounts {
+            buf.extend_from_slice(&amount.to_le_bytes());
+        }
+    }
+
     fn unpack_amount_decimals(input: &[u8]) -> Result<(u64, u8, &[u8]), ProgramError> {
         let (amount, rest) = Self::unpack_u64(input)?;
         let (&decimals, rest) = rest.split_first().ok_or(TokenError::InvalidInstruction)?;
@@ -711,6 +1490,12 @@ pub enum AuthorityType {
     AccountOwner,
     /// Authority to close a token account
     CloseAccount,
+    /// Authority over the non-transferable mode of a mint
+    NonTransferable,
+    /// Permanent delegate of a mint
+    PermanentDelegate,
+    /// Authority to update the rate of an interest-bearing mint
+    InterestRate,
 }
 
 impl AuthorityType {
@@ -720,6 +1505,9 @@ impl AuthorityType {
             AuthorityType::FreezeAccount => 1,
             AuthorityType::AccountOwner => 2,
             AuthorityType::CloseAccount => 3,
+            AuthorityType::NonTransferable => 4,
+            AuthorityType::PermanentDelegate => 5,
+            AuthorityType::InterestRate => 6,
         }
     }
 
@@ -729,6 +1517,9 @@ impl AuthorityType {
             1 => Ok(AuthorityType::FreezeAccount),
             2 => Ok(AuthorityType::AccountOwner),
             3 => Ok(AuthorityType::CloseAccount),
+            4 => Ok(AuthorityType::NonTransferable),
+            5 => Ok(AuthorityType::PermanentDelegate),
+            6 => Ok(AuthorityType::InterestRate),
             _ => Err(TokenError::InvalidInstruction.into()),
         }
     }
@@ -767,6 +1558,25 @@ pub fn initialize_mint2(
     mint_authority_pubkey: &Pubkey,
     freeze_authority_pubkey: Option<&Pubkey>,
     decimals: u8,
+) -> Result<Instruction, ProgramError> {
+    initialize_mint2_with_max_supply(
+        token_program_id,
+        mint_pubkey,
+        mint_authority_pubkey,
+        freeze_authority_pubkey,
+        decimals,
+        None,
+    )
+}
+
+/// Creates a `InitializeMint2` instruction that caps the supply of the mint.
+pub fn initialize_mint2_with_max_supply(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    mint_authority_pubkey: &Pubkey,
+    freeze_authority_pubkey: Option<&Pubkey>,
+    decimals: u8,
+    max_supply: Option<u64>,
 ) -> Result<Instruction, ProgramError> {
     check_program_account(token_program_id)?;
     let freeze_authority = freeze_authority_pubkey.cloned().into();
@@ -774,6 +1584,7 @@ pub fn initialize_mint2(
         mint_authority: *mint_authority_pubkey,
         freeze_authority,
         decimals,
+        max_supply: max_supply.into(),
     }
     .pack();
 
@@ -956,7 +1767,41 @@ pub fn revoke(
     signer_pubkeys: &[&Pubkey],
 ) -> Result<Instruction, ProgramError> {
     check_program_account(token_program_id)?;
-    let data = TokenInstruction::Revoke.pack();
+    let data = TokenInstruction::Revoke {
+        delegate: COption::None,
+    }
+    .pack();
+
+    let mut accounts = Vec::with_capacity(2 + signer_pubkeys.len());
+    accounts.push(AccountMeta::new(*source_pubkey, false));
+    accounts.push(AccountMeta::new_readonly(
+        *owner_pubkey,
+        signer_pubkeys.is_empty(),
+    ));
+    for signer_pubkey in signer_pubkeys.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, true));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `Revoke` instruction targeting a specific delegate.
+pub fn revoke_delegate(
+    token_program_id: &Pubkey,
+    source_pubkey: &Pubkey,
+    delegate_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::Revoke {
+        delegate: COption::Some(*delegate_pubkey),
+    }
+    .pack();
 
     let mut accounts = Vec::with_capacity(2 + signer_pubkeys.len());
     accounts.push(AccountMeta::new(*source_pubkey, false));
@@ -1173,7 +2018,7 @@ pub fn transfer_checked(
 
     let mut accounts = Vec::with_capacity(4 + signer_pubkeys.len());
     accounts.push(AccountMeta::new(*source_pubkey, false));
-    accounts.push(AccountMeta::new_readonly(*mint_pubkey, false));
+    accounts.push(AccountMeta::new(*mint_pubkey, false));
     accounts.push(AccountMeta::new(*destination_pubkey, false));
     accounts.push(AccountMeta::new_readonly(
         *authority_pubkey,
@@ -1343,90 +2188,772 @@ pub fn ui_amount_to_amount(
     })
 }
 
-/// Utility function that checks index is between `MIN_SIGNERS` and
-/// `MAX_SIGNERS`
-pub fn is_valid_signer_index(index: usize) -> bool {
-    (MIN_SIGNERS..=MAX_SIGNERS).contains(&index)
-}
-
-#[cfg(test)]
-mod test {
-    use {super::*, proptest::prelude::*};
+/// Creates a `SetMaxSupply` instruction.
+pub fn set_max_supply(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    max_supply: u64,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::SetMaxSupply { max_supply }.pack();
 
-    #[test]
-    fn test_instruction_packing() {
-        let check = TokenInstruction::InitializeMint {
-            decimals: 2,
-            mint_authority: Pubkey::from_slice(&[1u8; 32]),
-            freeze_authority: COption::None,
-        };
-        let packed = check.pack();
-        let mut expect = Vec::from([0u8, 2]);
-        expect.extend_from_slice(&[1u8; 32]);
-        expect.extend_from_slice(&[0]);
-        assert_eq!(packed, expect);
-        let unpacked = TokenInstruction::unpack(&expect).unwrap();
-        assert_eq!(unpacked, check);
+    let mut accounts = Vec::with_capacity(2 + signer_pubkeys.len());
+    accounts.push(AccountMeta::new(*mint_pubkey, false));
+    accounts.push(AccountMeta::new_readonly(
+        *owner_pubkey,
+        signer_pubkeys.is_empty(),
+    ));
+    for signer_pubkey in signer_pubkeys.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, true));
+    }
 
-        let check = TokenInstruction::InitializeMint {
-            decimals: 2,
-            mint_authority: Pubkey::from_slice(&[2u8; 32]),
-            freeze_authority: COption::Some(Pubkey::from_slice(&[3u8; 32])),
-        };
-        let packed = check.pack();
-        let mut expect = vec![0u8, 2];
-        expect.extend_from_slice(&[2u8; 32]);
-        expect.extend_from_slice(&[1]);
-        expect.extend_from_slice(&[3u8; 32]);
-        assert_eq!(packed, expect);
-        let unpacked = TokenInstruction::unpack(&expect).unwrap();
-        assert_eq!(unpacked, check);
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
 
-        let check = TokenInstruction::InitializeAccount;
-        let packed = check.pack();
-        let expect = Vec::from([1u8]);
-        assert_eq!(packed, expect);
-        let unpacked = TokenInstruction::unpack(&expect).unwrap();
-        assert_eq!(unpacked, check);
+/// Creates a `GetMintableSupply` instruction
+pub fn get_mintable_supply(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
 
-        let check = TokenInstruction::InitializeMultisig { m: 1 };
-        let packed = check.pack();
-        let expect = Vec::from([2u8, 1]);
-        assert_eq!(packed, expect);
-        let unpacked = TokenInstruction::unpack(&expect).unwrap();
-        assert_eq!(unpacked, check);
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts: vec![AccountMeta::new_readonly(*mint_pubkey, false)],
+        data: TokenInstruction::GetMintableSupply.pack(),
+    })
+}
 
-        let check = TokenInstruction::Transfer { amount: 1 };
-        let packed = check.pack();
-        let expect = Vec::from([3u8, 1, 0, 0, 0, 0, 0, 0, 0]);
-        assert_eq!(packed, expect);
-        let unpacked = TokenInstruction::unpack(&expect).unwrap();
-        assert_eq!(unpacked, check);
+/// Creates a `TransferBatch` instruction.
+pub fn transfer_batch(
+    token_program_id: &Pubkey,
+    source_pubkey: &Pubkey,
+    destination_pubkeys: &[&Pubkey],
+    authority_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    amounts: &[u64],
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    check_batch_len(destination_pubkeys, amounts)?;
+    let data = TokenInstruction::TransferBatch {
+        amounts: amounts.to_vec(),
+    }
+    .pack();
 
-        let check = TokenInstruction::Approve { amount: 1 };
-        let packed = check.pack();
-        let expect = Vec::from([4u8, 1, 0, 0, 0, 0, 0, 0, 0]);
-        assert_eq!(packed, expect);
-        let unpacked = TokenInstruction::unpack(&expect).unwrap();
-        assert_eq!(unpacked, check);
+    let mut accounts = Vec::with_capacity(2 + destination_pubkeys.len() + signer_pubkeys.len());
+    accounts.push(AccountMeta::new(*source_pubkey, false));
+    for destination_pubkey in destination_pubkeys.iter() {
+        accounts.push(AccountMeta::new(**destination_pubkey, false));
+    }
+    accounts.push(AccountMeta::new_readonly(
+        *authority_pubkey,
+        signer_pubkeys.is_empty(),
+    ));
+    for signer_pubkey in signer_pubkeys.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, true));
+    }
 
-        let check = TokenInstruction::Revoke;
-        let packed = check.pack();
-        let expect = Vec::from([5u8]);
-        assert_eq!(packed, expect);
-        let unpacked = TokenInstruction::unpack(&expect).unwrap();
-        assert_eq!(unpacked, check);
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
 
-        let check = TokenInstruction::SetAuthority {
-            authority_type: AuthorityType::FreezeAccount,
-            new_authority: COption::Some(Pubkey::from_slice(&[4u8; 32])),
-        };
-        let packed = check.pack();
-        let mut expect = Vec::from([6u8, 1]);
-        expect.extend_from_slice(&[1]);
-        expect.extend_from_slice(&[4u8; 32]);
-        assert_eq!(packed, expect);
-        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+/// Creates a `TransferBatchChecked` instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_batch_checked(
+    token_program_id: &Pubkey,
+    source_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    destination_pubkeys: &[&Pubkey],
+    authority_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    amounts: &[u64],
+    decimals: u8,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    check_batch_len(destination_pubkeys, amounts)?;
+    let data = TokenInstruction::TransferBatchChecked {
+        amounts: amounts.to_vec(),
+        decimals,
+    }
+    .pack();
+
+    let mut accounts = Vec::with_capacity(3 + destination_pubkeys.len() + signer_pubkeys.len());
+    accounts.push(AccountMeta::new(*source_pubkey, false));
+    accounts.push(AccountMeta::new(*mint_pubkey, false));
+    for destination_pubkey in destination_pubkeys.iter() {
+        accounts.push(AccountMeta::new(**destination_pubkey, false));
+    }
+    accounts.push(AccountMeta::new_readonly(
+        *authority_pubkey,
+        signer_pubkeys.is_empty(),
+    ));
+    for signer_pubkey in signer_pubkeys.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, true));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an `ApproveAllowance` instruction.
+pub fn approve_allowance(
+    token_program_id: &Pubkey,
+    source_pubkey: &Pubkey,
+    delegate_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    amount: u64,
+    expiry: AllowanceExpiry,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::ApproveAllowance { amount, expiry }.pack();
+
+    let mut accounts = Vec::with_capacity(3 + signer_pubkeys.len());
+    accounts.push(AccountMeta::new(*source_pubkey, false));
+    accounts.push(AccountMeta::new_readonly(*delegate_pubkey, false));
+    accounts.push(AccountMeta::new_readonly(
+        *owner_pubkey,
+        signer_pubkeys.is_empty(),
+    ));
+    for signer_pubkey in signer_pubkeys.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, true));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `CreatePermitNonce` instruction.
+pub fn create_permit_nonce(
+    token_program_id: &Pubkey,
+    owner_pubkey: &Pubkey,
+    txid: [u8; 32],
+    vout: u32,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let (nonce_pubkey, _) = get_permit_nonce_address(owner_pubkey, token_program_id);
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts: vec![
+            AccountMeta::new(nonce_pubkey, false),
+            AccountMeta::new_readonly(*owner_pubkey, false),
+            AccountMeta::new_readonly(Pubkey::system_program(), false),
+        ],
+        data: TokenInstruction::CreatePermitNonce { txid, vout }.pack(),
+    })
+}
+
+/// Creates a `Permit` instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn permit(
+    token_program_id: &Pubkey,
+    source_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+    spender_pubkey: &Pubkey,
+    amount: u64,
+    nonce: u64,
+    deadline: i64,
+    recovery_id: u8,
+    signature: [u8; 64],
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let (nonce_pubkey, _) = get_permit_nonce_address(owner_pubkey, token_program_id);
+    let data = TokenInstruction::Permit {
+        owner: *owner_pubkey,
+        spender: *spender_pubkey,
+        amount,
+        nonce,
+        deadline,
+        recovery_id,
+        signature,
+    }
+    .pack();
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts: vec![
+            AccountMeta::new(*source_pubkey, false),
+            AccountMeta::new(nonce_pubkey, false),
+        ],
+        data,
+    })
+}
+
+/// Creates an `InitializeTransferHook` instruction.
+pub fn initialize_transfer_hook(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    authority_pubkey: Option<&Pubkey>,
+    hook_program_id: Option<&Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::InitializeTransferHook {
+        authority: authority_pubkey.cloned().into(),
+        program_id: hook_program_id.cloned().into(),
+    }
+    .pack();
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts: vec![AccountMeta::new(*mint_pubkey, false)],
+        data,
+    })
+}
+
+/// Creates an `InitializeNonTransferableMint` instruction.
+pub fn initialize_non_transferable_mint(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    authority_pubkey: Option<&Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::InitializeNonTransferableMint {
+        authority: authority_pubkey.cloned().into(),
+    }
+    .pack();
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts: vec![AccountMeta::new(*mint_pubkey, false)],
+        data,
+    })
+}
+
+/// Creates an `InitializePermanentDelegate` instruction.
+pub fn initialize_permanent_delegate(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    delegate_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::InitializePermanentDelegate {
+        delegate: *delegate_pubkey,
+    }
+    .pack();
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts: vec![AccountMeta::new(*mint_pubkey, false)],
+        data,
+    })
+}
+
+/// Creates an `InitializeDefaultAccountState` instruction.
+pub fn initialize_default_account_state(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    state: AccountState,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts: vec![AccountMeta::new(*mint_pubkey, false)],
+        data: TokenInstruction::InitializeDefaultAccountState { state }.pack(),
+    })
+}
+
+/// Creates an `UpdateDefaultAccountState` instruction.
+pub fn update_default_account_state(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    freeze_authority_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    state: AccountState,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::UpdateDefaultAccountState { state }.pack();
+
+    let mut accounts = Vec::with_capacity(2 + signer_pubkeys.len());
+    accounts.push(AccountMeta::new(*mint_pubkey, false));
+    accounts.push(AccountMeta::new_readonly(
+        *freeze_authority_pubkey,
+        signer_pubkeys.is_empty(),
+    ));
+    for signer_pubkey in signer_pubkeys.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, true));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an `InitializeInterestBearingMint` instruction.
+pub fn initialize_interest_bearing_mint(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    rate_authority_pubkey: Option<&Pubkey>,
+    rate: i16,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::InitializeInterestBearingMint {
+        rate_authority: rate_authority_pubkey.cloned().into(),
+        rate,
+    }
+    .pack();
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts: vec![AccountMeta::new(*mint_pubkey, false)],
+        data,
+    })
+}
+
+/// Creates an `UpdateInterestRate` instruction.
+pub fn update_interest_rate(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    rate_authority_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    rate: i16,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::UpdateInterestRate { rate }.pack();
+
+    let mut accounts = Vec::with_capacity(2 + signer_pubkeys.len());
+    accounts.push(AccountMeta::new(*mint_pubkey, false));
+    accounts.push(AccountMeta::new_readonly(
+        *rate_authority_pubkey,
+        signer_pubkeys.is_empty(),
+    ));
+    for signer_pubkey in signer_pubkeys.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, true));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an `InitializeWeightedMultisig` instruction.
+pub fn initialize_weighted_multisig(
+    token_program_id: &Pubkey,
+    multisig_pubkey: &Pubkey,
+    signers: &[(&Pubkey, u8)],
+    threshold: u16,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    if !(MIN_SIGNERS..=MAX_WEIGHTED_SIGNERS).contains(&signers.len())
+        || threshold == 0
+        || threshold > signers.iter().map(|(_, weight)| *weight as u16).sum()
+    {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let data = TokenInstruction::InitializeWeightedMultisig {
+        threshold,
+        weights: signers.iter().map(|(_, weight)| *weight).collect(),
+    }
+    .pack();
+
+    let mut accounts = Vec::with_capacity(1 + signers.len());
+    accounts.push(AccountMeta::new(*multisig_pubkey, false));
+    for (signer_pubkey, _) in signers.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, false));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an `AddSigner` instruction.
+pub fn add_signer(
+    token_program_id: &Pubkey,
+    multisig_pubkey: &Pubkey,
+    new_signer_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    weight: u8,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::AddSigner { weight }.pack();
+
+    let mut accounts = Vec::with_capacity(2 + signer_pubkeys.len());
+    accounts.push(AccountMeta::new(*multisig_pubkey, false));
+    accounts.push(AccountMeta::new_readonly(*new_signer_pubkey, false));
+    for signer_pubkey in signer_pubkeys.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, true));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `RemoveSigner` instruction.
+pub fn remove_signer(
+    token_program_id: &Pubkey,
+    multisig_pubkey: &Pubkey,
+    removed_signer_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::RemoveSigner.pack();
+
+    let mut accounts = Vec::with_capacity(2 + signer_pubkeys.len());
+    accounts.push(AccountMeta::new(*multisig_pubkey, false));
+    accounts.push(AccountMeta::new_readonly(*removed_signer_pubkey, false));
+    for signer_pubkey in signer_pubkeys.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, true));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `SetThreshold` instruction.
+pub fn set_threshold(
+    token_program_id: &Pubkey,
+    multisig_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    threshold: u16,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::SetThreshold { threshold }.pack();
+
+    let mut accounts = Vec::with_capacity(1 + signer_pubkeys.len());
+    accounts.push(AccountMeta::new(*multisig_pubkey, false));
+    for signer_pubkey in signer_pubkeys.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, true));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an `InitializeBalanceCheckpoints` instruction.
+pub fn initialize_balance_checkpoints(
+    token_program_id: &Pubkey,
+    account_pubkey: &Pubkey,
+    min_credit: u64,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts: vec![AccountMeta::new(*account_pubkey, false)],
+        data: TokenInstruction::InitializeBalanceCheckpoints { min_credit }.pack(),
+    })
+}
+
+/// Creates an `InitializeSupplyCheckpoints` instruction.
+pub fn initialize_supply_checkpoints(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts: vec![AccountMeta::new(*mint_pubkey, false)],
+        data: TokenInstruction::InitializeSupplyCheckpoints.pack(),
+    })
+}
+
+/// Creates a `GetBalanceAt` instruction.
+pub fn get_balance_at(
+    token_program_id: &Pubkey,
+    account_pubkey: &Pubkey,
+    slot: u64,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts: vec![AccountMeta::new_readonly(*account_pubkey, false)],
+        data: TokenInstruction::GetBalanceAt { slot }.pack(),
+    })
+}
+
+/// Creates an `EnableRequiredTransferMemos` instruction.
+pub fn enable_required_transfer_memos(
+    token_program_id: &Pubkey,
+    account_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+) -> Result<Instruction, ProgramError> {
+    required_transfer_memos(
+        token_program_id,
+        account_pubkey,
+        owner_pubkey,
+        signer_pubkeys,
+        TokenInstruction::EnableRequiredTransferMemos,
+    )
+}
+
+/// Creates a `DisableRequiredTransferMemos` instruction.
+pub fn disable_required_transfer_memos(
+    token_program_id: &Pubkey,
+    account_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+) -> Result<Instruction, ProgramError> {
+    required_transfer_memos(
+        token_program_id,
+        account_pubkey,
+        owner_pubkey,
+        signer_pubkeys,
+        TokenInstruction::DisableRequiredTransferMemos,
+    )
+}
+
+fn required_transfer_memos(
+    token_program_id: &Pubkey,
+    account_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    instruction: TokenInstruction,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = instruction.pack();
+
+    let mut accounts = Vec::with_capacity(2 + signer_pubkeys.len());
+    accounts.push(AccountMeta::new(*account_pubkey, false));
+    accounts.push(AccountMeta::new_readonly(
+        *owner_pubkey,
+        signer_pubkeys.is_empty(),
+    ));
+    for signer_pubkey in signer_pubkeys.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, true));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `TransferWithMemo` instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_with_memo(
+    token_program_id: &Pubkey,
+    source_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    amount: u64,
+    decimals: u8,
+    memo: &str,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::TransferWithMemo {
+        amount,
+        decimals,
+        memo,
+    }
+    .pack();
+
+    let mut accounts = Vec::with_capacity(4 + signer_pubkeys.len());
+    accounts.push(AccountMeta::new(*source_pubkey, false));
+    accounts.push(AccountMeta::new(*mint_pubkey, false));
+    accounts.push(AccountMeta::new(*destination_pubkey, false));
+    accounts.push(AccountMeta::new_readonly(
+        *authority_pubkey,
+        signer_pubkeys.is_empty(),
+    ));
+    for signer_pubkey in signer_pubkeys.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, true));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an `UpdateTransferHook` instruction.
+pub fn update_transfer_hook(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    hook_program_id: Option<&Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(token_program_id)?;
+    let data = TokenInstruction::UpdateTransferHook {
+        program_id: hook_program_id.cloned().into(),
+    }
+    .pack();
+
+    let mut accounts = Vec::with_capacity(2 + signer_pubkeys.len());
+    accounts.push(AccountMeta::new(*mint_pubkey, false));
+    accounts.push(AccountMeta::new_readonly(
+        *authority_pubkey,
+        signer_pubkeys.is_empty(),
+    ));
+    for signer_pubkey in signer_pubkeys.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, true));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Adds the accounts a transfer hook needs to a `TransferChecked`
+/// instruction: the hook program, its validation account and the accounts
+/// listed there, as read by the client from the validation account.
+pub fn add_transfer_hook_accounts(
+    instruction: &mut Instruction,
+    hook_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    extra_account_metas: &[ExtraAccountMeta],
+) {
+    instruction
+        .accounts
+        .push(AccountMeta::new_readonly(*hook_program_id, false));
+    instruction.accounts.push(AccountMeta::new_readonly(
+        get_extra_account_metas_address(mint_pubkey, hook_program_id),
+        false,
+    ));
+    instruction
+        .accounts
+        .extend(extra_account_metas.iter().map(AccountMeta::from));
+}
+
+/// A batch needs one amount per destination, and its length must fit the
+/// `u8` count prefix
+fn check_batch_len(destination_pubkeys: &[&Pubkey], amounts: &[u64]) -> Result<(), ProgramError> {
+    if amounts.is_empty()
+        || amounts.len() > u8::MAX as usize
+        || destination_pubkeys.len() != amounts.len()
+    {
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok(())
+}
+
+/// Utility function that checks index is between `MIN_SIGNERS` and
+/// `MAX_SIGNERS`
+pub fn is_valid_signer_index(index: usize) -> bool {
+    (MIN_SIGNERS..=MAX_SIGNERS).contains(&index)
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, proptest::prelude::*};
+
+    #[test]
+    fn test_instruction_packing() {
+        let check = TokenInstruction::InitializeMint {
+            decimals: 2,
+            mint_authority: Pubkey::from_slice(&[1u8; 32]),
+            freeze_authority: COption::None,
+        };
+        let packed = check.pack();
+        let mut expect = Vec::from([0u8, 2]);
+        expect.extend_from_slice(&[1u8; 32]);
+        expect.extend_from_slice(&[0]);
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::InitializeMint {
+            decimals: 2,
+            mint_authority: Pubkey::from_slice(&[2u8; 32]),
+            freeze_authority: COption::Some(Pubkey::from_slice(&[3u8; 32])),
+        };
+        let packed = check.pack();
+        let mut expect = vec![0u8, 2];
+        expect.extend_from_slice(&[2u8; 32]);
+        expect.extend_from_slice(&[1]);
+        expect.extend_from_slice(&[3u8; 32]);
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::InitializeAccount;
+        let packed = check.pack();
+        let expect = Vec::from([1u8]);
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::InitializeMultisig { m: 1 };
+        let packed = check.pack();
+        let expect = Vec::from([2u8, 1]);
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::Transfer { amount: 1 };
+        let packed = check.pack();
+        let expect = Vec::from([3u8, 1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::Approve { amount: 1 };
+        let packed = check.pack();
+        let expect = Vec::from([4u8, 1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::Revoke {
+            delegate: COption::None,
+        };
+        let packed = check.pack();
+        let expect = Vec::from([5u8]);
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::Revoke {
+            delegate: COption::Some(Pubkey::from_slice(&[3u8; 32])),
+        };
+        let packed = check.pack();
+        let mut expect = Vec::from([5u8, 1]);
+        expect.extend_from_slice(&[3u8; 32]);
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::SetAuthority {
+            authority_type: AuthorityType::FreezeAccount,
+            new_authority: COption::Some(Pubkey::from_slice(&[4u8; 32])),
+        };
+        let packed = check.pack();
+        let mut expect = Vec::from([6u8, 1]);
+        expect.extend_from_slice(&[1]);
+        expect.extend_from_slice(&[4u8; 32]);
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
         assert_eq!(unpacked, check);
 
         let check = TokenInstruction::MintTo { amount: 1 };
@@ -1528,6 +3055,7 @@ mod test {
             decimals: 2,
             mint_authority: Pubkey::from_slice(&[1u8; 32]),
             freeze_authority: COption::None,
+            max_supply: COption::None,
         };
         let packed = check.pack();
         let mut expect = Vec::from([18u8, 2]);
@@ -1541,6 +3069,7 @@ mod test {
             decimals: 2,
             mint_authority: Pubkey::from_slice(&[2u8; 32]),
             freeze_authority: COption::Some(Pubkey::from_slice(&[3u8; 32])),
+            max_supply: COption::None,
         };
         let packed = check.pack();
         let mut expect = vec![18u8, 2];
@@ -1551,6 +3080,21 @@ mod test {
         let unpacked = TokenInstruction::unpack(&expect).unwrap();
         assert_eq!(unpacked, check);
 
+        let check = TokenInstruction::InitializeMint2 {
+            decimals: 2,
+            mint_authority: Pubkey::from_slice(&[2u8; 32]),
+            freeze_authority: COption::None,
+            max_supply: COption::Some(1_000),
+        };
+        let packed = check.pack();
+        let mut expect = vec![18u8, 2];
+        expect.extend_from_slice(&[2u8; 32]);
+        expect.extend_from_slice(&[0]);
+        expect.extend_from_slice(&[1, 232, 3, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
         let check = TokenInstruction::GetAccountDataSize;
         let packed = check.pack();
         let expect = vec![19u8];
@@ -1578,6 +3122,267 @@ mod test {
         assert_eq!(packed, expect);
         let unpacked = TokenInstruction::unpack(&expect).unwrap();
         assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::SetMaxSupply { max_supply: 42 };
+        let packed = check.pack();
+        let expect = vec![23u8, 42, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::GetMintableSupply;
+        let packed = check.pack();
+        let expect = vec![24u8];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::TransferBatch {
+            amounts: vec![1, 2],
+        };
+        let packed = check.pack();
+        let expect = vec![25u8, 2, 1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::TransferBatchChecked {
+            amounts: vec![1],
+            decimals: 2,
+        };
+        let packed = check.pack();
+        let expect = vec![26u8, 1, 1, 0, 0, 0, 0, 0, 0, 0, 2];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::ApproveAllowance {
+            amount: 1,
+            expiry: AllowanceExpiry::Slot(2),
+        };
+        let packed = check.pack();
+        let expect = vec![27u8, 1, 0, 0, 0, 0, 0, 0, 0, 1, 2, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::CreatePermitNonce {
+            txid: [1u8; 32],
+            vout: 2,
+        };
+        let packed = check.pack();
+        let mut expect = vec![28u8];
+        expect.extend_from_slice(&[1u8; 32]);
+        expect.extend_from_slice(&[2, 0, 0, 0]);
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::Permit {
+            owner: Pubkey::from_slice(&[1u8; 32]),
+            spender: Pubkey::from_slice(&[2u8; 32]),
+            amount: 3,
+            nonce: 4,
+            deadline: -1,
+            recovery_id: 1,
+            signature: [5u8; 64],
+        };
+        let packed = check.pack();
+        let mut expect = vec![29u8];
+        expect.extend_from_slice(&[1u8; 32]);
+        expect.extend_from_slice(&[2u8; 32]);
+        expect.extend_from_slice(&[3, 0, 0, 0, 0, 0, 0, 0]);
+        expect.extend_from_slice(&[4, 0, 0, 0, 0, 0, 0, 0]);
+        expect.extend_from_slice(&[255u8; 8]);
+        expect.push(1);
+        expect.extend_from_slice(&[5u8; 64]);
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::InitializeTransferHook {
+            authority: COption::None,
+            program_id: COption::Some(Pubkey::from_slice(&[6u8; 32])),
+        };
+        let packed = check.pack();
+        let mut expect = vec![30u8, 0, 1];
+        expect.extend_from_slice(&[6u8; 32]);
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::UpdateTransferHook {
+            program_id: COption::None,
+        };
+        let packed = check.pack();
+        let expect = vec![31u8, 0];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::InitializeNonTransferableMint {
+            authority: COption::None,
+        };
+        let packed = check.pack();
+        let expect = vec![32u8, 0];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::InitializePermanentDelegate {
+            delegate: Pubkey::from_slice(&[7u8; 32]),
+        };
+        let packed = check.pack();
+        let mut expect = vec![33u8];
+        expect.extend_from_slice(&[7u8; 32]);
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::SetAuthority {
+            authority_type: AuthorityType::PermanentDelegate,
+            new_authority: COption::None,
+        };
+        let packed = check.pack();
+        let expect = vec![6u8, 5, 0];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::InitializeDefaultAccountState {
+            state: AccountState::Frozen,
+        };
+        let packed = check.pack();
+        let expect = vec![34u8, 2];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::UpdateDefaultAccountState {
+            state: AccountState::Initialized,
+        };
+        let packed = check.pack();
+        let expect = vec![35u8, 1];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::InitializeInterestBearingMint {
+            rate_authority: COption::Some(Pubkey::from_slice(&[8u8; 32])),
+            rate: -250,
+        };
+        let packed = check.pack();
+        let mut expect = vec![36u8, 1];
+        expect.extend_from_slice(&[8u8; 32]);
+        expect.extend_from_slice(&(-250i16).to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::UpdateInterestRate { rate: 500 };
+        let packed = check.pack();
+        let expect = vec![37u8, 244, 1];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::SetAuthority {
+            authority_type: AuthorityType::InterestRate,
+            new_authority: COption::None,
+        };
+        let packed = check.pack();
+        let expect = vec![6u8, 6, 0];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::InitializeWeightedMultisig {
+            threshold: 300,
+            weights: vec![200, 100, 1],
+        };
+        let packed = check.pack();
+        let expect = vec![38u8, 44, 1, 3, 200, 100, 1];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+        assert_eq!(
+            TokenInstruction::unpack(&[38u8, 44, 1, 3, 200, 100]),
+            Err(TokenError::InvalidInstruction.into())
+        );
+
+        let check = TokenInstruction::AddSigner { weight: 5 };
+        let packed = check.pack();
+        let expect = vec![39u8, 5];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::RemoveSigner;
+        let packed = check.pack();
+        let expect = vec![40u8];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::SetThreshold { threshold: 2 };
+        let packed = check.pack();
+        let expect = vec![41u8, 2, 0];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::InitializeBalanceCheckpoints { min_credit: 100 };
+        let packed = check.pack();
+        let mut expect = vec![42u8];
+        expect.extend_from_slice(&100u64.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::InitializeSupplyCheckpoints;
+        let packed = check.pack();
+        let expect = vec![43u8];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::GetBalanceAt { slot: 42 };
+        let packed = check.pack();
+        let mut expect = vec![44u8];
+        expect.extend_from_slice(&42u64.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::EnableRequiredTransferMemos;
+        let packed = check.pack();
+        let expect = vec![45u8];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::DisableRequiredTransferMemos;
+        let packed = check.pack();
+        let expect = vec![46u8];
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = TokenInstruction::TransferWithMemo {
+            amount: 1,
+            decimals: 2,
+            memo: "deposit 7",
+        };
+        let packed = check.pack();
+        let mut expect = vec![47u8, 1, 0, 0, 0, 0, 0, 0, 0, 2];
+        expect.extend_from_slice(b"deposit 7");
+        assert_eq!(packed, expect);
+        let unpacked = TokenInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+        assert_eq!(
+            TokenInstruction::unpack(&[47u8, 1, 0, 0, 0, 0, 0, 0, 0, 2, 0xff]),
+            Err(TokenError::InvalidInstruction.into())
+        );
     }
 
     #[test]
@@ -5,7 +5,9 @@
 //! An ERC20-like Token program for the Arch blockchain
 
 pub mod error;
+pub mod extension;
 pub mod instruction;
+pub mod permit;
 pub mod processor;
 pub mod state;
 
@@ -0,0 +1,40 @@
+//! Off-chain signed approvals
+
+use {
+    arch_program::pubkey::Pubkey,
+    bitcoin::hashes::{sha256, Hash},
+};
+
+/// Seed of the permit nonce account of an owner
+pub const PERMIT_NONCE_SEED: &[u8] = b"permit-nonce";
+
+/// Domain separator prefixed to every permit message
+const PERMIT_DOMAIN: &[u8] = b"apl-token permit";
+
+/// Derives the address of the permit nonce account of `owner`
+pub fn get_permit_nonce_address(owner: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PERMIT_NONCE_SEED, &owner.serialize()], program_id)
+}
+
+/// Computes the hash an owner signs to approve `spender` for `amount` of the
+/// tokens in `source`, valid until the unix timestamp `deadline`
+pub fn permit_message_hash(
+    program_id: &Pubkey,
+    source: &Pubkey,
+    owner: &Pubkey,
+    spender: &Pubkey,
+    amount: u64,
+    nonce: u64,
+    deadline: i64,
+) -> [u8; 32] {
+    let mut message = Vec::with_capacity(PERMIT_DOMAIN.len() + 32 * 4 + 8 * 3);
+    message.extend_from_slice(PERMIT_DOMAIN);
+    message.extend_from_slice(program_id.as_ref());
+    message.extend_from_slice(source.as_ref());
+    message.extend_from_slice(owner.as_ref());
+    message.extend_from_slice(spender.as_ref());
+    message.extend_from_slice(&amount.to_le_bytes());
+    message.extend_from_slice(&nonce.to_le_bytes());
+    message.extend_from_slice(&deadline.to_le_bytes());
+    sha256::Hash::hash(&message).to_byte_array()
+}
@@ -4,20 +4,45 @@ use {
     crate::{
         amount_to_ui_amount_string_trimmed,
         error::TokenError,
-        instruction::{is_valid_signer_index, AuthorityType, TokenInstruction, MAX_SIGNERS},
-        state::{Account, AccountState, Mint, Multisig},
+        extension::{
+            allowances::{AllowanceExpiry, Allowances},
+            checkpoints::{BalanceCheckpoints, SupplyCheckpoints},
+            default_account_state::DefaultAccountState,
+            get_account_type, get_extension, get_required_account_extensions,
+            interest_bearing::InterestBearingConfig,
+            memo_transfer::MemoTransfer,
+            non_transferable::{NonTransferable, NonTransferableAccount},
+            permanent_delegate::PermanentDelegate,
+            set_extension,
+            supply_cap::SupplyCap,
+            transfer_hook::{
+                execute_instruction, get_extra_account_metas_address, ExtraAccountMeta,
+                TransferHook, TransferHookAccount,
+            },
+            AccountType, BaseState, ExtensionType,
+        },
+        instruction::{
+            is_valid_signer_index, AuthorityType, TokenInstruction, MAX_SIGNERS,
+            MAX_WEIGHTED_SIGNERS,
+        },
+        permit::{get_permit_nonce_address, permit_message_hash, PERMIT_NONCE_SEED},
+        state::{
+            Account, AccountState, Mint, Multisig, PermitNonce, WeightedMultisig, WeightedSigner,
+        },
         try_ui_amount_into_amount,
     },
     arch_program::{
         account::{next_account_info, AccountInfo},
         entrypoint::ProgramResult,
         msg,
-        program::set_return_data,
+        program::{get_clock, invoke, invoke_signed, set_return_data},
         program_error::ProgramError,
         program_memory::sol_memcmp,
         program_option::COption,
         program_pack::{IsInitialized, Pack},
         pubkey::{Pubkey, PUBKEY_BYTES},
+        sol_secp256k1_recover::secp256k1_recover,
+        system_instruction,
     },
 };
 
@@ -29,23 +54,34 @@ impl Processor {
         decimals: u8,
         mint_authority: Pubkey,
         freeze_authority: COption<Pubkey>,
+        max_supply: COption<u64>,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let mint_info = next_account_info(account_info_iter)?;
 
         msg!("mint_info: {:?}", mint_info);
-        let mut mint = Mint::unpack_unchecked(&mint_info.data.borrow())?;
+        let mut mint_data = mint_info.data.borrow_mut();
+        let mut mint = Mint::unpack_base_unchecked(&mint_data)?;
         msg!("mint: {:?}", mint);
         if mint.is_initialized {
             return Err(TokenError::AlreadyInUse.into());
         }
 
+        if let COption::Some(max_supply) = max_supply {
+            set_extension(&mut mint_data, SupplyCap { max_supply })?;
+        }
+        if let Some(default_account_state) = get_extension::<DefaultAccountState>(&mint_data)? {
+            if default_account_state.state == AccountState::Frozen && freeze_authority.is_none() {
+                return Err(TokenError::MintCannotFreeze.into());
+            }
+        }
+
         mint.mint_authority = COption::Some(mint_authority);
         mint.decimals = decimals;
         mint.is_initialized = true;
         mint.freeze_authority = freeze_authority;
 
-        Mint::pack(mint, &mut mint_info.data.borrow_mut())?;
+        Mint::pack_base(mint, &mut mint_data)?;
 
         Ok(())
     }
@@ -57,7 +93,13 @@ impl Processor {
         mint_authority: Pubkey,
         freeze_authority: COption<Pubkey>,
     ) -> ProgramResult {
-        Self::_process_initialize_mint(accounts, decimals, mint_authority, freeze_authority)
+        Self::_process_initialize_mint(
+            accounts,
+            decimals,
+            mint_authority,
+            freeze_authority,
+            COption::None,
+        )
     }
 
     /// Processes an [`InitializeMint2`](enum.TokenInstruction.html)
@@ -67,8 +109,15 @@ impl Processor {
         decimals: u8,
         mint_authority: Pubkey,
         freeze_authority: COption<Pubkey>,
+        max_supply: COption<u64>,
     ) -> ProgramResult {
-        Self::_process_initialize_mint(accounts, decimals, mint_authority, freeze_authority)
+        Self::_process_initialize_mint(
+            accounts,
+            decimals,
+            mint_authority,
+            freeze_authority,
+            max_supply,
+        )
     }
 
     fn _process_initialize_account(
@@ -85,19 +134,33 @@ impl Processor {
             next_account_info(account_info_iter)?.key
         };
 
-        let mut account = Account::unpack_unchecked(&new_account_info.data.borrow())?;
+        let mut account = Account::unpack_base_unchecked(&new_account_info.data.borrow())?;
         if account.is_initialized() {
             return Err(TokenError::AlreadyInUse.into());
         }
+        let required_extensions = get_required_account_extensions(&mint_info.data.borrow())?;
+        let default_account_state =
+            get_extension::<DefaultAccountState>(&mint_info.data.borrow())?.unwrap_or_default();
 
         account.mint = *mint_info.key;
         account.owner = *owner;
         account.close_authority = COption::None;
         account.delegate = COption::None;
         account.delegated_amount = 0;
-        account.state = AccountState::Initialized;
+        account.state = if default_account_state.state == AccountState::Frozen {
+            AccountState::Frozen
+        } else {
+            AccountState::Initialized
+        };
 
-        Account::pack(account, &mut new_account_info.data.borrow_mut())?;
+        let mut new_account_data = new_account_info.data.borrow_mut();
+        Account::pack_base(account, &mut new_account_data)?;
+        if required_extensions.contains(&ExtensionType::TransferHookAccount) {
+            set_extension(&mut new_account_data, TransferHookAccount)?;
+        }
+        if required_extensions.contains(&ExtensionType::NonTransferableAccount) {
+            set_extension(&mut new_account_data, NonTransferableAccount)?;
+        }
 
         Ok(())
     }
@@ -165,12 +228,151 @@ impl Processor {
         Self::_process_initialize_multisig(accounts, m)
     }
 
-    /// Processes a [`Transfer`](enum.TokenInstruction.html) instruction.
-    pub fn process_transfer(
+    /// Processes an [`InitializeWeightedMultisig`](enum.TokenInstruction.html)
+    /// instruction.
+    pub fn process_initialize_weighted_multisig(
+        accounts: &[AccountInfo],
+        threshold: u16,
+        weights: &[u8],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let multisig_info = next_account_info(account_info_iter)?;
+
+        let mut multisig = WeightedMultisig::unpack_unchecked(&multisig_info.data.borrow())?;
+        if multisig.is_initialized {
+            return Err(TokenError::AlreadyInUse.into());
+        }
+
+        let signer_infos = account_info_iter.as_slice();
+        if signer_infos.is_empty()
+            || signer_infos.len() > MAX_WEIGHTED_SIGNERS
+            || signer_infos.len() != weights.len()
+        {
+            return Err(TokenError::InvalidNumberOfProvidedSigners.into());
+        }
+        for (signer_info, &weight) in signer_infos.iter().zip(weights) {
+            Self::add_weighted_signer(&mut multisig, signer_info.key, weight)?;
+        }
+        if !multisig.is_valid_threshold(threshold) {
+            return Err(TokenError::InvalidNumberOfRequiredSigners.into());
+        }
+        multisig.threshold = threshold;
+        multisig.is_initialized = true;
+
+        WeightedMultisig::pack(multisig, &mut multisig_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Processes an [`AddSigner`](enum.TokenInstruction.html) instruction.
+    pub fn process_add_signer(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        weight: u8,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let multisig_info = next_account_info(account_info_iter)?;
+        let new_signer_info = next_account_info(account_info_iter)?;
+
+        let mut multisig = Self::validate_weighted_multisig(
+            program_id,
+            multisig_info,
+            account_info_iter.as_slice(),
+        )?;
+        Self::add_weighted_signer(&mut multisig, new_signer_info.key, weight)?;
+
+        WeightedMultisig::pack(multisig, &mut multisig_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Processes a [`RemoveSigner`](enum.TokenInstruction.html) instruction.
+    pub fn process_remove_signer(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let multisig_info = next_account_info(account_info_iter)?;
+        let removed_signer_info = next_account_info(account_info_iter)?;
+
+        let mut multisig = Self::validate_weighted_multisig(
+            program_id,
+            multisig_info,
+            account_info_iter.as_slice(),
+        )?;
+        let position = multisig
+            .position(removed_signer_info.key)
+            .ok_or(TokenError::MultisigSignerNotFound)?;
+        multisig.remove(position);
+        if !multisig.is_valid_threshold(multisig.threshold) {
+            return Err(TokenError::InvalidNumberOfRequiredSigners.into());
+        }
+
+        WeightedMultisig::pack(multisig, &mut multisig_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Processes a [`SetThreshold`](enum.TokenInstruction.html) instruction.
+    pub fn process_set_threshold(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        threshold: u16,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let multisig_info = next_account_info(account_info_iter)?;
+
+        let mut multisig = Self::validate_weighted_multisig(
+            program_id,
+            multisig_info,
+            account_info_iter.as_slice(),
+        )?;
+        if !multisig.is_valid_threshold(threshold) {
+            return Err(TokenError::InvalidNumberOfRequiredSigners.into());
+        }
+        multisig.threshold = threshold;
+
+        WeightedMultisig::pack(multisig, &mut multisig_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Unpacks a weighted multisignature account after checking that its
+    /// current threshold approves the change
+    fn validate_weighted_multisig(
+        program_id: &Pubkey,
+        multisig_info: &AccountInfo,
+        signers: &[AccountInfo],
+    ) -> Result<WeightedMultisig, ProgramError> {
+        Self::check_account_owner(program_id, multisig_info)?;
+        let multisig = WeightedMultisig::unpack(&multisig_info.data.borrow())?;
+        Self::validate_owner(program_id, multisig_info.key, multisig_info, signers)?;
+        Ok(multisig)
+    }
+
+    fn add_weighted_signer(
+        multisig: &mut WeightedMultisig,
+        pubkey: &Pubkey,
+        weight: u8,
+    ) -> ProgramResult {
+        if weight == 0 {
+            return Err(ProgramError::InvalidArgument);
+        }
+        if multisig.position(pubkey).is_some() {
+            return Err(TokenError::MultisigSignerExists.into());
+        }
+        if !multisig.push(WeightedSigner {
+            pubkey: *pubkey,
+            weight,
+        }) {
+            return Err(TokenError::InvalidNumberOfProvidedSigners.into());
+        }
+        Ok(())
+    }
+
+    fn _process_transfer(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
         amount: u64,
         expected_decimals: Option<u8>,
+        memo: Option<&str>,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
 
@@ -185,8 +387,9 @@ impl Processor {
         let destination_account_info = next_account_info(account_info_iter)?;
         let authority_info = next_account_info(account_info_iter)?;
 
-        let mut source_account = Account::unpack(&source_account_info.data.borrow())?;
-        let mut destination_account = Account::unpack(&destination_account_info.data.borrow())?;
+        let mut source_account = Account::unpack_base(&source_account_info.data.borrow())?;
+        let mut destination_account =
+            Account::unpack_base(&destination_account_info.data.borrow())?;
 
         if source_account.is_frozen() || destination_account.is_frozen() {
             return Err(TokenError::AccountFrozen.into());
@@ -197,49 +400,37 @@ impl Processor {
         if !Self::cmp_pubkeys(&source_account.mint, &destination_account.mint) {
             return Err(TokenError::MintMismatch.into());
         }
+        Self::check_memo(destination_account_info, memo)?;
 
         if let Some((mint_info, expected_decimals)) = expected_mint_info {
             if !Self::cmp_pubkeys(mint_info.key, &source_account.mint) {
                 return Err(TokenError::MintMismatch.into());
             }
 
-            let mint = Mint::unpack(&mint_info.data.borrow_mut())?;
+            let mint = Mint::unpack_base(&mint_info.data.borrow_mut())?;
             if expected_decimals != mint.decimals {
                 return Err(TokenError::MintDecimalsMismatch.into());
             }
         }
+        let mint_info = expected_mint_info.map(|(mint_info, _)| mint_info);
+        Self::check_not_transferring(mint_info)?;
+        Self::check_transferable(source_account_info, mint_info)?;
+        let hook_program_id = Self::get_transfer_hook_program_id(source_account_info, mint_info)?;
+        let permanent_delegate = Self::get_permanent_delegate(mint_info)?;
 
         let self_transfer =
             Self::cmp_pubkeys(source_account_info.key, destination_account_info.key);
 
-        match source_account.delegate {
-            COption::Some(ref delegate) if Self::cmp_pubkeys(authority_info.key, delegate) => {
-                Self::validate_owner(
-                    program_id,
-                    delegate,
-                    authority_info,
-                    account_info_iter.as_slice(),
-                )?;
-                if source_account.delegated_amount < amount {
-                    return Err(TokenError::InsufficientFunds.into());
-                }
-                if !self_transfer {
-                    source_account.delegated_amount = source_account
-                        .delegated_amount
-                        .checked_sub(amount)
-                        .ok_or(TokenError::Overflow)?;
-                    if source_account.delegated_amount == 0 {
-                        source_account.delegate = COption::None;
-                    }
-                }
-            }
-            _ => Self::validate_owner(
-                program_id,
-                &source_account.owner,
-                authority_info,
-                account_info_iter.as_slice(),
-            )?,
-        };
+        Self::validate_spender(
+            program_id,
+            source_account_info,
+            &mut source_account,
+            permanent_delegate,
+            authority_info,
+            account_info_iter.as_slice(),
+            amount,
+            if self_transfer { 0 } else { amount },
+        )?;
 
         if self_transfer || amount == 0 {
             Self::check_account_owner(program_id, source_account_info)?;
@@ -261,11 +452,189 @@ impl Processor {
             .checked_add(amount)
             .ok_or(TokenError::Overflow)?;
 
-        Account::pack(source_account, &mut source_account_info.data.borrow_mut())?;
-        Account::pack(
+        Account::pack_base(source_account, &mut source_account_info.data.borrow_mut())?;
+        Account::pack_base(
             destination_account,
             &mut destination_account_info.data.borrow_mut(),
         )?;
+        Self::record_balance(source_account_info, source_account.amount)?;
+        Self::record_balance(destination_account_info, destination_account.amount)?;
+
+        if let (Some(hook_program_id), Some(mint_info)) = (hook_program_id, mint_info) {
+            Self::invoke_transfer_hook(
+                &hook_program_id,
+                source_account_info,
+                mint_info,
+                destination_account_info,
+                authority_info,
+                account_info_iter.as_slice(),
+                amount,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Processes a [`Transfer`](enum.TokenInstruction.html) instruction.
+    pub fn process_transfer(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount: u64,
+        expected_decimals: Option<u8>,
+    ) -> ProgramResult {
+        Self::_process_transfer(program_id, accounts, amount, expected_decimals, None)
+    }
+
+    /// Processes a [`TransferWithMemo`](enum.TokenInstruction.html)
+    /// instruction.
+    pub fn process_transfer_with_memo(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount: u64,
+        decimals: u8,
+        memo: &str,
+    ) -> ProgramResult {
+        msg!("Memo (len {}): {:?}", memo.len(), memo);
+        Self::_process_transfer(program_id, accounts, amount, Some(decimals), Some(memo))
+    }
+
+    /// Processes a [`TransferBatch`](enum.TokenInstruction.html) instruction.
+    pub fn process_transfer_batch(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amounts: &[u64],
+        expected_decimals: Option<u8>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+
+        let source_account_info = next_account_info(account_info_iter)?;
+
+        let expected_mint_info = if let Some(expected_decimals) = expected_decimals {
+            Some((next_account_info(account_info_iter)?, expected_decimals))
+        } else {
+            None
+        };
+
+        if amounts.is_empty() || account_info_iter.as_slice().len() < amounts.len() {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        let (destination_account_infos, rest) =
+            account_info_iter.as_slice().split_at(amounts.len());
+        let account_info_iter = &mut rest.iter();
+        let authority_info = next_account_info(account_info_iter)?;
+
+        let mut source_account = Account::unpack_base(&source_account_info.data.borrow())?;
+        if source_account.is_frozen() {
+            return Err(TokenError::AccountFrozen.into());
+        }
+
+        if let Some((mint_info, expected_decimals)) = expected_mint_info {
+            if !Self::cmp_pubkeys(mint_info.key, &source_account.mint) {
+                return Err(TokenError::MintMismatch.into());
+            }
+
+            let mint = Mint::unpack_base(&mint_info.data.borrow())?;
+            if expected_decimals != mint.decimals {
+                return Err(TokenError::MintDecimalsMismatch.into());
+            }
+        }
+        let mint_info = expected_mint_info.map(|(mint_info, _)| mint_info);
+        Self::check_not_transferring(mint_info)?;
+        Self::check_transferable(source_account_info, mint_info)?;
+        let hook_program_id = Self::get_transfer_hook_program_id(source_account_info, mint_info)?;
+        let permanent_delegate = Self::get_permanent_delegate(mint_info)?;
+
+        // Self-transfers leave the balance untouched but, as with `Transfer`,
+        // still need to be covered by it
+        let mut total: u64 = 0;
+        let mut debit: u64 = 0;
+        for (destination_account_info, &amount) in
+            destination_account_infos.iter().zip(amounts.iter())
+        {
+            total = total.checked_add(amount).ok_or(TokenError::Overflow)?;
+            if !Self::cmp_pubkeys(source_account_info.key, destination_account_info.key) {
+                debit = debit.checked_add(amount).ok_or(TokenError::Overflow)?;
+            }
+        }
+        if source_account.amount < total {
+            return Err(TokenError::InsufficientFunds.into());
+        }
+
+        // Every destination is validated before any is credited
+        Self::check_account_owner(program_id, source_account_info)?;
+        for destination_account_info in destination_account_infos {
+            Self::check_account_owner(program_id, destination_account_info)?;
+            if Self::cmp_pubkeys(source_account_info.key, destination_account_info.key) {
+                continue;
+            }
+            let destination_account =
+                Account::unpack_base(&destination_account_info.data.borrow())?;
+            if destination_account.is_frozen() {
+                return Err(TokenError::AccountFrozen.into());
+            }
+            if !Self::cmp_pubkeys(&source_account.mint, &destination_account.mint) {
+                return Err(TokenError::MintMismatch.into());
+            }
+            Self::check_memo(destination_account_info, None)?;
+        }
+
+        Self::validate_spender(
+            program_id,
+            source_account_info,
+            &mut source_account,
+            permanent_delegate,
+            authority_info,
+            account_info_iter.as_slice(),
+            total,
+            debit,
+        )?;
+
+        for (destination_account_info, &amount) in
+            destination_account_infos.iter().zip(amounts.iter())
+        {
+            if Self::cmp_pubkeys(source_account_info.key, destination_account_info.key) {
+                continue;
+            }
+
+            // Repeated destinations are re-read so each credit builds on the last
+            let mut destination_account =
+                Account::unpack_base(&destination_account_info.data.borrow())?;
+            destination_account.amount = destination_account
+                .amount
+                .checked_add(amount)
+                .ok_or(TokenError::Overflow)?;
+            Account::pack_base(
+                destination_account,
+                &mut destination_account_info.data.borrow_mut(),
+            )?;
+            Self::record_balance(destination_account_info, destination_account.amount)?;
+        }
+
+        source_account.amount = source_account
+            .amount
+            .checked_sub(debit)
+            .ok_or(TokenError::Overflow)?;
+        Account::pack_base(source_account, &mut source_account_info.data.borrow_mut())?;
+        Self::record_balance(source_account_info, source_account.amount)?;
+
+        if let (Some(hook_program_id), Some(mint_info)) = (hook_program_id, mint_info) {
+            for (destination_account_info, &amount) in
+                destination_account_infos.iter().zip(amounts.iter())
+            {
+                if Self::cmp_pubkeys(source_account_info.key, destination_account_info.key) {
+                    continue;
+                }
+                Self::invoke_transfer_hook(
+                    &hook_program_id,
+                    source_account_info,
+                    mint_info,
+                    destination_account_info,
+                    authority_info,
+                    account_info_iter.as_slice(),
+                    amount,
+                )?;
+            }
+        }
 
         Ok(())
     }
@@ -289,7 +658,7 @@ impl Processor {
         let delegate_info = next_account_info(account_info_iter)?;
         let owner_info = next_account_info(account_info_iter)?;
 
-        let mut source_account = Account::unpack(&source_account_info.data.borrow())?;
+        let mut source_account = Account::unpack_base(&source_account_info.data.borrow())?;
 
         if source_account.is_frozen() {
             return Err(TokenError::AccountFrozen.into());
@@ -300,7 +669,7 @@ impl Processor {
                 return Err(TokenError::MintMismatch.into());
             }
 
-            let mint = Mint::unpack(&mint_info.data.borrow_mut())?;
+            let mint = Mint::unpack_base(&mint_info.data.borrow_mut())?;
             if expected_decimals != mint.decimals {
                 return Err(TokenError::MintDecimalsMismatch.into());
             }
@@ -316,17 +685,21 @@ impl Processor {
         source_account.delegate = COption::Some(*delegate_info.key);
         source_account.delegated_amount = amount;
 
-        Account::pack(source_account, &mut source_account_info.data.borrow_mut())?;
+        Account::pack_base(source_account, &mut source_account_info.data.borrow_mut())?;
 
         Ok(())
     }
 
     /// Processes an [`Revoke`](enum.TokenInstruction.html) instruction.
-    pub fn process_revoke(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    pub fn process_revoke(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        delegate: COption<Pubkey>,
+    ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let source_account_info = next_account_info(account_info_iter)?;
 
-        let mut source_account = Account::unpack(&source_account_info.data.borrow())?;
+        let mut source_account = Account::unpack_base(&source_account_info.data.borrow())?;
 
         let owner_info = next_account_info(account_info_iter)?;
 
@@ -341,56 +714,436 @@ impl Processor {
             account_info_iter.as_slice(),
         )?;
 
-        source_account.delegate = COption::None;
-        source_account.delegated_amount = 0;
+        let COption::Some(delegate) = delegate else {
+            source_account.delegate = COption::None;
+            source_account.delegated_amount = 0;
+            Account::pack_base(source_account, &mut source_account_info.data.borrow_mut())?;
+            return Ok(());
+        };
 
-        Account::pack(source_account, &mut source_account_info.data.borrow_mut())?;
+        if source_account.delegate == COption::Some(delegate) {
+            source_account.delegate = COption::None;
+            source_account.delegated_amount = 0;
+            Account::pack_base(source_account, &mut source_account_info.data.borrow_mut())?;
+        }
+        let mut source_data = source_account_info.data.borrow_mut();
+        if let Some(mut allowances) = get_extension::<Allowances>(&source_data)? {
+            if allowances.revoke(&delegate) {
+                set_extension(&mut source_data, allowances)?;
+            }
+        }
 
         Ok(())
     }
 
-    /// Processes a [`SetAuthority`](enum.TokenInstruction.html) instruction.
-    pub fn process_set_authority(
+    /// Processes an [`ApproveAllowance`](enum.TokenInstruction.html)
+    /// instruction.
+    pub fn process_approve_allowance(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        authority_type: AuthorityType,
-        new_authority: COption<Pubkey>,
+        amount: u64,
+        expiry: AllowanceExpiry,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
-        let account_info = next_account_info(account_info_iter)?;
-        let authority_info = next_account_info(account_info_iter)?;
+        let source_account_info = next_account_info(account_info_iter)?;
+        let delegate_info = next_account_info(account_info_iter)?;
+        let owner_info = next_account_info(account_info_iter)?;
 
-        if account_info.data_len() == Account::get_packed_len() {
-            let mut account = Account::unpack(&account_info.data.borrow())?;
+        let source_account = Account::unpack_base(&source_account_info.data.borrow())?;
 
-            if account.is_frozen() {
-                return Err(TokenError::AccountFrozen.into());
-            }
+        if source_account.is_frozen() {
+            return Err(TokenError::AccountFrozen.into());
+        }
 
-            match authority_type {
-                AuthorityType::AccountOwner => {
-                    Self::validate_owner(
-                        program_id,
-                        &account.owner,
-                        authority_info,
-                        account_info_iter.as_slice(),
-                    )?;
+        Self::validate_owner(
+            program_id,
+            &source_account.owner,
+            owner_info,
+            account_info_iter.as_slice(),
+        )?;
 
-                    if let COption::Some(authority) = new_authority {
-                        account.owner = authority;
-                    } else {
-                        return Err(TokenError::InvalidInstruction.into());
-                    }
+        let mut source_data = source_account_info.data.borrow_mut();
+        let mut allowances = get_extension::<Allowances>(&source_data)?.unwrap_or_default();
+        allowances.approve(delegate_info.key, amount, expiry)?;
+        set_extension(&mut source_data, allowances)?;
 
-                    account.delegate = COption::None;
-                    account.delegated_amount = 0;
-                }
-                AuthorityType::CloseAccount => {
-                    let authority = account.close_authority.unwrap_or(account.owner);
-                    Self::validate_owner(
-                        program_id,
-                        &authority,
-                        authority_info,
+        Ok(())
+    }
+
+    /// Processes a [`CreatePermitNonce`](enum.TokenInstruction.html)
+    /// instruction.
+    pub fn process_create_permit_nonce(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        txid: [u8; 32],
+        vout: u32,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let nonce_info = next_account_info(account_info_iter)?;
+        let owner_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        let (nonce_address, bump_seed) = get_permit_nonce_address(owner_info.key, program_id);
+        if !Self::cmp_pubkeys(&nonce_address, nonce_info.key) {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        let owner_seed = owner_info.key.serialize();
+        let nonce_signer_seeds: &[&[u8]] = &[PERMIT_NONCE_SEED, &owner_seed, &[bump_seed]];
+
+        invoke_signed(
+            &system_instruction::create_account(txid, vout, *nonce_info.key),
+            &[nonce_info.clone(), system_program_info.clone()],
+            &[nonce_signer_seeds],
+        )?;
+        invoke_signed(
+            &system_instruction::write_bytes(
+                0,
+                PermitNonce::LEN as u32,
+                vec![0; PermitNonce::LEN],
+                *nonce_info.key,
+            ),
+            &[nonce_info.clone(), system_program_info.clone()],
+            &[nonce_signer_seeds],
+        )?;
+        invoke_signed(
+            &system_instruction::assign(*nonce_info.key, *program_id),
+            &[nonce_info.clone(), system_program_info.clone()],
+            &[nonce_signer_seeds],
+        )?;
+
+        PermitNonce::pack(
+            PermitNonce {
+                is_initialized: true,
+                nonce: 0,
+            },
+            &mut nonce_info.data.borrow_mut(),
+        )?;
+
+        Ok(())
+    }
+
+    /// Processes a [`Permit`](enum.TokenInstruction.html) instruction.
+    #[allow(clippy::too_many_arguments)]
+    pub fn process_permit(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        owner: Pubkey,
+        spender: Pubkey,
+        amount: u64,
+        nonce: u64,
+        deadline: i64,
+        recovery_id: u8,
+        signature: [u8; 64],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let source_account_info = next_account_info(account_info_iter)?;
+        let nonce_info = next_account_info(account_info_iter)?;
+
+        let mut source_account = Account::unpack_base(&source_account_info.data.borrow())?;
+
+        if source_account.is_frozen() {
+            return Err(TokenError::AccountFrozen.into());
+        }
+        if !Self::cmp_pubkeys(&source_account.owner, &owner) {
+            return Err(TokenError::OwnerMismatch.into());
+        }
+
+        Self::check_account_owner(program_id, nonce_info)?;
+        if !Self::cmp_pubkeys(
+            &get_permit_nonce_address(&owner, program_id).0,
+            nonce_info.key,
+        ) {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        let mut permit_nonce = PermitNonce::unpack(&nonce_info.data.borrow())?;
+        if permit_nonce.nonce != nonce {
+            return Err(TokenError::InvalidPermitNonce.into());
+        }
+        if get_clock().unix_timestamp > deadline {
+            return Err(TokenError::PermitExpired.into());
+        }
+
+        let hash = permit_message_hash(
+            program_id,
+            source_account_info.key,
+            &owner,
+            &spender,
+            amount,
+            nonce,
+            deadline,
+        );
+        // Owners are x-only keys, so only the x coordinate of the recovered
+        // key is compared
+        let recovered = secp256k1_recover(&hash, recovery_id, &signature)
+            .map_err(|_| TokenError::InvalidPermitSignature)?;
+        if recovered.to_bytes()[..PUBKEY_BYTES] != owner.serialize() {
+            return Err(TokenError::InvalidPermitSignature.into());
+        }
+
+        permit_nonce.nonce = permit_nonce
+            .nonce
+            .checked_add(1)
+            .ok_or(TokenError::Overflow)?;
+        PermitNonce::pack(permit_nonce, &mut nonce_info.data.borrow_mut())?;
+
+        source_account.delegate = COption::Some(spender);
+        source_account.delegated_amount = amount;
+
+        Account::pack_base(source_account, &mut source_account_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Processes an [`InitializeTransferHook`](enum.TokenInstruction.html)
+    /// instruction.
+    pub fn process_initialize_transfer_hook(
+        accounts: &[AccountInfo],
+        authority: COption<Pubkey>,
+        hook_program_id: COption<Pubkey>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let mint_info = next_account_info(account_info_iter)?;
+
+        let mut mint_data = mint_info.data.borrow_mut();
+        let mint = Mint::unpack_base_unchecked(&mint_data)?;
+        if mint.is_initialized {
+            return Err(TokenError::AlreadyInUse.into());
+        }
+
+        set_extension(
+            &mut mint_data,
+            TransferHook {
+                authority: authority.unwrap_or_default(),
+                program_id: hook_program_id.unwrap_or_default(),
+                transferring: false,
+            },
+        )
+    }
+
+    /// Processes an
+    /// [`InitializeNonTransferableMint`](enum.TokenInstruction.html)
+    /// instruction.
+    pub fn process_initialize_non_transferable_mint(
+        accounts: &[AccountInfo],
+        authority: COption<Pubkey>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let mint_info = next_account_info(account_info_iter)?;
+
+        let mut mint_data = mint_info.data.borrow_mut();
+        let mint = Mint::unpack_base_unchecked(&mint_data)?;
+        if mint.is_initialized {
+            return Err(TokenError::AlreadyInUse.into());
+        }
+
+        set_extension(
+            &mut mint_data,
+            NonTransferable {
+                authority: authority.unwrap_or_default(),
+            },
+        )
+    }
+
+    /// Processes an [`InitializePermanentDelegate`](enum.TokenInstruction.html)
+    /// instruction.
+    pub fn process_initialize_permanent_delegate(
+        accounts: &[AccountInfo],
+        delegate: Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let mint_info = next_account_info(account_info_iter)?;
+
+        let mut mint_data = mint_info.data.borrow_mut();
+        let mint = Mint::unpack_base_unchecked(&mint_data)?;
+        if mint.is_initialized {
+            return Err(TokenError::AlreadyInUse.into());
+        }
+
+        set_extension(&mut mint_data, PermanentDelegate { delegate })
+    }
+
+    /// Processes an
+    /// [`InitializeDefaultAccountState`](enum.TokenInstruction.html)
+    /// instruction.
+    pub fn process_initialize_default_account_state(
+        accounts: &[AccountInfo],
+        state: AccountState,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let mint_info = next_account_info(account_info_iter)?;
+
+        if state == AccountState::Uninitialized {
+            return Err(TokenError::InvalidState.into());
+        }
+
+        let mut mint_data = mint_info.data.borrow_mut();
+        let mint = Mint::unpack_base_unchecked(&mint_data)?;
+        if mint.is_initialized {
+            return Err(TokenError::AlreadyInUse.into());
+        }
+
+        set_extension(&mut mint_data, DefaultAccountState { state })
+    }
+
+    /// Processes an [`UpdateDefaultAccountState`](enum.TokenInstruction.html)
+    /// instruction.
+    pub fn process_update_default_account_state(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        state: AccountState,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let mint_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if state == AccountState::Uninitialized {
+            return Err(TokenError::InvalidState.into());
+        }
+
+        let mint = Mint::unpack_base(&mint_info.data.borrow())?;
+        let mut default_account_state =
+            get_extension::<DefaultAccountState>(&mint_info.data.borrow())?
+                .ok_or(ProgramError::InvalidAccountData)?;
+        match mint.freeze_authority {
+            COption::Some(authority) => Self::validate_owner(
+                program_id,
+                &authority,
+                authority_info,
+                account_info_iter.as_slice(),
+            ),
+            COption::None => Err(TokenError::MintCannotFreeze.into()),
+        }?;
+
+        default_account_state.state = state;
+        set_extension(&mut mint_info.data.borrow_mut(), default_account_state)
+    }
+
+    /// Processes an
+    /// [`InitializeInterestBearingMint`](enum.TokenInstruction.html)
+    /// instruction.
+    pub fn process_initialize_interest_bearing_mint(
+        accounts: &[AccountInfo],
+        rate_authority: COption<Pubkey>,
+        rate: i16,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let mint_info = next_account_info(account_info_iter)?;
+
+        let mut mint_data = mint_info.data.borrow_mut();
+        let mint = Mint::unpack_base_unchecked(&mint_data)?;
+        if mint.is_initialized {
+            return Err(TokenError::AlreadyInUse.into());
+        }
+
+        let config =
+            InterestBearingConfig::new(rate_authority.into(), rate, get_clock().unix_timestamp);
+        set_extension(&mut mint_data, config)
+    }
+
+    /// Processes an [`UpdateInterestRate`](enum.TokenInstruction.html)
+    /// instruction.
+    pub fn process_update_interest_rate(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        rate: i16,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let mint_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        Mint::unpack_base(&mint_info.data.borrow())?;
+        let mut config = get_extension::<InterestBearingConfig>(&mint_info.data.borrow())?
+            .ok_or(ProgramError::InvalidAccountData)?;
+        let authority = config
+            .rate_authority()
+            .ok_or(TokenError::AuthorityTypeNotSupported)?;
+
+        Self::validate_owner(
+            program_id,
+            &authority,
+            authority_info,
+            account_info_iter.as_slice(),
+        )?;
+
+        config
+            .update_rate(rate, get_clock().unix_timestamp)
+            .ok_or(TokenError::Overflow)?;
+        set_extension(&mut mint_info.data.borrow_mut(), config)
+    }
+
+    /// Processes an [`UpdateTransferHook`](enum.TokenInstruction.html)
+    /// instruction.
+    pub fn process_update_transfer_hook(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        hook_program_id: COption<Pubkey>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let mint_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        Mint::unpack_base(&mint_info.data.borrow())?;
+        let mut transfer_hook = get_extension::<TransferHook>(&mint_info.data.borrow())?
+            .ok_or(ProgramError::InvalidAccountData)?;
+        let authority = transfer_hook
+            .authority()
+            .ok_or(TokenError::AuthorityTypeNotSupported)?;
+
+        Self::validate_owner(
+            program_id,
+            &authority,
+            authority_info,
+            account_info_iter.as_slice(),
+        )?;
+
+        transfer_hook.program_id = hook_program_id.unwrap_or_default();
+        set_extension(&mut mint_info.data.borrow_mut(), transfer_hook)
+    }
+
+    /// Processes a [`SetAuthority`](enum.TokenInstruction.html) instruction.
+    pub fn process_set_authority(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        authority_type: AuthorityType,
+        new_authority: COption<Pubkey>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let account_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        let account_type = get_account_type(&account_info.data.borrow())
+            .map_err(|_| ProgramError::InvalidArgument)?;
+        if account_type == AccountType::Account {
+            let mut account = Account::unpack_base(&account_info.data.borrow())?;
+
+            if account.is_frozen() {
+                return Err(TokenError::AccountFrozen.into());
+            }
+
+            match authority_type {
+                AuthorityType::AccountOwner => {
+                    Self::validate_owner(
+                        program_id,
+                        &account.owner,
+                        authority_info,
+                        account_info_iter.as_slice(),
+                    )?;
+
+                    if let COption::Some(authority) = new_authority {
+                        account.owner = authority;
+                    } else {
+                        return Err(TokenError::InvalidInstruction.into());
+                    }
+
+                    account.delegate = COption::None;
+                    account.delegated_amount = 0;
+                }
+                AuthorityType::CloseAccount => {
+                    let authority = account.close_authority.unwrap_or(account.owner);
+                    Self::validate_owner(
+                        program_id,
+                        &authority,
+                        authority_info,
                         account_info_iter.as_slice(),
                     )?;
                     account.close_authority = new_authority;
@@ -399,9 +1152,17 @@ impl Processor {
                     return Err(TokenError::AuthorityTypeNotSupported.into());
                 }
             }
-            Account::pack(account, &mut account_info.data.borrow_mut())?;
-        } else if account_info.data_len() == Mint::get_packed_len() {
-            let mut mint = Mint::unpack(&account_info.data.borrow())?;
+            let mut account_data = account_info.data.borrow_mut();
+            Account::pack_base(account, &mut account_data)?;
+            // Allowances are approved by the previous owner and must not
+            // outlive the change of ownership.
+            if authority_type == AuthorityType::AccountOwner
+                && get_extension::<Allowances>(&account_data)?.is_some()
+            {
+                set_extension(&mut account_data, Allowances::default())?;
+            }
+        } else if account_type == AccountType::Mint {
+            let mut mint = Mint::unpack_base(&account_info.data.borrow())?;
             match authority_type {
                 AuthorityType::MintTokens => {
                     // Once a mint's supply is fixed, it cannot be undone by setting a new
@@ -431,11 +1192,61 @@ impl Processor {
                     )?;
                     mint.freeze_authority = new_authority;
                 }
+                AuthorityType::NonTransferable => {
+                    let mut non_transferable =
+                        get_extension::<NonTransferable>(&account_info.data.borrow())?
+                            .ok_or(TokenError::AuthorityTypeNotSupported)?;
+                    let authority = non_transferable
+                        .authority()
+                        .ok_or(TokenError::AuthorityTypeNotSupported)?;
+                    Self::validate_owner(
+                        program_id,
+                        &authority,
+                        authority_info,
+                        account_info_iter.as_slice(),
+                    )?;
+                    non_transferable.authority = new_authority.unwrap_or_default();
+                    set_extension(&mut account_info.data.borrow_mut(), non_transferable)?;
+                }
+                AuthorityType::PermanentDelegate => {
+                    // Once a mint's permanent delegate is removed, it cannot be restored by
+                    // setting a new delegate
+                    let mut permanent_delegate =
+                        get_extension::<PermanentDelegate>(&account_info.data.borrow())?
+                            .ok_or(TokenError::AuthorityTypeNotSupported)?;
+                    let delegate = permanent_delegate
+                        .delegate()
+                        .ok_or(TokenError::AuthorityTypeNotSupported)?;
+                    Self::validate_owner(
+                        program_id,
+                        &delegate,
+                        authority_info,
+                        account_info_iter.as_slice(),
+                    )?;
+                    permanent_delegate.delegate = new_authority.unwrap_or_default();
+                    set_extension(&mut account_info.data.borrow_mut(), permanent_delegate)?;
+                }
+                AuthorityType::InterestRate => {
+                    let mut config =
+                        get_extension::<InterestBearingConfig>(&account_info.data.borrow())?
+                            .ok_or(TokenError::AuthorityTypeNotSupported)?;
+                    let authority = config
+                        .rate_authority()
+                        .ok_or(TokenError::AuthorityTypeNotSupported)?;
+                    Self::validate_owner(
+                        program_id,
+                        &authority,
+                        authority_info,
+                        account_info_iter.as_slice(),
+                    )?;
+                    config.rate_authority = new_authority.unwrap_or_default();
+                    set_extension(&mut account_info.data.borrow_mut(), config)?;
+                }
                 _ => {
                     return Err(TokenError::AuthorityTypeNotSupported.into());
                 }
             }
-            Mint::pack(mint, &mut account_info.data.borrow_mut())?;
+            Mint::pack_base(mint, &mut account_info.data.borrow_mut())?;
         } else {
             return Err(ProgramError::InvalidArgument);
         }
@@ -455,7 +1266,8 @@ impl Processor {
         let destination_account_info = next_account_info(account_info_iter)?;
         let owner_info = next_account_info(account_info_iter)?;
 
-        let mut destination_account = Account::unpack(&destination_account_info.data.borrow())?;
+        let mut destination_account =
+            Account::unpack_base(&destination_account_info.data.borrow())?;
         if destination_account.is_frozen() {
             return Err(TokenError::AccountFrozen.into());
         }
@@ -464,7 +1276,7 @@ impl Processor {
             return Err(TokenError::MintMismatch.into());
         }
 
-        let mut mint = Mint::unpack(&mint_info.data.borrow())?;
+        let mut mint = Mint::unpack_base(&mint_info.data.borrow())?;
         if let Some(expected_decimals) = expected_decimals {
             if expected_decimals != mint.decimals {
                 return Err(TokenError::MintDecimalsMismatch.into());
@@ -496,11 +1308,19 @@ impl Processor {
             .checked_add(amount)
             .ok_or(TokenError::Overflow)?;
 
-        Account::pack(
+        if let Some(supply_cap) = get_extension::<SupplyCap>(&mint_info.data.borrow())? {
+            if mint.supply > supply_cap.max_supply {
+                return Err(TokenError::SupplyCapExceeded.into());
+            }
+        }
+
+        Account::pack_base(
             destination_account,
             &mut destination_account_info.data.borrow_mut(),
         )?;
-        Mint::pack(mint, &mut mint_info.data.borrow_mut())?;
+        Mint::pack_base(mint, &mut mint_info.data.borrow_mut())?;
+        Self::record_balance(destination_account_info, destination_account.amount)?;
+        Self::record_supply(mint_info, mint.supply)?;
 
         Ok(())
     }
@@ -518,8 +1338,8 @@ impl Processor {
         let mint_info = next_account_info(account_info_iter)?;
         let authority_info = next_account_info(account_info_iter)?;
 
-        let mut source_account = Account::unpack(&source_account_info.data.borrow())?;
-        let mut mint = Mint::unpack(&mint_info.data.borrow())?;
+        let mut source_account = Account::unpack_base(&source_account_info.data.borrow())?;
+        let mut mint = Mint::unpack_base(&mint_info.data.borrow())?;
 
         if source_account.is_frozen() {
             return Err(TokenError::AccountFrozen.into());
@@ -537,6 +1357,17 @@ impl Processor {
             }
         }
 
+        Self::validate_spender(
+            program_id,
+            source_account_info,
+            &mut source_account,
+            Self::get_permanent_delegate(Some(mint_info))?,
+            authority_info,
+            account_info_iter.as_slice(),
+            amount,
+            amount,
+        )?;
+
         if amount == 0 {
             Self::check_account_owner(program_id, source_account_info)?;
             Self::check_account_owner(program_id, mint_info)?;
@@ -551,8 +1382,10 @@ impl Processor {
             .checked_sub(amount)
             .ok_or(TokenError::Overflow)?;
 
-        Account::pack(source_account, &mut source_account_info.data.borrow_mut())?;
-        Mint::pack(mint, &mut mint_info.data.borrow_mut())?;
+        Account::pack_base(source_account, &mut source_account_info.data.borrow_mut())?;
+        Mint::pack_base(mint, &mut mint_info.data.borrow_mut())?;
+        Self::record_balance(source_account_info, source_account.amount)?;
+        Self::record_supply(mint_info, mint.supply)?;
 
         Ok(())
     }
@@ -568,7 +1401,7 @@ impl Processor {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        let source_account = Account::unpack(&source_account_info.data.borrow())?;
+        let source_account = Account::unpack_base(&source_account_info.data.borrow())?;
 
         delete_account(source_account_info)?;
 
@@ -587,7 +1420,7 @@ impl Processor {
         let mint_info = next_account_info(account_info_iter)?;
         let authority_info = next_account_info(account_info_iter)?;
 
-        let mut source_account = Account::unpack(&source_account_info.data.borrow())?;
+        let mut source_account = Account::unpack_base(&source_account_info.data.borrow())?;
         if freeze && source_account.is_frozen() || !freeze && !source_account.is_frozen() {
             return Err(TokenError::InvalidState.into());
         }
@@ -595,7 +1428,7 @@ impl Processor {
             return Err(TokenError::MintMismatch.into());
         }
 
-        let mint = Mint::unpack(&mint_info.data.borrow_mut())?;
+        let mint = Mint::unpack_base(&mint_info.data.borrow_mut())?;
         match mint.freeze_authority {
             COption::Some(authority) => Self::validate_owner(
                 program_id,
@@ -612,7 +1445,7 @@ impl Processor {
             AccountState::Initialized
         };
 
-        Account::pack(source_account, &mut source_account_info.data.borrow_mut())?;
+        Account::pack_base(source_account, &mut source_account_info.data.borrow_mut())?;
 
         Ok(())
     }
@@ -627,7 +1460,7 @@ impl Processor {
         // make sure the mint is valid
         let mint_info = next_account_info(account_info_iter)?;
         Self::check_account_owner(program_id, mint_info)?;
-        let _ = Mint::unpack(&mint_info.data.borrow())
+        let _ = Mint::unpack_base(&mint_info.data.borrow())
             .map_err(|_| Into::<ProgramError>::into(TokenError::InvalidMint))?;
         set_return_data(&Account::LEN.to_le_bytes());
         Ok(())
@@ -638,7 +1471,7 @@ impl Processor {
     pub fn process_initialize_immutable_owner(accounts: &[AccountInfo]) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let token_account_info = next_account_info(account_info_iter)?;
-        let account = Account::unpack_unchecked(&token_account_info.data.borrow())?;
+        let account = Account::unpack_base_unchecked(&token_account_info.data.borrow())?;
         if account.is_initialized() {
             return Err(TokenError::AlreadyInUse.into());
         }
@@ -657,9 +1490,14 @@ impl Processor {
         let mint_info = next_account_info(account_info_iter)?;
         Self::check_account_owner(program_id, mint_info)?;
 
-        let mint = Mint::unpack(&mint_info.data.borrow_mut())
+        let mint = Mint::unpack_base(&mint_info.data.borrow_mut())
             .map_err(|_| Into::<ProgramError>::into(TokenError::InvalidMint))?;
-        let ui_amount = amount_to_ui_amount_string_trimmed(amount, mint.decimals);
+        let ui_amount = match get_extension::<InterestBearingConfig>(&mint_info.data.borrow())? {
+            Some(config) => config
+                .amount_to_ui_amount(amount, mint.decimals, get_clock().unix_timestamp)
+                .ok_or(TokenError::Overflow)?,
+            None => amount_to_ui_amount_string_trimmed(amount, mint.decimals),
+        };
 
         set_return_data(&ui_amount.into_bytes());
         Ok(())
@@ -676,23 +1514,196 @@ impl Processor {
         let mint_info = next_account_info(account_info_iter)?;
         Self::check_account_owner(program_id, mint_info)?;
 
-        let mint = Mint::unpack(&mint_info.data.borrow_mut())
+        let mint = Mint::unpack_base(&mint_info.data.borrow_mut())
             .map_err(|_| Into::<ProgramError>::into(TokenError::InvalidMint))?;
-        let amount = try_ui_amount_into_amount(ui_amount.to_string(), mint.decimals)?;
+        let amount = match get_extension::<InterestBearingConfig>(&mint_info.data.borrow())? {
+            Some(config) => config.try_ui_amount_into_amount(
+                ui_amount,
+                mint.decimals,
+                get_clock().unix_timestamp,
+            )?,
+            None => try_ui_amount_into_amount(ui_amount.to_string(), mint.decimals)?,
+        };
 
         set_return_data(&amount.to_le_bytes());
         Ok(())
     }
 
-    /// Processes an [`Instruction`](enum.Instruction.html).
-    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
-        let instruction = TokenInstruction::unpack(input)?;
+    /// Processes a [`SetMaxSupply`](enum.TokenInstruction.html) instruction.
+    pub fn process_set_max_supply(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        max_supply: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let mint_info = next_account_info(account_info_iter)?;
+        let owner_info = next_account_info(account_info_iter)?;
 
-        match instruction {
-            TokenInstruction::InitializeMint {
-                decimals,
-                mint_authority,
-                freeze_authority,
+        let mut mint_data = mint_info.data.borrow_mut();
+        let mint = Mint::unpack_base(&mint_data)?;
+
+        // A mint without a minting authority already has a fixed supply
+        let mint_authority = mint
+            .mint_authority
+            .ok_or(Into::<ProgramError>::into(TokenError::FixedSupply))?;
+        Self::validate_owner(
+            program_id,
+            &mint_authority,
+            owner_info,
+            account_info_iter.as_slice(),
+        )?;
+
+        if max_supply < mint.supply {
+            return Err(TokenError::SupplyCapExceeded.into());
+        }
+        if let Some(supply_cap) = get_extension::<SupplyCap>(&mint_data)? {
+            if max_supply > supply_cap.max_supply {
+                return Err(TokenError::SupplyCapIncrease.into());
+            }
+        }
+
+        set_extension(&mut mint_data, SupplyCap { max_supply })?;
+
+        Ok(())
+    }
+
+    /// Processes a [`GetMintableSupply`](enum.TokenInstruction.html)
+    /// instruction
+    pub fn process_get_mintable_supply(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let mint_info = next_account_info(account_info_iter)?;
+        Self::check_account_owner(program_id, mint_info)?;
+
+        let mint_data = mint_info.data.borrow();
+        let mint = Mint::unpack_base(&mint_data)
+            .map_err(|_| Into::<ProgramError>::into(TokenError::InvalidMint))?;
+        let mintable_supply = if mint.mint_authority.is_none() {
+            0
+        } else if let Some(supply_cap) = get_extension::<SupplyCap>(&mint_data)? {
+            supply_cap.remaining(mint.supply)
+        } else {
+            u64::MAX - mint.supply
+        };
+
+        set_return_data(&mintable_supply.to_le_bytes());
+        Ok(())
+    }
+
+    /// Processes an
+    /// [`InitializeBalanceCheckpoints`](enum.TokenInstruction.html)
+    /// instruction.
+    pub fn process_initialize_balance_checkpoints(
+        accounts: &[AccountInfo],
+        min_credit: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let token_account_info = next_account_info(account_info_iter)?;
+
+        let mut account_data = token_account_info.data.borrow_mut();
+        let account = Account::unpack_base_unchecked(&account_data)?;
+        if account.is_initialized() {
+            return Err(TokenError::AlreadyInUse.into());
+        }
+
+        let mut balance_checkpoints = BalanceCheckpoints {
+            min_credit,
+            ..BalanceCheckpoints::default()
+        };
+        balance_checkpoints.checkpoints.record(get_clock().slot, 0);
+        set_extension(&mut account_data, balance_checkpoints)
+    }
+
+    /// Processes an
+    /// [`InitializeSupplyCheckpoints`](enum.TokenInstruction.html)
+    /// instruction.
+    pub fn process_initialize_supply_checkpoints(accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let mint_info = next_account_info(account_info_iter)?;
+
+        let mut mint_data = mint_info.data.borrow_mut();
+        let mint = Mint::unpack_base_unchecked(&mint_data)?;
+        if mint.is_initialized {
+            return Err(TokenError::AlreadyInUse.into());
+        }
+
+        let mut supply_checkpoints = SupplyCheckpoints::default();
+        supply_checkpoints.checkpoints.record(get_clock().slot, 0);
+        set_extension(&mut mint_data, supply_checkpoints)
+    }
+
+    /// Processes a [`GetBalanceAt`](enum.TokenInstruction.html) instruction
+    pub fn process_get_balance_at(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        slot: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let account_info = next_account_info(account_info_iter)?;
+        Self::check_account_owner(program_id, account_info)?;
+
+        let data = account_info.data.borrow();
+        let checkpoints = match get_account_type(&data)? {
+            AccountType::Account => {
+                Account::unpack_base(&data)?;
+                get_extension::<BalanceCheckpoints>(&data)?.map(|ext| ext.checkpoints)
+            }
+            AccountType::Mint => {
+                Mint::unpack_base(&data)?;
+                get_extension::<SupplyCheckpoints>(&data)?.map(|ext| ext.checkpoints)
+            }
+            AccountType::Uninitialized => None,
+        }
+        .ok_or(ProgramError::InvalidAccountData)?;
+        let amount = checkpoints
+            .get_at(slot)
+            .ok_or(TokenError::CheckpointNotFound)?;
+
+        set_return_data(&amount.to_le_bytes());
+        Ok(())
+    }
+
+    /// Processes an [`EnableRequiredTransferMemos`] or
+    /// [`DisableRequiredTransferMemos`] instruction.
+    ///
+    /// [`EnableRequiredTransferMemos`]: enum.TokenInstruction.html
+    /// [`DisableRequiredTransferMemos`]: enum.TokenInstruction.html
+    pub fn process_toggle_required_transfer_memos(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        require_incoming_transfer_memos: bool,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let token_account_info = next_account_info(account_info_iter)?;
+        let owner_info = next_account_info(account_info_iter)?;
+
+        let account = Account::unpack_base(&token_account_info.data.borrow())?;
+        Self::validate_owner(
+            program_id,
+            &account.owner,
+            owner_info,
+            account_info_iter.as_slice(),
+        )?;
+
+        set_extension(
+            &mut token_account_info.data.borrow_mut(),
+            MemoTransfer {
+                require_incoming_transfer_memos,
+            },
+        )
+    }
+
+    /// Processes an [`Instruction`](enum.Instruction.html).
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
+        let instruction = TokenInstruction::unpack(input)?;
+
+        match instruction {
+            TokenInstruction::InitializeMint {
+                decimals,
+                mint_authority,
+                freeze_authority,
             } => {
                 msg!("Instruction: InitializeMint");
                 Self::process_initialize_mint(accounts, decimals, mint_authority, freeze_authority)
@@ -701,9 +1712,16 @@ impl Processor {
                 decimals,
                 mint_authority,
                 freeze_authority,
+                max_supply,
             } => {
                 msg!("Instruction: InitializeMint2");
-                Self::process_initialize_mint2(accounts, decimals, mint_authority, freeze_authority)
+                Self::process_initialize_mint2(
+                    accounts,
+                    decimals,
+                    mint_authority,
+                    freeze_authority,
+                    max_supply,
+                )
             }
             TokenInstruction::InitializeAccount => {
                 msg!("Instruction: InitializeAccount");
@@ -729,9 +1747,9 @@ impl Processor {
                 msg!("Instruction: Approve");
                 Self::process_approve(program_id, accounts, amount, None)
             }
-            TokenInstruction::Revoke => {
+            TokenInstruction::Revoke { delegate } => {
                 msg!("Instruction: Revoke");
-                Self::process_revoke(program_id, accounts)
+                Self::process_revoke(program_id, accounts, delegate)
             }
             TokenInstruction::SetAuthority {
                 authority_type,
@@ -792,6 +1810,136 @@ impl Processor {
                 msg!("Instruction: UiAmountToAmount");
                 Self::process_ui_amount_to_amount(program_id, accounts, ui_amount)
             }
+            TokenInstruction::SetMaxSupply { max_supply } => {
+                msg!("Instruction: SetMaxSupply");
+                Self::process_set_max_supply(program_id, accounts, max_supply)
+            }
+            TokenInstruction::TransferBatch { amounts } => {
+                msg!("Instruction: TransferBatch");
+                Self::process_transfer_batch(program_id, accounts, &amounts, None)
+            }
+            TokenInstruction::TransferBatchChecked { amounts, decimals } => {
+                msg!("Instruction: TransferBatchChecked");
+                Self::process_transfer_batch(program_id, accounts, &amounts, Some(decimals))
+            }
+            TokenInstruction::ApproveAllowance { amount, expiry } => {
+                msg!("Instruction: ApproveAllowance");
+                Self::process_approve_allowance(program_id, accounts, amount, expiry)
+            }
+            TokenInstruction::CreatePermitNonce { txid, vout } => {
+                msg!("Instruction: CreatePermitNonce");
+                Self::process_create_permit_nonce(program_id, accounts, txid, vout)
+            }
+            TokenInstruction::Permit {
+                owner,
+                spender,
+                amount,
+                nonce,
+                deadline,
+                recovery_id,
+                signature,
+            } => {
+                msg!("Instruction: Permit");
+                Self::process_permit(
+                    program_id,
+                    accounts,
+                    owner,
+                    spender,
+                    amount,
+                    nonce,
+                    deadline,
+                    recovery_id,
+                    signature,
+                )
+            }
+            TokenInstruction::InitializeTransferHook {
+                authority,
+                program_id: hook_program_id,
+            } => {
+                msg!("Instruction: InitializeTransferHook");
+                Self::process_initialize_transfer_hook(accounts, authority, hook_program_id)
+            }
+            TokenInstruction::UpdateTransferHook {
+                program_id: hook_program_id,
+            } => {
+                msg!("Instruction: UpdateTransferHook");
+                Self::process_update_transfer_hook(program_id, accounts, hook_program_id)
+            }
+            TokenInstruction::InitializeNonTransferableMint { authority } => {
+                msg!("Instruction: InitializeNonTransferableMint");
+                Self::process_initialize_non_transferable_mint(accounts, authority)
+            }
+            TokenInstruction::InitializePermanentDelegate { delegate } => {
+                msg!("Instruction: InitializePermanentDelegate");
+                Self::process_initialize_permanent_delegate(accounts, delegate)
+            }
+            TokenInstruction::InitializeDefaultAccountState { state } => {
+                msg!("Instruction: InitializeDefaultAccountState");
+                Self::process_initialize_default_account_state(accounts, state)
+            }
+            TokenInstruction::UpdateDefaultAccountState { state } => {
+                msg!("Instruction: UpdateDefaultAccountState");
+                Self::process_update_default_account_state(program_id, accounts, state)
+            }
+            TokenInstruction::InitializeInterestBearingMint {
+                rate_authority,
+                rate,
+            } => {
+                msg!("Instruction: InitializeInterestBearingMint");
+                Self::process_initialize_interest_bearing_mint(accounts, rate_authority, rate)
+            }
+            TokenInstruction::UpdateInterestRate { rate } => {
+                msg!("Instruction: UpdateInterestRate");
+                Self::process_update_interest_rate(program_id, accounts, rate)
+            }
+            TokenInstruction::InitializeWeightedMultisig { threshold, weights } => {
+                msg!("Instruction: InitializeWeightedMultisig");
+                Self::process_initialize_weighted_multisig(accounts, threshold, &weights)
+            }
+            TokenInstruction::AddSigner { weight } => {
+                msg!("Instruction: AddSigner");
+                Self::process_add_signer(program_id, accounts, weight)
+            }
+            TokenInstruction::RemoveSigner => {
+                msg!("Instruction: RemoveSigner");
+                Self::process_remove_signer(program_id, accounts)
+            }
+            TokenInstruction::SetThreshold { threshold } => {
+                msg!("Instruction: SetThreshold");
+                Self::process_set_threshold(program_id, accounts, threshold)
+            }
+            TokenInstruction::InitializeBalanceCheckpoints { min_credit } => {
+                msg!("Instruction: InitializeBalanceCheckpoints");
+                Self::process_initialize_balance_checkpoints(accounts, min_credit)
+            }
+            TokenInstruction::InitializeSupplyCheckpoints => {
+                msg!("Instruction: InitializeSupplyCheckpoints");
+                Self::process_initialize_supply_checkpoints(accounts)
+            }
+            TokenInstruction::GetBalanceAt { slot } => {
+                msg!("Instruction: GetBalanceAt");
+                Self::process_get_balance_at(program_id, accounts, slot)
+            }
+            TokenInstruction::EnableRequiredTransferMemos => {
+                msg!("Instruction: EnableRequiredTransferMemos");
+                Self::process_toggle_required_transfer_memos(program_id, accounts, true)
+            }
+            TokenInstruction::DisableRequiredTransferMemos => {
+                msg!("Instruction: DisableRequiredTransferMemos");
+                Self::process_toggle_required_transfer_memos(program_id, accounts, false)
+            }
+            TokenInstruction::TransferWithMemo {
+                amount,
+                decimals,
+                memo,
+            } => {
+                msg!("Instruction: TransferWithMemo");
+                Self::process_transfer_with_memo(program_id, accounts, amount, decimals, memo)
+            }
+            TokenInstruction::GetMintableSupply => {
+                msg!("Instruction: GetMintableSupply");
+                Self::process_get_mintable_supply(program_id, accounts)
+            }
         }
     }
 
@@ -810,6 +1958,242 @@ impl Processor {
         sol_memcmp(a.as_ref(), b.as_ref(), PUBKEY_BYTES) == 0
     }
 
+    /// Finds the hook program to invoke for a transfer. Transfers that do not
+    /// name the mint are refused for accounts of mints with a transfer hook.
+    fn get_transfer_hook_program_id(
+        source_account_info: &AccountInfo,
+        mint_info: Option<&AccountInfo>,
+    ) -> Result<Option<Pubkey>, ProgramError> {
+        match mint_info {
+            Some(mint_info) => Ok(get_extension::<TransferHook>(&mint_info.data.borrow())?
+                .and_then(|transfer_hook| transfer_hook.program_id())),
+            None => {
+                if get_extension::<TransferHookAccount>(&source_account_info.data.borrow())?
+                    .is_some()
+                {
+                    return Err(TokenError::MintRequiredForTransfer.into());
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Refuses transfers out of accounts of non-transferable mints
+    fn check_transferable(
+        source_account_info: &AccountInfo,
+        mint_info: Option<&AccountInfo>,
+    ) -> ProgramResult {
+        if get_extension::<NonTransferableAccount>(&source_account_info.data.borrow())?.is_some() {
+            return Err(TokenError::NonTransferable.into());
+        }
+        if let Some(mint_info) = mint_info {
+            if get_extension::<NonTransferable>(&mint_info.data.borrow())?.is_some() {
+                return Err(TokenError::NonTransferable.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds the permanent delegate of a mint, when the mint is named
+    fn get_permanent_delegate(
+        mint_info: Option<&AccountInfo>,
+    ) -> Result<Option<Pubkey>, ProgramError> {
+        let Some(mint_info) = mint_info else {
+            return Ok(None);
+        };
+        Ok(
+            get_extension::<PermanentDelegate>(&mint_info.data.borrow())?
+                .and_then(|permanent_delegate| permanent_delegate.delegate()),
+        )
+    }
+
+    /// Fails if the account requires incoming transfers to carry a memo and
+    /// none was given
+    fn check_memo(account_info: &AccountInfo, memo: Option<&str>) -> ProgramResult {
+        if let Some(memo_transfer) = get_extension::<MemoTransfer>(&account_info.data.borrow())? {
+            if memo_transfer.require_incoming_transfer_memos
+                && memo.is_none_or(|memo| memo.is_empty())
+            {
+                return Err(TokenError::NoMemo.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the balance of a token account carrying checkpoints
+    fn record_balance(account_info: &AccountInfo, amount: u64) -> ProgramResult {
+        let mut account_data = account_info.data.borrow_mut();
+        if let Some(mut balance_checkpoints) = get_extension::<BalanceCheckpoints>(&account_data)? {
+            balance_checkpoints.record(get_clock().slot, amount);
+            set_extension(&mut account_data, balance_checkpoints)?;
+        }
+        Ok(())
+    }
+
+    /// Records the supply of a mint carrying checkpoints
+    fn record_supply(mint_info: &AccountInfo, supply: u64) -> ProgramResult {
+        let mut mint_data = mint_info.data.borrow_mut();
+        if let Some(mut supply_checkpoints) = get_extension::<SupplyCheckpoints>(&mint_data)? {
+            supply_checkpoints
+                .checkpoints
+                .record(get_clock().slot, supply);
+            set_extension(&mut mint_data, supply_checkpoints)?;
+        }
+        Ok(())
+    }
+
+    /// Fails if the hook of a transfer of the mint is running, when the mint
+    /// is named
+    fn check_not_transferring(mint_info: Option<&AccountInfo>) -> ProgramResult {
+        let Some(mint_info) = mint_info else {
+            return Ok(());
+        };
+        if let Some(transfer_hook) = get_extension::<TransferHook>(&mint_info.data.borrow())? {
+            if transfer_hook.transferring {
+                return Err(TokenError::TransferHookReentrancy.into());
+            }
+        }
+        Ok(())
+    }
+
+    fn set_transferring(mint_info: &AccountInfo, transferring: bool) -> ProgramResult {
+        if !mint_info.is_writable {
+            return Err(TokenError::MintNotWritable.into());
+        }
+        let mut mint_data = mint_info.data.borrow_mut();
+        let mut transfer_hook =
+            get_extension::<TransferHook>(&mint_data)?.ok_or(ProgramError::InvalidAccountData)?;
+        transfer_hook.transferring = transferring;
+        set_extension(&mut mint_data, transfer_hook)
+    }
+
+    /// Invokes the hook program of a mint after a transfer, with the mint
+    /// marked as transferring for the duration of the call.
+    /// The hook program, its validation account and the accounts listed there
+    /// are looked up in `additional_accounts`.
+    fn invoke_transfer_hook<'a>(
+        hook_program_id: &Pubkey,
+        source_account_info: &AccountInfo<'a>,
+        mint_info: &AccountInfo<'a>,
+        destination_account_info: &AccountInfo<'a>,
+        authority_info: &AccountInfo<'a>,
+        additional_accounts: &[AccountInfo<'a>],
+        amount: u64,
+    ) -> ProgramResult {
+        let find_account_info = |key: &Pubkey| {
+            additional_accounts
+                .iter()
+                .find(|account_info| Self::cmp_pubkeys(account_info.key, key))
+                .ok_or(ProgramError::NotEnoughAccountKeys)
+        };
+
+        let hook_program_info = find_account_info(hook_program_id)?;
+        let validation_info = find_account_info(&get_extra_account_metas_address(
+            mint_info.key,
+            hook_program_id,
+        ))?;
+        let extra_account_metas = if validation_info.data_is_empty() {
+            vec![]
+        } else {
+            if !Self::cmp_pubkeys(validation_info.owner, hook_program_id) {
+                return Err(ProgramError::IncorrectProgramId);
+            }
+            ExtraAccountMeta::unpack_list(&validation_info.data.borrow())?
+        };
+
+        let mut account_infos = Vec::with_capacity(6 + extra_account_metas.len());
+        account_infos.push(source_account_info.clone());
+        account_infos.push(mint_info.clone());
+        account_infos.push(destination_account_info.clone());
+        account_infos.push(authority_info.clone());
+        account_infos.push(validation_info.clone());
+        for extra_account_meta in &extra_account_metas {
+            account_infos.push(find_account_info(&extra_account_meta.pubkey)?.clone());
+        }
+        account_infos.push(hook_program_info.clone());
+
+        let instruction = execute_instruction(
+            hook_program_id,
+            source_account_info.key,
+            mint_info.key,
+            destination_account_info.key,
+            authority_info.key,
+            &extra_account_metas,
+            amount,
+        );
+
+        Self::set_transferring(mint_info, true)?;
+        invoke(&instruction, &account_infos)?;
+        Self::set_transferring(mint_info, false)
+    }
+
+    /// Validates that `authority_info` may spend `amount` from the source
+    /// account, as the mint's permanent delegate, the account's owner, its
+    /// single delegate, or a delegate with an allowance. Only `spent`, the part
+    /// of `amount` that actually leaves the account, is deducted from a
+    /// delegate's approval.
+    #[allow(clippy::too_many_arguments)]
+    fn validate_spender(
+        program_id: &Pubkey,
+        source_account_info: &AccountInfo,
+        source_account: &mut Account,
+        permanent_delegate: Option<Pubkey>,
+        authority_info: &AccountInfo,
+        signers: &[AccountInfo],
+        amount: u64,
+        spent: u64,
+    ) -> ProgramResult {
+        if let Some(permanent_delegate) = permanent_delegate {
+            if Self::cmp_pubkeys(authority_info.key, &permanent_delegate) {
+                return Self::validate_owner(
+                    program_id,
+                    &permanent_delegate,
+                    authority_info,
+                    signers,
+                );
+            }
+        }
+
+        match source_account.delegate {
+            COption::Some(ref delegate) if Self::cmp_pubkeys(authority_info.key, delegate) => {
+                Self::validate_owner(program_id, delegate, authority_info, signers)?;
+                if source_account.delegated_amount < amount {
+                    return Err(TokenError::InsufficientFunds.into());
+                }
+                if spent > 0 {
+                    source_account.delegated_amount = source_account
+                        .delegated_amount
+                        .checked_sub(spent)
+                        .ok_or(TokenError::Overflow)?;
+                    if source_account.delegated_amount == 0 {
+                        source_account.delegate = COption::None;
+                    }
+                }
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let mut source_data = source_account_info.data.borrow_mut();
+        let allowance = get_extension::<Allowances>(&source_data)?
+            .and_then(|allowances| Some((*allowances.get(authority_info.key)?, allowances)));
+        let Some((allowance, mut allowances)) = allowance else {
+            return Self::validate_owner(
+                program_id,
+                &source_account.owner,
+                authority_info,
+                signers,
+            );
+        };
+
+        Self::validate_owner(program_id, &allowance.delegate, authority_info, signers)?;
+        if allowance.amount < amount {
+            return Err(TokenError::InsufficientFunds.into());
+        }
+        allowances.spend(&allowance.delegate, spent, &get_clock())?;
+        set_extension(&mut source_data, allowances)
+    }
+
     /// Validates owner(s) are present
     pub fn validate_owner(
         program_id: &Pubkey,
@@ -841,6 +2225,28 @@ impl Processor {
                 return Err(ProgramError::MissingRequiredSignature);
             }
             return Ok(());
+        } else if Self::cmp_pubkeys(program_id, owner_account_info.owner)
+            && owner_account_info.data_len() == WeightedMultisig::get_packed_len()
+        {
+            let multisig = WeightedMultisig::unpack(&owner_account_info.data.borrow())?;
+            let mut weight = 0u16;
+            let mut matched = [false; MAX_WEIGHTED_SIGNERS];
+            for signer in signers.iter() {
+                for (position, weighted_signer) in multisig.signers().iter().enumerate() {
+                    if Self::cmp_pubkeys(&weighted_signer.pubkey, signer.key) && !matched[position]
+                    {
+                        if !signer.is_signer {
+                            return Err(ProgramError::MissingRequiredSignature);
+                        }
+                        matched[position] = true;
+                        weight += weighted_signer.weight as u16;
+                    }
+                }
+            }
+            if weight < multisig.threshold {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            return Ok(());
         } else if !owner_account_info.is_signer {
             return Err(ProgramError::MissingRequiredSignature);
         }
@@ -866,3 +2272,1073 @@ fn delete_account(account_info: &AccountInfo) -> Result<(), ProgramError> {
     account_info.set_owner(&Pubkey::system_program());
     account_info.realloc(0, false)
 }
+
+#[cfg(test)]
+mod tests {
+    use {super::*, arch_program::utxo::UtxoMeta};
+
+    struct TestAccount {
+        key: Pubkey,
+        owner: Pubkey,
+        data: Vec<u8>,
+        utxo: UtxoMeta,
+        is_signer: bool,
+        is_writable: bool,
+    }
+
+    impl TestAccount {
+        fn new(owner: &Pubkey, data: Vec<u8>) -> Self {
+            Self {
+                key: Pubkey::new_unique(),
+                owner: *owner,
+                data,
+                utxo: UtxoMeta::from([0; 32], 0),
+                is_signer: false,
+                is_writable: true,
+            }
+        }
+
+        fn wallet() -> Self {
+            Self::new(&Pubkey::system_program(), vec![]).signer()
+        }
+
+        fn token_account(
+            mint: &Pubkey,
+            owner: &Pubkey,
+            amount: u64,
+            extension_types: &[ExtensionType],
+        ) -> Self {
+            let len =
+                ExtensionType::try_calculate_account_len(AccountType::Account, extension_types)
+                    .unwrap();
+            let mut data = vec![0; len];
+            let account = Account {
+                mint: *mint,
+                owner: *owner,
+                amount,
+                state: AccountState::Initialized,
+                ..Account::default()
+            };
+            Account::pack_base(account, &mut data).unwrap();
+            Self::new(&crate::id(), data)
+        }
+
+        fn mint(decimals: u8, extension_types: &[ExtensionType]) -> Self {
+            let len = ExtensionType::try_calculate_account_len(AccountType::Mint, extension_types)
+                .unwrap();
+            let mut data = vec![0; len];
+            let mint = Mint {
+                mint_authority: COption::Some(Pubkey::new_unique()),
+                decimals,
+                is_initialized: true,
+                ..Mint::default()
+            };
+            Mint::pack_base(mint, &mut data).unwrap();
+            Self::new(&crate::id(), data)
+        }
+
+        fn account(&self) -> Account {
+            Account::unpack_base(&self.data).unwrap()
+        }
+
+        fn set_account(&mut self, account: Account) {
+            Account::pack_base(account, &mut self.data).unwrap();
+        }
+
+        fn mint_state(&self) -> Mint {
+            Mint::unpack_base(&self.data).unwrap()
+        }
+
+        fn set_mint(&mut self, mint: Mint) {
+            Mint::pack_base(mint, &mut self.data).unwrap();
+        }
+
+        fn with_key(self, key: &Pubkey) -> Self {
+            Self { key: *key, ..self }
+        }
+
+        fn signer(self) -> Self {
+            Self {
+                is_signer: true,
+                ..self
+            }
+        }
+
+        fn info(&mut self) -> AccountInfo<'_> {
+            AccountInfo::new(
+                &self.key,
+                &mut self.data,
+                &self.owner,
+                &self.utxo,
+                self.is_signer,
+                self.is_writable,
+                false,
+            )
+        }
+    }
+
+    #[test]
+    fn test_owner_change_resets_allowances() {
+        let program_id = crate::id();
+        let mint = Pubkey::new_unique();
+        let mut owner = TestAccount::wallet();
+        let mut new_owner = TestAccount::wallet();
+        let mut delegate = TestAccount::wallet();
+        let mut source =
+            TestAccount::token_account(&mint, &owner.key, 100, &[ExtensionType::Allowances]);
+        let mut destination = TestAccount::token_account(&mint, &owner.key, 0, &[]);
+
+        Processor::process_approve_allowance(
+            &program_id,
+            &[source.info(), delegate.info(), owner.info()],
+            50,
+            AllowanceExpiry::Never,
+        )
+        .unwrap();
+        Processor::process_transfer(
+            &program_id,
+            &[source.info(), destination.info(), delegate.info()],
+            10,
+            None,
+        )
+        .unwrap();
+
+        Processor::process_set_authority(
+            &program_id,
+            &[source.info(), owner.info()],
+            AuthorityType::AccountOwner,
+            COption::Some(new_owner.key),
+        )
+        .unwrap();
+        assert_eq!(
+            get_extension::<Allowances>(&source.data).unwrap(),
+            Some(Allowances::default())
+        );
+        assert_eq!(
+            Processor::process_transfer(
+                &program_id,
+                &[source.info(), destination.info(), delegate.info()],
+                10,
+                None,
+            ),
+            Err(TokenError::OwnerMismatch.into())
+        );
+
+        Processor::process_transfer(
+            &program_id,
+            &[source.info(), destination.info(), new_owner.info()],
+            10,
+            None,
+        )
+        .unwrap();
+        assert_eq!(source.account().amount, 80);
+    }
+
+    #[test]
+    fn test_transfer_batch() {
+        let program_id = crate::id();
+        let mint = Pubkey::new_unique();
+        let mut owner = TestAccount::wallet();
+        let mut source = TestAccount::token_account(&mint, &owner.key, 100, &[]);
+        let mut destination_a = TestAccount::token_account(&mint, &owner.key, 0, &[]);
+        let mut destination_b = TestAccount::token_account(&mint, &owner.key, 0, &[]);
+
+        // Repeated destinations receive every leg, and legs back to the
+        // source only count toward the total spent
+        let source_info = source.info();
+        let destination_a_info = destination_a.info();
+        let owner_info = owner.info();
+        Processor::process_transfer_batch(
+            &program_id,
+            &[
+                source_info.clone(),
+                destination_a_info.clone(),
+                destination_a_info.clone(),
+                source_info.clone(),
+                destination_b.info(),
+                owner_info.clone(),
+            ],
+            &[10, 20, 30, 5],
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            Processor::process_transfer_batch(
+                &program_id,
+                &[
+                    source_info.clone(),
+                    destination_a_info,
+                    source_info,
+                    owner_info,
+                ],
+                &[10, 60],
+                None,
+            ),
+            Err(TokenError::InsufficientFunds.into())
+        );
+        assert_eq!(source.account().amount, 65);
+        assert_eq!(destination_a.account().amount, 30);
+        assert_eq!(destination_b.account().amount, 5);
+
+        assert_eq!(
+            Processor::process_transfer_batch(
+                &program_id,
+                &[
+                    source.info(),
+                    destination_a.info(),
+                    destination_b.info(),
+                    owner.info(),
+                ],
+                &[u64::MAX, 1],
+                None,
+            ),
+            Err(TokenError::Overflow.into())
+        );
+        assert_eq!(source.account().amount, 65);
+    }
+
+    #[test]
+    fn test_transfer_batch_delegate() {
+        let program_id = crate::id();
+        let mint = Pubkey::new_unique();
+        let mut owner = TestAccount::wallet();
+        let mut delegate = TestAccount::wallet();
+        let mut source = TestAccount::token_account(&mint, &owner.key, 100, &[]);
+        let mut destination_a = TestAccount::token_account(&mint, &owner.key, 0, &[]);
+        let mut destination_b = TestAccount::token_account(&mint, &owner.key, 0, &[]);
+
+        Processor::process_approve(
+            &program_id,
+            &[source.info(), delegate.info(), owner.info()],
+            50,
+            None,
+        )
+        .unwrap();
+
+        // The delegation covers the whole batch and drops by its total
+        Processor::process_transfer_batch(
+            &program_id,
+            &[
+                source.info(),
+                destination_a.info(),
+                destination_b.info(),
+                delegate.info(),
+            ],
+            &[10, 15],
+            None,
+        )
+        .unwrap();
+        assert_eq!(source.account().delegated_amount, 25);
+        assert_eq!(source.account().amount, 75);
+
+        assert_eq!(
+            Processor::process_transfer_batch(
+                &program_id,
+                &[
+                    source.info(),
+                    destination_a.info(),
+                    destination_b.info(),
+                    delegate.info(),
+                ],
+                &[20, 6],
+                None,
+            ),
+            Err(TokenError::InsufficientFunds.into())
+        );
+        assert_eq!(source.account().delegated_amount, 25);
+    }
+
+    #[test]
+    fn test_transfer_batch_atomic() {
+        let program_id = crate::id();
+        let mint = Pubkey::new_unique();
+        let mut owner = TestAccount::wallet();
+        let mut source = TestAccount::token_account(&mint, &owner.key, 100, &[]);
+        let mut destination = TestAccount::token_account(&mint, &owner.key, 0, &[]);
+        let mut frozen = TestAccount::token_account(&mint, &owner.key, 0, &[]);
+        frozen.set_account(Account {
+            state: AccountState::Frozen,
+            ..frozen.account()
+        });
+        let mut foreign = TestAccount::token_account(&Pubkey::new_unique(), &owner.key, 0, &[]);
+
+        // A destination failing partway through leaves every balance as it was
+        for (invalid, error) in [
+            (&mut frozen, TokenError::AccountFrozen),
+            (&mut foreign, TokenError::MintMismatch),
+        ] {
+            let destination_info = destination.info();
+            assert_eq!(
+                Processor::process_transfer_batch(
+                    &program_id,
+                    &[
+                        source.info(),
+                        destination_info.clone(),
+                        invalid.info(),
+                        destination_info,
+                        owner.info(),
+                    ],
+                    &[10, 10, 10],
+                    None,
+                ),
+                Err(error.into())
+            );
+            assert_eq!(invalid.account().amount, 0);
+        }
+        assert_eq!(source.account().amount, 100);
+        assert_eq!(destination.account().amount, 0);
+    }
+
+    #[test]
+    fn test_transfer_hook_reentrancy() {
+        let program_id = crate::id();
+        let hook_program_id = Pubkey::new_unique();
+        let mut mint = TestAccount::mint(2, &[ExtensionType::TransferHook]);
+        let transfer_hook = TransferHook {
+            program_id: hook_program_id,
+            ..TransferHook::default()
+        };
+        set_extension(&mut mint.data, transfer_hook).unwrap();
+        let mut owner = TestAccount::wallet();
+        let mut accounts: Vec<TestAccount> = (0..4)
+            .map(|_| {
+                let mut account = TestAccount::token_account(
+                    &mint.key,
+                    &owner.key,
+                    100,
+                    &[ExtensionType::TransferHookAccount],
+                );
+                set_extension(&mut account.data, TransferHookAccount).unwrap();
+                account
+            })
+            .collect();
+        let [source, destination, other_source, other_destination] = &mut accounts[..] else {
+            unreachable!()
+        };
+        let mut hook_program =
+            TestAccount::new(&Pubkey::default(), vec![]).with_key(&hook_program_id);
+        let mut validation = TestAccount::new(&hook_program_id, vec![]).with_key(
+            &get_extra_account_metas_address(&mint.key, &hook_program_id),
+        );
+
+        // Transfers of the mint must name it, so the hook is not skipped
+        assert_eq!(
+            Processor::process_transfer(
+                &program_id,
+                &[source.info(), destination.info(), owner.info()],
+                10,
+                None,
+            ),
+            Err(TokenError::MintRequiredForTransfer.into())
+        );
+
+        // The guard is kept on the mint, which must then be writable
+        mint.is_writable = false;
+        assert_eq!(
+            Processor::process_transfer(
+                &program_id,
+                &[
+                    source.info(),
+                    mint.info(),
+                    destination.info(),
+                    owner.info(),
+                    hook_program.info(),
+                    validation.info(),
+                ],
+                10,
+                Some(2),
+            ),
+            Err(TokenError::MintNotWritable.into())
+        );
+        mint.is_writable = true;
+
+        // The mint is only marked as transferring while the hook runs
+        Processor::process_transfer(
+            &program_id,
+            &[
+                source.info(),
+                mint.info(),
+                destination.info(),
+                owner.info(),
+                hook_program.info(),
+                validation.info(),
+            ],
+            10,
+            Some(2),
+        )
+        .unwrap();
+        assert_eq!(
+            get_extension::<TransferHook>(&mint.data).unwrap(),
+            Some(transfer_hook)
+        );
+
+        // While it runs, the hook cannot move tokens of the mint, even
+        // between accounts unrelated to the transfer
+        set_extension(
+            &mut mint.data,
+            TransferHook {
+                transferring: true,
+                ..transfer_hook
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            Processor::process_transfer(
+                &program_id,
+                &[
+                    other_source.info(),
+                    mint.info(),
+                    other_destination.info(),
+                    owner.info(),
+                ],
+                10,
+                Some(2),
+            ),
+            Err(TokenError::TransferHookReentrancy.into())
+        );
+        assert_eq!(
+            Processor::process_transfer_batch(
+                &program_id,
+                &[
+                    other_source.info(),
+                    mint.info(),
+                    other_destination.info(),
+                    owner.info(),
+                ],
+                &[10],
+                Some(2),
+            ),
+            Err(TokenError::TransferHookReentrancy.into())
+        );
+    }
+
+    #[test]
+    fn test_non_transferable() {
+        let program_id = crate::id();
+        let mut mint = TestAccount::mint(0, &[ExtensionType::NonTransferable]);
+        set_extension(&mut mint.data, NonTransferable::default()).unwrap();
+        mint.set_mint(Mint {
+            supply: 110,
+            ..mint.mint_state()
+        });
+        let mut owner = TestAccount::wallet();
+        let mut source = TestAccount::token_account(
+            &mint.key,
+            &owner.key,
+            100,
+            &[ExtensionType::NonTransferableAccount],
+        );
+        set_extension(&mut source.data, NonTransferableAccount).unwrap();
+        let mut unmarked = TestAccount::token_account(&mint.key, &owner.key, 10, &[]);
+        let mut destination = TestAccount::token_account(&mint.key, &owner.key, 0, &[]);
+
+        // The account marker refuses transfers that do not name the mint, and
+        // the mint refuses those that do
+        assert_eq!(
+            Processor::process_transfer(
+                &program_id,
+                &[source.info(), destination.info(), owner.info()],
+                10,
+                None,
+            ),
+            Err(TokenError::NonTransferable.into())
+        );
+        for source in [&mut source, &mut unmarked] {
+            assert_eq!(
+                Processor::process_transfer(
+                    &program_id,
+                    &[source.info(), mint.info(), destination.info(), owner.info()],
+                    10,
+                    Some(0),
+                ),
+                Err(TokenError::NonTransferable.into())
+            );
+        }
+        assert_eq!(
+            Processor::process_transfer_batch(
+                &program_id,
+                &[source.info(), destination.info(), owner.info()],
+                &[10],
+                None,
+            ),
+            Err(TokenError::NonTransferable.into())
+        );
+        assert_eq!(destination.account().amount, 0);
+
+        // Tokens can still be burned, and the emptied account closed
+        Processor::process_burn(
+            &program_id,
+            &[source.info(), mint.info(), owner.info()],
+            100,
+            Some(0),
+        )
+        .unwrap();
+        assert_eq!(source.account().amount, 0);
+        assert_eq!(mint.mint_state().supply, 10);
+        Processor::process_close_account(
+            &program_id,
+            &[source.info(), destination.info(), owner.info()],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_permanent_delegate() {
+        let program_id = crate::id();
+        let mut delegate = TestAccount::wallet();
+        let mut mint = TestAccount::mint(2, &[ExtensionType::PermanentDelegate]);
+        set_extension(
+            &mut mint.data,
+            PermanentDelegate {
+                delegate: delegate.key,
+            },
+        )
+        .unwrap();
+        mint.set_mint(Mint {
+            supply: 100,
+            ..mint.mint_state()
+        });
+        let owner = TestAccount::wallet();
+        let mut source = TestAccount::token_account(&mint.key, &owner.key, 100, &[]);
+        let mut destination = TestAccount::token_account(&mint.key, &delegate.key, 0, &[]);
+
+        // The permanent delegate is only recognized when the mint is named
+        assert_eq!(
+            Processor::process_transfer(
+                &program_id,
+                &[source.info(), destination.info(), delegate.info()],
+                30,
+                None,
+            ),
+            Err(TokenError::OwnerMismatch.into())
+        );
+
+        // It spends and burns from an account it was never approved on
+        Processor::process_transfer(
+            &program_id,
+            &[
+                source.info(),
+                mint.info(),
+                destination.info(),
+                delegate.info(),
+            ],
+            30,
+            Some(2),
+        )
+        .unwrap();
+        Processor::process_burn(
+            &program_id,
+            &[source.info(), mint.info(), delegate.info()],
+            20,
+            Some(2),
+        )
+        .unwrap();
+        assert_eq!(source.account().amount, 50);
+        assert_eq!(source.account().delegate, COption::None);
+        assert_eq!(destination.account().amount, 30);
+        assert_eq!(mint.mint_state().supply, 80);
+    }
+
+    #[test]
+    fn test_set_authority_non_transferable_and_permanent_delegate() {
+        let program_id = crate::id();
+        let mut old_authority = TestAccount::wallet();
+        let mut new_authority = TestAccount::wallet();
+        let new_key = new_authority.key;
+        let mut mint = TestAccount::mint(
+            0,
+            &[
+                ExtensionType::NonTransferable,
+                ExtensionType::PermanentDelegate,
+            ],
+        );
+        set_extension(
+            &mut mint.data,
+            NonTransferable {
+                authority: old_authority.key,
+            },
+        )
+        .unwrap();
+        set_extension(
+            &mut mint.data,
+            PermanentDelegate {
+                delegate: old_authority.key,
+            },
+        )
+        .unwrap();
+        let authority = |data: &[u8], authority_type: &AuthorityType| match authority_type {
+            AuthorityType::NonTransferable => get_extension::<NonTransferable>(data)
+                .unwrap()
+                .unwrap()
+                .authority(),
+            _ => get_extension::<PermanentDelegate>(data)
+                .unwrap()
+                .unwrap()
+                .delegate(),
+        };
+
+        for authority_type in [
+            AuthorityType::NonTransferable,
+            AuthorityType::PermanentDelegate,
+        ] {
+            assert_eq!(
+                Processor::process_set_authority(
+                    &program_id,
+                    &[mint.info(), new_authority.info()],
+                    authority_type.clone(),
+                    COption::Some(new_key),
+                ),
+                Err(TokenError::OwnerMismatch.into())
+            );
+
+            // Once rotated, only the new authority can change it again
+            Processor::process_set_authority(
+                &program_id,
+                &[mint.info(), old_authority.info()],
+                authority_type.clone(),
+                COption::Some(new_key),
+            )
+            .unwrap();
+            assert_eq!(authority(&mint.data, &authority_type), Some(new_key));
+            assert_eq!(
+                Processor::process_set_authority(
+                    &program_id,
+                    &[mint.info(), old_authority.info()],
+                    authority_type.clone(),
+                    COption::None,
+                ),
+                Err(TokenError::OwnerMismatch.into())
+            );
+
+            // Once cleared, it cannot be set again
+            Processor::process_set_authority(
+                &program_id,
+                &[mint.info(), new_authority.info()],
+                authority_type.clone(),
+                COption::None,
+            )
+            .unwrap();
+            assert_eq!(authority(&mint.data, &authority_type), None);
+            assert_eq!(
+                Processor::process_set_authority(
+                    &program_id,
+                    &[mint.info(), new_authority.info()],
+                    authority_type,
+                    COption::Some(new_key),
+                ),
+                Err(TokenError::AuthorityTypeNotSupported.into())
+            );
+        }
+    }
+
+    #[test]
+    fn test_default_account_state() {
+        let program_id = crate::id();
+        let mut freeze_authority = TestAccount::wallet();
+        let mut mint = TestAccount::mint(0, &[ExtensionType::DefaultAccountState]);
+        mint.set_mint(Mint {
+            freeze_authority: COption::Some(freeze_authority.key),
+            ..mint.mint_state()
+        });
+        set_extension(
+            &mut mint.data,
+            DefaultAccountState {
+                state: AccountState::Frozen,
+            },
+        )
+        .unwrap();
+        let mut owner = TestAccount::wallet();
+        let mut frozen = TestAccount::new(&program_id, vec![0; Account::LEN]);
+
+        Processor::process_initialize_account(
+            &program_id,
+            &[frozen.info(), mint.info(), owner.info()],
+        )
+        .unwrap();
+        assert_eq!(frozen.account().state, AccountState::Frozen);
+        assert_eq!(frozen.account().owner, owner.key);
+
+        // Only the freeze authority can change the default
+        assert_eq!(
+            Processor::process_update_default_account_state(
+                &program_id,
+                &[mint.info(), owner.info()],
+                AccountState::Initialized,
+            ),
+            Err(TokenError::OwnerMismatch.into())
+        );
+        freeze_authority.is_signer = false;
+        assert_eq!(
+            Processor::process_update_default_account_state(
+                &program_id,
+                &[mint.info(), freeze_authority.info()],
+                AccountState::Initialized,
+            ),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+        freeze_authority.is_signer = true;
+        Processor::process_update_default_account_state(
+            &program_id,
+            &[mint.info(), freeze_authority.info()],
+            AccountState::Initialized,
+        )
+        .unwrap();
+
+        // Accounts created afterwards start out usable, and existing ones
+        // keep their state
+        let mut initialized = TestAccount::new(&program_id, vec![0; Account::LEN]);
+        Processor::process_initialize_account(
+            &program_id,
+            &[initialized.info(), mint.info(), owner.info()],
+        )
+        .unwrap();
+        assert_eq!(initialized.account().state, AccountState::Initialized);
+        assert_eq!(frozen.account().state, AccountState::Frozen);
+
+        // A mint without a freeze authority cannot change its default
+        mint.set_mint(Mint {
+            freeze_authority: COption::None,
+            ..mint.mint_state()
+        });
+        assert_eq!(
+            Processor::process_update_default_account_state(
+                &program_id,
+                &[mint.info(), freeze_authority.info()],
+                AccountState::Frozen,
+            ),
+            Err(TokenError::MintCannotFreeze.into())
+        );
+    }
+
+    #[test]
+    fn test_mint_to_supply_cap() {
+        let program_id = crate::id();
+        let mut mint_authority = TestAccount::wallet();
+        let mut mint = TestAccount::mint(0, &[ExtensionType::SupplyCap]);
+        mint.set_mint(Mint {
+            mint_authority: COption::Some(mint_authority.key),
+            ..mint.mint_state()
+        });
+        set_extension(&mut mint.data, SupplyCap { max_supply: 100 }).unwrap();
+        let mut destination = TestAccount::token_account(&mint.key, &mint_authority.key, 0, &[]);
+
+        Processor::process_mint_to(
+            &program_id,
+            &[mint.info(), destination.info(), mint_authority.info()],
+            60,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            Processor::process_mint_to(
+                &program_id,
+                &[mint.info(), destination.info(), mint_authority.info()],
+                41,
+                Some(0),
+            ),
+            Err(TokenError::SupplyCapExceeded.into())
+        );
+        assert_eq!(destination.account().amount, 60);
+        assert_eq!(mint.mint_state().supply, 60);
+
+        // The cap itself can be reached, but not passed
+        Processor::process_mint_to(
+            &program_id,
+            &[mint.info(), destination.info(), mint_authority.info()],
+            40,
+            Some(0),
+        )
+        .unwrap();
+        assert_eq!(
+            Processor::process_mint_to(
+                &program_id,
+                &[mint.info(), destination.info(), mint_authority.info()],
+                1,
+                None,
+            ),
+            Err(TokenError::SupplyCapExceeded.into())
+        );
+
+        // Burning frees room under the cap again
+        Processor::process_burn(
+            &program_id,
+            &[destination.info(), mint.info(), mint_authority.info()],
+            10,
+            None,
+        )
+        .unwrap();
+        Processor::process_mint_to(
+            &program_id,
+            &[mint.info(), destination.info(), mint_authority.info()],
+            10,
+            None,
+        )
+        .unwrap();
+        assert_eq!(destination.account().amount, 100);
+        assert_eq!(mint.mint_state().supply, 100);
+    }
+
+    /// Signs a permit hash with a fixed nonce, returning the recovery id and
+    /// the compact signature
+    fn sign_permit(secret_key: &[u8; 32], hash: &[u8; 32]) -> (u8, [u8; 64]) {
+        let mut seckey = libsecp256k1::curve::Scalar::default();
+        let _ = seckey.set_b32(secret_key);
+        let mut nonce = libsecp256k1::curve::Scalar::default();
+        let _ = nonce.set_b32(&[3; 32]);
+        let message = libsecp256k1::Message::parse(hash);
+        let (r, s, recovery_id) = libsecp256k1::ECMULT_GEN_CONTEXT
+            .sign_raw(&seckey, &message.0, &nonce)
+            .unwrap();
+        (recovery_id, libsecp256k1::Signature { r, s }.serialize())
+    }
+
+    #[test]
+    fn test_permit() {
+        let program_id = crate::id();
+        let secret_key = [7; 32];
+        let owner = Pubkey::from_slice(
+            &libsecp256k1::PublicKey::from_secret_key(
+                &libsecp256k1::SecretKey::parse(&secret_key).unwrap(),
+            )
+            .serialize()[1..33],
+        );
+        let spender = Pubkey::new_unique();
+        let mut source = TestAccount::token_account(&Pubkey::new_unique(), &owner, 100, &[]);
+        let mut nonce_data = vec![0; PermitNonce::LEN];
+        PermitNonce::pack(
+            PermitNonce {
+                is_initialized: true,
+                nonce: 0,
+            },
+            &mut nonce_data,
+        )
+        .unwrap();
+        let mut permit_nonce = TestAccount::new(&program_id, nonce_data)
+            .with_key(&get_permit_nonce_address(&owner, &program_id).0);
+        let hash = |source: &Pubkey, amount, nonce, deadline| {
+            permit_message_hash(
+                &program_id,
+                source,
+                &owner,
+                &spender,
+                amount,
+                nonce,
+                deadline,
+            )
+        };
+
+        // The host clock stays at timestamp 0
+        let (recovery_id, signature) = sign_permit(&secret_key, &hash(&source.key, 50, 0, 0));
+        Processor::process_permit(
+            &program_id,
+            &[source.info(), permit_nonce.info()],
+            owner,
+            spender,
+            50,
+            0,
+            0,
+            recovery_id,
+            signature,
+        )
+        .unwrap();
+        assert_eq!(source.account().delegate, COption::Some(spender));
+        assert_eq!(source.account().delegated_amount, 50);
+        assert_eq!(PermitNonce::unpack(&permit_nonce.data).unwrap().nonce, 1);
+
+        // A used permit cannot be replayed
+        assert_eq!(
+            Processor::process_permit(
+                &program_id,
+                &[source.info(), permit_nonce.info()],
+                owner,
+                spender,
+                50,
+                0,
+                0,
+                recovery_id,
+                signature,
+            ),
+            Err(TokenError::InvalidPermitNonce.into())
+        );
+
+        // The recovered key must be the owner's, over the exact terms signed
+        for (secret_key, amount) in [([9; 32], 20), (secret_key, 21)] {
+            let (recovery_id, signature) = sign_permit(&secret_key, &hash(&source.key, 20, 1, 0));
+            assert_eq!(
+                Processor::process_permit(
+                    &program_id,
+                    &[source.info(), permit_nonce.info()],
+                    owner,
+                    spender,
+                    amount,
+                    1,
+                    0,
+                    recovery_id,
+                    signature,
+                ),
+                Err(TokenError::InvalidPermitSignature.into())
+            );
+        }
+
+        let (recovery_id, signature) = sign_permit(&secret_key, &hash(&source.key, 20, 1, -1));
+        assert_eq!(
+            Processor::process_permit(
+                &program_id,
+                &[source.info(), permit_nonce.info()],
+                owner,
+                spender,
+                20,
+                1,
+                -1,
+                recovery_id,
+                signature,
+            ),
+            Err(TokenError::PermitExpired.into())
+        );
+        assert_eq!(source.account().delegated_amount, 50);
+        assert_eq!(PermitNonce::unpack(&permit_nonce.data).unwrap().nonce, 1);
+    }
+
+    #[test]
+    fn test_weighted_multisig_owner() {
+        let program_id = crate::id();
+        let mut signers: Vec<TestAccount> = (0..3).map(|_| TestAccount::wallet()).collect();
+        let mut multisig = TestAccount::new(&program_id, vec![0; WeightedMultisig::LEN]);
+        let mut accounts = vec![multisig.info()];
+        accounts.extend(signers.iter_mut().map(TestAccount::info));
+        Processor::process_initialize_weighted_multisig(&accounts, 4, &[1, 2, 3]).unwrap();
+        let mint = Pubkey::new_unique();
+        let mut source = TestAccount::token_account(&mint, &multisig.key, 100, &[]);
+        let mut destination = TestAccount::token_account(&mint, &multisig.key, 0, &[]);
+        let [light, _, heavy] = &mut signers[..] else {
+            unreachable!()
+        };
+
+        // Below the threshold, even when a signer is listed twice
+        let heavy_info = heavy.info();
+        assert_eq!(
+            Processor::process_transfer(
+                &program_id,
+                &[
+                    source.info(),
+                    destination.info(),
+                    multisig.info(),
+                    heavy_info.clone(),
+                    heavy_info,
+                ],
+                10,
+                None,
+            ),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+
+        // A listed signer must have signed
+        light.is_signer = false;
+        assert_eq!(
+            Processor::process_transfer(
+                &program_id,
+                &[
+                    source.info(),
+                    destination.info(),
+                    multisig.info(),
+                    light.info(),
+                    heavy.info(),
+                ],
+                10,
+                None,
+            ),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+        assert_eq!(source.account().amount, 100);
+
+        // At the threshold
+        light.is_signer = true;
+        Processor::process_transfer(
+            &program_id,
+            &[
+                source.info(),
+                destination.info(),
+                multisig.info(),
+                light.info(),
+                heavy.info(),
+            ],
+            10,
+            None,
+        )
+        .unwrap();
+        assert_eq!(source.account().amount, 90);
+        assert_eq!(destination.account().amount, 10);
+    }
+
+    #[test]
+    fn test_required_transfer_memos() {
+        let program_id = crate::id();
+        let mut mint = TestAccount::mint(2, &[]);
+        let mut owner = TestAccount::wallet();
+        let mut source = TestAccount::token_account(&mint.key, &owner.key, 100, &[]);
+        let mut destination =
+            TestAccount::token_account(&mint.key, &owner.key, 0, &[ExtensionType::MemoTransfer]);
+        Processor::process_toggle_required_transfer_memos(
+            &program_id,
+            &[destination.info(), owner.info()],
+            true,
+        )
+        .unwrap();
+
+        // Transfers without a memo, or with an empty one, are refused
+        assert_eq!(
+            Processor::process_transfer(
+                &program_id,
+                &[source.info(), destination.info(), owner.info()],
+                10,
+                None,
+            ),
+            Err(TokenError::NoMemo.into())
+        );
+        assert_eq!(
+            Processor::process_transfer_batch(
+                &program_id,
+                &[source.info(), destination.info(), owner.info()],
+                &[10],
+                None,
+            ),
+            Err(TokenError::NoMemo.into())
+        );
+        assert_eq!(
+            Processor::process_transfer_with_memo(
+                &program_id,
+                &[source.info(), mint.info(), destination.info(), owner.info()],
+                10,
+                2,
+                "",
+            ),
+            Err(TokenError::NoMemo.into())
+        );
+        assert_eq!(destination.account().amount, 0);
+
+        Processor::process_transfer_with_memo(
+            &program_id,
+            &[source.info(), mint.info(), destination.info(), owner.info()],
+            10,
+            2,
+            "invoice 7",
+        )
+        .unwrap();
+        assert_eq!(destination.account().amount, 10);
+
+        Processor::process_toggle_required_transfer_memos(
+            &program_id,
+            &[destination.info(), owner.info()],
+            false,
+        )
+        .unwrap();
+        Processor::process_transfer(
+            &program_id,
+            &[source.info(), destination.info(), owner.info()],
+            10,
+            None,
+        )
+        .unwrap();
+        assert_eq!(destination.account().amount, 20);
+    }
+}
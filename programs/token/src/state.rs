@@ -1,7 +1,7 @@
 //! State transition types
 
 use {
-    crate::instruction::MAX_SIGNERS,
+    crate::instruction::{MAX_SIGNERS, MAX_WEIGHTED_SIGNERS},
     arch_program::{
         program_error::ProgramError,
         program_option::COption,
@@ -239,6 +239,164 @@ impl Pack for Multisig {
     }
 }
 
+/// Signer of a weighted multisignature account.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WeightedSigner {
+    /// Signer public key
+    pub pubkey: Pubkey,
+    /// Weight the signer contributes towards the threshold
+    pub weight: u8,
+}
+
+/// Weighted multisignature data. A set of signers is sufficient once the sum
+/// of their weights reaches the threshold. Unlike `Multisig`, the signers and
+/// threshold can be changed after initialization.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WeightedMultisig {
+    /// Total weight of signers required
+    pub threshold: u16,
+    /// Number of valid signers
+    pub n: u8,
+    /// Is `true` if this structure has been initialized
+    pub is_initialized: bool,
+    /// Signers, of which the first `n` are valid
+    pub signers: [WeightedSigner; MAX_WEIGHTED_SIGNERS],
+}
+impl WeightedMultisig {
+    /// Valid signers
+    pub fn signers(&self) -> &[WeightedSigner] {
+        &self.signers[..self.n as usize]
+    }
+
+    /// Sum of the weights of all valid signers
+    pub fn total_weight(&self) -> u16 {
+        self.signers()
+            .iter()
+            .map(|signer| signer.weight as u16)
+            .sum()
+    }
+
+    /// Checks if `threshold` can be reached by the valid signers
+    pub fn is_valid_threshold(&self, threshold: u16) -> bool {
+        threshold > 0 && threshold <= self.total_weight()
+    }
+
+    /// Finds the position of `pubkey` among the valid signers
+    pub fn position(&self, pubkey: &Pubkey) -> Option<usize> {
+        self.signers()
+            .iter()
+            .position(|signer| signer.pubkey == *pubkey)
+    }
+
+    /// Appends a signer, returning `false` if all signer slots are taken
+    pub fn push(&mut self, signer: WeightedSigner) -> bool {
+        match self.signers.get_mut(self.n as usize) {
+            Some(slot) => {
+                *slot = signer;
+                self.n += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes the signer at `position`, keeping the order of the others
+    pub fn remove(&mut self, position: usize) {
+        let n = self.n as usize;
+        self.signers.copy_within(position + 1..n, position);
+        self.signers[n - 1] = WeightedSigner::default();
+        self.n -= 1;
+    }
+}
+impl Sealed for WeightedMultisig {}
+impl IsInitialized for WeightedMultisig {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+impl Pack for WeightedMultisig {
+    const LEN: usize = 4 + 33 * MAX_WEIGHTED_SIGNERS;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, WeightedMultisig::LEN];
+        let (threshold, n, is_initialized, signers_flat) =
+            array_refs![src, 2, 1, 1, 33 * MAX_WEIGHTED_SIGNERS];
+        if n[0] as usize > MAX_WEIGHTED_SIGNERS {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mut result = WeightedMultisig {
+            threshold: u16::from_le_bytes(*threshold),
+            n: n[0],
+            is_initialized: match is_initialized {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            signers: [WeightedSigner::default(); MAX_WEIGHTED_SIGNERS],
+        };
+        for (src, dst) in signers_flat.chunks(33).zip(result.signers.iter_mut()) {
+            let (pubkey, weight) = array_refs![array_ref![src, 0, 33], 32, 1];
+            *dst = WeightedSigner {
+                pubkey: Pubkey::from_slice(pubkey),
+                weight: weight[0],
+            };
+        }
+        Ok(result)
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, WeightedMultisig::LEN];
+        let (threshold, n, is_initialized, signers_flat) =
+            mut_array_refs![dst, 2, 1, 1, 33 * MAX_WEIGHTED_SIGNERS];
+        *threshold = self.threshold.to_le_bytes();
+        *n = [self.n];
+        *is_initialized = [self.is_initialized as u8];
+        for (src, dst) in self.signers.iter().zip(signers_flat.chunks_mut(33)) {
+            let (pubkey, weight) = mut_array_refs![array_mut_ref![dst, 0, 33], 32, 1];
+            pubkey.copy_from_slice(src.pubkey.as_ref());
+            *weight = [src.weight];
+        }
+    }
+}
+
+/// Permit nonce of an owner, stored at the address derived by
+/// [`get_permit_nonce_address`](crate::permit::get_permit_nonce_address).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PermitNonce {
+    /// Is `true` if this structure has been initialized
+    pub is_initialized: bool,
+    /// Nonce the next permit of the owner must carry
+    pub nonce: u64,
+}
+impl Sealed for PermitNonce {}
+impl IsInitialized for PermitNonce {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+impl Pack for PermitNonce {
+    const LEN: usize = 9;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 9];
+        let (is_initialized, nonce) = array_refs![src, 1, 8];
+        Ok(PermitNonce {
+            is_initialized: match is_initialized {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            nonce: u64::from_le_bytes(*nonce),
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 9];
+        let (is_initialized, nonce) = mut_array_refs![dst, 1, 8];
+        *is_initialized = [self.is_initialized as u8];
+        *nonce = self.nonce.to_le_bytes();
+    }
+}
+
 // Helpers
 fn pack_coption_key(src: &COption<Pubkey>, dst: &mut [u8; 36]) {
     let (tag, body) = mut_array_refs![dst, 4, 32];
@@ -362,6 +520,24 @@ mod tests {
         assert_eq!(mint, ProgramError::InvalidAccountData);
     }
 
+    #[test]
+    fn test_permit_nonce_pack_unpack() {
+        let permit_nonce = PermitNonce {
+            is_initialized: true,
+            nonce: 7,
+        };
+        let mut packed = [0u8; PermitNonce::LEN];
+        PermitNonce::pack(permit_nonce, &mut packed).unwrap();
+        assert_eq!(packed, [1, 7, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(PermitNonce::unpack(&packed).unwrap(), permit_nonce);
+
+        packed[0] = 2;
+        assert_eq!(
+            PermitNonce::unpack_from_slice(&packed).unwrap_err(),
+            ProgramError::InvalidAccountData
+        );
+    }
+
     #[test]
     fn test_account_state() {
         let account_state = AccountState::default();
@@ -391,6 +567,51 @@ mod tests {
         assert_eq!(multisig, ProgramError::InvalidAccountData);
     }
 
+    #[test]
+    fn test_weighted_multisig() {
+        let mut multisig = WeightedMultisig::default();
+        let signers: Vec<WeightedSigner> = (1..=3)
+            .map(|weight| WeightedSigner {
+                pubkey: Pubkey::new_unique(),
+                weight,
+            })
+            .collect();
+        for signer in &signers {
+            assert!(multisig.push(*signer));
+        }
+        assert_eq!(multisig.total_weight(), 6);
+        assert!(multisig.is_valid_threshold(6));
+        assert!(!multisig.is_valid_threshold(7));
+        assert!(!multisig.is_valid_threshold(0));
+
+        multisig.remove(0);
+        assert_eq!(multisig.signers(), &signers[1..]);
+        assert_eq!(multisig.position(&signers[2].pubkey), Some(1));
+        assert_eq!(multisig.position(&signers[0].pubkey), None);
+        assert_eq!(multisig.signers[2], WeightedSigner::default());
+
+        multisig.threshold = 4;
+        multisig.is_initialized = true;
+        let mut packed = [0u8; WeightedMultisig::LEN];
+        multisig.pack_into_slice(&mut packed);
+        assert_eq!(
+            WeightedMultisig::unpack_from_slice(&packed).unwrap(),
+            multisig
+        );
+
+        let mut full = WeightedMultisig::default();
+        for _ in 0..MAX_WEIGHTED_SIGNERS {
+            assert!(full.push(signers[0]));
+        }
+        assert!(!full.push(signers[0]));
+
+        packed[2] = MAX_WEIGHTED_SIGNERS as u8 + 1;
+        assert_eq!(
+            WeightedMultisig::unpack_from_slice(&packed).unwrap_err(),
+            ProgramError::InvalidAccountData
+        );
+    }
+
     #[test]
     fn test_unpack_coption_key() {
         let src: [u8; 36] = [0; 36];
@@ -0,0 +1,23 @@
+//! Program entrypoint
+
+use {
+    crate::{error::VestingError, processor::Processor},
+    arch_program::{
+        account::AccountInfo, entrypoint::ProgramResult, program_error::PrintProgramError,
+        pubkey::Pubkey,
+    },
+};
+
+arch_program::entrypoint!(process_instruction);
+fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    if let Err(error) = Processor::process(program_id, accounts, instruction_data) {
+        // catch the error so we can print it
+        error.print::<VestingError>();
+        return Err(error);
+    }
+    Ok(())
+}
@@ -0,0 +1,84 @@
+//! Error types
+
+use {
+    arch_program::{
+        decode_error::DecodeError,
+        msg,
+        program_error::{PrintProgramError, ProgramError},
+    },
+    num_derive::FromPrimitive,
+    thiserror::Error,
+};
+
+/// Errors that may be returned by the Vesting program.
+#[derive(Clone, Debug, Eq, Error, FromPrimitive, PartialEq)]
+pub enum VestingError {
+    // 0
+    /// Invalid instruction
+    #[error("Invalid instruction")]
+    InvalidInstruction,
+    /// The schedule cannot be initialized because it is already being used.
+    #[error("Already in use")]
+    AlreadyInUse,
+    /// The schedule's timestamps or amount are inconsistent.
+    #[error("Invalid schedule")]
+    InvalidSchedule,
+    /// The vault is not a token account of the schedule's mint owned by its
+    /// vault authority.
+    #[error("Invalid vault")]
+    InvalidVault,
+    /// The beneficiary does not match the schedule.
+    #[error("Beneficiary mismatch")]
+    BeneficiaryMismatch,
+
+    // 5
+    /// The grantor does not match the schedule.
+    #[error("Grantor mismatch")]
+    GrantorMismatch,
+    /// The schedule cannot be revoked by its grantor.
+    #[error("Schedule not revocable")]
+    NotRevocable,
+    /// The schedule has already been revoked.
+    #[error("Schedule already revoked")]
+    AlreadyRevoked,
+    /// No vested tokens are left to claim.
+    #[error("Nothing to claim")]
+    NothingToClaim,
+    /// Operation overflowed
+    #[error("Operation overflowed")]
+    Overflow,
+}
+impl From<VestingError> for ProgramError {
+    fn from(e: VestingError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+impl<T> DecodeError<T> for VestingError {
+    fn type_of() -> &'static str {
+        "VestingError"
+    }
+}
+
+impl PrintProgramError for VestingError {
+    fn print<E>(&self)
+    where
+        E: 'static
+            + std::error::Error
+            + DecodeError<E>
+            + PrintProgramError
+            + num_traits::FromPrimitive,
+    {
+        match self {
+            VestingError::InvalidInstruction => msg!("Error: Invalid instruction"),
+            VestingError::AlreadyInUse => msg!("Error: schedule already in use"),
+            VestingError::InvalidSchedule => msg!("Error: invalid schedule"),
+            VestingError::InvalidVault => msg!("Error: invalid vault"),
+            VestingError::BeneficiaryMismatch => msg!("Error: beneficiary mismatch"),
+            VestingError::GrantorMismatch => msg!("Error: grantor mismatch"),
+            VestingError::NotRevocable => msg!("Error: schedule not revocable"),
+            VestingError::AlreadyRevoked => msg!("Error: schedule already revoked"),
+            VestingError::NothingToClaim => msg!("Error: nothing to claim"),
+            VestingError::Overflow => msg!("Error: Operation overflowed"),
+        }
+    }
+}
@@ -0,0 +1,366 @@
+//! Instruction types
+
+use {
+    crate::{check_program_account, error::VestingError, get_vault_authority_address},
+    arch_program::{
+        account::AccountMeta, instruction::Instruction, program_error::ProgramError, pubkey::Pubkey,
+    },
+    std::{convert::TryInto, mem::size_of},
+};
+
+/// Instructions supported by the vesting program.
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum VestingInstruction {
+    /// Creates a vesting schedule and funds its vault from the grantor's
+    /// tokens. The schedule account must be allocated and assigned to the
+    /// vesting program, and the vault must be a token account of the mint
+    /// owned by the schedule's vault authority.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The schedule account to initialize.
+    ///   1. `[writable]` The vault token account.
+    ///   2. `[]` The token mint.
+    ///   3. `[]` The beneficiary.
+    ///   4. `[writable]` The grantor's source token account.
+    ///   5. `[signer]` The grantor, owner of the source account.
+    ///   6. `[]` The token program.
+    CreateSchedule {
+        /// Unix timestamp from which tokens vest.
+        start_timestamp: i64,
+        /// Unix timestamp before which nothing can be claimed.
+        cliff_timestamp: i64,
+        /// Unix timestamp at which all tokens have vested.
+        end_timestamp: i64,
+        /// Length in seconds of each tranche, or zero to vest continuously.
+        period: i64,
+        /// Amount of tokens moved into the vault.
+        total_amount: u64,
+        /// Whether the grantor can revoke the unvested tokens.
+        revocable: bool,
+    },
+    /// Transfers the vested tokens not claimed yet to the beneficiary.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The schedule account.
+    ///   1. `[writable]` The vault token account.
+    ///   2. `[]` The token mint.
+    ///   3. `[writable]` The destination token account.
+    ///   4. `[signer]` The beneficiary.
+    ///   5. `[]` The vault authority.
+    ///   6. `[]` The token program.
+    Claim,
+    /// Stops vesting and returns the unvested tokens to the grantor. Tokens
+    /// vested so far remain claimable by the beneficiary.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` The schedule account.
+    ///   1. `[writable]` The vault token account.
+    ///   2. `[]` The token mint.
+    ///   3. `[writable]` The token account receiving the unvested tokens.
+    ///   4. `[signer]` The grantor.
+    ///   5. `[]` The vault authority.
+    ///   6. `[]` The token program.
+    Revoke,
+    /// Gets the amount of tokens vested so far, claimed or not.
+    ///
+    /// Return data can be fetched using `sol_get_return_data` and
+    /// deserializing the return data as a little-endian `u64`.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[]` The schedule account.
+    GetVestedAmount,
+}
+
+impl VestingInstruction {
+    /// Unpacks a byte buffer into a
+    /// [`VestingInstruction`](enum.VestingInstruction.html).
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        use VestingError::InvalidInstruction;
+
+        let (&tag, rest) = input.split_first().ok_or(InvalidInstruction)?;
+        Ok(match tag {
+            0 => {
+                let (start_timestamp, rest) = Self::unpack_i64(rest)?;
+                let (cliff_timestamp, rest) = Self::unpack_i64(rest)?;
+                let (end_timestamp, rest) = Self::unpack_i64(rest)?;
+                let (period, rest) = Self::unpack_i64(rest)?;
+                let (total_amount, rest) = Self::unpack_u64(rest)?;
+                let revocable = match rest.first() {
+                    Some(0) => false,
+                    Some(1) => true,
+                    _ => return Err(InvalidInstruction.into()),
+                };
+                Self::CreateSchedule {
+                    start_timestamp,
+                    cliff_timestamp,
+                    end_timestamp,
+                    period,
+                    total_amount,
+                    revocable,
+                }
+            }
+            1 => Self::Claim,
+            2 => Self::Revoke,
+            3 => Self::GetVestedAmount,
+            _ => return Err(InvalidInstruction.into()),
+        })
+    }
+
+    /// Packs a [`VestingInstruction`](enum.VestingInstruction.html) into a
+    /// byte buffer.
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(size_of::<Self>());
+        match self {
+            &Self::CreateSchedule {
+                start_timestamp,
+                cliff_timestamp,
+                end_timestamp,
+                period,
+                total_amount,
+                revocable,
+            } => {
+                buf.push(0);
+                buf.extend_from_slice(&start_timestamp.to_le_bytes());
+                buf.extend_from_slice(&cliff_timestamp.to_le_bytes());
+                buf.extend_from_slice(&end_timestamp.to_le_bytes());
+                buf.extend_from_slice(&period.to_le_bytes());
+                buf.extend_from_slice(&total_amount.to_le_bytes());
+                buf.push(revocable as u8);
+            }
+            Self::Claim => buf.push(1),
+            Self::Revoke => buf.push(2),
+            Self::GetVestedAmount => buf.push(3),
+        };
+        buf
+    }
+
+    fn unpack_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
+        let value = input
+            .get(..8)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(VestingError::InvalidInstruction)?;
+        Ok((value, &input[8..]))
+    }
+
+    fn unpack_i64(input: &[u8]) -> Result<(i64, &[u8]), ProgramError> {
+        let value = input
+            .get(..8)
+            .and_then(|slice| slice.try_into().ok())
+            .map(i64::from_le_bytes)
+            .ok_or(VestingError::InvalidInstruction)?;
+        Ok((value, &input[8..]))
+    }
+}
+
+/// Creates a `CreateSchedule` instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn create_schedule(
+    vesting_program_id: &Pubkey,
+    schedule_pubkey: &Pubkey,
+    vault_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    beneficiary_pubkey: &Pubkey,
+    source_pubkey: &Pubkey,
+    grantor_pubkey: &Pubkey,
+    start_timestamp: i64,
+    cliff_timestamp: i64,
+    end_timestamp: i64,
+    period: i64,
+    total_amount: u64,
+    revocable: bool,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(vesting_program_id)?;
+    let data = VestingInstruction::CreateSchedule {
+        start_timestamp,
+        cliff_timestamp,
+        end_timestamp,
+        period,
+        total_amount,
+        revocable,
+    }
+    .pack();
+
+    let accounts = vec![
+        AccountMeta::new(*schedule_pubkey, false),
+        AccountMeta::new(*vault_pubkey, false),
+        AccountMeta::new_readonly(*mint_pubkey, false),
+        AccountMeta::new_readonly(*beneficiary_pubkey, false),
+        AccountMeta::new(*source_pubkey, false),
+        AccountMeta::new_readonly(*grantor_pubkey, true),
+        AccountMeta::new_readonly(apl_token::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *vesting_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `Claim` instruction.
+pub fn claim(
+    vesting_program_id: &Pubkey,
+    schedule_pubkey: &Pubkey,
+    vault_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    beneficiary_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(vesting_program_id)?;
+
+    Ok(Instruction {
+        program_id: *vesting_program_id,
+        accounts: vec![
+            AccountMeta::new(*schedule_pubkey, false),
+            AccountMeta::new(*vault_pubkey, false),
+            AccountMeta::new_readonly(*mint_pubkey, false),
+            AccountMeta::new(*destination_pubkey, false),
+            AccountMeta::new_readonly(*beneficiary_pubkey, true),
+            AccountMeta::new_readonly(get_vault_authority_address(schedule_pubkey), false),
+            AccountMeta::new_readonly(apl_token::id(), false),
+        ],
+        data: VestingInstruction::Claim.pack(),
+    })
+}
+
+/// Creates a `Revoke` instruction.
+pub fn revoke(
+    vesting_program_id: &Pubkey,
+    schedule_pubkey: &Pubkey,
+    vault_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    refund_pubkey: &Pubkey,
+    grantor_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(vesting_program_id)?;
+
+    Ok(Instruction {
+        program_id: *vesting_program_id,
+        accounts: vec![
+            AccountMeta::new(*schedule_pubkey, false),
+            AccountMeta::new(*vault_pubkey, false),
+            AccountMeta::new_readonly(*mint_pubkey, false),
+            AccountMeta::new(*refund_pubkey, false),
+            AccountMeta::new_readonly(*grantor_pubkey, true),
+            AccountMeta::new_readonly(get_vault_authority_address(schedule_pubkey), false),
+            AccountMeta::new_readonly(apl_token::id(), false),
+        ],
+        data: VestingInstruction::Revoke.pack(),
+    })
+}
+
+/// Creates a `GetVestedAmount` instruction.
+pub fn get_vested_amount(
+    vesting_program_id: &Pubkey,
+    schedule_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(vesting_program_id)?;
+
+    Ok(Instruction {
+        program_id: *vesting_program_id,
+        accounts: vec![AccountMeta::new_readonly(*schedule_pubkey, false)],
+        data: VestingInstruction::GetVestedAmount.pack(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_instruction_packing() {
+        let check = VestingInstruction::CreateSchedule {
+            start_timestamp: 1,
+            cliff_timestamp: 2,
+            end_timestamp: 3,
+            period: 0,
+            total_amount: 4,
+            revocable: true,
+        };
+        let packed = check.pack();
+        let mut expect = vec![0u8];
+        for value in [1u64, 2, 3, 0, 4] {
+            expect.extend_from_slice(&value.to_le_bytes());
+        }
+        expect.push(1);
+        assert_eq!(packed, expect);
+        let unpacked = VestingInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = VestingInstruction::Claim;
+        let packed = check.pack();
+        let expect = vec![1u8];
+        assert_eq!(packed, expect);
+        let unpacked = VestingInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = VestingInstruction::Revoke;
+        let packed = check.pack();
+        let expect = vec![2u8];
+        assert_eq!(packed, expect);
+        let unpacked = VestingInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        let check = VestingInstruction::GetVestedAmount;
+        let packed = check.pack();
+        let expect = vec![3u8];
+        assert_eq!(packed, expect);
+        let unpacked = VestingInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn test_instruction_unpack_errors() {
+        let mut data = VestingInstruction::CreateSchedule {
+            start_timestamp: 1,
+            cliff_timestamp: 2,
+            end_timestamp: 3,
+            period: 0,
+            total_amount: 4,
+            revocable: false,
+        }
+        .pack();
+        *data.last_mut().unwrap() = 2;
+        assert_eq!(
+            VestingInstruction::unpack(&data),
+            Err(VestingError::InvalidInstruction.into())
+        );
+        data.pop();
+        assert_eq!(
+            VestingInstruction::unpack(&data),
+            Err(VestingError::InvalidInstruction.into())
+        );
+        assert_eq!(
+            VestingInstruction::unpack(&[4]),
+            Err(VestingError::InvalidInstruction.into())
+        );
+    }
+
+    #[test]
+    fn test_instruction_builders() {
+        let schedule = Pubkey::new_unique();
+        let instruction = claim(
+            &crate::id(),
+            &schedule,
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+        )
+        .unwrap();
+        assert_eq!(
+            instruction.accounts[5].pubkey,
+            get_vault_authority_address(&schedule)
+        );
+        assert_eq!(
+            get_vested_amount(&Pubkey::new_unique(), &schedule),
+            Err(ProgramError::IncorrectProgramId)
+        );
+    }
+}
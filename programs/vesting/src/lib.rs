@@ -0,0 +1,48 @@
+#![deny(missing_docs)]
+#![cfg_attr(not(test), forbid(unsafe_code))]
+
+//! A program that escrows APL tokens and releases them to a beneficiary
+//! along a cliff, linear or periodic vesting schedule
+
+pub mod error;
+pub mod instruction;
+pub mod processor;
+pub mod state;
+
+#[cfg(not(feature = "no-entrypoint"))]
+mod entrypoint;
+
+// Export current sdk types for downstream users building with a different sdk
+// version
+pub use arch_program;
+use arch_program::{entrypoint::ProgramResult, program_error::ProgramError, pubkey::Pubkey};
+
+/// Seed of the authority owning the vault of a schedule, derived with the
+/// schedule address
+pub const VAULT_AUTHORITY_SEED: &[u8] = b"vault-authority";
+
+/// The program id of the vesting program
+pub fn id() -> Pubkey {
+    Pubkey::from_slice(b"apl-vesting000000000000000000000")
+}
+
+/// Checks that the supplied program ID is the correct one for APL-vesting
+pub fn check_program_account(vesting_program_id: &Pubkey) -> ProgramResult {
+    if vesting_program_id != &id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
+
+/// Derives the authority owning the vault of a schedule, and its bump seed
+pub fn get_vault_authority_address_and_bump_seed(
+    schedule: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[VAULT_AUTHORITY_SEED, &schedule.serialize()], program_id)
+}
+
+/// Derives the authority owning the vault of a schedule
+pub fn get_vault_authority_address(schedule: &Pubkey) -> Pubkey {
+    get_vault_authority_address_and_bump_seed(schedule, &id()).0
+}
@@ -0,0 +1,361 @@
+//! Program state processor
+
+use {
+    crate::{
+        error::VestingError, get_vault_authority_address_and_bump_seed,
+        instruction::VestingInstruction, state::VestingSchedule, VAULT_AUTHORITY_SEED,
+    },
+    apl_token::{
+        extension::BaseState,
+        state::{Account, Mint},
+    },
+    arch_program::{
+        account::{next_account_info, AccountInfo},
+        entrypoint::ProgramResult,
+        msg,
+        program::{get_clock, invoke_signed, set_return_data},
+        program_error::ProgramError,
+        program_pack::Pack,
+        pubkey::Pubkey,
+    },
+};
+
+/// Program state handler.
+pub struct Processor {}
+impl Processor {
+    /// Processes a [`CreateSchedule`](enum.VestingInstruction.html)
+    /// instruction.
+    #[allow(clippy::too_many_arguments)]
+    pub fn process_create_schedule(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        start_timestamp: i64,
+        cliff_timestamp: i64,
+        end_timestamp: i64,
+        period: i64,
+        total_amount: u64,
+        revocable: bool,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let schedule_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let mint_info = next_account_info(account_info_iter)?;
+        let beneficiary_info = next_account_info(account_info_iter)?;
+        let source_info = next_account_info(account_info_iter)?;
+        let grantor_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        Self::check_account_owner(program_id, schedule_info)?;
+        let mut schedule = VestingSchedule::unpack_unchecked(&schedule_info.data.borrow())?;
+        if schedule.is_initialized {
+            return Err(VestingError::AlreadyInUse.into());
+        }
+        if !grantor_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let (vault_authority, vault_authority_bump) =
+            get_vault_authority_address_and_bump_seed(schedule_info.key, program_id);
+        let decimals = Self::check_vault(vault_info, mint_info, &vault_authority)?;
+
+        schedule = VestingSchedule {
+            is_initialized: true,
+            vault_authority_bump,
+            revocable,
+            is_revoked: false,
+            grantor: *grantor_info.key,
+            beneficiary: *beneficiary_info.key,
+            mint: *mint_info.key,
+            vault: *vault_info.key,
+            start_timestamp,
+            cliff_timestamp,
+            end_timestamp,
+            period,
+            revoked_timestamp: 0,
+            total_amount,
+            claimed_amount: 0,
+        };
+        if !schedule.is_valid() {
+            return Err(VestingError::InvalidSchedule.into());
+        }
+        VestingSchedule::pack(schedule, &mut schedule_info.data.borrow_mut())?;
+
+        Self::transfer(
+            token_program_info,
+            source_info,
+            mint_info,
+            vault_info,
+            grantor_info,
+            total_amount,
+            decimals,
+            &[],
+        )
+    }
+
+    /// Processes a [`Claim`](enum.VestingInstruction.html) instruction.
+    pub fn process_claim(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let schedule_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let mint_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        let beneficiary_info = next_account_info(account_info_iter)?;
+        let vault_authority_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        let mut schedule = Self::unpack_schedule(program_id, schedule_info, vault_info)?;
+        if schedule.beneficiary != *beneficiary_info.key {
+            return Err(VestingError::BeneficiaryMismatch.into());
+        }
+        if !beneficiary_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let amount = schedule
+            .claimable_amount(get_clock().unix_timestamp)
+            .ok_or(VestingError::Overflow)?;
+        if amount == 0 {
+            return Err(VestingError::NothingToClaim.into());
+        }
+        schedule.claimed_amount = schedule
+            .claimed_amount
+            .checked_add(amount)
+            .ok_or(VestingError::Overflow)?;
+        VestingSchedule::pack(schedule, &mut schedule_info.data.borrow_mut())?;
+
+        Self::transfer_from_vault(
+            program_id,
+            &schedule,
+            schedule_info,
+            token_program_info,
+            vault_info,
+            mint_info,
+            destination_info,
+            vault_authority_info,
+            amount,
+        )
+    }
+
+    /// Processes a [`Revoke`](enum.VestingInstruction.html) instruction.
+    pub fn process_revoke(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let schedule_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let mint_info = next_account_info(account_info_iter)?;
+        let refund_info = next_account_info(account_info_iter)?;
+        let grantor_info = next_account_info(account_info_iter)?;
+        let vault_authority_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        let mut schedule = Self::unpack_schedule(program_id, schedule_info, vault_info)?;
+        if schedule.grantor != *grantor_info.key {
+            return Err(VestingError::GrantorMismatch.into());
+        }
+        if !grantor_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if !schedule.revocable {
+            return Err(VestingError::NotRevocable.into());
+        }
+        if schedule.is_revoked {
+            return Err(VestingError::AlreadyRevoked.into());
+        }
+
+        schedule.is_revoked = true;
+        schedule.revoked_timestamp = get_clock().unix_timestamp;
+        let unvested_amount = schedule
+            .vested_amount(schedule.revoked_timestamp)
+            .and_then(|vested_amount| schedule.total_amount.checked_sub(vested_amount))
+            .ok_or(VestingError::Overflow)?;
+        VestingSchedule::pack(schedule, &mut schedule_info.data.borrow_mut())?;
+
+        if unvested_amount == 0 {
+            return Ok(());
+        }
+        Self::transfer_from_vault(
+            program_id,
+            &schedule,
+            schedule_info,
+            token_program_info,
+            vault_info,
+            mint_info,
+            refund_info,
+            vault_authority_info,
+            unvested_amount,
+        )
+    }
+
+    /// Processes a [`GetVestedAmount`](enum.VestingInstruction.html)
+    /// instruction.
+    pub fn process_get_vested_amount(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let schedule_info = next_account_info(account_info_iter)?;
+        Self::check_account_owner(program_id, schedule_info)?;
+
+        let schedule = VestingSchedule::unpack(&schedule_info.data.borrow())?;
+        let vested_amount = schedule
+            .vested_amount(get_clock().unix_timestamp)
+            .ok_or(VestingError::Overflow)?;
+
+        set_return_data(&vested_amount.to_le_bytes());
+        Ok(())
+    }
+
+    /// Processes an [`Instruction`](enum.Instruction.html).
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
+        let instruction = VestingInstruction::unpack(input)?;
+
+        match instruction {
+            VestingInstruction::CreateSchedule {
+                start_timestamp,
+                cliff_timestamp,
+                end_timestamp,
+                period,
+                total_amount,
+                revocable,
+            } => {
+                msg!("Instruction: CreateSchedule");
+                Self::process_create_schedule(
+                    program_id,
+                    accounts,
+                    start_timestamp,
+                    cliff_timestamp,
+                    end_timestamp,
+                    period,
+                    total_amount,
+                    revocable,
+                )
+            }
+            VestingInstruction::Claim => {
+                msg!("Instruction: Claim");
+                Self::process_claim(program_id, accounts)
+            }
+            VestingInstruction::Revoke => {
+                msg!("Instruction: Revoke");
+                Self::process_revoke(program_id, accounts)
+            }
+            VestingInstruction::GetVestedAmount => {
+                msg!("Instruction: GetVestedAmount");
+                Self::process_get_vested_amount(program_id, accounts)
+            }
+        }
+    }
+
+    /// Checks that the account is owned by the given program
+    pub fn check_account_owner(program_id: &Pubkey, account_info: &AccountInfo) -> ProgramResult {
+        if program_id != account_info.owner {
+            Err(ProgramError::IncorrectProgramId)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Unpacks an initialized schedule, checking the vault passed with it
+    fn unpack_schedule(
+        program_id: &Pubkey,
+        schedule_info: &AccountInfo,
+        vault_info: &AccountInfo,
+    ) -> Result<VestingSchedule, ProgramError> {
+        Self::check_account_owner(program_id, schedule_info)?;
+        let schedule = VestingSchedule::unpack(&schedule_info.data.borrow())?;
+        if schedule.vault != *vault_info.key {
+            return Err(VestingError::InvalidVault.into());
+        }
+        Ok(schedule)
+    }
+
+    /// Checks that the vault is a token account of the mint owned by the
+    /// vault authority, returning the mint decimals
+    fn check_vault(
+        vault_info: &AccountInfo,
+        mint_info: &AccountInfo,
+        vault_authority: &Pubkey,
+    ) -> Result<u8, ProgramError> {
+        if vault_info.owner != &apl_token::id() || mint_info.owner != &apl_token::id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let vault = Account::unpack_base(&vault_info.data.borrow())?;
+        if vault.mint != *mint_info.key || vault.owner != *vault_authority {
+            return Err(VestingError::InvalidVault.into());
+        }
+        let mint = Mint::unpack_base(&mint_info.data.borrow())?;
+        Ok(mint.decimals)
+    }
+
+    /// Transfers tokens out of the vault of a schedule, signing as its vault
+    /// authority
+    #[allow(clippy::too_many_arguments)]
+    fn transfer_from_vault<'a>(
+        program_id: &Pubkey,
+        schedule: &VestingSchedule,
+        schedule_info: &AccountInfo<'a>,
+        token_program_info: &AccountInfo<'a>,
+        vault_info: &AccountInfo<'a>,
+        mint_info: &AccountInfo<'a>,
+        destination_info: &AccountInfo<'a>,
+        vault_authority_info: &AccountInfo<'a>,
+        amount: u64,
+    ) -> ProgramResult {
+        let schedule_key = schedule_info.key.serialize();
+        let vault_authority_seeds: &[&[u8]] = &[
+            VAULT_AUTHORITY_SEED,
+            &schedule_key,
+            &[schedule.vault_authority_bump],
+        ];
+        let vault_authority = Pubkey::create_program_address(vault_authority_seeds, program_id)?;
+        if vault_authority != *vault_authority_info.key {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        let decimals = Self::check_vault(vault_info, mint_info, &vault_authority)?;
+
+        Self::transfer(
+            token_program_info,
+            vault_info,
+            mint_info,
+            destination_info,
+            vault_authority_info,
+            amount,
+            decimals,
+            &[vault_authority_seeds],
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn transfer<'a>(
+        token_program_info: &AccountInfo<'a>,
+        source_info: &AccountInfo<'a>,
+        mint_info: &AccountInfo<'a>,
+        destination_info: &AccountInfo<'a>,
+        authority_info: &AccountInfo<'a>,
+        amount: u64,
+        decimals: u8,
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        if token_program_info.key != &apl_token::id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        invoke_signed(
+            &apl_token::instruction::transfer_checked(
+                token_program_info.key,
+                source_info.key,
+                mint_info.key,
+                destination_info.key,
+                authority_info.key,
+                &[],
+                amount,
+                decimals,
+            )?,
+            &[
+                source_info.clone(),
+                mint_info.clone(),
+                destination_info.clone(),
+                authority_info.clone(),
+                token_program_info.clone(),
+            ],
+            signers_seeds,
+        )
+    }
+}
@@ -0,0 +1,286 @@
+//! State transition types
+
+use {
+    arch_program::{
+        program_error::ProgramError,
+        program_pack::{IsInitialized, Pack, Sealed},
+        pubkey::Pubkey,
+    },
+    arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs},
+};
+
+/// Vesting schedule data.
+///
+/// Nothing vests before the cliff. From then on, the vested amount is the
+/// share of `total_amount` proportional to the time elapsed between start and
+/// end, counted in whole periods when `period` is non-zero. Setting the cliff
+/// to the end releases everything at once.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct VestingSchedule {
+    /// Is `true` if this structure has been initialized
+    pub is_initialized: bool,
+    /// Bump seed of the vault authority
+    pub vault_authority_bump: u8,
+    /// Is `true` if the grantor can revoke the unvested tokens
+    pub revocable: bool,
+    /// Is `true` if the grantor has revoked the schedule
+    pub is_revoked: bool,
+    /// Account that funded the schedule
+    pub grantor: Pubkey,
+    /// Account that can claim the vested tokens
+    pub beneficiary: Pubkey,
+    /// Mint of the vested tokens
+    pub mint: Pubkey,
+    /// Token account holding the unclaimed tokens
+    pub vault: Pubkey,
+    /// Unix timestamp from which tokens vest
+    pub start_timestamp: i64,
+    /// Unix timestamp before which nothing can be claimed
+    pub cliff_timestamp: i64,
+    /// Unix timestamp at which all tokens have vested
+    pub end_timestamp: i64,
+    /// Length in seconds of each tranche, or zero to vest continuously
+    pub period: i64,
+    /// Unix timestamp at which vesting stopped, if revoked
+    pub revoked_timestamp: i64,
+    /// Amount of tokens vesting over the schedule
+    pub total_amount: u64,
+    /// Amount of tokens already claimed
+    pub claimed_amount: u64,
+}
+
+impl VestingSchedule {
+    /// Checks that the timestamps are ordered and the amount non-zero
+    pub fn is_valid(&self) -> bool {
+        self.start_timestamp <= self.cliff_timestamp
+            && self.cliff_timestamp <= self.end_timestamp
+            && self.period >= 0
+            && self.total_amount > 0
+    }
+
+    /// Amount of tokens vested at `unix_timestamp`, frozen at the revocation
+    /// time if revoked
+    pub fn vested_amount(&self, unix_timestamp: i64) -> Option<u64> {
+        let unix_timestamp = if self.is_revoked {
+            unix_timestamp.min(self.revoked_timestamp)
+        } else {
+            unix_timestamp
+        };
+        if unix_timestamp < self.cliff_timestamp {
+            return Some(0);
+        }
+        if unix_timestamp >= self.end_timestamp {
+            return Some(self.total_amount);
+        }
+        let mut elapsed = unix_timestamp.checked_sub(self.start_timestamp)?;
+        if self.period > 0 {
+            elapsed -= elapsed % self.period;
+        }
+        let duration = self.end_timestamp.checked_sub(self.start_timestamp)?;
+        u64::try_from(
+            (self.total_amount as u128)
+                .checked_mul(elapsed as u128)?
+                .checked_div(duration as u128)?,
+        )
+        .ok()
+    }
+
+    /// Amount of vested tokens not claimed yet at `unix_timestamp`
+    pub fn claimable_amount(&self, unix_timestamp: i64) -> Option<u64> {
+        self.vested_amount(unix_timestamp)?
+            .checked_sub(self.claimed_amount)
+    }
+}
+
+impl Sealed for VestingSchedule {}
+impl IsInitialized for VestingSchedule {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+impl Pack for VestingSchedule {
+    const LEN: usize = 188;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 188];
+        let (
+            is_initialized,
+            vault_authority_bump,
+            revocable,
+            is_revoked,
+            grantor,
+            beneficiary,
+            mint,
+            vault,
+            start_timestamp,
+            cliff_timestamp,
+            end_timestamp,
+            period,
+            revoked_timestamp,
+            total_amount,
+            claimed_amount,
+        ) = array_refs![src, 1, 1, 1, 1, 32, 32, 32, 32, 8, 8, 8, 8, 8, 8, 8];
+        Ok(VestingSchedule {
+            is_initialized: unpack_bool(is_initialized)?,
+            vault_authority_bump: vault_authority_bump[0],
+            revocable: unpack_bool(revocable)?,
+            is_revoked: unpack_bool(is_revoked)?,
+            grantor: Pubkey::from_slice(grantor),
+            beneficiary: Pubkey::from_slice(beneficiary),
+            mint: Pubkey::from_slice(mint),
+            vault: Pubkey::from_slice(vault),
+            start_timestamp: i64::from_le_bytes(*start_timestamp),
+            cliff_timestamp: i64::from_le_bytes(*cliff_timestamp),
+            end_timestamp: i64::from_le_bytes(*end_timestamp),
+            period: i64::from_le_bytes(*period),
+            revoked_timestamp: i64::from_le_bytes(*revoked_timestamp),
+            total_amount: u64::from_le_bytes(*total_amount),
+            claimed_amount: u64::from_le_bytes(*claimed_amount),
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 188];
+        let (
+            is_initialized_dst,
+            vault_authority_bump_dst,
+            revocable_dst,
+            is_revoked_dst,
+            grantor_dst,
+            beneficiary_dst,
+            mint_dst,
+            vault_dst,
+            start_timestamp_dst,
+            cliff_timestamp_dst,
+            end_timestamp_dst,
+            period_dst,
+            revoked_timestamp_dst,
+            total_amount_dst,
+            claimed_amount_dst,
+        ) = mut_array_refs![dst, 1, 1, 1, 1, 32, 32, 32, 32, 8, 8, 8, 8, 8, 8, 8];
+        *is_initialized_dst = [self.is_initialized as u8];
+        *vault_authority_bump_dst = [self.vault_authority_bump];
+        *revocable_dst = [self.revocable as u8];
+        *is_revoked_dst = [self.is_revoked as u8];
+        grantor_dst.copy_from_slice(self.grantor.as_ref());
+        beneficiary_dst.copy_from_slice(self.beneficiary.as_ref());
+        mint_dst.copy_from_slice(self.mint.as_ref());
+        vault_dst.copy_from_slice(self.vault.as_ref());
+        *start_timestamp_dst = self.start_timestamp.to_le_bytes();
+        *cliff_timestamp_dst = self.cliff_timestamp.to_le_bytes();
+        *end_timestamp_dst = self.end_timestamp.to_le_bytes();
+        *period_dst = self.period.to_le_bytes();
+        *revoked_timestamp_dst = self.revoked_timestamp.to_le_bytes();
+        *total_amount_dst = self.total_amount.to_le_bytes();
+        *claimed_amount_dst = self.claimed_amount.to_le_bytes();
+    }
+}
+
+fn unpack_bool(src: &[u8; 1]) -> Result<bool, ProgramError> {
+    match src {
+        [0] => Ok(false),
+        [1] => Ok(true),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_schedule() -> VestingSchedule {
+        VestingSchedule {
+            is_initialized: true,
+            vault_authority_bump: 254,
+            revocable: true,
+            is_revoked: false,
+            grantor: Pubkey::new_unique(),
+            beneficiary: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            vault: Pubkey::new_unique(),
+            start_timestamp: 1_000,
+            cliff_timestamp: 1_250,
+            end_timestamp: 2_000,
+            period: 0,
+            revoked_timestamp: 0,
+            total_amount: 1_000_000,
+            claimed_amount: 0,
+        }
+    }
+
+    #[test]
+    fn test_pack_unpack() {
+        let schedule = test_schedule();
+        let mut packed = vec![0u8; VestingSchedule::LEN];
+        VestingSchedule::pack(schedule, &mut packed).unwrap();
+        assert_eq!(VestingSchedule::unpack(&packed).unwrap(), schedule);
+
+        packed[2] = 2;
+        assert_eq!(
+            VestingSchedule::unpack(&packed),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn test_linear_with_cliff() {
+        let schedule = test_schedule();
+        assert!(schedule.is_valid());
+        assert_eq!(schedule.vested_amount(0), Some(0));
+        assert_eq!(schedule.vested_amount(1_249), Some(0));
+        assert_eq!(schedule.vested_amount(1_250), Some(250_000));
+        assert_eq!(schedule.vested_amount(1_500), Some(500_000));
+        assert_eq!(schedule.vested_amount(2_000), Some(1_000_000));
+        assert_eq!(schedule.vested_amount(i64::MAX), Some(1_000_000));
+    }
+
+    #[test]
+    fn test_periodic() {
+        let schedule = VestingSchedule {
+            cliff_timestamp: 1_000,
+            period: 300,
+            ..test_schedule()
+        };
+        assert_eq!(schedule.vested_amount(1_299), Some(0));
+        assert_eq!(schedule.vested_amount(1_300), Some(300_000));
+        assert_eq!(schedule.vested_amount(1_899), Some(600_000));
+        assert_eq!(schedule.vested_amount(1_900), Some(900_000));
+        assert_eq!(schedule.vested_amount(2_000), Some(1_000_000));
+    }
+
+    #[test]
+    fn test_cliff_only() {
+        let schedule = VestingSchedule {
+            cliff_timestamp: 2_000,
+            ..test_schedule()
+        };
+        assert_eq!(schedule.vested_amount(1_999), Some(0));
+        assert_eq!(schedule.vested_amount(2_000), Some(1_000_000));
+
+        let immediate = VestingSchedule {
+            start_timestamp: 2_000,
+            cliff_timestamp: 2_000,
+            ..test_schedule()
+        };
+        assert!(immediate.is_valid());
+        assert_eq!(immediate.vested_amount(2_000), Some(1_000_000));
+    }
+
+    #[test]
+    fn test_revoked_and_claimed() {
+        let schedule = VestingSchedule {
+            is_revoked: true,
+            revoked_timestamp: 1_500,
+            claimed_amount: 100_000,
+            ..test_schedule()
+        };
+        assert_eq!(schedule.vested_amount(1_400), Some(400_000));
+        assert_eq!(schedule.vested_amount(1_900), Some(500_000));
+        assert_eq!(schedule.claimable_amount(1_900), Some(400_000));
+
+        let invalid = VestingSchedule {
+            cliff_timestamp: 900,
+            ..test_schedule()
+        };
+        assert!(!invalid.is_valid());
+    }
+}